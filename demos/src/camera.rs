@@ -24,11 +24,11 @@ impl Drop for Camera {
 }
 
 impl Camera {
-  pub fn new(mut mem: vk::mem::Mem) -> Self {
+  pub fn new(mem: vk::mem::Mem) -> Self {
     let mut ub = vk::NULL_HANDLE;
     vk::mem::Buffer::new(&mut ub)
       .uniform_buffer(std::mem::size_of::<CameraUb>() as vk::DeviceSize)
-      .bind(&mut mem.alloc, vk::mem::BindType::Block)
+      .bind(&mem.alloc, vk::mem::BindType::Block)
       .unwrap();
 
     let stage = vk::mem::Staging::new(mem.clone(), std::mem::size_of::<CameraUb>() as vk::DeviceSize).unwrap();
@@ -17,14 +17,6 @@ mod make_sequence {
       include = ["src", "other"],
     }
   }
-
-  #[repr(C)]
-  #[derive(Debug)]
-  pub struct Ub {
-    pub num_elems: u32,
-    pub i_first: u32,
-    pub i_step: u32,
-  }
 }
 
 pub fn main() {
@@ -56,19 +48,19 @@ pub fn main() {
   let pool = DescriptorPool::new(device.handle, DescriptorPool::new_capacity().add(&p.dsets[0], 1));
   let ds = pool.new_dset(&p.dsets[0]).unwrap();
 
-  let mut allocator = vk::mem::Allocator::new(pdevice.handle, device.handle);
+  let allocator = vk::mem::Allocator::new(pdevice.handle, device.handle);
 
   let mut buf_ub = vk::NULL_HANDLE;
   let mut buf_out = vk::NULL_HANDLE;
   vk::mem::Buffer::new(&mut buf_ub)
-    .size(std::mem::size_of::<make_sequence::Ub>() as vk::DeviceSize)
+    .size(std::mem::size_of::<make_sequence::blocks::Ub>() as vk::DeviceSize)
     .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT)
     .devicelocal(false)
     .new_buffer(&mut buf_out)
     .size(123 * std::mem::size_of::<u32>() as vk::DeviceSize)
     .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_STORAGE_BUFFER_BIT)
     .devicelocal(false)
-    .bind(&mut allocator, vk::mem::BindType::Scatter)
+    .bind(&allocator, vk::mem::BindType::Scatter)
     .unwrap();
 
   make_sequence::dset::write(device.handle, ds)
@@ -82,7 +74,7 @@ pub fn main() {
 
   {
     let mapped = allocator.get_mapped(Handle::Buffer(buf_ub)).unwrap();
-    let ubb = make_sequence::Ub {
+    let ubb = make_sequence::blocks::Ub {
       num_elems: 123,
       i_first: 0,
       i_step: 1,
@@ -91,8 +83,8 @@ pub fn main() {
   }
   {
     let mapped = allocator.get_mapped(Handle::Buffer(buf_ub)).unwrap();
-    //let mut ubb: make_sequence::Ub = unsafe { std::mem::uninitialized() };
-    let ubb = mapped.device_to_host::<make_sequence::Ub>();
+    //let mut ubb: make_sequence::blocks::Ub = unsafe { std::mem::uninitialized() };
+    let ubb = mapped.device_to_host::<make_sequence::blocks::Ub>();
     println!("{:?}", ubb);
   }
 
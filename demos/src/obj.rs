@@ -165,7 +165,7 @@ pub fn resize_window(
   pdevice: &vk::device::PhysicalDevice,
   device: &vk::device::Device,
   window: &vk::wnd::Window,
-  alloc: &mut vk::mem::Allocator,
+  alloc: &vk::mem::Allocator,
   mut sc: Option<vk::wnd::Swapchain>,
   pass: Option<vk::pass::Renderpass>,
   mut fb: Option<vk::pass::Framebuffer>,
@@ -205,11 +205,11 @@ pub fn resize_window(
 pub fn main() {
   let (_inst, pdevice, device, mut events_loop, window) = setup_vulkan_window();
 
-  let mut alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
+  let alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
   let cmds = vk::cmd::CmdPool::new(device.handle, device.queues[0].family).unwrap();
 
-  let (mut sc, mut rp, mut fb) = resize_window(&pdevice, &device, &window, &mut alloc, None, None, None);
-  let mut mem = vk::mem::Mem::new(alloc, 1);
+  let (mut sc, mut rp, mut fb) = resize_window(&pdevice, &device, &window, &alloc, None, None, None);
+  let mem = vk::mem::Mem::new(alloc, 1);
 
   let mut camera = camera::Camera::new(mem.clone());
   camera.transform.view = vkm::Mat4::look_at(vec3!(0.0, 0.0, -10.0), vec3!(0.0, 0.0, 0.0), vec3!(0.0, 1.0, 0.0));
@@ -233,7 +233,7 @@ pub fn main() {
   let mut ub_model = vk::NULL_HANDLE;
   vk::mem::Buffer::new(&mut ub_model)
     .uniform_buffer(std::mem::size_of::<obj::UbModel>() as vk::DeviceSize)
-    .bind(&mut mem.alloc, vk::mem::BindType::Block)
+    .bind(&mem.alloc, vk::mem::BindType::Block)
     .unwrap();
 
   // update uniform buffers
@@ -293,7 +293,7 @@ pub fn main() {
 
     if resize {
       vk_uncheck!(vk::DeviceWaitIdle(device.handle));
-      let (nsc, nrp, nfb) = resize_window(&pdevice, &device, &window, &mut mem.alloc, Some(sc), Some(rp), Some(fb));
+      let (nsc, nrp, nfb) = resize_window(&pdevice, &device, &window, &mem.alloc, Some(sc), Some(rp), Some(fb));
       sc = nsc;
       rp = nrp;
       fb = nfb;
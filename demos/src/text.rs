@@ -64,7 +64,7 @@ pub fn resize(
   pdevice: &vk::device::PhysicalDevice,
   device: &vk::device::Device,
   window: &vk::wnd::Window,
-  alloc: &mut vk::mem::Allocator,
+  alloc: &vk::mem::Allocator,
   sc: Option<vk::wnd::Swapchain>,
   rp: Option<vk::pass::Renderpass>,
   fb: Option<vk::pass::Framebuffer>,
@@ -110,10 +110,10 @@ pub fn resize(
 pub fn main() {
   let (_inst, pdevice, device, mut events_loop, window) = setup_vulkan_window();
 
-  let mut alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
+  let alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
   let cmds = vk::cmd::CmdPool::new(device.handle, device.queues[0].family).unwrap();
 
-  let (mut sc, mut rp, mut fb) = resize(&pdevice, &device, &window, &mut alloc, None, None, None);
+  let (mut sc, mut rp, mut fb) = resize(&pdevice, &device, &window, &alloc, None, None, None);
   let mem = vk::mem::Mem::new(alloc.clone(), 2);
 
   let mut gui = Gui::new(&device, &window, fb.images[0], mem.clone());
@@ -171,7 +171,7 @@ pub fn main() {
       batch.sync().unwrap();
       vk::DeviceWaitIdle(device.handle);
 
-      let (nsc, nrp, nfb) = resize(&pdevice, &device, &window, &mut alloc, Some(sc), Some(rp), Some(fb));
+      let (nsc, nrp, nfb) = resize(&pdevice, &device, &window, &alloc, Some(sc), Some(rp), Some(fb));
       sc = nsc;
       rp = nrp;
       fb = nfb;
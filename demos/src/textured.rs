@@ -82,7 +82,7 @@ pub fn setup_rendertargets(
   pdevice: &vk::device::PhysicalDevice,
   device: &vk::device::Device,
   window: &vk::wnd::Window,
-  alloc: &mut vk::mem::Allocator,
+  alloc: &vk::mem::Allocator,
 ) -> (vk::wnd::Swapchain, vk::pass::Renderpass, Vec<vk::pass::Framebuffer>) {
   let sc = vk::wnd::Swapchain::build(pdevice.handle, device.handle, window.surface).create();
 
@@ -135,11 +135,11 @@ pub fn update_mvp(
 pub fn main() {
   let (_inst, pdevice, device, mut events_loop, window) = setup_vulkan_window();
 
-  let mut alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
+  let alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
   let cmds = vk::cmd::CmdPool::new(device.handle, device.queues[0].family).unwrap();
 
-  let (mut sc, rp, fbs) = setup_rendertargets(&pdevice, &device, &window, &mut alloc);
-  let mut mem = vk::mem::Mem::new(alloc, fbs.len());
+  let (mut sc, rp, fbs) = setup_rendertargets(&pdevice, &device, &window, &alloc);
+  let mem = vk::mem::Mem::new(alloc, fbs.len());
 
   let pipe = tex::new(device.handle, rp.pass, 0)
     .vertex_input(
@@ -176,7 +176,7 @@ pub fn main() {
     .uniform_buffer(std::mem::size_of::<tex::UbTransform>() as vk::DeviceSize)
     .new_image(&mut texture)
     .texture2d(256, 256, vk::FORMAT_R8G8B8A8_UNORM)
-    .bind(&mut mem.alloc, vk::mem::BindType::Block)
+    .bind(&mem.alloc, vk::mem::BindType::Block)
     .unwrap();
 
   let texview = vk::ImageViewCreateInfo::build()
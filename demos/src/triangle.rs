@@ -66,7 +66,7 @@ pub fn setup_rendertargets(
   pdevice: &vk::device::PhysicalDevice,
   device: &vk::device::Device,
   window: &vk::wnd::Window,
-  alloc: &mut vk::mem::Allocator,
+  alloc: &vk::mem::Allocator,
 ) -> (vk::wnd::Swapchain, vk::pass::Renderpass, Vec<vk::pass::Framebuffer>) {
   let sc = vk::wnd::Swapchain::build(pdevice.handle, device.handle, window.surface).create();
 
@@ -102,10 +102,10 @@ pub fn setup_rendertargets(
 pub fn main() {
   let (_inst, pdevice, device, mut events_loop, window) = setup_vulkan_window();
 
-  let mut alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
+  let alloc = vk::mem::Allocator::new(pdevice.handle, device.handle);
   let cmds = vk::cmd::CmdPool::new(device.handle, device.queues[0].family).unwrap();
 
-  let (mut sc, rp, fbs) = setup_rendertargets(&pdevice, &device, &window, &mut alloc);
+  let (mut sc, rp, fbs) = setup_rendertargets(&pdevice, &device, &window, &alloc);
 
   let pipe = tri::new(device.handle, rp.pass, 0)
     .dynamic(
@@ -30,7 +30,7 @@ impl crate::Asset for Asset {
           _ => panic!("invalid texture format"),
         },
       )
-      .bind(&mut up.get_mem().alloc, vk::mem::BindType::Block)
+      .bind(&up.get_mem().alloc, vk::mem::BindType::Block)
       .unwrap();
 
     let mut stage = up.get_staging(tga.img.data().len() as vk::DeviceSize);
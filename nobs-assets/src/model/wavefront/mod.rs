@@ -95,7 +95,7 @@ impl crate::Asset for Asset {
         s.count = o.indices.len() as u32;
       }
     }
-    builder.bind(&mut up.get_mem().alloc, vk::mem::BindType::Block).unwrap();
+    builder.bind(&up.get_mem().alloc, vk::mem::BindType::Block).unwrap();
 
     for (s, o) in shapes.iter_mut().zip(obj.iter()) {
       {
@@ -61,7 +61,7 @@ impl<S: Style> ImGui<S> {
   /// * `wnd` - vulkanism window handle
   /// * `target` - vulkan image on which the gui is rendered
   /// * `mem` - vulkanism memory manager on which additional buffers and textures are allocated
-  pub fn new(device: &vk::device::Device, wnd: &vk::wnd::Window, target: vk::Image, mut mem: vk::mem::Mem) -> Self {
+  pub fn new(device: &vk::device::Device, wnd: &vk::wnd::Window, target: vk::Image, mem: vk::mem::Mem) -> Self {
     // we need dpi and size of window to get the render target extent
     let dpi = wnd.window.scale_factor();
     let extent = wnd.window.inner_size();
@@ -75,7 +75,7 @@ impl<S: Style> ImGui<S> {
     vk::mem::Buffer::new(&mut ub_viewport)
       .uniform_buffer(2 * std::mem::size_of::<f32>() as vk::DeviceSize)
       .devicelocal(false)
-      .bind(&mut mem.alloc, vk::mem::BindType::Block)
+      .bind(&mem.alloc, vk::mem::BindType::Block)
       .unwrap();
 
     {
@@ -106,7 +106,7 @@ impl<S: Style> ImGui<S> {
         .unwrap();
 
       let fb = Mutex::new(
-        vk::pass::Framebuffer::build_from_pass(&rp, &mut mem.alloc)
+        vk::pass::Framebuffer::build_from_pass(&rp, &mem.alloc)
           .extent(extent)
           .target(0, target)
           .create(),
@@ -182,8 +182,8 @@ impl<S: Style> ImGui<S> {
   /// * `size` - The new size of the gui
   /// * `target` - The new render target image for the gui
   pub fn resize(&mut self, size: vk::Extent2D, target: vk::Image) {
-    let mut mem = self.gui.mem.clone();
-    *self.gui.draw.fb.lock().unwrap() = vk::pass::Framebuffer::build_from_pass(&self.gui.draw.rp, &mut mem.alloc)
+    let mem = self.gui.mem.clone();
+    *self.gui.draw.fb.lock().unwrap() = vk::pass::Framebuffer::build_from_pass(&self.gui.draw.rp, &mem.alloc)
       .extent(size)
       .target(0, target)
       .create();
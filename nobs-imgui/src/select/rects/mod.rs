@@ -237,7 +237,7 @@ impl Rects {
         vk::mem::Buffer::new(&mut self.vb)
           .vertex_buffer((self.vb_data.len() * std::mem::size_of::<Vertex>()) as vk::DeviceSize)
           .devicelocal(false)
-          .bind(&mut self.mem.alloc, vk::mem::BindType::Block)
+          .bind(&self.mem.alloc, vk::mem::BindType::Block)
           .unwrap();
 
         self.vb_capacity = self.vb_data.len();
@@ -114,7 +114,7 @@ impl SelectPass {
   /// * `dpi` - winit dpi factor of the window, that is needed since mouse pointer coordinates are given in logical coordinates by the window events.
   /// * `mem` - memory manager to allocate framebuffers
   pub fn new(device: vk::Device, extent: vk::Extent2D, dpi: f64, mem: vk::mem::Mem) -> Self {
-    let mut mem = mem.clone();
+    let mem = mem.clone();
     let rp = vk::pass::Renderpass::build(device)
       .attachment(
         0,
@@ -130,7 +130,7 @@ impl SelectPass {
       .create()
       .unwrap();
 
-    let mut fb = vk::pass::Framebuffer::build_from_pass(&rp, &mut mem.alloc).extent(extent).create();
+    let mut fb = vk::pass::Framebuffer::build_from_pass(&rp, &mem.alloc).extent(extent).create();
     let c = SelectId::invalid().into();
     fb.set_clear(&[vk::ClearValue::build().coloru32([c, c, c, c]).into()]);
 
@@ -158,9 +158,9 @@ impl SelectPass {
   /// Resizes the framebuffer of this pass
   pub fn resize(&mut self, size: vk::Extent2D) {
     let mut pass = self.pass.lock().unwrap();
-    let mut mem = pass.mem.clone();
+    let mem = pass.mem.clone();
     mem.alloc.destroy(Handle::Image(pass.fb.images[0]));
-    pass.fb = vk::pass::Framebuffer::build_from_pass(&pass.rp, &mut mem.alloc)
+    pass.fb = vk::pass::Framebuffer::build_from_pass(&pass.rp, &mem.alloc)
       .extent(size)
       .create();
     let c = u32::max_value();
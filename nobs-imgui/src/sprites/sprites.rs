@@ -45,13 +45,13 @@ impl<S: Style> Sprites<S> {
     let vb = vk::NULL_HANDLE;
 
     let device = gui.get_device();
-    let mut mem = gui.get_mem();
+    let mem = gui.get_mem();
 
     let mut ub = vk::NULL_HANDLE;
     vk::mem::Buffer::new(&mut ub)
       .uniform_buffer(std::mem::size_of::<UbText>() as vk::DeviceSize)
       .devicelocal(false)
-      .bind(&mut mem.alloc, vk::mem::BindType::Block)
+      .bind(&mem.alloc, vk::mem::BindType::Block)
       .unwrap();
 
     let pipe = Pipeline::new_instance(&gui.get_pipes());
@@ -111,7 +111,7 @@ impl<S: Style> Sprites<S> {
   }
 
   pub fn sprites(&mut self, sprites: &[Vertex]) -> &mut Self {
-    let mut mem = self.gui.get_mem();
+    let mem = self.gui.get_mem();
 
     // create new buffer if capacity of cached one is not enough
     if sprites.len() > self.vb_capacity {
@@ -121,7 +121,7 @@ impl<S: Style> Sprites<S> {
       vk::mem::Buffer::new(&mut self.vb)
         .vertex_buffer((sprites.len() * std::mem::size_of::<Vertex>()) as vk::DeviceSize)
         .devicelocal(false)
-        .bind(&mut mem.alloc, vk::mem::BindType::Block)
+        .bind(&mem.alloc, vk::mem::BindType::Block)
         .unwrap();
 
       self.vb_capacity = sprites.len();
@@ -57,7 +57,7 @@ impl Style for Simple {
 
   fn new(
     device: &vk::device::Device,
-    mut mem: vk::mem::Mem,
+    mem: vk::mem::Mem,
     pass_draw: vk::RenderPass,
     pass_select: vk::RenderPass,
     ds_viewport: vk::DescriptorSet,
@@ -76,7 +76,7 @@ impl Style for Simple {
       vk::mem::Buffer::new(&mut ub)
         .uniform_buffer(std::mem::size_of::<UbStyle>() as vk::DeviceSize)
         .devicelocal(false)
-        .bind(&mut mem.alloc, vk::mem::BindType::Block)
+        .bind(&mem.alloc, vk::mem::BindType::Block)
         .unwrap();
 
       let mut map = mem.alloc.get_mapped(vk::mem::Handle::Buffer(ub)).unwrap();
@@ -116,7 +116,7 @@ impl Style for Simple {
       vk::mem::Buffer::new(&mut ub)
         .uniform_buffer(std::mem::size_of::<UbStyle>() as vk::DeviceSize)
         .devicelocal(false)
-        .bind(&mut im.mem.alloc, vk::mem::BindType::Block)
+        .bind(&im.mem.alloc, vk::mem::BindType::Block)
         .unwrap();
 
       (im.pipe.new_style(ub), ub)
@@ -161,7 +161,7 @@ impl Style for Simple {
         .devicelocal(false)
         .submit()
     }
-    builder.bind(&mut im.mem.alloc, vk::mem::BindType::Block).unwrap();
+    builder.bind(&im.mem.alloc, vk::mem::BindType::Block).unwrap();
 
     for (_, e) in styles.iter_mut() {
       im.mem
@@ -41,12 +41,12 @@ impl Drop for SimpleComponent {
 
 impl StyleComponent<Simple> for SimpleComponent {
   fn new(gui: &ImGui<Simple>, style: String, movable: bool, resizable: bool) -> Self {
-    let mut mem = gui.get_mem();
+    let mem = gui.get_mem();
     let mut ub = vk::NULL_HANDLE;
     vk::mem::Buffer::new(&mut ub)
       .uniform_buffer(std::mem::size_of::<super::pipeline::UbStyle>() as vk::DeviceSize)
       .devicelocal(false)
-      .bind(&mut mem.alloc, vk::mem::BindType::Block)
+      .bind(&mem.alloc, vk::mem::BindType::Block)
       .unwrap();
 
     let mut sim = gui.style.im.lock().unwrap();
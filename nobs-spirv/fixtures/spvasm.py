@@ -0,0 +1,222 @@
+#!/usr/bin/env python3
+"""Assembles the test fixtures of nobs-spirv.
+
+The fixtures are hand written SPIR-V assembly in the syntax of spirv-as. This script
+implements the small subset of that syntax the fixtures use, so that they can be
+rebuilt without the SPIRV-Tools:
+
+    cd nobs-spirv/fixtures && python3 spvasm.py *.spvasm
+
+writes X.spv next to every X.spvasm.
+
+- ids are numbered in order of their first appearance
+- the version is taken from the `; Version: X.Y` comment, 1.0 if there is none
+- the generator word is 0, as the script is not a registered tool
+
+Opcodes and operand enumerants are taken from the SPIR-V specification and are
+deliberately not generated from nobs-spirv/src/enums.rs, so that the tests also
+check the enums of the parser.
+"""
+import re
+import struct
+import sys
+
+MAGIC = 0x07230203
+GENERATOR = 0
+
+ENUMS = {
+    'SourceLanguage': {'Unknown': 0, 'ESSL': 1, 'GLSL': 2, 'OpenCL_C': 3, 'OpenCL_CPP': 4, 'HLSL': 5},
+    'ExecutionModel': {'Vertex': 0, 'TessellationControl': 1, 'TessellationEvaluation': 2, 'Geometry': 3, 'Fragment': 4, 'GLCompute': 5},
+    'AddressingModel': {'Logical': 0},
+    'MemoryModel': {'Simple': 0, 'GLSL450': 1, 'Vulkan': 3},
+    'ExecutionMode': {'OriginUpperLeft': 7, 'OriginLowerLeft': 8, 'DepthReplacing': 12, 'LocalSize': 17, 'LocalSizeId': 38},
+    'StorageClass': {
+        'UniformConstant': 0, 'Input': 1, 'Uniform': 2, 'Output': 3, 'Workgroup': 4, 'Private': 6, 'Function': 7,
+        'PushConstant': 9, 'Image': 11, 'StorageBuffer': 12,
+    },
+    'Dim': {'1D': 0, '2D': 1, '3D': 2, 'Cube': 3, 'Rect': 4, 'Buffer': 5, 'SubpassData': 6},
+    'ImageFormat': {'Unknown': 0, 'Rgba32f': 1, 'Rgba16f': 2, 'R32f': 3, 'Rgba8': 4},
+    'AccessQualifier': {'ReadOnly': 0, 'WriteOnly': 1, 'ReadWrite': 2},
+    'Decoration': {
+        'SpecId': 1, 'Block': 2, 'BufferBlock': 3, 'RowMajor': 4, 'ColMajor': 5, 'ArrayStride': 6, 'MatrixStride': 7,
+        'BuiltIn': 11, 'Flat': 14, 'Restrict': 19, 'NonWritable': 24, 'NonReadable': 25, 'Location': 30, 'Component': 31,
+        'Binding': 33, 'DescriptorSet': 34, 'Offset': 35, 'InputAttachmentIndex': 43,
+        'HlslCounterBufferGOOGLE': 5634, 'UserSemantic': 5635, 'UserTypeGOOGLE': 5636,
+    },
+    'BuiltIn': {
+        'Position': 0, 'PointSize': 1, 'ClipDistance': 3, 'CullDistance': 4, 'FragCoord': 15, 'WorkgroupSize': 25,
+        'LocalInvocationId': 27, 'GlobalInvocationId': 28, 'VertexIndex': 42, 'InstanceIndex': 43,
+    },
+    'Capability': {
+        'Matrix': 0, 'Shader': 1, 'Float64': 10, 'Int64': 11, 'InputAttachment': 40, 'SampledCubeArray': 45,
+        'SampledBuffer': 46, 'ImageBuffer': 47, 'RuntimeDescriptorArray': 5302,
+    },
+    'FunctionControl': {'None': 0, 'Inline': 1, 'DontInline': 2, 'Pure': 4, 'Const': 8},
+}
+
+# opcode and operand kinds of every supported instruction
+#   res: result id, id: id operand, lit: literal number, str: literal string, val: literal of the result type
+#   dec: decoration with its literal operands, e:X: enumerant of X
+#   a trailing `?` marks an optional operand, a trailing `*` repeats the operand until the end of the line
+OPS = {
+    'OpSource': (3, ['e:SourceLanguage', 'lit']),
+    'OpName': (5, ['id', 'str']),
+    'OpMemberName': (6, ['id', 'lit', 'str']),
+    'OpExtension': (10, ['str']),
+    'OpExtInstImport': (11, ['res', 'str']),
+    'OpMemoryModel': (14, ['e:AddressingModel', 'e:MemoryModel']),
+    'OpEntryPoint': (15, ['e:ExecutionModel', 'id', 'str', 'id*']),
+    'OpExecutionMode': (16, ['id', 'e:ExecutionMode', 'lit*']),
+    'OpCapability': (17, ['e:Capability']),
+    'OpTypeVoid': (19, ['res']),
+    'OpTypeBool': (20, ['res']),
+    'OpTypeInt': (21, ['res', 'lit', 'lit']),
+    'OpTypeFloat': (22, ['res', 'lit']),
+    'OpTypeVector': (23, ['res', 'id', 'lit']),
+    'OpTypeMatrix': (24, ['res', 'id', 'lit']),
+    'OpTypeImage': (25, ['res', 'id', 'e:Dim', 'lit', 'lit', 'lit', 'lit', 'e:ImageFormat', 'e:AccessQualifier?']),
+    'OpTypeSampler': (26, ['res']),
+    'OpTypeSampledImage': (27, ['res', 'id']),
+    'OpTypeArray': (28, ['res', 'id', 'id']),
+    'OpTypeRuntimeArray': (29, ['res', 'id']),
+    'OpTypeStruct': (30, ['res', 'id*']),
+    'OpTypePointer': (32, ['res', 'e:StorageClass', 'id']),
+    'OpTypeFunction': (33, ['res', 'id', 'id*']),
+    'OpConstantTrue': (41, ['id', 'res']),
+    'OpConstantFalse': (42, ['id', 'res']),
+    'OpConstant': (43, ['id', 'res', 'val']),
+    'OpConstantComposite': (44, ['id', 'res', 'id*']),
+    'OpConstantNull': (46, ['id', 'res']),
+    'OpSpecConstantTrue': (48, ['id', 'res']),
+    'OpSpecConstantFalse': (49, ['id', 'res']),
+    'OpSpecConstant': (50, ['id', 'res', 'val']),
+    'OpSpecConstantComposite': (51, ['id', 'res', 'id*']),
+    'OpFunction': (54, ['id', 'res', 'e:FunctionControl', 'id']),
+    'OpFunctionEnd': (56, []),
+    'OpVariable': (59, ['id', 'res', 'e:StorageClass', 'id?']),
+    'OpDecorate': (71, ['id', 'dec']),
+    'OpMemberDecorate': (72, ['id', 'lit', 'dec']),
+    'OpDecorationGroup': (73, ['res']),
+    'OpGroupDecorate': (74, ['id', 'id*']),
+    'OpGroupMemberDecorate': (75, ['id', 'idlit*']),
+    'OpLabel': (248, ['res']),
+    'OpReturn': (253, []),
+    'OpExecutionModeId': (331, ['id', 'e:ExecutionMode', 'id*']),
+    'OpDecorateId': (332, ['id', 'e:Decoration', 'id*']),
+    'OpDecorateString': (5632, ['id', 'e:Decoration', 'str']),
+    'OpMemberDecorateString': (5633, ['id', 'lit', 'e:Decoration', 'str']),
+}
+
+
+def tokenize(line):
+    return re.findall(r'"(?:[^"\\]|\\.)*"|\S+', line)
+
+
+def strip_comment(line):
+    """Removes a trailing comment, that is not part of a string literal"""
+    for m in re.finditer(r'"(?:[^"\\]|\\.)*"|;', line):
+        if m.group(0) == ';':
+            return line[:m.start()].strip()
+    return line.strip()
+
+
+def string_words(s):
+    b = s.encode('utf-8') + b'\0'
+    b += b'\0' * ((4 - len(b) % 4) % 4)
+    return list(struct.unpack('<%dI' % (len(b) // 4), b))
+
+
+def literal_words(ty, v):
+    kind, width = ty
+    if kind == 'float':
+        fmt = '<f' if width == 32 else '<d'
+        return list(struct.unpack('<%dI' % (width // 32), struct.pack(fmt, float(v))))
+    iv = int(v, 0) & ((1 << width) - 1)
+    return [iv & 0xffffffff] if width == 32 else [iv & 0xffffffff, iv >> 32]
+
+
+def assemble(text):
+    ids = {}
+
+    def idof(name):
+        if not name.startswith('%'):
+            raise ValueError('expected an id, found `%s`' % name)
+        return ids.setdefault(name, len(ids) + 1)
+
+    types = {}
+    version = 0x00010000
+    words = []
+    for line in text.splitlines():
+        m = re.match(r'\s*;\s*Version:\s*(\d+)\.(\d+)', line)
+        if m:
+            version = (int(m.group(1)) << 16) | (int(m.group(2)) << 8)
+        line = strip_comment(line)
+        if not line:
+            continue
+
+        toks = tokenize(line)
+        res = None
+        if len(toks) > 2 and toks[1] == '=':
+            res = toks[0]
+            idof(res)
+            toks = toks[2:]
+        op, args = toks[0], toks[1:]
+        code, kinds = OPS[op]
+
+        out = []
+        for k in kinds:
+            if k == 'res':
+                out.append(idof(res))
+            elif k == 'id':
+                out.append(idof(args.pop(0)))
+            elif k == 'id?':
+                out += [idof(args.pop(0))] if args else []
+            elif k == 'id*':
+                out += [idof(a) for a in args]
+                args = []
+            elif k == 'idlit*':
+                while args:
+                    out += [idof(args.pop(0)), int(args.pop(0), 0)]
+            elif k == 'lit':
+                out.append(int(args.pop(0), 0) & 0xffffffff)
+            elif k == 'lit*':
+                out += [int(a, 0) & 0xffffffff for a in args]
+                args = []
+            elif k == 'str':
+                s = args.pop(0)
+                out += string_words(s[1:-1].replace('\\"', '"'))
+            elif k == 'val':
+                out += literal_words(types[toks[1]], args.pop(0))
+            elif k == 'dec':
+                d = args.pop(0)
+                out.append(ENUMS['Decoration'][d])
+                if d == 'BuiltIn':
+                    out.append(ENUMS['BuiltIn'][args.pop(0)])
+                out += [int(a, 0) & 0xffffffff for a in args]
+                args = []
+            elif k.startswith('e:'):
+                if k.endswith('?') and not args:
+                    continue
+                out.append(ENUMS[k[2:].rstrip('?')][args.pop(0)])
+            else:
+                raise ValueError('unknown operand kind `%s`' % k)
+        if args:
+            raise ValueError('unexpected operands in `%s`' % line)
+
+        if op == 'OpTypeInt':
+            types[res] = ('int', int(toks[1]))
+        elif op == 'OpTypeFloat':
+            types[res] = ('float', int(toks[1]))
+
+        words.append(((len(out) + 1) << 16) | code)
+        words += out
+
+    return [MAGIC, version, GENERATOR, len(ids) + 1, 0] + words
+
+
+if __name__ == '__main__':
+    for path in sys.argv[1:]:
+        w = assemble(open(path).read())
+        with open(path[:-len('.spvasm')] + '.spv', 'wb') as f:
+            f.write(struct.pack('<%dI' % len(w), *w))
+        print(path, len(w), 'words')
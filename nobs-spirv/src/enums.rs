@@ -13,18 +13,18 @@
 macro_rules! enumeration {
     ($(typedef enum $unused:ident { $($elem:ident = $value:expr,)+ } $name:ident;)+) => (
         $(
-            #[derive(Debug, Clone, PartialEq)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
             pub enum $name {
                 $($elem),+
             }
 
             impl $name {
-                pub fn from_num(num: u32) -> Result<$name, String> {
+                pub fn from_num(num: u32) -> Option<$name> {
                     match num {
                         $(
-                            $value => Ok($name::$elem),
+                            $value => Some($name::$elem),
                         )+
-                        _ => Err(format!("Parse spv: Unknown constant {}, {}", stringify!($name), num)),
+                        _ => None,
                     }
                 }
             }
@@ -33,6 +33,7 @@ macro_rules! enumeration {
 }
 
 // The code below is a copy-paste from `spirv-2.h`, with the `Spv` prefixes removed.
+// Values of later SPIR-V versions and of commonly used extensions are appended at the end of an enumeration.
 
 enumeration! {
     typedef enum SourceLanguage_ {
@@ -41,6 +42,7 @@ enumeration! {
         SourceLanguageGLSL = 2,
         SourceLanguageOpenCL_C = 3,
         SourceLanguageOpenCL_CPP = 4,
+        SourceLanguageHLSL = 5,
     } SourceLanguage;
 
     typedef enum ExecutionModel_ {
@@ -97,6 +99,15 @@ enumeration! {
         ExecutionModeOutputTriangleStrip = 29,
         ExecutionModeVecTypeHint = 30,
         ExecutionModeContractionOff = 31,
+        ExecutionModeInitializer = 33,
+        ExecutionModeFinalizer = 34,
+        ExecutionModeSubgroupSize = 35,
+        ExecutionModeSubgroupsPerWorkgroup = 36,
+        ExecutionModeSubgroupsPerWorkgroupId = 37,
+        ExecutionModeLocalSizeId = 38,
+        ExecutionModeLocalSizeHintId = 39,
+        ExecutionModePostDepthCoverage = 4446,
+        ExecutionModeStencilRefReplacingEXT = 5027,
     } ExecutionMode;
 
     typedef enum StorageClass_ {
@@ -112,6 +123,7 @@ enumeration! {
         StorageClassPushConstant = 9,
         StorageClassAtomicCounter = 10,
         StorageClassImage = 11,
+        StorageClassStorageBuffer = 12,
     } StorageClass;
 
     typedef enum Dim_ {
@@ -335,6 +347,13 @@ enumeration! {
         DecorationNoContraction = 42,
         DecorationInputAttachmentIndex = 43,
         DecorationAlignment = 44,
+        DecorationMaxByteOffset = 45,
+        DecorationAlignmentId = 46,
+        DecorationMaxByteOffsetId = 47,
+        DecorationNonUniform = 5300,
+        DecorationHlslCounterBufferGOOGLE = 5634,
+        DecorationUserSemantic = 5635,
+        DecorationUserTypeGOOGLE = 5636,
     } Decoration;
 
     typedef enum BuiltIn_ {
@@ -379,6 +398,17 @@ enumeration! {
         BuiltInSubgroupLocalInvocationId = 41,
         BuiltInVertexIndex = 42,
         BuiltInInstanceIndex = 43,
+        BuiltInSubgroupEqMask = 4416,
+        BuiltInSubgroupGeMask = 4417,
+        BuiltInSubgroupGtMask = 4418,
+        BuiltInSubgroupLeMask = 4419,
+        BuiltInSubgroupLtMask = 4420,
+        BuiltInBaseVertex = 4424,
+        BuiltInBaseInstance = 4425,
+        BuiltInDrawIndex = 4426,
+        BuiltInDeviceIndex = 4438,
+        BuiltInViewIndex = 4440,
+        BuiltInFragStencilRefEXT = 5014,
     } BuiltIn;
 
     typedef enum SelectionControlShift_ {
@@ -544,5 +574,40 @@ enumeration! {
         CapabilityStorageImageReadWithoutFormat = 55,
         CapabilityStorageImageWriteWithoutFormat = 56,
         CapabilityMultiViewport = 57,
+        CapabilitySubgroupDispatch = 58,
+        CapabilityNamedBarrier = 59,
+        CapabilityPipeStorage = 60,
+        CapabilityGroupNonUniform = 61,
+        CapabilityGroupNonUniformVote = 62,
+        CapabilityGroupNonUniformArithmetic = 63,
+        CapabilityGroupNonUniformBallot = 64,
+        CapabilityGroupNonUniformShuffle = 65,
+        CapabilityGroupNonUniformShuffleRelative = 66,
+        CapabilityGroupNonUniformClustered = 67,
+        CapabilityGroupNonUniformQuad = 68,
+        CapabilityDrawParameters = 4427,
+        CapabilityStorageBuffer16BitAccess = 4433,
+        CapabilityUniformAndStorageBuffer16BitAccess = 4434,
+        CapabilityStoragePushConstant16 = 4435,
+        CapabilityStorageInputOutput16 = 4436,
+        CapabilityDeviceGroup = 4437,
+        CapabilityMultiView = 4439,
+        CapabilityVariablePointersStorageBuffer = 4441,
+        CapabilityVariablePointers = 4442,
+        CapabilityStorageBuffer8BitAccess = 4448,
+        CapabilityUniformAndStorageBuffer8BitAccess = 4449,
+        CapabilityStoragePushConstant8 = 4450,
+        CapabilityShaderNonUniform = 5301,
+        CapabilityRuntimeDescriptorArray = 5302,
+        CapabilityInputAttachmentArrayDynamicIndexing = 5303,
+        CapabilityUniformTexelBufferArrayDynamicIndexing = 5304,
+        CapabilityStorageTexelBufferArrayDynamicIndexing = 5305,
+        CapabilityUniformBufferArrayNonUniformIndexing = 5306,
+        CapabilitySampledImageArrayNonUniformIndexing = 5307,
+        CapabilityStorageBufferArrayNonUniformIndexing = 5308,
+        CapabilityStorageImageArrayNonUniformIndexing = 5309,
+        CapabilityInputAttachmentArrayNonUniformIndexing = 5310,
+        CapabilityUniformTexelBufferArrayNonUniformIndexing = 5311,
+        CapabilityStorageTexelBufferArrayNonUniformIndexing = 5312,
     } Capability;
 }
@@ -0,0 +1,585 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::enums::*;
+
+/// Decoded SPIR-V instruction
+///
+/// Only instructions, that are needed for reflection are decoded, all other instructions are kept as `Unknown` with their opcode and operands.
+/// Instructions with enumerants, that are not known to this crate (e.g. capabilities or decorations of vendor extensions), are kept as `Unknown` as well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+  Unknown(u16, Vec<u32>),
+  Nop,
+  Source {
+    language: SourceLanguage,
+    version: u32,
+  },
+  Name {
+    target_id: u32,
+    name: String,
+  },
+  MemberName {
+    target_id: u32,
+    member: u32,
+    name: String,
+  },
+  Extension(String),
+  ExtInstImport {
+    result_id: u32,
+    name: String,
+  },
+  MemoryModel(AddressingModel, MemoryModel),
+  EntryPoint {
+    execution: ExecutionModel,
+    id: u32,
+    name: String,
+    interface: Vec<u32>,
+  },
+  /// `OpExecutionMode` or `OpExecutionModeId`, for the latter the operands are ids instead of literals
+  ExecutionMode {
+    target_id: u32,
+    mode: ExecutionMode,
+    optional_literals: Vec<u32>,
+  },
+  Capability(Capability),
+  TypeVoid {
+    result_id: u32,
+  },
+  TypeBool {
+    result_id: u32,
+  },
+  TypeInt {
+    result_id: u32,
+    width: u32,
+    signedness: bool,
+  },
+  TypeFloat {
+    result_id: u32,
+    width: u32,
+  },
+  TypeVector {
+    result_id: u32,
+    component_id: u32,
+    count: u32,
+  },
+  TypeMatrix {
+    result_id: u32,
+    column_type_id: u32,
+    column_count: u32,
+  },
+  TypeImage {
+    result_id: u32,
+    sampled_type_id: u32,
+    dim: Dim,
+    depth: Option<bool>,
+    arrayed: bool,
+    ms: bool,
+    sampled: Option<bool>,
+    format: ImageFormat,
+    access: Option<AccessQualifier>,
+  },
+  TypeSampler {
+    result_id: u32,
+  },
+  TypeSampledImage {
+    result_id: u32,
+    image_type_id: u32,
+  },
+  TypeArray {
+    result_id: u32,
+    type_id: u32,
+    length_id: u32,
+  },
+  TypeRuntimeArray {
+    result_id: u32,
+    type_id: u32,
+  },
+  TypeStruct {
+    result_id: u32,
+    member_types: Vec<u32>,
+  },
+  TypeOpaque {
+    result_id: u32,
+    name: String,
+  },
+  TypePointer {
+    result_id: u32,
+    storage_class: StorageClass,
+    type_id: u32,
+  },
+  TypeFunction {
+    result_id: u32,
+    return_type_id: u32,
+    parameter_types: Vec<u32>,
+  },
+  ConstantTrue {
+    result_type_id: u32,
+    result_id: u32,
+  },
+  ConstantFalse {
+    result_type_id: u32,
+    result_id: u32,
+  },
+  Constant {
+    result_type_id: u32,
+    result_id: u32,
+    data: Vec<u32>,
+  },
+  ConstantComposite {
+    result_type_id: u32,
+    result_id: u32,
+    constituents: Vec<u32>,
+  },
+  ConstantNull {
+    result_type_id: u32,
+    result_id: u32,
+  },
+  SpecConstantTrue {
+    result_type_id: u32,
+    result_id: u32,
+  },
+  SpecConstantFalse {
+    result_type_id: u32,
+    result_id: u32,
+  },
+  SpecConstant {
+    result_type_id: u32,
+    result_id: u32,
+    data: Vec<u32>,
+  },
+  SpecConstantComposite {
+    result_type_id: u32,
+    result_id: u32,
+    data: Vec<u32>,
+  },
+  Function {
+    result_type_id: u32,
+    result_id: u32,
+    control: u32,
+    function_type_id: u32,
+  },
+  FunctionEnd,
+  Variable {
+    result_type_id: u32,
+    result_id: u32,
+    storage_class: StorageClass,
+    initializer: Option<u32>,
+  },
+  Decorate {
+    target_id: u32,
+    decoration: Decoration,
+    params: Vec<u32>,
+  },
+  MemberDecorate {
+    target_id: u32,
+    member: u32,
+    decoration: Decoration,
+    params: Vec<u32>,
+  },
+  DecorationGroup {
+    result_id: u32,
+  },
+  GroupDecorate {
+    decoration_group: u32,
+    targets: Vec<u32>,
+  },
+  GroupMemberDecorate {
+    decoration_group: u32,
+    targets: Vec<(u32, u32)>,
+  },
+  /// `OpDecorateId`, the parameters are ids instead of literals
+  DecorateId {
+    target_id: u32,
+    decoration: Decoration,
+    params: Vec<u32>,
+  },
+  /// `OpDecorateString` (or `OpDecorateStringGOOGLE`)
+  DecorateString {
+    target_id: u32,
+    decoration: Decoration,
+    value: String,
+  },
+  /// `OpMemberDecorateString` (or `OpMemberDecorateStringGOOGLE`)
+  MemberDecorateString {
+    target_id: u32,
+    member: u32,
+    decoration: Decoration,
+    value: String,
+  },
+  Label {
+    result_id: u32,
+  },
+  Branch {
+    result_id: u32,
+  },
+  Kill,
+  Return,
+}
+
+/// Unwraps an enumerant or returns the instruction as `Unknown`
+macro_rules! enumerant {
+  ($ty:ident, $num:expr, $opcode:expr, $operands:expr) => {
+    match $ty::from_num($num) {
+      Some(e) => e,
+      None => return Some(Instruction::Unknown($opcode, $operands.to_owned())),
+    }
+  };
+}
+
+impl Instruction {
+  /// Decodes an instruction from its opcode and operands (without the leading word with word count and opcode)
+  ///
+  /// # Returns
+  /// `None` if the instruction does not have enough operands for its opcode.
+  pub fn decode(opcode: u16, operands: &[u32]) -> Option<Instruction> {
+    let o = |i: usize| operands.get(i).cloned();
+    let rest = |i: usize| operands.get(i..);
+
+    Some(match opcode {
+      0 => Instruction::Nop,
+      3 => Instruction::Source {
+        language: enumerant!(SourceLanguage, o(0)?, opcode, operands),
+        version: o(1)?,
+      },
+      5 => Instruction::Name {
+        target_id: o(0)?,
+        name: parse_string(rest(1)?).0,
+      },
+      6 => Instruction::MemberName {
+        target_id: o(0)?,
+        member: o(1)?,
+        name: parse_string(rest(2)?).0,
+      },
+      10 => Instruction::Extension(parse_string(operands).0),
+      11 => Instruction::ExtInstImport {
+        result_id: o(0)?,
+        name: parse_string(rest(1)?).0,
+      },
+      14 => Instruction::MemoryModel(
+        enumerant!(AddressingModel, o(0)?, opcode, operands),
+        enumerant!(MemoryModel, o(1)?, opcode, operands),
+      ),
+      15 => {
+        let (name, interface) = parse_string(rest(2)?);
+        Instruction::EntryPoint {
+          execution: enumerant!(ExecutionModel, o(0)?, opcode, operands),
+          id: o(1)?,
+          name,
+          interface: interface.to_owned(),
+        }
+      }
+      16 | 331 => Instruction::ExecutionMode {
+        target_id: o(0)?,
+        mode: enumerant!(ExecutionMode, o(1)?, opcode, operands),
+        optional_literals: rest(2)?.to_owned(),
+      },
+      17 => Instruction::Capability(enumerant!(Capability, o(0)?, opcode, operands)),
+      19 => Instruction::TypeVoid { result_id: o(0)? },
+      20 => Instruction::TypeBool { result_id: o(0)? },
+      21 => Instruction::TypeInt {
+        result_id: o(0)?,
+        width: o(1)?,
+        signedness: o(2)? != 0,
+      },
+      22 => Instruction::TypeFloat {
+        result_id: o(0)?,
+        width: o(1)?,
+      },
+      23 => Instruction::TypeVector {
+        result_id: o(0)?,
+        component_id: o(1)?,
+        count: o(2)?,
+      },
+      24 => Instruction::TypeMatrix {
+        result_id: o(0)?,
+        column_type_id: o(1)?,
+        column_count: o(2)?,
+      },
+      25 => Instruction::TypeImage {
+        result_id: o(0)?,
+        sampled_type_id: o(1)?,
+        dim: enumerant!(Dim, o(2)?, opcode, operands),
+        depth: match o(3)? {
+          0 => Some(false),
+          1 => Some(true),
+          _ => None,
+        },
+        arrayed: o(4)? != 0,
+        ms: o(5)? != 0,
+        sampled: match o(6)? {
+          1 => Some(true),
+          2 => Some(false),
+          _ => None,
+        },
+        format: enumerant!(ImageFormat, o(7)?, opcode, operands),
+        access: match o(8) {
+          Some(a) => Some(enumerant!(AccessQualifier, a, opcode, operands)),
+          None => None,
+        },
+      },
+      26 => Instruction::TypeSampler { result_id: o(0)? },
+      27 => Instruction::TypeSampledImage {
+        result_id: o(0)?,
+        image_type_id: o(1)?,
+      },
+      28 => Instruction::TypeArray {
+        result_id: o(0)?,
+        type_id: o(1)?,
+        length_id: o(2)?,
+      },
+      29 => Instruction::TypeRuntimeArray {
+        result_id: o(0)?,
+        type_id: o(1)?,
+      },
+      30 => Instruction::TypeStruct {
+        result_id: o(0)?,
+        member_types: rest(1)?.to_owned(),
+      },
+      31 => Instruction::TypeOpaque {
+        result_id: o(0)?,
+        name: parse_string(rest(1)?).0,
+      },
+      32 => Instruction::TypePointer {
+        result_id: o(0)?,
+        storage_class: enumerant!(StorageClass, o(1)?, opcode, operands),
+        type_id: o(2)?,
+      },
+      33 => Instruction::TypeFunction {
+        result_id: o(0)?,
+        return_type_id: o(1)?,
+        parameter_types: rest(2)?.to_owned(),
+      },
+      41 => Instruction::ConstantTrue {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+      },
+      42 => Instruction::ConstantFalse {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+      },
+      43 => Instruction::Constant {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        data: rest(2)?.to_owned(),
+      },
+      44 => Instruction::ConstantComposite {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        constituents: rest(2)?.to_owned(),
+      },
+      46 => Instruction::ConstantNull {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+      },
+      48 => Instruction::SpecConstantTrue {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+      },
+      49 => Instruction::SpecConstantFalse {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+      },
+      50 => Instruction::SpecConstant {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        data: rest(2)?.to_owned(),
+      },
+      51 => Instruction::SpecConstantComposite {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        data: rest(2)?.to_owned(),
+      },
+      54 => Instruction::Function {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        control: o(2)?,
+        function_type_id: o(3)?,
+      },
+      56 => Instruction::FunctionEnd,
+      59 => Instruction::Variable {
+        result_type_id: o(0)?,
+        result_id: o(1)?,
+        storage_class: enumerant!(StorageClass, o(2)?, opcode, operands),
+        initializer: o(3),
+      },
+      71 => Instruction::Decorate {
+        target_id: o(0)?,
+        decoration: enumerant!(Decoration, o(1)?, opcode, operands),
+        params: rest(2)?.to_owned(),
+      },
+      72 => Instruction::MemberDecorate {
+        target_id: o(0)?,
+        member: o(1)?,
+        decoration: enumerant!(Decoration, o(2)?, opcode, operands),
+        params: rest(3)?.to_owned(),
+      },
+      73 => Instruction::DecorationGroup { result_id: o(0)? },
+      74 => Instruction::GroupDecorate {
+        decoration_group: o(0)?,
+        targets: rest(1)?.to_owned(),
+      },
+      75 => Instruction::GroupMemberDecorate {
+        decoration_group: o(0)?,
+        targets: rest(1)?.chunks(2).filter(|x| x.len() == 2).map(|x| (x[0], x[1])).collect(),
+      },
+      248 => Instruction::Label { result_id: o(0)? },
+      249 => Instruction::Branch { result_id: o(0)? },
+      252 => Instruction::Kill,
+      253 => Instruction::Return,
+      332 => Instruction::DecorateId {
+        target_id: o(0)?,
+        decoration: enumerant!(Decoration, o(1)?, opcode, operands),
+        params: rest(2)?.to_owned(),
+      },
+      5632 => Instruction::DecorateString {
+        target_id: o(0)?,
+        decoration: enumerant!(Decoration, o(1)?, opcode, operands),
+        value: parse_string(rest(2)?).0,
+      },
+      5633 => Instruction::MemberDecorateString {
+        target_id: o(0)?,
+        member: o(1)?,
+        decoration: enumerant!(Decoration, o(2)?, opcode, operands),
+        value: parse_string(rest(3)?).0,
+      },
+      _ => Instruction::Unknown(opcode, operands.to_owned()),
+    })
+  }
+
+  /// Get the id, that is defined by this instruction
+  pub fn result_id(&self) -> Option<u32> {
+    match self {
+      Instruction::ExtInstImport { result_id, .. }
+      | Instruction::TypeVoid { result_id }
+      | Instruction::TypeBool { result_id }
+      | Instruction::TypeInt { result_id, .. }
+      | Instruction::TypeFloat { result_id, .. }
+      | Instruction::TypeVector { result_id, .. }
+      | Instruction::TypeMatrix { result_id, .. }
+      | Instruction::TypeImage { result_id, .. }
+      | Instruction::TypeSampler { result_id }
+      | Instruction::TypeSampledImage { result_id, .. }
+      | Instruction::TypeArray { result_id, .. }
+      | Instruction::TypeRuntimeArray { result_id, .. }
+      | Instruction::TypeStruct { result_id, .. }
+      | Instruction::TypeOpaque { result_id, .. }
+      | Instruction::TypePointer { result_id, .. }
+      | Instruction::TypeFunction { result_id, .. }
+      | Instruction::ConstantTrue { result_id, .. }
+      | Instruction::ConstantFalse { result_id, .. }
+      | Instruction::Constant { result_id, .. }
+      | Instruction::ConstantComposite { result_id, .. }
+      | Instruction::ConstantNull { result_id, .. }
+      | Instruction::SpecConstantTrue { result_id, .. }
+      | Instruction::SpecConstantFalse { result_id, .. }
+      | Instruction::SpecConstant { result_id, .. }
+      | Instruction::SpecConstantComposite { result_id, .. }
+      | Instruction::Function { result_id, .. }
+      | Instruction::Variable { result_id, .. }
+      | Instruction::DecorationGroup { result_id }
+      | Instruction::Label { result_id } => Some(*result_id),
+      _ => None,
+    }
+  }
+}
+
+/// Parses a nul terminated UTF-8 string from a list of words
+///
+/// Invalid UTF-8 sequences are replaced, strings without nul terminator take up all words.
+///
+/// # Returns
+/// The string and the words following it.
+pub fn parse_string(data: &[u32]) -> (String, &[u32]) {
+  let bytes = data
+    .iter()
+    .flat_map(|&n| n.to_le_bytes().to_vec().into_iter())
+    .take_while(|&b| b != 0)
+    .collect::<Vec<u8>>();
+
+  let r = usize::min(1 + bytes.len() / 4, data.len());
+  let s = String::from_utf8_lossy(&bytes).into_owned();
+
+  (s, &data[r..])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn words(s: &str) -> Vec<u32> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+    bytes.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+  }
+
+  #[test]
+  fn string() {
+    let mut w = words("main");
+    assert_eq!(w.len(), 2);
+    w.push(42);
+    assert_eq!(parse_string(&w), ("main".to_string(), &[42][..]));
+    assert_eq!(parse_string(&words("abc")), ("abc".to_string(), &[][..]));
+    assert_eq!(parse_string(&[0x64636261]), ("abcd".to_string(), &[][..]));
+    assert_eq!(parse_string(&[]), (String::new(), &[][..]));
+  }
+
+  #[test]
+  fn too_few_operands() {
+    assert_eq!(Instruction::decode(21, &[1, 32]), None);
+    assert_eq!(Instruction::decode(25, &[1, 2, 1, 0, 0, 0, 1]), None);
+    assert_eq!(Instruction::decode(253, &[]), Some(Instruction::Return));
+  }
+
+  #[test]
+  fn unknown_enumerants() {
+    assert_eq!(Instruction::decode(17, &[9999]), Some(Instruction::Unknown(17, vec![9999])));
+    assert_eq!(
+      Instruction::decode(71, &[4, 9999, 1]),
+      Some(Instruction::Unknown(71, vec![4, 9999, 1]))
+    );
+    assert_eq!(
+      Instruction::decode(17, &[5302]),
+      Some(Instruction::Capability(Capability::CapabilityRuntimeDescriptorArray))
+    );
+    assert_eq!(Instruction::decode(1000, &[1, 2]), Some(Instruction::Unknown(1000, vec![1, 2])));
+  }
+
+  #[test]
+  fn image() {
+    assert_eq!(
+      Instruction::decode(25, &[3, 2, 1, 2, 1, 0, 2, 4, 1]),
+      Some(Instruction::TypeImage {
+        result_id: 3,
+        sampled_type_id: 2,
+        dim: Dim::Dim2D,
+        depth: None,
+        arrayed: true,
+        ms: false,
+        sampled: Some(false),
+        format: ImageFormat::ImageFormatRgba8,
+        access: Some(AccessQualifier::AccessQualifierWriteOnly),
+      })
+    );
+  }
+
+  #[test]
+  fn decorate_string() {
+    let mut ops = vec![7, 5635];
+    ops.extend(words("TEXCOORD0"));
+    assert_eq!(
+      Instruction::decode(5632, &ops),
+      Some(Instruction::DecorateString {
+        target_id: 7,
+        decoration: Decoration::DecorationUserSemantic,
+        value: "TEXCOORD0".to_string(),
+      })
+    );
+  }
+}
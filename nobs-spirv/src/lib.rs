@@ -0,0 +1,74 @@
+//! SPIR-V parser and reflection
+//!
+//! Parses SPIR-V modules into a list of decoded [instructions](enum.Instruction.html) and reflects on them
+//! without any dependency on a shader compiler or vulkan, so that it can be used in build scripts, proc macros and at runtime.
+//!
+//! 1. [Module](struct.Module.html) parses a module from words or bytes (of either byte order) and answers queries for names,
+//!    [decorations](struct.Decorations.html) (including decoration groups), capabilities and extensions
+//! 2. [Module::resolve_type](struct.Module.html#method.resolve_type) resolves a type id into a [Type](enum.Type.html) tree
+//!    of scalars, vectors, matrices, arrays, runtime arrays, structs with their member decorations, images and samplers
+//! 3. [Module::entry_points](struct.Module.html#method.entry_points) lists the entry points with their execution modes
+//! 4. [Module::descriptor_bindings](struct.Module.html#method.descriptor_bindings) lists the resource variables with their set, binding and
+//!    [descriptor type](enum.DescriptorKind.html)
+//! 5. Interface variables, push constant blocks and specialization constants
+//!
+//! ## Example
+//! ```rust,ignore
+//! let module = nobs_spirv::Module::from_bytes(&std::fs::read("shader.spv")?)?;
+//!
+//! let main = module.entry_point("main").unwrap();
+//! println!("{:?} {:?}", main.execution_model, main.local_size());
+//!
+//! for b in module.descriptor_bindings()? {
+//!   if let nobs_spirv::Type::Struct(s) = b.ty.element() {
+//!     for m in s.members.iter() {
+//!       println!("{:?} at offset {:?}: {:?}", m.name, m.offset(), m.ty);
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Enumerants, that are not known to this crate (e.g. capabilities or decorations of vendor extensions), do not fail parsing,
+//! the instructions using them are kept as [Instruction::Unknown](enum.Instruction.html#variant.Unknown).
+pub mod enums;
+mod instruction;
+mod module;
+mod reflect;
+mod types;
+
+pub use instruction::parse_string;
+pub use instruction::Instruction;
+pub use module::Decorations;
+pub use module::Module;
+pub use module::MAGIC;
+pub use reflect::DescriptorBinding;
+pub use reflect::DescriptorKind;
+pub use reflect::EntryPoint;
+pub use reflect::SpecConstant;
+pub use reflect::Variable;
+pub use types::Image;
+pub use types::Member;
+pub use types::Struct;
+pub use types::Type;
+
+/// Errors of parsing and reflection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+  /// Indicates, that the data does not start with a SPIR-V header
+  InvalidHeader,
+  /// Indicates, that an instruction is truncated or does not have enough operands.
+  /// The wrapped value is the index of the instruction's first word.
+  InvalidInstruction(usize),
+  /// Indicates, that an id is not a type, or a type could not be resolved.
+  /// The wrapped value is the id.
+  UnknownType(u32),
+  /// Indicates, that the length of an array is neither a constant nor a specialization constant.
+  /// The wrapped value is the id of the length.
+  UnsupportedArrayLength(u32),
+  /// Indicates, that the type of a variable is not a pointer.
+  /// The wrapped value is the id of the type.
+  NotAPointer(u32),
+  /// Indicates, that the descriptor type of a variable with a binding could not be determined.
+  /// The wrapped value is the id of the variable.
+  UnsupportedDescriptor(u32),
+}
@@ -0,0 +1,496 @@
+use crate::enums::*;
+use crate::Error;
+use crate::Instruction;
+
+/// Magic number of a SPIR-V module
+pub const MAGIC: u32 = 0x0723_0203;
+
+/// Parsed SPIR-V module
+///
+/// Holds the header and the decoded instructions of a module.
+/// The reflection of types, entry points and interface variables is built on top of the instructions,
+/// see [resolve_type](struct.Module.html#method.resolve_type), [entry_points](struct.Module.html#method.entry_points)
+/// and [descriptor_bindings](struct.Module.html#method.descriptor_bindings).
+///
+/// ## Example
+/// ```rust,ignore
+/// let module = nobs_spirv::Module::from_bytes(include_bytes!("shader.spv"))?;
+/// for b in module.descriptor_bindings()? {
+///   println!("set {} binding {}: {:?} {:?}", b.set, b.binding, b.kind, b.name);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Module {
+  /// Major and minor version of the SPIR-V specification
+  pub version: (u8, u8),
+  /// Magic number of the tool, that generated the module
+  pub generator: u32,
+  /// All ids of the module are smaller than the bound
+  pub bound: u32,
+  pub instructions: Vec<Instruction>,
+}
+
+impl Module {
+  /// Parses a SPIR-V module from a list of words
+  ///
+  /// Fails with `Error::InvalidHeader`, if the words do not start with a SPIR-V header in native byte order,
+  /// and with `Error::InvalidInstruction`, if an instruction is truncated or has not enough operands.
+  /// The wrapped value is the index of the first word of the instruction.
+  pub fn from_words(words: &[u32]) -> Result<Module, Error> {
+    if words.len() < 5 || words[0] != MAGIC {
+      Err(Error::InvalidHeader)?
+    }
+
+    let version = (((words[1] & 0x00ff_0000) >> 16) as u8, ((words[1] & 0x0000_ff00) >> 8) as u8);
+
+    let mut instructions = Vec::new();
+    let mut i = 5;
+    while i < words.len() {
+      let word_count = (words[i] >> 16) as usize;
+      let opcode = (words[i] & 0xffff) as u16;
+      if word_count == 0 || i + word_count > words.len() {
+        Err(Error::InvalidInstruction(i))?
+      }
+
+      instructions.push(Instruction::decode(opcode, &words[i + 1..i + word_count]).ok_or(Error::InvalidInstruction(i))?);
+      i += word_count;
+    }
+
+    Ok(Module {
+      version,
+      generator: words[2],
+      bound: words[3],
+      instructions,
+    })
+  }
+
+  /// Parses a SPIR-V module from bytes, e.g. a `.spv` file
+  ///
+  /// The byte order is detected from the magic number.
+  /// Fails with `Error::InvalidHeader`, if the number of bytes is not a multiple of four or there is no SPIR-V header.
+  // `is_multiple_of` would raise the minimum rust version to 1.87
+  #[allow(clippy::manual_is_multiple_of)]
+  pub fn from_bytes(bytes: &[u8]) -> Result<Module, Error> {
+    if bytes.len() < 4 || bytes.len() % 4 != 0 {
+      Err(Error::InvalidHeader)?
+    }
+
+    let words = bytes.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]);
+    let words = match u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) {
+      MAGIC => words.map(u32::from_le_bytes).collect::<Vec<_>>(),
+      _ => words.map(u32::from_be_bytes).collect::<Vec<_>>(),
+    };
+    Self::from_words(&words)
+  }
+
+  /// Get the debug name of an id
+  ///
+  /// Returns `None` if there is no `OpName` for the id or the name is empty.
+  pub fn name(&self, id: u32) -> Option<&str> {
+    self.instructions.iter().find_map(|i| match i {
+      Instruction::Name { target_id, name } if *target_id == id && !name.is_empty() => Some(name.as_str()),
+      _ => None,
+    })
+  }
+
+  /// Get the debug name of a struct member
+  ///
+  /// Returns `None` if there is no `OpMemberName` for the member or the name is empty.
+  pub fn member_name(&self, id: u32, member: u32) -> Option<&str> {
+    self.instructions.iter().find_map(|i| match i {
+      Instruction::MemberName {
+        target_id,
+        member: m,
+        name,
+      } if *target_id == id && *m == member && !name.is_empty() => Some(name.as_str()),
+      _ => None,
+    })
+  }
+
+  /// Get all decorations of an id, including decorations applied through decoration groups
+  pub fn decorations(&self, id: u32) -> Decorations {
+    let groups = self.groups(|targets| targets.contains(&id));
+    let mut d = Decorations::default();
+    for i in self.instructions.iter() {
+      match i {
+        Instruction::Decorate {
+          target_id,
+          decoration,
+          params,
+        }
+        | Instruction::DecorateId {
+          target_id,
+          decoration,
+          params,
+        } if *target_id == id || groups.contains(target_id) => d.push(*decoration, params.clone(), None),
+        Instruction::DecorateString {
+          target_id,
+          decoration,
+          value,
+        } if *target_id == id || groups.contains(target_id) => d.push(*decoration, Vec::new(), Some(value.clone())),
+        _ => (),
+      }
+    }
+    d
+  }
+
+  /// Get all decorations of a struct member, including decorations applied through decoration groups
+  pub fn member_decorations(&self, id: u32, member: u32) -> Decorations {
+    let mut d = Decorations::default();
+    for i in self.instructions.iter() {
+      match i {
+        Instruction::MemberDecorate {
+          target_id,
+          member: m,
+          decoration,
+          params,
+        } if *target_id == id && *m == member => d.push(*decoration, params.clone(), None),
+        Instruction::MemberDecorateString {
+          target_id,
+          member: m,
+          decoration,
+          value,
+        } if *target_id == id && *m == member => d.push(*decoration, Vec::new(), Some(value.clone())),
+        Instruction::GroupMemberDecorate { decoration_group, targets } if targets.contains(&(id, member)) => {
+          for (decoration, params, value) in self.decorations(*decoration_group).iter() {
+            d.push(decoration, params.to_vec(), value.map(|v| v.to_string()));
+          }
+        }
+        _ => (),
+      }
+    }
+    d
+  }
+
+  /// Get the ids of the decoration groups, whose `OpGroupDecorate` targets satisfy `f`
+  fn groups<F: Fn(&[u32]) -> bool>(&self, f: F) -> Vec<u32> {
+    self
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::GroupDecorate { decoration_group, targets } if f(targets) => Some(*decoration_group),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Get the capabilities declared by the module
+  ///
+  /// Capabilities of extensions, that are not known to this crate, are not included.
+  pub fn capabilities(&self) -> Vec<Capability> {
+    self
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::Capability(c) => Some(*c),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Get the extensions declared by the module
+  pub fn extensions(&self) -> Vec<&str> {
+    self
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::Extension(e) => Some(e.as_str()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Get the source language and its version
+  pub fn source(&self) -> Option<(SourceLanguage, u32)> {
+    self.instructions.iter().find_map(|i| match i {
+      Instruction::Source { language, version } => Some((*language, *version)),
+      _ => None,
+    })
+  }
+
+  /// Get the instruction, that defines an id
+  pub fn definition(&self, id: u32) -> Option<&Instruction> {
+    self.instructions.iter().find(|i| i.result_id() == Some(id))
+  }
+
+  /// Get the value of a scalar integer constant
+  ///
+  /// Returns `None`, if the id is not an `OpConstant`, e.g. if it is a specialization constant.
+  pub fn constant_u32(&self, id: u32) -> Option<u32> {
+    match self.definition(id) {
+      Some(Instruction::Constant { data, .. }) => data.first().cloned(),
+      _ => None,
+    }
+  }
+}
+
+/// Decorations of an id or struct member
+///
+/// Every decoration is stored with its literal parameters, or with its string for `OpDecorateString`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Decorations {
+  decorations: Vec<(Decoration, Vec<u32>, Option<String>)>,
+}
+
+impl Decorations {
+  fn push(&mut self, decoration: Decoration, params: Vec<u32>, value: Option<String>) {
+    self.decorations.push((decoration, params, value));
+  }
+
+  /// Checks if the decoration is present
+  pub fn contains(&self, decoration: Decoration) -> bool {
+    self.decorations.iter().any(|(d, _, _)| *d == decoration)
+  }
+
+  /// Get the parameters of a decoration
+  pub fn get(&self, decoration: Decoration) -> Option<&[u32]> {
+    self
+      .decorations
+      .iter()
+      .find(|(d, _, _)| *d == decoration)
+      .map(|(_, p, _)| p.as_slice())
+  }
+
+  /// Get the string of a decoration, that was applied with `OpDecorateString`
+  pub fn get_string(&self, decoration: Decoration) -> Option<&str> {
+    self
+      .decorations
+      .iter()
+      .find(|(d, _, _)| *d == decoration)
+      .and_then(|(_, _, s)| s.as_deref())
+  }
+
+  /// Get the first parameter of a decoration
+  pub fn get_u32(&self, decoration: Decoration) -> Option<u32> {
+    self.get(decoration).and_then(|p| p.first().cloned())
+  }
+
+  /// Iterates over all decorations with their parameters and string
+  pub fn iter(&self) -> impl Iterator<Item = (Decoration, &[u32], Option<&str>)> {
+    self.decorations.iter().map(|(d, p, s)| (*d, p.as_slice(), s.as_deref()))
+  }
+
+  /// Get the number of decorations
+  pub fn len(&self) -> usize {
+    self.decorations.len()
+  }
+
+  /// Checks if there are no decorations
+  pub fn is_empty(&self) -> bool {
+    self.decorations.is_empty()
+  }
+
+  pub fn binding(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationBinding)
+  }
+
+  pub fn set(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationDescriptorSet)
+  }
+
+  pub fn location(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationLocation)
+  }
+
+  pub fn offset(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationOffset)
+  }
+
+  pub fn array_stride(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationArrayStride)
+  }
+
+  pub fn matrix_stride(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationMatrixStride)
+  }
+
+  pub fn spec_id(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationSpecId)
+  }
+
+  pub fn input_attachment_index(&self) -> Option<u32> {
+    self.get_u32(Decoration::DecorationInputAttachmentIndex)
+  }
+
+  pub fn builtin(&self) -> Option<BuiltIn> {
+    self.get_u32(Decoration::DecorationBuiltIn).and_then(BuiltIn::from_num)
+  }
+
+  pub fn row_major(&self) -> bool {
+    self.contains(Decoration::DecorationRowMajor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MULTI: &[u8] = include_bytes!("../fixtures/multi.spv");
+  const HLSL: &[u8] = include_bytes!("../fixtures/hlsl.spv");
+  const COMPUTE: &[u8] = include_bytes!("../fixtures/compute.spv");
+
+  fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+  }
+
+  fn id(m: &Module, name: &str) -> u32 {
+    m.instructions
+      .iter()
+      .find_map(|i| match i {
+        Instruction::Name { target_id, name: n } if n == name => Some(*target_id),
+        _ => None,
+      })
+      .unwrap()
+  }
+
+  #[test]
+  fn header() {
+    let m = Module::from_bytes(MULTI).unwrap();
+    assert_eq!(m.version, (1, 0));
+    assert_eq!(m.generator, 0);
+    assert_eq!(m.bound, 23);
+    assert!(m.instructions.iter().all(|i| i.result_id().map(|id| id < m.bound).unwrap_or(true)));
+
+    let m = Module::from_bytes(HLSL).unwrap();
+    assert_eq!(m.version, (1, 3));
+    assert_eq!(m.source(), Some((SourceLanguage::SourceLanguageHLSL, 600)));
+  }
+
+  #[test]
+  fn words_and_bytes() {
+    let w = Module::from_words(&words(COMPUTE)).unwrap();
+    let b = Module::from_bytes(COMPUTE).unwrap();
+    assert_eq!(w.instructions, b.instructions);
+    assert_eq!(w.bound, b.bound);
+  }
+
+  #[test]
+  fn big_endian() {
+    let be = words(COMPUTE).iter().flat_map(|w| w.to_be_bytes().to_vec()).collect::<Vec<_>>();
+    let m = Module::from_bytes(&be).unwrap();
+    assert_eq!(m.instructions, Module::from_bytes(COMPUTE).unwrap().instructions);
+    assert_eq!(m.name(id(&m, "main")), Some("main"));
+  }
+
+  #[test]
+  fn invalid_header() {
+    assert_eq!(Module::from_bytes(&[]).unwrap_err(), Error::InvalidHeader);
+    assert_eq!(Module::from_bytes(&COMPUTE[..18]).unwrap_err(), Error::InvalidHeader);
+    assert_eq!(Module::from_words(&[MAGIC, 0x0001_0000, 0, 1]).unwrap_err(), Error::InvalidHeader);
+    assert_eq!(
+      Module::from_words(&[0x0203_0723, 0x0001_0000, 0, 1, 0]).unwrap_err(),
+      Error::InvalidHeader
+    );
+    assert!(Module::from_words(&[MAGIC, 0x0001_0000, 0, 1, 0]).unwrap().instructions.is_empty());
+  }
+
+  #[test]
+  fn truncated() {
+    let w = words(COMPUTE);
+    // the first instruction is OpCapability with two words
+    assert_eq!(Module::from_words(&w[..6]).unwrap_err(), Error::InvalidInstruction(5));
+    assert!(Module::from_words(&w[..7]).is_ok());
+
+    // zero word count
+    let mut z = w.clone();
+    z[5] = 17;
+    assert_eq!(Module::from_words(&z).unwrap_err(), Error::InvalidInstruction(5));
+
+    // OpTypeInt with a word count, that is too small for its operands
+    let mut z = w[..7].to_vec();
+    z.extend(&[(3 << 16) | 21, 1, 32]);
+    assert_eq!(Module::from_words(&z).unwrap_err(), Error::InvalidInstruction(7));
+  }
+
+  #[test]
+  fn names() {
+    let m = Module::from_bytes(HLSL).unwrap();
+    let light = id(&m, "Light");
+    assert_eq!(m.name(light), Some("Light"));
+    assert_eq!(m.member_name(light, 1), Some("intensity"));
+    assert_eq!(m.member_name(light, 2), None);
+    // unnamed members
+    assert_eq!(m.member_name(id(&m, "type.RWStructuredBuffer.uint"), 0), None);
+    assert_eq!(m.member_name(id(&m, "type.StructuredBuffer.Light"), 0), None);
+    assert_eq!(m.name(9999), None);
+  }
+
+  #[test]
+  fn decorations() {
+    let m = Module::from_bytes(COMPUTE).unwrap();
+    let d = m.decorations(id(&m, "data"));
+    assert_eq!(d.len(), 2);
+    assert_eq!(d.set(), Some(1));
+    assert_eq!(d.binding(), Some(2));
+    assert_eq!(d.location(), None);
+    assert!(m.decorations(id(&m, "Data")).contains(Decoration::DecorationBufferBlock));
+    assert_eq!(m.member_decorations(id(&m, "Data"), 1).offset(), Some(4));
+    assert!(m.decorations(id(&m, "main")).is_empty());
+
+    let d = m.decorations(id(&m, "count"));
+    assert_eq!(d.iter().collect::<Vec<_>>(), vec![(Decoration::DecorationSpecId, &[0][..], None)]);
+  }
+
+  #[test]
+  fn decoration_groups() {
+    let m = Module::from_bytes(MULTI).unwrap();
+    for (name, binding) in [("a", 0), ("b", 1)].iter() {
+      let d = m.decorations(id(&m, name));
+      assert_eq!(d.binding(), Some(*binding));
+      assert_eq!(d.set(), Some(2));
+      assert!(d.contains(Decoration::DecorationRestrict));
+    }
+    for name in ["A", "B"].iter() {
+      assert_eq!(m.member_decorations(id(&m, name), 0).offset(), Some(0));
+      assert_eq!(m.member_decorations(id(&m, name), 1).offset(), None);
+    }
+  }
+
+  #[test]
+  fn string_decorations() {
+    let m = Module::from_bytes(HLSL).unwrap();
+    let d = m.decorations(id(&m, "in.var.TEXCOORD0"));
+    assert_eq!(d.get_string(Decoration::DecorationUserSemantic), Some("TEXCOORD0"));
+    assert_eq!(d.location(), Some(0));
+    let d = m.decorations(id(&m, "lights"));
+    assert_eq!(d.get_string(Decoration::DecorationUserTypeGOOGLE), Some("structuredbuffer:<Light>"));
+    assert_eq!(d.get_string(Decoration::DecorationBinding), None);
+
+    let counters = m.decorations(id(&m, "counters"));
+    assert_eq!(
+      counters.get(Decoration::DecorationHlslCounterBufferGOOGLE),
+      Some(&[id(&m, "counter.var.counters")][..])
+    );
+  }
+
+  #[test]
+  fn capabilities_and_extensions() {
+    let m = Module::from_bytes(MULTI).unwrap();
+    assert_eq!(m.capabilities(), vec![Capability::CapabilityShader, Capability::CapabilityFloat64]);
+    assert!(m.extensions().is_empty());
+    assert_eq!(m.source(), None);
+
+    let m = Module::from_bytes(include_bytes!("../fixtures/images.spv")).unwrap();
+    assert!(m.capabilities().contains(&Capability::CapabilityRuntimeDescriptorArray));
+    assert_eq!(m.extensions(), vec!["SPV_EXT_descriptor_indexing"]);
+
+    let m = Module::from_bytes(HLSL).unwrap();
+    assert_eq!(m.extensions(), vec!["SPV_GOOGLE_hlsl_functionality1", "SPV_GOOGLE_user_type"]);
+  }
+
+  #[test]
+  fn constants() {
+    let m = Module::from_bytes(COMPUTE).unwrap();
+    let c = m
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::Constant { result_id, .. } => m.constant_u32(*result_id),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(c, vec![64, 2, 1]);
+    // specialization constants are not constants
+    assert_eq!(m.constant_u32(id(&m, "count")), None);
+  }
+}
@@ -0,0 +1,637 @@
+use crate::enums::*;
+use crate::Decorations;
+use crate::Error;
+use crate::Instruction;
+use crate::Module;
+use crate::Type;
+
+/// Entry point of a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+  /// Id of the entry point's function
+  pub id: u32,
+  pub name: String,
+  pub execution_model: ExecutionModel,
+  /// Ids of the input and output variables (and since SPIR-V 1.4 all global variables) used by the entry point
+  pub interface: Vec<u32>,
+  /// Execution modes with their literal operands, or id operands for modes like `LocalSizeId`
+  pub execution_modes: Vec<(ExecutionMode, Vec<u32>)>,
+}
+
+impl EntryPoint {
+  /// Get the operands of an execution mode
+  pub fn execution_mode(&self, mode: ExecutionMode) -> Option<&[u32]> {
+    self.execution_modes.iter().find(|(m, _)| *m == mode).map(|(_, o)| o.as_slice())
+  }
+
+  /// Get the workgroup size of a compute shader
+  ///
+  /// Returns `None`, if the size is not declared with the `LocalSize` execution mode.
+  /// Note that the size can be overridden with a `WorkgroupSize` built-in constant.
+  pub fn local_size(&self) -> Option<[u32; 3]> {
+    match self.execution_mode(ExecutionMode::ExecutionModeLocalSize) {
+      Some(&[x, y, z]) => Some([x, y, z]),
+      _ => None,
+    }
+  }
+}
+
+/// Type of a descriptor, mirrors `VkDescriptorType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DescriptorKind {
+  Sampler,
+  CombinedImageSampler,
+  SampledImage,
+  StorageImage,
+  UniformTexelBuffer,
+  StorageTexelBuffer,
+  UniformBuffer,
+  StorageBuffer,
+  InputAttachment,
+}
+
+/// Resource variable with a descriptor set and binding
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescriptorBinding {
+  /// Id of the variable
+  pub id: u32,
+  /// Name of the variable, or of the block if the variable is unnamed
+  pub name: Option<String>,
+  pub set: u32,
+  pub binding: u32,
+  pub kind: DescriptorKind,
+  /// Number of descriptors, `None` for runtime sized arrays
+  pub count: Option<u32>,
+  /// Type of the variable (without the pointer)
+  pub ty: Type,
+  /// Decorations of the variable
+  pub decorations: Decorations,
+}
+
+/// Global variable of a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+  pub id: u32,
+  pub name: Option<String>,
+  pub storage_class: StorageClass,
+  /// Type of the variable (without the pointer)
+  pub ty: Type,
+  pub decorations: Decorations,
+}
+
+/// Specialization constant with a `SpecId`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecConstant {
+  pub id: u32,
+  pub spec_id: u32,
+  pub name: Option<String>,
+  pub ty: Type,
+  /// Bits of the default value, 64 bit constants have the low order word first, bools are 0 or 1
+  pub default: Vec<u32>,
+}
+
+impl Module {
+  /// Get all entry points with their execution modes
+  pub fn entry_points(&self) -> Vec<EntryPoint> {
+    self
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::EntryPoint {
+          execution,
+          id,
+          name,
+          interface,
+        } => Some(EntryPoint {
+          id: *id,
+          name: name.clone(),
+          execution_model: *execution,
+          interface: interface.clone(),
+          execution_modes: self
+            .instructions
+            .iter()
+            .filter_map(|i| match i {
+              Instruction::ExecutionMode {
+                target_id,
+                mode,
+                optional_literals,
+              } if target_id == id => Some((*mode, optional_literals.clone())),
+              _ => None,
+            })
+            .collect(),
+        }),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Get an entry point by its name
+  pub fn entry_point(&self, name: &str) -> Option<EntryPoint> {
+    self.entry_points().into_iter().find(|e| e.name == name)
+  }
+
+  /// Get all global variables, except for variables in the `Function` storage class
+  pub fn variables(&self) -> Result<Vec<Variable>, Error> {
+    let mut vars = Vec::new();
+    for i in self.instructions.iter() {
+      match i {
+        Instruction::Variable {
+          result_id,
+          result_type_id,
+          storage_class,
+          ..
+        } if *storage_class != StorageClass::StorageClassFunction => vars.push(Variable {
+          id: *result_id,
+          name: self.name(*result_id).map(|n| n.to_string()),
+          storage_class: *storage_class,
+          ty: self.pointee(*result_type_id)?,
+          decorations: self.decorations(*result_id),
+        }),
+        _ => (),
+      }
+    }
+    Ok(vars)
+  }
+
+  /// Get the variables of a storage class
+  pub fn variables_of(&self, storage_class: StorageClass) -> Result<Vec<Variable>, Error> {
+    Ok(self.variables()?.into_iter().filter(|v| v.storage_class == storage_class).collect())
+  }
+
+  /// Get the input variables, including built-ins
+  pub fn inputs(&self) -> Result<Vec<Variable>, Error> {
+    self.variables_of(StorageClass::StorageClassInput)
+  }
+
+  /// Get the output variables, including built-ins
+  pub fn outputs(&self) -> Result<Vec<Variable>, Error> {
+    self.variables_of(StorageClass::StorageClassOutput)
+  }
+
+  /// Get the push constant blocks
+  ///
+  /// Every entry point uses at most one push constant block.
+  pub fn push_constants(&self) -> Result<Vec<Variable>, Error> {
+    self.variables_of(StorageClass::StorageClassPushConstant)
+  }
+
+  /// Get all variables with a descriptor binding, sorted by set and binding
+  ///
+  /// Variables without a `DescriptorSet` decoration are in set 0.
+  /// Fails with `Error::UnsupportedDescriptor`, if the descriptor type of a variable with a `Binding` decoration can not be determined.
+  pub fn descriptor_bindings(&self) -> Result<Vec<DescriptorBinding>, Error> {
+    let mut bindings = Vec::new();
+    for v in self.variables()? {
+      let binding = match v.decorations.binding() {
+        Some(b) => b,
+        None => continue,
+      };
+
+      let (count, element) = match &v.ty {
+        Type::Array { element, len, .. } => (Some(*len), element.as_ref()),
+        Type::RuntimeArray { element, .. } => (None, element.as_ref()),
+        t => (Some(1), t),
+      };
+
+      let kind = match (v.storage_class, element) {
+        (StorageClass::StorageClassStorageBuffer, Type::Struct(_)) => DescriptorKind::StorageBuffer,
+        (StorageClass::StorageClassUniform, Type::Struct(s)) if s.decorations.contains(Decoration::DecorationBufferBlock) => {
+          DescriptorKind::StorageBuffer
+        }
+        (StorageClass::StorageClassUniform, Type::Struct(s)) if s.decorations.contains(Decoration::DecorationBlock) => {
+          DescriptorKind::UniformBuffer
+        }
+        (StorageClass::StorageClassUniformConstant, Type::Sampler) => DescriptorKind::Sampler,
+        (StorageClass::StorageClassUniformConstant, Type::SampledImage(i)) if i.dim == Dim::DimBuffer => DescriptorKind::UniformTexelBuffer,
+        (StorageClass::StorageClassUniformConstant, Type::SampledImage(_)) => DescriptorKind::CombinedImageSampler,
+        (StorageClass::StorageClassUniformConstant, Type::Image(i)) => match (i.dim, i.sampled) {
+          (Dim::DimSubpassData, _) => DescriptorKind::InputAttachment,
+          (Dim::DimBuffer, Some(true)) => DescriptorKind::UniformTexelBuffer,
+          (Dim::DimBuffer, _) => DescriptorKind::StorageTexelBuffer,
+          (_, Some(true)) => DescriptorKind::SampledImage,
+          _ => DescriptorKind::StorageImage,
+        },
+        _ => Err(Error::UnsupportedDescriptor(v.id))?,
+      };
+
+      let name = v.name.clone().or_else(|| match element {
+        Type::Struct(s) => s.name.clone(),
+        _ => None,
+      });
+
+      bindings.push(DescriptorBinding {
+        id: v.id,
+        name,
+        set: v.decorations.set().unwrap_or(0),
+        binding,
+        kind,
+        count,
+        ty: v.ty,
+        decorations: v.decorations,
+      });
+    }
+    bindings.sort_by_key(|b| (b.set, b.binding));
+    Ok(bindings)
+  }
+
+  /// Get the ids of the constants, that define the workgroup size of compute shaders
+  ///
+  /// The ids are the constituents of the `WorkgroupSize` built-in constant, or the operands of the first `LocalSizeId` execution mode.
+  /// Components, that are specialization constants, can be matched with the [spec_constants](struct.Module.html#method.spec_constants),
+  /// e.g. GLSL declares them with `local_size_x_id`, but does not name them.
+  /// Returns `None`, if there is neither a `WorkgroupSize` built-in nor a `LocalSizeId` execution mode.
+  pub fn workgroup_size_ids(&self) -> Option<[u32; 3]> {
+    let builtin = self.instructions.iter().find_map(|i| match i {
+      Instruction::ConstantComposite {
+        result_id, constituents, ..
+      }
+      | Instruction::SpecConstantComposite {
+        result_id,
+        data: constituents,
+        ..
+      } if self.decorations(*result_id).builtin() == Some(BuiltIn::BuiltInWorkgroupSize) => Some(constituents.as_slice()),
+      _ => None,
+    });
+    let ids = builtin.or_else(|| {
+      self.instructions.iter().find_map(|i| match i {
+        Instruction::ExecutionMode {
+          mode: ExecutionMode::ExecutionModeLocalSizeId,
+          optional_literals,
+          ..
+        } => Some(optional_literals.as_slice()),
+        _ => None,
+      })
+    });
+    match ids {
+      Some(&[x, y, z]) => Some([x, y, z]),
+      _ => None,
+    }
+  }
+
+  /// Get the specialization constants, that have a `SpecId`
+  ///
+  /// Composite and operation specialization constants do not have a `SpecId` and are not included.
+  pub fn spec_constants(&self) -> Result<Vec<SpecConstant>, Error> {
+    let mut constants = Vec::new();
+    for i in self.instructions.iter() {
+      let (ty, id, default) = match i {
+        Instruction::SpecConstantTrue { result_type_id, result_id } => (*result_type_id, *result_id, vec![1]),
+        Instruction::SpecConstantFalse { result_type_id, result_id } => (*result_type_id, *result_id, vec![0]),
+        Instruction::SpecConstant {
+          result_type_id,
+          result_id,
+          data,
+        } => (*result_type_id, *result_id, data.clone()),
+        _ => continue,
+      };
+
+      if let Some(spec_id) = self.decorations(id).spec_id() {
+        constants.push(SpecConstant {
+          id,
+          spec_id,
+          name: self.name(id).map(|n| n.to_string()),
+          ty: self.resolve_type(ty)?,
+          default,
+        });
+      }
+    }
+    Ok(constants)
+  }
+
+  /// Resolves the pointee type of a pointer type
+  fn pointee(&self, id: u32) -> Result<Type, Error> {
+    match self.resolve_type(id)? {
+      Type::Pointer { pointee, .. } => Ok(*pointee),
+      _ => Err(Error::NotAPointer(id)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn load(bytes: &[u8]) -> Module {
+    Module::from_bytes(bytes).unwrap()
+  }
+
+  type Summary = (u32, u32, DescriptorKind, Option<u32>, Option<String>);
+
+  fn bindings(m: &Module) -> Vec<Summary> {
+    m.descriptor_bindings()
+      .unwrap()
+      .into_iter()
+      .map(|b| (b.set, b.binding, b.kind, b.count, b.name))
+      .collect()
+  }
+
+  fn s(s: &str) -> Option<String> {
+    Some(s.to_string())
+  }
+
+  #[test]
+  fn entry_points() {
+    let m = load(include_bytes!("../fixtures/multi.spv"));
+    let e = m.entry_points();
+    assert_eq!(
+      e.iter().map(|e| (e.name.as_str(), e.execution_model)).collect::<Vec<_>>(),
+      vec![
+        ("vs_main", ExecutionModel::ExecutionModelVertex),
+        ("fs_main", ExecutionModel::ExecutionModelFragment),
+        ("main", ExecutionModel::ExecutionModelGLCompute),
+      ]
+    );
+    assert!(e[0].execution_modes.is_empty());
+    assert_eq!(
+      e[1].execution_modes,
+      vec![
+        (ExecutionMode::ExecutionModeOriginUpperLeft, vec![]),
+        (ExecutionMode::ExecutionModeDepthReplacing, vec![]),
+      ]
+    );
+    assert_eq!(e[1].execution_mode(ExecutionMode::ExecutionModeDepthReplacing), Some(&[][..]));
+    assert_eq!(e[1].local_size(), None);
+    assert_eq!(e[2].local_size(), Some([8, 8, 1]));
+    assert_eq!(m.name(e[2].id), Some("main"));
+
+    assert_eq!(m.entry_point("fs_main"), Some(e[1].clone()));
+    assert_eq!(m.entry_point("nope"), None);
+  }
+
+  #[test]
+  fn entry_point_interface() {
+    let m = load(include_bytes!("../fixtures/vertex.spv"));
+    let e = m.entry_point("main").unwrap();
+    assert_eq!(e.execution_model, ExecutionModel::ExecutionModelVertex);
+    let names = e.interface.iter().map(|id| m.name(*id)).collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      vec![
+        None,
+        Some("position"),
+        Some("model"),
+        Some("ids"),
+        Some("uv"),
+        Some("gl_VertexIndex")
+      ]
+    );
+
+    let m = load(include_bytes!("../fixtures/compute.spv"));
+    assert_eq!(m.entry_point("main").unwrap().local_size(), Some([64, 2, 1]));
+  }
+
+  #[test]
+  fn descriptor_kinds() {
+    let m = load(include_bytes!("../fixtures/images.spv"));
+    assert_eq!(
+      bindings(&m),
+      vec![
+        (0, 0, DescriptorKind::CombinedImageSampler, Some(1), s("tex")),
+        (0, 1, DescriptorKind::SampledImage, Some(1), s("separate")),
+        (0, 2, DescriptorKind::Sampler, Some(1), s("samp")),
+        (0, 3, DescriptorKind::StorageImage, Some(1), s("img")),
+        (0, 4, DescriptorKind::UniformTexelBuffer, Some(1), s("texels")),
+        (0, 5, DescriptorKind::StorageTexelBuffer, Some(1), s("storage_texels")),
+        (0, 6, DescriptorKind::InputAttachment, Some(1), s("attachment")),
+        (0, 7, DescriptorKind::CombinedImageSampler, Some(4), s("shadows")),
+        (0, 8, DescriptorKind::CombinedImageSampler, Some(1), s("cubes")),
+        (0, 9, DescriptorKind::CombinedImageSampler, Some(1), s("ms")),
+        (0, 10, DescriptorKind::CombinedImageSampler, Some(1), s("itex")),
+        (1, 0, DescriptorKind::SampledImage, None, s("textures")),
+      ]
+    );
+
+    let b = m.descriptor_bindings().unwrap();
+    assert!(b[3].decorations.contains(Decoration::DecorationNonReadable));
+    assert_eq!(b[6].decorations.input_attachment_index(), Some(0));
+    assert_eq!(m.variables().unwrap().len(), b.len());
+  }
+
+  #[test]
+  fn buffers() {
+    let m = load(include_bytes!("../fixtures/compute.spv"));
+    assert_eq!(
+      bindings(&m),
+      vec![
+        (0, 0, DescriptorKind::UniformBuffer, Some(1), s("params")),
+        (1, 2, DescriptorKind::StorageBuffer, Some(1), s("data")),
+      ]
+    );
+    let b = m.descriptor_bindings().unwrap();
+    match &b[1].ty {
+      Type::Struct(s) => {
+        assert_eq!(s.name.as_deref(), Some("Data"));
+        assert_eq!(s.members.len(), 2);
+      }
+      t => panic!("{:?}", t),
+    }
+
+    let m = load(include_bytes!("../fixtures/multi.spv"));
+    assert_eq!(
+      bindings(&m),
+      vec![
+        (2, 0, DescriptorKind::UniformBuffer, Some(1), s("a")),
+        (2, 1, DescriptorKind::UniformBuffer, Some(1), s("b")),
+      ]
+    );
+  }
+
+  #[test]
+  fn hlsl() {
+    let m = load(include_bytes!("../fixtures/hlsl.spv"));
+    assert_eq!(
+      bindings(&m),
+      vec![
+        (0, 1, DescriptorKind::StorageBuffer, Some(1), s("counters")),
+        (0, 2, DescriptorKind::UniformBuffer, Some(1), s("$Globals")),
+        (0, 3, DescriptorKind::StorageBuffer, Some(1), s("counter.var.counters")),
+        (0, 4, DescriptorKind::UniformTexelBuffer, Some(1), s("texels")),
+        (1, 0, DescriptorKind::StorageBuffer, Some(1), s("lights")),
+      ]
+    );
+
+    let lights = m.descriptor_bindings().unwrap().pop().unwrap();
+    let light = match &lights.ty {
+      Type::Struct(s) => match &s.members[0].ty {
+        Type::RuntimeArray { element, stride: Some(16) } => element.clone(),
+        t => panic!("{:?}", t),
+      },
+      t => panic!("{:?}", t),
+    };
+    match *light {
+      Type::Struct(l) => {
+        assert_eq!(l.name.as_deref(), Some("Light"));
+        assert_eq!(l.member("intensity").unwrap().offset(), Some(12));
+      }
+      t => panic!("{:?}", t),
+    }
+
+    let inputs = m.inputs().unwrap();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0].name.as_deref(), Some("in.var.TEXCOORD0"));
+    assert_eq!(
+      inputs[0].decorations.get_string(Decoration::DecorationUserSemantic),
+      Some("TEXCOORD0")
+    );
+    assert_eq!(
+      m.outputs().unwrap()[0].decorations.get_string(Decoration::DecorationUserSemantic),
+      Some("SV_Target")
+    );
+  }
+
+  #[test]
+  fn unnamed_variable() {
+    // the name of the block is used, if the variable has no name
+    let mut m = load(include_bytes!("../fixtures/compute.spv"));
+    let params = m.variables().unwrap().into_iter().find(|v| v.name == s("params")).unwrap().id;
+    m.instructions.retain(|i| match i {
+      Instruction::Name { target_id, .. } => *target_id != params,
+      _ => true,
+    });
+    assert_eq!(m.descriptor_bindings().unwrap()[0].name, s("Params"));
+  }
+
+  #[test]
+  fn unsupported_descriptor() {
+    let mut m = load(include_bytes!("../fixtures/vertex.spv"));
+    let position = m.inputs().unwrap().into_iter().find(|v| v.name == s("position")).unwrap().id;
+    m.instructions.push(Instruction::Decorate {
+      target_id: position,
+      decoration: Decoration::DecorationBinding,
+      params: vec![3],
+    });
+    assert_eq!(m.descriptor_bindings(), Err(Error::UnsupportedDescriptor(position)));
+  }
+
+  #[test]
+  fn inputs_and_outputs() {
+    let m = load(include_bytes!("../fixtures/vertex.spv"));
+    let inputs = m.inputs().unwrap();
+    assert_eq!(
+      inputs
+        .iter()
+        .map(|v| (v.name.clone(), v.decorations.location()))
+        .collect::<Vec<_>>(),
+      vec![
+        (s("position"), Some(0)),
+        (s("model"), Some(1)),
+        (s("ids"), Some(5)),
+        (s("gl_VertexIndex"), None),
+      ]
+    );
+    assert_eq!(inputs[3].decorations.builtin(), Some(BuiltIn::BuiltInVertexIndex));
+    assert_eq!(inputs[3].ty, Type::Int { width: 32, signed: true });
+    assert_eq!(
+      inputs[2].ty,
+      Type::Vector {
+        component: Box::new(Type::Int { width: 32, signed: false }),
+        count: 2
+      }
+    );
+    match &inputs[1].ty {
+      Type::Matrix { columns: 4, .. } => (),
+      t => panic!("{:?}", t),
+    }
+
+    let outputs = m.outputs().unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].name, None);
+    let per_vertex = match &outputs[0].ty {
+      Type::Struct(s) => s.clone(),
+      t => panic!("{:?}", t),
+    };
+    assert_eq!(
+      per_vertex
+        .members
+        .iter()
+        .map(|m| m.decorations.builtin().unwrap())
+        .collect::<Vec<_>>(),
+      vec![
+        BuiltIn::BuiltInPosition,
+        BuiltIn::BuiltInPointSize,
+        BuiltIn::BuiltInClipDistance,
+        BuiltIn::BuiltInCullDistance
+      ]
+    );
+    assert_eq!(outputs[1].decorations.location(), Some(0));
+
+    assert_eq!(bindings(&m), vec![(0, 0, DescriptorKind::UniformBuffer, Some(1), s("camera"))]);
+    assert!(m.push_constants().unwrap().is_empty());
+  }
+
+  #[test]
+  fn push_constants() {
+    let m = load(include_bytes!("../fixtures/compute.spv"));
+    let pc = m.push_constants().unwrap();
+    assert_eq!(pc.len(), 1);
+    assert_eq!(pc[0].name, s("pc"));
+    assert_eq!(pc[0].storage_class, StorageClass::StorageClassPushConstant);
+    match &pc[0].ty {
+      Type::Struct(s) => {
+        assert_eq!(s.name.as_deref(), Some("Push"));
+        assert_eq!(s.member("offset").unwrap().ty, Type::Int { width: 32, signed: false });
+      }
+      t => panic!("{:?}", t),
+    }
+    // push constant blocks have no binding
+    assert_eq!(m.descriptor_bindings().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn spec_constants() {
+    let m = load(include_bytes!("../fixtures/compute.spv"));
+    let c = m.spec_constants().unwrap();
+    assert_eq!(
+      c.iter()
+        .map(|c| (c.spec_id, c.name.clone(), c.ty.clone(), c.default.clone()))
+        .collect::<Vec<_>>(),
+      vec![
+        (0, s("count"), Type::Int { width: 32, signed: false }, vec![16]),
+        (1, s("enabled"), Type::Bool, vec![1]),
+        (2, s("scale"), Type::Float { width: 32 }, vec![0.5f32.to_bits()]),
+      ]
+    );
+
+    let m = load(include_bytes!("../fixtures/multi.spv"));
+    let c = m.spec_constants().unwrap();
+    assert_eq!(c.len(), 2);
+    assert_eq!((c[0].spec_id, c[0].default.clone()), (3, vec![3]));
+    let bits = 1.5f64.to_bits();
+    assert_eq!(c[1].ty, Type::Float { width: 64 });
+    assert_eq!(c[1].default, vec![bits as u32, (bits >> 32) as u32]);
+
+    assert!(load(include_bytes!("../fixtures/images.spv")).spec_constants().unwrap().is_empty());
+  }
+
+  #[test]
+  fn workgroup_size() {
+    // the WorkgroupSize built-in of glsl
+    let m = load(include_bytes!("../fixtures/workgroup.spv"));
+    let c = m.spec_constants().unwrap();
+    let ids = m.workgroup_size_ids().unwrap();
+    assert_eq!((ids[0], ids[1]), (c[0].id, c[1].id));
+    assert_eq!(m.constant_u32(ids[2]), Some(1));
+    assert_eq!((c[0].spec_id, c[0].name.clone()), (0, None));
+    assert_eq!((c[2].spec_id, c[2].name.clone()), (2, s("groupSize")));
+    assert!(!ids.contains(&c[2].id));
+
+    // the LocalSizeId execution mode of spirv 1.6
+    let m = load(include_bytes!("../fixtures/workgroup_id.spv"));
+    let c = m.spec_constants().unwrap();
+    let ids = m.workgroup_size_ids().unwrap();
+    assert_eq!((ids[0], ids[1]), (c[0].id, c[1].id));
+    assert_eq!(m.constant_u32(ids[2]), Some(4));
+    let main = m.entry_point("main").unwrap();
+    assert_eq!(main.execution_mode(ExecutionMode::ExecutionModeLocalSizeId), Some(&ids[..]));
+    assert_eq!(main.local_size(), None);
+
+    // literal sizes are constants, not specialization constants
+    let m = load(include_bytes!("../fixtures/compute.spv"));
+    let ids = m.workgroup_size_ids().unwrap();
+    assert_eq!(
+      ids.iter().map(|id| m.constant_u32(*id)).collect::<Vec<_>>(),
+      vec![Some(64), Some(2), Some(1)]
+    );
+    assert_eq!(load(include_bytes!("../fixtures/images.spv")).workgroup_size_ids(), None);
+  }
+}
@@ -0,0 +1,601 @@
+use crate::enums::*;
+use crate::Decorations;
+use crate::Error;
+use crate::Instruction;
+use crate::Module;
+
+/// Type of a SPIR-V id, resolved into a tree
+///
+/// Composite types own their element types, so that a type can be inspected without the module.
+/// Decorations, that are needed to describe the memory layout (array strides, member offsets and matrix strides) are part of the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+  Void,
+  Bool,
+  Int {
+    width: u32,
+    signed: bool,
+  },
+  Float {
+    width: u32,
+  },
+  Vector {
+    component: Box<Type>,
+    count: u32,
+  },
+  /// Matrix with `columns` column vectors of type `column`
+  Matrix {
+    column: Box<Type>,
+    columns: u32,
+  },
+  Image(Image),
+  Sampler,
+  SampledImage(Image),
+  /// Array with a fixed length
+  ///
+  /// If the length is a specialization constant, `spec_id` is set and `len` is the default value of the constant.
+  /// `stride` is the `ArrayStride` decoration, that is present for arrays in uniform, storage and push constant blocks.
+  Array {
+    element: Box<Type>,
+    len: u32,
+    spec_id: Option<u32>,
+    stride: Option<u32>,
+  },
+  RuntimeArray {
+    element: Box<Type>,
+    stride: Option<u32>,
+  },
+  Struct(Struct),
+  Pointer {
+    storage_class: StorageClass,
+    pointee: Box<Type>,
+  },
+  /// Types, that are not relevant for reflection, e.g. function types or `OpTypeOpaque`
+  Opaque(u32),
+}
+
+/// Image type, also used for the image of a sampled image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+  /// Type of the components, that result from sampling or reading the image
+  pub sampled_type: Box<Type>,
+  pub dim: Dim,
+  /// `None` if it is not known, whether this is a depth image
+  pub depth: Option<bool>,
+  pub arrayed: bool,
+  pub multisampled: bool,
+  /// `Some(true)` for images used with a sampler, `Some(false)` for storage images and `None` if only known at runtime
+  pub sampled: Option<bool>,
+  pub format: ImageFormat,
+  pub access: Option<AccessQualifier>,
+}
+
+/// Struct type with its members
+#[derive(Debug, Clone, PartialEq)]
+pub struct Struct {
+  pub id: u32,
+  pub name: Option<String>,
+  pub members: Vec<Member>,
+  /// Decorations of the struct, e.g. `Block` or `BufferBlock`
+  pub decorations: Decorations,
+}
+
+/// Member of a struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+  pub name: Option<String>,
+  pub ty: Type,
+  /// Decorations of the member, e.g. `Offset`, `MatrixStride`, `RowMajor` or `BuiltIn`
+  pub decorations: Decorations,
+}
+
+impl Type {
+  /// Checks if the type is a bool, integer or float
+  pub fn is_scalar(&self) -> bool {
+    matches!(self, Type::Bool | Type::Int { .. } | Type::Float { .. })
+  }
+
+  /// Get the scalar type of scalars, vectors and matrices
+  pub fn scalar(&self) -> Option<&Type> {
+    match self {
+      Type::Vector { component, .. } => component.scalar(),
+      Type::Matrix { column, .. } => column.scalar(),
+      t if t.is_scalar() => Some(t),
+      _ => None,
+    }
+  }
+
+  /// Get the size of a scalar or vector in bytes
+  ///
+  /// Bools have no defined size and return `None`, as do all other types,
+  /// because their size depends on the strides of the block they are used in.
+  pub fn scalar_size(&self) -> Option<u32> {
+    match self {
+      Type::Int { width, .. } | Type::Float { width } => Some(width / 8),
+      Type::Vector { component, count } => component.scalar_size().map(|s| s * count),
+      _ => None,
+    }
+  }
+
+  /// Removes arrays and runtime arrays from a type
+  ///
+  /// # Returns
+  /// The element type, or the type itself if it is not an array.
+  pub fn element(&self) -> &Type {
+    match self {
+      Type::Array { element, .. } | Type::RuntimeArray { element, .. } => element.element(),
+      t => t,
+    }
+  }
+
+  /// Get the pointee type of a pointer, or the type itself if it is not a pointer
+  pub fn pointee(&self) -> &Type {
+    match self {
+      Type::Pointer { pointee, .. } => pointee,
+      t => t,
+    }
+  }
+}
+
+impl Struct {
+  /// Get a member by its name
+  pub fn member(&self, name: &str) -> Option<&Member> {
+    self.members.iter().find(|m| m.name.as_deref() == Some(name))
+  }
+
+  /// Checks if the struct is the type of a uniform, storage or push constant block
+  pub fn is_block(&self) -> bool {
+    self.decorations.contains(Decoration::DecorationBlock) || self.decorations.contains(Decoration::DecorationBufferBlock)
+  }
+}
+
+impl Member {
+  /// Get the byte offset of the member, which is set for members of blocks
+  pub fn offset(&self) -> Option<u32> {
+    self.decorations.offset()
+  }
+}
+
+impl Module {
+  /// Resolves the type tree of a type id
+  ///
+  /// Fails with `Error::UnknownType`, if `id` or any type it references is not a type, or if the type is recursive,
+  /// and with `Error::UnsupportedArrayLength`, if the length of an array is neither a constant nor a specialization constant with a default value.
+  pub fn resolve_type(&self, id: u32) -> Result<Type, Error> {
+    self.resolve_type_impl(id, &mut Vec::new())
+  }
+
+  /// Resolves the type of an id with a result type, e.g. a variable or a constant
+  pub fn type_of(&self, id: u32) -> Result<Type, Error> {
+    let ty = match self.definition(id) {
+      Some(Instruction::Variable { result_type_id, .. })
+      | Some(Instruction::Constant { result_type_id, .. })
+      | Some(Instruction::ConstantTrue { result_type_id, .. })
+      | Some(Instruction::ConstantFalse { result_type_id, .. })
+      | Some(Instruction::ConstantComposite { result_type_id, .. })
+      | Some(Instruction::ConstantNull { result_type_id, .. })
+      | Some(Instruction::SpecConstant { result_type_id, .. })
+      | Some(Instruction::SpecConstantTrue { result_type_id, .. })
+      | Some(Instruction::SpecConstantFalse { result_type_id, .. })
+      | Some(Instruction::SpecConstantComposite { result_type_id, .. })
+      | Some(Instruction::Function { result_type_id, .. }) => *result_type_id,
+      _ => Err(Error::UnknownType(id))?,
+    };
+    self.resolve_type(ty)
+  }
+
+  fn resolve_type_impl(&self, id: u32, stack: &mut Vec<u32>) -> Result<Type, Error> {
+    if stack.contains(&id) {
+      Err(Error::UnknownType(id))?
+    }
+    stack.push(id);
+    let ty = self.resolve_definition(id, stack);
+    stack.pop();
+    ty
+  }
+
+  fn resolve_definition(&self, id: u32, stack: &mut Vec<u32>) -> Result<Type, Error> {
+    let mut resolve = |id| self.resolve_type_impl(id, stack).map(Box::new);
+    Ok(match self.definition(id).ok_or(Error::UnknownType(id))? {
+      Instruction::TypeVoid { .. } => Type::Void,
+      Instruction::TypeBool { .. } => Type::Bool,
+      Instruction::TypeInt { width, signedness, .. } => Type::Int {
+        width: *width,
+        signed: *signedness,
+      },
+      Instruction::TypeFloat { width, .. } => Type::Float { width: *width },
+      Instruction::TypeVector { component_id, count, .. } => Type::Vector {
+        component: resolve(*component_id)?,
+        count: *count,
+      },
+      Instruction::TypeMatrix {
+        column_type_id,
+        column_count,
+        ..
+      } => Type::Matrix {
+        column: resolve(*column_type_id)?,
+        columns: *column_count,
+      },
+      Instruction::TypeImage { .. } => Type::Image(self.resolve_image(id, stack)?),
+      Instruction::TypeSampler { .. } => Type::Sampler,
+      Instruction::TypeSampledImage { image_type_id, .. } => Type::SampledImage(self.resolve_image(*image_type_id, stack)?),
+      Instruction::TypeArray { type_id, length_id, .. } => {
+        let (len, spec_id) = self.array_length(*length_id)?;
+        Type::Array {
+          element: resolve(*type_id)?,
+          len,
+          spec_id,
+          stride: self.decorations(id).array_stride(),
+        }
+      }
+      Instruction::TypeRuntimeArray { type_id, .. } => Type::RuntimeArray {
+        element: resolve(*type_id)?,
+        stride: self.decorations(id).array_stride(),
+      },
+      Instruction::TypeStruct { member_types, .. } => {
+        let mut members = Vec::with_capacity(member_types.len());
+        for (m, ty) in member_types.iter().enumerate() {
+          members.push(Member {
+            name: self.member_name(id, m as u32).map(|n| n.to_string()),
+            ty: self.resolve_type_impl(*ty, stack)?,
+            decorations: self.member_decorations(id, m as u32),
+          });
+        }
+        Type::Struct(Struct {
+          id,
+          name: self.name(id).map(|n| n.to_string()),
+          members,
+          decorations: self.decorations(id),
+        })
+      }
+      Instruction::TypePointer {
+        storage_class, type_id, ..
+      } => Type::Pointer {
+        storage_class: *storage_class,
+        pointee: resolve(*type_id)?,
+      },
+      Instruction::TypeOpaque { .. } | Instruction::TypeFunction { .. } => Type::Opaque(id),
+      _ => Err(Error::UnknownType(id))?,
+    })
+  }
+
+  fn resolve_image(&self, id: u32, stack: &mut Vec<u32>) -> Result<Image, Error> {
+    match self.definition(id) {
+      Some(Instruction::TypeImage {
+        sampled_type_id,
+        dim,
+        depth,
+        arrayed,
+        ms,
+        sampled,
+        format,
+        access,
+        ..
+      }) => Ok(Image {
+        sampled_type: Box::new(self.resolve_type_impl(*sampled_type_id, stack)?),
+        dim: *dim,
+        depth: *depth,
+        arrayed: *arrayed,
+        multisampled: *ms,
+        sampled: *sampled,
+        format: *format,
+        access: *access,
+      }),
+      _ => Err(Error::UnknownType(id)),
+    }
+  }
+
+  /// Get the length of an array and the SpecId, if the length is a specialization constant
+  fn array_length(&self, id: u32) -> Result<(u32, Option<u32>), Error> {
+    match self.definition(id) {
+      Some(Instruction::Constant { data, .. }) if !data.is_empty() => Ok((data[0], None)),
+      Some(Instruction::SpecConstant { data, .. }) if !data.is_empty() => match self.decorations(id).spec_id() {
+        Some(spec_id) => Ok((data[0], Some(spec_id))),
+        None => Err(Error::UnsupportedArrayLength(id)),
+      },
+      _ => Err(Error::UnsupportedArrayLength(id)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn float(width: u32) -> Type {
+    Type::Float { width }
+  }
+
+  fn vec(count: u32) -> Type {
+    Type::Vector {
+      component: Box::new(float(32)),
+      count,
+    }
+  }
+
+  fn id(m: &Module, name: &str) -> u32 {
+    m.instructions
+      .iter()
+      .find_map(|i| match i {
+        Instruction::Name { target_id, name: n } if n == name => Some(*target_id),
+        _ => None,
+      })
+      .unwrap()
+  }
+
+  fn block(m: &Module, var: &str) -> Struct {
+    match m.type_of(id(m, var)).unwrap() {
+      Type::Pointer {
+        storage_class: StorageClass::StorageClassUniform,
+        pointee,
+      } => match *pointee {
+        Type::Struct(s) => s,
+        t => panic!("{:?}", t),
+      },
+      t => panic!("{:?}", t),
+    }
+  }
+
+  fn image(m: &Module, var: &str) -> Type {
+    m.type_of(id(m, var)).unwrap().pointee().clone()
+  }
+
+  #[test]
+  fn scalars_vectors_matrices() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/types.spv")).unwrap();
+    let s = block(&m, "types");
+    assert_eq!(s.id, id(&m, "Types"));
+    assert_eq!(s.name.as_deref(), Some("Types"));
+    assert!(s.is_block());
+    assert_eq!(s.members.len(), 10);
+
+    let ty = |name| s.member(name).unwrap().ty.clone();
+    assert_eq!(ty("i"), Type::Int { width: 32, signed: true });
+    assert_eq!(ty("u"), Type::Int { width: 32, signed: false });
+    assert_eq!(ty("f"), float(32));
+    assert_eq!(ty("d"), float(64));
+    assert_eq!(ty("v"), vec(3));
+    assert_eq!(
+      ty("m"),
+      Type::Matrix {
+        column: Box::new(vec(4)),
+        columns: 4
+      }
+    );
+    assert_eq!(
+      ty("rm"),
+      Type::Matrix {
+        column: Box::new(vec(3)),
+        columns: 2
+      }
+    );
+
+    let offsets = s.members.iter().map(|m| m.offset().unwrap()).collect::<Vec<_>>();
+    assert_eq!(offsets, vec![0, 4, 8, 16, 32, 48, 112, 160, 224, 240]);
+
+    let m = s.member("m").unwrap();
+    assert_eq!(m.decorations.matrix_stride(), Some(16));
+    assert!(m.decorations.contains(Decoration::DecorationColMajor));
+    assert!(!m.decorations.row_major());
+    assert!(s.member("rm").unwrap().decorations.row_major());
+    assert!(s.member("nope").is_none());
+  }
+
+  #[test]
+  fn arrays_and_structs() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/types.spv")).unwrap();
+    let s = block(&m, "types");
+
+    assert_eq!(
+      s.member("arr").unwrap().ty,
+      Type::Array {
+        element: Box::new(float(32)),
+        len: 4,
+        spec_id: None,
+        stride: Some(16),
+      }
+    );
+
+    let inner = match &s.member("inner").unwrap().ty {
+      Type::Struct(inner) => inner.clone(),
+      t => panic!("{:?}", t),
+    };
+    assert_eq!(inner.name.as_deref(), Some("Inner"));
+    assert!(!inner.is_block());
+    assert_eq!(inner.member("a").unwrap().ty, vec(2));
+    assert_eq!(inner.member("b").unwrap().offset(), Some(8));
+
+    match &s.member("inners").unwrap().ty {
+      Type::Array {
+        element,
+        len: 2,
+        spec_id: None,
+        stride: Some(16),
+      } => assert_eq!(**element, Type::Struct(inner)),
+      t => panic!("{:?}", t),
+    }
+  }
+
+  #[test]
+  fn runtime_arrays() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/compute.spv")).unwrap();
+    let s = block(&m, "data");
+    assert!(s.decorations.contains(Decoration::DecorationBufferBlock));
+    assert_eq!(
+      s.members[1].ty,
+      Type::RuntimeArray {
+        element: Box::new(float(32)),
+        stride: Some(4),
+      }
+    );
+    assert_eq!(s.members[1].ty.element(), &float(32));
+  }
+
+  #[test]
+  fn spec_constant_array_length() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/multi.spv")).unwrap();
+    assert_eq!(
+      block(&m, "b").members[0].ty,
+      Type::Array {
+        element: Box::new(float(32)),
+        len: 3,
+        spec_id: Some(3),
+        stride: Some(4),
+      }
+    );
+  }
+
+  #[test]
+  fn images() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/images.spv")).unwrap();
+    let tex = Image {
+      sampled_type: Box::new(float(32)),
+      dim: Dim::Dim2D,
+      depth: Some(false),
+      arrayed: false,
+      multisampled: false,
+      sampled: Some(true),
+      format: ImageFormat::ImageFormatUnknown,
+      access: None,
+    };
+    assert_eq!(image(&m, "tex"), Type::SampledImage(tex.clone()));
+    assert_eq!(image(&m, "separate"), Type::Image(tex.clone()));
+    assert_eq!(image(&m, "samp"), Type::Sampler);
+    assert_eq!(
+      image(&m, "img"),
+      Type::Image(Image {
+        sampled: Some(false),
+        format: ImageFormat::ImageFormatRgba8,
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "storage_texels"),
+      Type::Image(Image {
+        dim: Dim::DimBuffer,
+        sampled: Some(false),
+        format: ImageFormat::ImageFormatR32f,
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "attachment"),
+      Type::Image(Image {
+        dim: Dim::DimSubpassData,
+        sampled: Some(false),
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "shadows"),
+      Type::Array {
+        element: Box::new(Type::SampledImage(Image {
+          depth: Some(true),
+          ..tex.clone()
+        })),
+        len: 4,
+        spec_id: None,
+        stride: None,
+      }
+    );
+    assert_eq!(
+      image(&m, "cubes"),
+      Type::SampledImage(Image {
+        dim: Dim::DimCube,
+        arrayed: true,
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "ms"),
+      Type::SampledImage(Image {
+        multisampled: true,
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "itex"),
+      Type::SampledImage(Image {
+        sampled_type: Box::new(Type::Int { width: 32, signed: true }),
+        ..tex.clone()
+      })
+    );
+    assert_eq!(
+      image(&m, "textures"),
+      Type::RuntimeArray {
+        element: Box::new(Type::Image(tex)),
+        stride: None,
+      }
+    );
+  }
+
+  #[test]
+  fn helpers() {
+    let mat = Type::Matrix {
+      column: Box::new(vec(4)),
+      columns: 4,
+    };
+    assert_eq!(mat.scalar(), Some(&float(32)));
+    assert_eq!(mat.scalar_size(), None);
+    assert_eq!(vec(3).scalar_size(), Some(12));
+    assert_eq!(Type::Bool.scalar(), Some(&Type::Bool));
+    assert_eq!(Type::Bool.scalar_size(), None);
+    assert!(!vec(3).is_scalar());
+    assert_eq!(Type::Sampler.scalar(), None);
+    assert_eq!(Type::Sampler.pointee(), &Type::Sampler);
+    assert_eq!(Type::Sampler.element(), &Type::Sampler);
+  }
+
+  #[test]
+  fn functions_and_errors() {
+    let m = Module::from_bytes(include_bytes!("../fixtures/multi.spv")).unwrap();
+    let main = id(&m, "main");
+    assert_eq!(m.type_of(main).unwrap(), Type::Void);
+    assert!(match m.definition(main) {
+      Some(Instruction::Function { function_type_id, .. }) => m.resolve_type(*function_type_id) == Ok(Type::Opaque(*function_type_id)),
+      _ => false,
+    });
+
+    // variables and constants are not types
+    assert_eq!(m.resolve_type(id(&m, "a")), Err(Error::UnknownType(id(&m, "a"))));
+    assert_eq!(m.resolve_type(9999), Err(Error::UnknownType(9999)));
+    assert_eq!(m.type_of(id(&m, "A")), Err(Error::UnknownType(id(&m, "A"))));
+  }
+
+  #[test]
+  fn recursive_and_unsupported() {
+    // struct %1 with a member of type %2, which is an array of %1 with a non-constant length
+    let mut m = Module::from_words(&[crate::MAGIC, 0x0001_0000, 0, 8, 0]).unwrap();
+    m.instructions = vec![
+      Instruction::TypeInt {
+        result_id: 3,
+        width: 32,
+        signedness: false,
+      },
+      Instruction::TypeStruct {
+        result_id: 1,
+        member_types: vec![2],
+      },
+      Instruction::TypeRuntimeArray { result_id: 2, type_id: 1 },
+      Instruction::TypeArray {
+        result_id: 4,
+        type_id: 3,
+        length_id: 3,
+      },
+      Instruction::SpecConstant {
+        result_type_id: 3,
+        result_id: 5,
+        data: vec![4],
+      },
+      Instruction::TypeArray {
+        result_id: 6,
+        type_id: 3,
+        length_id: 5,
+      },
+    ];
+    assert_eq!(m.resolve_type(1), Err(Error::UnknownType(1)));
+    assert_eq!(m.resolve_type(4), Err(Error::UnsupportedArrayLength(3)));
+    // specialization constant without SpecId
+    assert_eq!(m.resolve_type(6), Err(Error::UnsupportedArrayLength(5)));
+  }
+}
@@ -0,0 +1,18 @@
+extern crate nobs_vkmem as vkmem;
+
+/// Replays an allocator trace, that has been recorded with `Allocator::record`, and prints peak usage, page count and fragmentation over time
+///
+/// usage: replay <trace> [default pagesize]
+fn main() {
+  let args = std::env::args().collect::<Vec<_>>();
+  if args.len() < 2 {
+    eprintln!("usage: {} <trace> [default pagesize]", args[0]);
+    std::process::exit(1);
+  }
+
+  let pagesize = args.get(2).map(|s| s.parse().expect("invalid pagesize")).unwrap_or(1 << 26);
+  let mut file = std::io::BufReader::new(std::fs::File::open(&args[1]).expect("could not open trace"));
+  let trace = vkmem::Trace::read(&mut file).expect("could not read trace");
+  let report = trace.replay(pagesize).expect("replay failed");
+  print!("{}", report);
+}
@@ -1,20 +1,44 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::thread::ThreadId;
 
+use crate::backend::Backend;
+use crate::backend::VulkanBackend;
 use crate::bindinfo::BindInfoInner;
 use crate::block::Block;
+use crate::cache::ThreadCache;
+use crate::leak;
+use crate::page::Page;
 use crate::table::Table;
+use crate::trace;
+use crate::trace::Event;
+use crate::trace::TraceWriter;
 use crate::BindInfo;
 use crate::BindType;
+use crate::CacheSizes;
 use crate::Error;
 use crate::Handle;
+use crate::Label;
+use crate::Leak;
+use crate::LeakMode;
+use crate::LeakReport;
 use crate::Mapped;
 use crate::Memtype;
+use crate::SparseLayout;
+use crate::SparsePage;
+use crate::SparseResidency;
 
 /// Defines meta information for the [Allocator](struct.Allocator.html)
 ///
 /// Caches `vk::MemoryRequirements` for buffers and images.
 /// Defines a pagesize for memory types of all common combinations of resource types [buffer, image] and memory properties [device local, host accessible].
+///
+/// Create AllocatorSizes with [new](struct.AllocatorSizes.html#method.new) or [with_requirements](struct.AllocatorSizes.html#method.with_requirements)
+/// and modify the fields afterwards, instead of using a struct literal, so that code does not break when new fields are added.
 #[derive(Debug)]
 pub struct AllocatorSizes {
   /// Handle to the physical device
@@ -39,6 +63,10 @@ pub struct AllocatorSizes {
   pub pagesize_default: vk::DeviceSize,
   /// Page size mapped by memory type index
   pub pagesizes: HashMap<Memtype, vk::DeviceSize>,
+  /// Sizes of the per-thread caches, caches are disabled if None
+  ///
+  /// See [CacheSizes](struct.CacheSizes.html).
+  pub cache: Option<CacheSizes>,
 }
 
 impl AllocatorSizes {
@@ -49,6 +77,8 @@ impl AllocatorSizes {
   /// - 8MiB for host accessible resources
   /// - 64MiB fallback pagesize
   ///
+  /// Per-thread caches are disabled.
+  ///
   /// # Arguments
   /// * `pdevice` - physical device handle
   /// * `device` - device handle
@@ -161,6 +191,7 @@ impl AllocatorSizes {
     Self {
       pagesize_default,
       pagesizes,
+      cache: None,
 
       pdevice,
       image_requirements,
@@ -168,6 +199,32 @@ impl AllocatorSizes {
     }
   }
 
+  /// Creates an AllocatorSizes object from cached memory requirements without querying the device
+  ///
+  /// No pagesizes are mapped, so that all memory types use `pagesize_default`.
+  /// Per-thread caches are disabled.
+  ///
+  /// # Arguments
+  /// * `pdevice` - physical device handle
+  /// * `image_requirements` - memory requirements of an image, see [image_requirements](struct.AllocatorSizes.html#structfield.image_requirements)
+  /// * `buffer_requirements` - memory requirements of a buffer, see [buffer_requirements](struct.AllocatorSizes.html#structfield.buffer_requirements)
+  /// * `pagesize_default` - fallback pagesize in bytes
+  pub fn with_requirements(
+    pdevice: vk::PhysicalDevice,
+    image_requirements: vk::MemoryRequirements,
+    buffer_requirements: vk::MemoryRequirements,
+    pagesize_default: vk::DeviceSize,
+  ) -> Self {
+    Self {
+      pdevice,
+      image_requirements,
+      buffer_requirements,
+      pagesize_default,
+      pagesizes: Default::default(),
+      cache: None,
+    }
+  }
+
   /// Get the memtype of an image with the specified memory properties
   ///
   /// ## Returns
@@ -210,19 +267,157 @@ impl AllocatorSizes {
   }
 }
 
+/// Get the index of the first memory type, that is allowed by `requirements` and has all of the `properties`
+fn find_memtype(
+  types: &[vk::MemoryPropertyFlags],
+  requirements: &vk::MemoryRequirements,
+  properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+  types
+    .iter()
+    .enumerate()
+    .position(|(i, p)| (requirements.memoryTypeBits & (1 << i)) != 0 && (p & properties) == properties)
+    .map(|i| i as u32)
+}
+
+/// Thread cache, that is shared between the registry entries of its resources
+type Cache = Arc<Mutex<ThreadCache>>;
+
+/// Number of shards of the resource registry
+const SHARDS: usize = 16;
+
+/// Resource that is registered in the allocator
+struct Registration {
+  memtype: Memtype,
+  /// Block of the resource in a slot and the cache, if the resource is bound in a thread cache
+  ///
+  /// The block starts at the beginning of the slot and has the size of the resource, not of the slot.
+  cached: Option<(Block, Cache)>,
+  /// Label of a resource in a thread cache, labels of all other resources are kept in the page table
+  label: Option<Label>,
+  backtrace: Option<leak::Backtrace>,
+}
+
+/// Shared state of the allocator
+///
+/// Every part has its own lock, so that resources of different memory types can be bound and destroyed concurrently.
+/// Locks are always acquired in the order sparse, thread cache, map of page tables, page table, trace.
+/// The registry shards are always locked last and released before any other lock is acquired.
 struct AllocatorImpl {
-  device: vk::Device,
-  pagetbls: HashMap<Memtype, Table>,
-  handles: HashMap<Handle<u64>, Memtype>,
+  backend: Arc<dyn Backend>,
+  pagetbls: RwLock<HashMap<Memtype, Arc<Mutex<Table>>>>,
+  handles: Vec<Mutex<HashMap<Handle<u64>, Registration>>>,
+  sparse: Mutex<HashMap<Handle<u64>, SparseResidency>>,
+  caches: RwLock<HashMap<(ThreadId, Memtype), Cache>>,
+  next_chunk: AtomicU64,
+  debug_utils: AtomicBool,
+  leak_mode: Mutex<LeakMode>,
+  trace: Mutex<Option<TraceWriter>>,
+  /// Error, that stopped the recording of the trace early
+  trace_error: Mutex<Option<std::io::Error>>,
+}
+
+impl AllocatorImpl {
+  /// Get the registry shard of the handle
+  fn shard(&self, handle: Handle<u64>) -> MutexGuard<'_, HashMap<Handle<u64>, Registration>> {
+    let i = (handle.get().wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as usize % SHARDS;
+    self.handles[i].lock().unwrap()
+  }
+
+  /// Registers a bound resource, captures the backtrace of the call if the feature `backtrace` is enabled
+  fn insert_handle(&self, handle: Handle<u64>, memtype: Memtype, cached: Option<(Block, Cache)>) {
+    let entry = Registration {
+      memtype,
+      cached,
+      label: None,
+      backtrace: leak::capture(),
+    };
+    self.shard(handle).insert(handle, entry);
+  }
+
+  /// Get the memory type of a registered resource and if it is bound in a thread cache
+  fn get_memtype(&self, handle: Handle<u64>) -> Option<(Memtype, bool)> {
+    self.shard(handle).get(&handle).map(|e| (e.memtype, e.cached.is_some()))
+  }
+
+  fn get_table(&self, memtype: Memtype) -> Option<Arc<Mutex<Table>>> {
+    self.pagetbls.read().unwrap().get(&memtype).cloned()
+  }
+
+  /// Writes the event to the trace, if recording is enabled
+  ///
+  /// Recording stops if the event could not be written, the error is kept until recording is stopped.
+  fn record<F: FnOnce() -> Event>(&self, f: F) {
+    let mut trace = self.trace.lock().unwrap();
+    if let Some(w) = trace.as_mut() {
+      if let Err(e) = w.write(&f()) {
+        *trace = None;
+        *self.trace_error.lock().unwrap() = Some(e);
+      }
+    }
+  }
+
+  fn leak_report(&self) -> LeakReport {
+    let entries = self
+      .handles
+      .iter()
+      .flat_map(|shard| {
+        shard
+          .lock()
+          .unwrap()
+          .iter()
+          .map(|(h, e)| {
+            (
+              *h,
+              e.memtype,
+              e.cached.as_ref().map(|(b, _)| b.size()),
+              e.label.clone(),
+              e.backtrace.clone(),
+            )
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+
+    let sparse = self.sparse.lock().unwrap();
+    let mut leaks = entries
+      .into_iter()
+      .map(|(h, memtype, cached, label, backtrace)| {
+        let tbl = self.get_table(memtype);
+        let tbl = tbl.as_ref().map(|t| t.lock().unwrap());
+        Leak {
+          handle: h,
+          size: match (sparse.get(&h), cached) {
+            (Some(residency), _) => residency.resident_size(),
+            (None, Some(size)) => size,
+            (None, None) => tbl.as_ref().and_then(|t| t.get_mem(h)).map_or(0, |b| b.size_padded()),
+          },
+          memtype,
+          label: label.or_else(|| tbl.as_ref().and_then(|t| t.get_label(h)).cloned()),
+          backtrace,
+        }
+      })
+      .collect::<Vec<_>>();
+    leaks.sort_by_key(|l| (l.memtype, l.handle.get()));
+    LeakReport { leaks }
+  }
 }
 
 impl Drop for AllocatorImpl {
   fn drop(&mut self) {
-    for (h, _) in self.handles.iter() {
-      match h {
-        Handle::Buffer(h) => vk::DestroyBuffer(self.device, *h, std::ptr::null()),
-        Handle::Image(h) => vk::DestroyImage(self.device, *h, std::ptr::null()),
-      };
+    let leak_mode = *self.leak_mode.get_mut().unwrap();
+    if leak_mode != LeakMode::Ignore && self.handles.iter_mut().any(|s| !s.get_mut().unwrap().is_empty()) {
+      let report = self.leak_report();
+      match leak_mode {
+        LeakMode::Panic if !std::thread::panicking() => panic!("{}", report),
+        _ => eprintln!("{}", report),
+      }
+    }
+
+    for shard in self.handles.iter_mut() {
+      for (h, _) in shard.get_mut().unwrap().iter() {
+        self.backend.destroy(*h);
+      }
     }
   }
 }
@@ -233,6 +428,11 @@ impl Drop for AllocatorImpl {
 /// Pages are allocated lazyly, as soon as memory is needed. The pagesizes may be specified in the [AllocatorSizes](struct.AllocatorSizes.html).
 ///
 /// When the Allocator is dropped, all buffers and allocated device momory is freed.
+/// Resources that are still bound at this point are reported as leaks in debug builds, see [leak_mode](struct.Allocator.html#method.leak_mode).
+///
+/// The Allocator is internally synchronized, all methods take `&self` and clones of the Allocator share the same state, so that it can be used from multiple threads.
+/// Every memory type has its own page table with a separate lock, binding and destroying resources of different memory types does not contend.
+/// Optionally every thread gets its own cache of small blocks, see [CacheSizes](struct.CacheSizes.html).
 ///
 /// # Example
 /// The Allocator is created from a device handle and it's associated physical device.
@@ -275,7 +475,7 @@ impl Drop for AllocatorImpl {
 ///
 /// // create an allocator with default page size
 /// // (128MiB for device local / 8MB for host visible memory)
-/// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+/// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
 ///
 /// // declare handles
 /// let mut buf_ub = vk::NULL_HANDLE;
@@ -288,6 +488,9 @@ impl Drop for AllocatorImpl {
 ///   .size(std::mem::size_of::<Ub>() as vk::DeviceSize)
 ///   .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT)
 ///   .devicelocal(false)
+///   // labels show up in print_stats and validation messages
+///   .name("uniforms")
+///   .tag("example")
 ///   .new_buffer(&mut buf_out)
 ///   .size(123 * std::mem::size_of::<u32>() as vk::DeviceSize)
 ///   .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_STORAGE_BUFFER_BIT)
@@ -304,7 +507,7 @@ impl Drop for AllocatorImpl {
 ///
 ///   // this will create the image/buffers and bind them to memory
 ///   // all BindTypes will NOT shuffle the order of the resources around
-///   .bind(&mut allocator, vkmem::BindType::Scatter)
+///   .bind(&allocator, vkmem::BindType::Scatter)
 ///   .unwrap();
 ///
 /// // Mapped gives a convenient view on the memory
@@ -338,13 +541,16 @@ impl Drop for AllocatorImpl {
 /// //allocator.free_unused();
 ///
 /// // dropping the allocator automatically destroys bound resources and frees all memory
+/// // resources that are still bound are listed in a leak report (in debug builds)
+/// print!("{}", allocator.leak_report());
 /// # }
 /// ```
 #[derive(Clone)]
 pub struct Allocator {
   device: vk::Device,
+  backend: Arc<dyn Backend>,
   sizes: std::sync::Arc<AllocatorSizes>,
-  alloc: std::sync::Arc<std::sync::Mutex<AllocatorImpl>>,
+  alloc: Arc<AllocatorImpl>,
 }
 
 impl Allocator {
@@ -378,17 +584,31 @@ impl Allocator {
     requirements: &vk::MemoryRequirements,
     properties: vk::MemoryPropertyFlags,
   ) -> Option<u32> {
-    let mut device_properties = std::mem::MaybeUninit::uninit();
-    vk::GetPhysicalDeviceMemoryProperties(pdevice, device_properties.as_mut_ptr());
-    let device_properties = unsafe { device_properties.assume_init() };
+    find_memtype(&VulkanBackend::get_memory_types(pdevice), requirements, properties)
+  }
 
-    (0..device_properties.memoryTypeCount)
-      .into_iter()
-      .position(|i| {
-        (requirements.memoryTypeBits & (1 << i)) != 0
-          && (device_properties.memoryTypes[i as usize].propertyFlags & properties) == properties
-      })
-      .map(|i| i as u32)
+  /// Get the memory properties of a memory type
+  ///
+  /// # Arguments
+  /// * `pdevice` - physical device handle
+  /// * `index` - the memory type index
+  ///
+  /// # Returns
+  /// The `vk::MemoryPropertyFlags` of the memory type, or 0 if there is no memory type with this index.
+  pub fn get_memtype_properties(pdevice: vk::PhysicalDevice, index: u32) -> vk::MemoryPropertyFlags {
+    VulkanBackend::get_memory_types(pdevice).get(index as usize).cloned().unwrap_or(0)
+  }
+
+  /// Checks if a memory type is host visible
+  ///
+  /// # Arguments
+  /// * `pdevice` - physical device handle
+  /// * `index` - the memory type index
+  ///
+  /// # Returns
+  /// True if the memory type has the `vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT` set.
+  pub fn is_host_visible(pdevice: vk::PhysicalDevice, index: u32) -> bool {
+    (Self::get_memtype_properties(pdevice, index) & vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT) != 0
   }
 
   /// Creates an Allocator
@@ -439,18 +659,41 @@ impl Allocator {
   /// if let Some(memtype) = sizes.get_buffer_memtype(vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT) {
   ///   sizes.set_pagesize(memtype, 1 << 25);
   /// }
-  /// let mut allocator = vkmem::Allocator::with_sizes(pdevice.handle, sizes);
+  /// let allocator = vkmem::Allocator::with_sizes(pdevice.handle, sizes);
   /// # }
   /// ```
   pub fn with_sizes(device: vk::Device, sizes: AllocatorSizes) -> Allocator {
+    let backend = Arc::new(VulkanBackend::new(sizes.pdevice, device));
+    Self::with_backend(device, sizes, backend)
+  }
+
+  /// Creates an Allocator with a custom memory backend
+  ///
+  /// The page tables allocate, map and bind device memory through `backend` instead of calling vulkan directly.
+  /// Memory requirements and memory types are queried and bound resources are destroyed through `backend` as well.
+  /// Sparse bindings, debug names and the memory types of [AllocatorSizes](struct.AllocatorSizes.html) still use `device`.
+  ///
+  /// # Arguments
+  /// * `device` - device handle
+  /// * `sizes` - AllocatorSizes with the physical device handle and pagesize definitions
+  /// * `backend` - backend for device memory operations, see [Backend](trait.Backend.html)
+  pub fn with_backend(device: vk::Device, sizes: AllocatorSizes, backend: Arc<dyn Backend>) -> Allocator {
     Allocator {
       device,
+      backend: backend.clone(),
       sizes: std::sync::Arc::new(sizes),
-      alloc: std::sync::Arc::new(std::sync::Mutex::new(AllocatorImpl {
-        device,
+      alloc: Arc::new(AllocatorImpl {
+        backend,
         pagetbls: Default::default(),
-        handles: Default::default(),
-      })),
+        handles: (0..SHARDS).map(|_| Default::default()).collect(),
+        sparse: Default::default(),
+        caches: Default::default(),
+        next_chunk: AtomicU64::new(0),
+        debug_utils: AtomicBool::new(false),
+        leak_mode: Default::default(),
+        trace: Default::default(),
+        trace_error: Default::default(),
+      }),
     }
   }
 
@@ -463,6 +706,7 @@ impl Allocator {
   /// Predominatly this is important after resources have been deleted and free blocks of memory are distributed over multiple pages.
   ///
   /// We can specify a rough strategy of how resources are bound with the `bindtype` parameter. See [BindType](page/enum.BindType.html) for the different strategies
+  /// With `BindType::Alias` all resources are bound to the same memory, see also [bind_alias](struct.Allocator.html#method.bind_alias).
   ///
   /// The Allocator takes ownorship ower the resource handles.
   ///
@@ -482,6 +726,10 @@ impl Allocator {
   /// * `Error::InvalidMemoryType` - if for one or more resources no memory type is found that satisfies the memory requirements and properties
   /// * `Error::BindMemoryFailed` - if one or more resources could not be bound to device memory
   /// * `Error::AlreadyBound` - if one or more resources are already bound to device memory
+  /// * `Error::AliasMismatch` - if `bindtype` is `BindType::Alias` and the resources do not share the same memory properties, tiling and at least one memory type
+  ///
+  /// If binding an aliased resource fails, the memory block of the group is released again and none of the resources is registered.
+  /// Vulkan does not allow to bind a resource twice, resources that have already been bound to memory before the failure have to be destroyed by the caller.
   ///
   /// # Example
   /// Shows how buffers and images can be bound to the allocator. Prior to bind the resources have to be created with either `vk::CreateBuffer` or `vk::CreateImage`.
@@ -509,7 +757,7 @@ impl Allocator {
   /// #      transfer: true,
   /// #    }).create()
   /// #    .unwrap();
-  /// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+  /// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
   ///
   /// let buf = vk::NULL_HANDLE;
   /// let img = vk::NULL_HANDLE;
@@ -531,52 +779,479 @@ impl Allocator {
   /// ).expect("binding buffers failed");
   /// # }
   /// ```
-  pub fn bind(&mut self, bindinfos: &[BindInfo], bindtype: BindType) -> Result<(), Error> {
+  pub fn bind(&self, bindinfos: &[BindInfo], bindtype: BindType) -> Result<(), Error> {
     if bindinfos.is_empty() {
       return Ok(());
     }
 
+    if let BindType::Alias = bindtype {
+      return self.bind_aliased(bindinfos);
+    }
+
     // sort handles into groups of the same memory type
+    // sparse resources are only registered, their pages are bound with bind_sparse
     let mut by_memtype = HashMap::new();
+    let mut sparse = Vec::new();
     for info in bindinfos.iter() {
-      let pageinfo = BindInfoInner::new(info, self.device);
+      let pageinfo = BindInfoInner::new(info, &*self.backend);
       let memtype = Memtype {
-        index: Self::get_memtype(self.sizes.pdevice, &pageinfo.requirements, info.properties).ok_or(Error::InvalidMemoryType)?,
+        index: find_memtype(&self.backend.memory_types(), &pageinfo.requirements, info.properties).ok_or(Error::InvalidMemoryType)?,
         linear: info.linear,
       };
-      by_memtype.entry(memtype).or_insert(Vec::new()).push(pageinfo);
+      match info.sparse {
+        Some(layout) => sparse.push((memtype, self.get_sparse_residency(&pageinfo, layout))),
+        None => by_memtype.entry(memtype).or_insert(Vec::new()).push(pageinfo),
+      }
     }
 
-    let mut alloc = self.alloc.lock().unwrap();
-
     // for every group with the same memtype bind the buffers to a page table
-    let device = self.device;
+    // small resources are bound to the thread cache, if enabled
     for (memtype, infos) in by_memtype {
+      let (cached, infos) = match (bindtype, self.sizes.cache) {
+        (BindType::Scatter, Some(cache)) => infos.into_iter().partition(|i| cache.fits(&i.requirements)),
+        _ => (Vec::new(), infos),
+      };
+
+      if !infos.is_empty() {
+        let tbl = self.get_table(memtype);
+        let mut tbl = tbl.lock().unwrap();
+        tbl.bind(&infos, bindtype)?;
+        self.alloc.record(|| Event::Bind {
+          memtype,
+          block: matches!(bindtype, BindType::Block),
+          resources: trace::resources(&infos),
+        });
+        drop(tbl);
+
+        for h in infos.iter().map(|i| i.handle) {
+          self.alloc.insert_handle(h, memtype, None);
+        }
+      }
+
+      if !cached.is_empty() {
+        self.bind_cached(memtype, &cached)?;
+      }
+    }
+
+    for (memtype, (handle, residency)) in sparse {
+      self.alloc.sparse.lock().unwrap().insert(handle, residency);
+      self.alloc.insert_handle(handle, memtype, None);
+    }
+
+    Ok(())
+  }
+
+  /// Binds resources to slots of the thread cache
+  fn bind_cached(&self, memtype: Memtype, infos: &[BindInfoInner]) -> Result<(), Error> {
+    let cache = self.get_cache(memtype);
+    let mut c = cache.lock().unwrap();
+    for i in infos.iter() {
+      let slot = c.alloc(|size, alignment| self.reserve(memtype, size, alignment))?;
+      if let Err(e) = self.backend.bind(i.handle, slot.mem, slot.beg) {
+        if let Some(id) = c.free(slot) {
+          self.release(memtype, id);
+        }
+        Err(e)?
+      }
+      let block = Block::new(slot.mem, slot.beg, slot.beg + i.requirements.size, 0);
+      self.alloc.insert_handle(i.handle, memtype, Some((block, cache.clone())));
+    }
+    Ok(())
+  }
+
+  /// Gets the cache of the calling thread for the memory type, creates it if it does not exist yet
+  fn get_cache(&self, memtype: Memtype) -> Cache {
+    let key = (std::thread::current().id(), memtype);
+    if let Some(cache) = self.alloc.caches.read().unwrap().get(&key) {
+      return cache.clone();
+    }
+    let sizes = self.sizes.cache.unwrap_or_default();
+    self
+      .alloc
+      .caches
+      .write()
+      .unwrap()
+      .entry(key)
+      .or_insert_with(|| Arc::new(Mutex::new(ThreadCache::new(sizes))))
+      .clone()
+  }
+
+  /// Reserves a chunk for a thread cache in the page table of the memory type
+  fn reserve(&self, memtype: Memtype, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Result<(u64, Block), Error> {
+    let id = self.alloc.next_chunk.fetch_add(1, Ordering::Relaxed);
+    let tbl = self.get_table(memtype);
+    let mut tbl = tbl.lock().unwrap();
+    let block = tbl.reserve(id, size, alignment)?;
+    self.alloc.record(|| Event::Reserve {
+      memtype,
+      id,
+      size,
+      alignment,
+    });
+    Ok((id, block))
+  }
+
+  /// Releases a chunk of a thread cache
+  fn release(&self, memtype: Memtype, id: u64) {
+    if let Some(tbl) = self.alloc.get_table(memtype) {
+      tbl.lock().unwrap().release(id);
+      self.alloc.record(|| Event::Release { memtype, id });
+    }
+  }
+
+  /// Binds all resources to the same block of memory, see [BindType::Alias](enum.BindType.html)
+  ///
+  /// The largest resource is bound with the combined memory requirements of all resources, the others alias it.
+  fn bind_aliased(&self, bindinfos: &[BindInfo]) -> Result<(), Error> {
+    let linear = bindinfos[0].linear;
+    let properties = bindinfos[0].properties;
+    if bindinfos
+      .iter()
+      .any(|i| i.linear != linear || i.properties != properties || i.sparse.is_some())
+    {
+      Err(Error::AliasMismatch)?
+    }
+
+    let mut infos = bindinfos.iter().map(|i| BindInfoInner::new(i, &*self.backend)).collect::<Vec<_>>();
+    let requirements = infos.iter().fold(
+      vk::MemoryRequirements {
+        size: 0,
+        alignment: 1,
+        memoryTypeBits: !0,
+      },
+      |r, i| vk::MemoryRequirements {
+        size: vk::DeviceSize::max(r.size, i.requirements.size),
+        alignment: vk::DeviceSize::max(r.alignment, i.requirements.alignment),
+        memoryTypeBits: r.memoryTypeBits & i.requirements.memoryTypeBits,
+      },
+    );
+    if requirements.memoryTypeBits == 0 {
+      Err(Error::AliasMismatch)?
+    }
+    let memtype = Memtype {
+      index: find_memtype(&self.backend.memory_types(), &requirements, properties).ok_or(Error::InvalidMemoryType)?,
+      linear,
+    };
+
+    // the largest resource owns the memory block
+    let owner = (0..infos.len()).max_by_key(|i| infos[*i].requirements.size).unwrap();
+    let mut owner = infos.remove(owner);
+    owner.requirements = requirements;
+
+    let tbl = self.get_table(memtype);
+    let mut tbl = tbl.lock().unwrap();
+
+    // roll back the binding of the owner, if an alias can not be bound
+    let handle = owner.handle;
+    tbl.bind(std::slice::from_ref(&owner), BindType::Block)?;
+    if let Err(e) = tbl.bind_alias(handle, &infos) {
+      tbl.unbind(&[handle]);
+      Err(e)?
+    }
+
+    self.alloc.record(|| Event::Bind {
+      memtype,
+      block: true,
+      resources: trace::resources(std::slice::from_ref(&owner)),
+    });
+    self.alloc.record(|| Event::Alias {
+      target: handle,
+      resources: trace::resources(&infos),
+    });
+    drop(tbl);
+
+    for h in bindinfos.iter().map(|i| i.handle) {
+      self.alloc.insert_handle(h, memtype, None);
+    }
+    Ok(())
+  }
+
+  /// Bind resources to the same memory as an already bound resource
+  ///
+  /// The resources in `bindinfos` alias the memory of `target`.
+  /// The caller has to guarantee, that the lifetimes of the aliased resources do not overlap.
+  ///
+  /// Destroying `target` or one of its aliases does not free the memory. The memory is only made available again, after the last alias has been destroyed.
+  ///
+  /// # Arguments
+  /// * `target` - the resource whose memory is aliased
+  /// * `bindinfos` - Array of [BindInfo](struct.BindInfo.html) that specifies the resources to be bound
+  ///
+  /// # Returns
+  /// [Error](enum.Error.html) if the allocator failed to bind one or more resources:
+  /// * `Error::NotBound` - if `target` is not bound to this allocator
+  /// * `Error::AliasMismatch` - if a resource is larger than `target`, or the memory of `target` does not satisfy its memory type or alignment
+  /// * `Error::BindMemoryFailed` - if one or more resources could not be bound to device memory
+  pub fn bind_alias(&self, target: Handle<u64>, bindinfos: &[BindInfo]) -> Result<(), Error> {
+    let (memtype, cached) = self.alloc.get_memtype(target).ok_or(Error::NotBound)?;
+    let memtype_properties = self.backend.memory_types().get(memtype.index as usize).cloned().unwrap_or(0);
+
+    if cached || self.alloc.sparse.lock().unwrap().contains_key(&target) {
+      Err(Error::AliasMismatch)?
+    }
+
+    let mut infos = Vec::with_capacity(bindinfos.len());
+    for info in bindinfos.iter() {
+      let inner = BindInfoInner::new(info, &*self.backend);
+      if inner.requirements.memoryTypeBits & (1 << memtype.index) == 0
+        || info.sparse.is_some()
+        || info.linear != memtype.linear
+        || (memtype_properties & info.properties) != info.properties
+      {
+        Err(Error::AliasMismatch)?
+      }
+      infos.push(inner);
+    }
+
+    let tbl = self.alloc.get_table(memtype).ok_or(Error::NotBound)?;
+    let mut tbl = tbl.lock().unwrap();
+    tbl.bind_alias(target, &infos)?;
+    self.alloc.record(|| Event::Alias {
+      target,
+      resources: trace::resources(&infos),
+    });
+    drop(tbl);
+
+    for h in bindinfos.iter().map(|i| i.handle) {
+      self.alloc.insert_handle(h, memtype, None);
+    }
+    Ok(())
+  }
+
+  /// Gets the page table of the memory type, creates it if it does not exist yet
+  fn get_table(&self, memtype: Memtype) -> Arc<Mutex<Table>> {
+    if let Some(tbl) = self.alloc.get_table(memtype) {
+      return tbl;
+    }
+
+    let mut pagetbls = self.alloc.pagetbls.write().unwrap();
+    if let Entry::Vacant(e) = pagetbls.entry(memtype) {
       let pagesize = self.sizes.get_pagesize(memtype);
-      alloc
-        .pagetbls
-        .entry(memtype)
-        .or_insert_with(|| Table::new(device, memtype, pagesize))
-        .bind(&infos, bindtype)?;
-
-      for h in infos.iter().map(|i| i.handle) {
-        alloc.handles.insert(h, memtype);
+      let mappable = self
+        .backend
+        .memory_types()
+        .get(memtype.index as usize)
+        .map_or(false, |p| (p & vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT) != 0);
+      e.insert(Arc::new(Mutex::new(Table::new(self.backend.clone(), memtype, pagesize, mappable))));
+      self.alloc.record(|| Event::Table { memtype, pagesize });
+    }
+    pagetbls[&memtype].clone()
+  }
+
+  /// Creates the residency bookkeeping for a sparse resource
+  fn get_sparse_residency(&self, info: &BindInfoInner, layout: SparseLayout) -> (Handle<u64>, SparseResidency) {
+    let size = info.requirements.size;
+    let page_size = info.requirements.alignment;
+    let residency = match (info.handle, layout) {
+      (
+        Handle::Image(img),
+        SparseLayout::Image {
+          extent,
+          mip_levels,
+          array_layers,
+        },
+      ) => {
+        let mut count = 0;
+        vk::GetImageSparseMemoryRequirements(self.device, img, &mut count, std::ptr::null_mut());
+        let mut requirements = Vec::with_capacity(count as usize);
+        vk::GetImageSparseMemoryRequirements(self.device, img, &mut count, requirements.as_mut_ptr());
+        unsafe { requirements.set_len(count as usize) };
+        SparseResidency::image(size, page_size, extent, mip_levels, array_layers, requirements)
+      }
+      _ => SparseResidency::buffer(size, page_size),
+    };
+    (info.handle, residency)
+  }
+
+  /// Binds memory to pages of a sparse resource
+  ///
+  /// The resource must have been created with sparse binding flags and bound to the allocator with a [BindInfo](struct.BindInfo.html) that specifies its [SparseLayout](enum.SparseLayout.html),
+  /// e.g. with [Buffer::sparse](builder/struct.Buffer.html#method.sparse) or [Image::sparse](builder/struct.Image.html#method.sparse).
+  ///
+  /// Backing memory for the pages is allocated from the page table of the resource's memory type.
+  /// Pages that are already resident are skipped. The binding is submitted with `vk::QueueBindSparse` to `queue`, `fence` is signaled when the binding is complete.
+  ///
+  /// # Arguments
+  /// * `queue` - queue with sparse binding support
+  /// * `handle` - the sparse resource
+  /// * `pages` - the pages that are made resident
+  /// * `fence` - fence to signal, may be `vk::NULL_HANDLE`
+  ///
+  /// # Returns
+  /// [Error](enum.Error.html) if the pages could not be bound:
+  /// * `Error::NotBound` - if `handle` is not a sparse resource bound to this allocator
+  /// * `Error::InvalidSparsePage` - if a page does not address a valid region of the resource
+  /// * `Error::AllocError` - if a new page of device memory could not be allocated
+  /// * `Error::BindMemoryFailed` - if `vk::QueueBindSparse` failed
+  pub fn bind_sparse(&self, queue: vk::Queue, handle: Handle<u64>, pages: &[SparsePage], fence: vk::Fence) -> Result<(), Error> {
+    let (memtype, _) = self.alloc.get_memtype(handle).ok_or(Error::NotBound)?;
+    let mut sparse = self.alloc.sparse.lock().unwrap();
+    let residency = sparse.get_mut(&handle).ok_or(Error::NotBound)?;
+
+    let mut regions = Vec::with_capacity(pages.len());
+    let mut new_pages = Vec::with_capacity(pages.len());
+    for p in pages.iter() {
+      let region = residency.region(*p)?;
+      if !residency.is_resident(*p) && !new_pages.contains(p) {
+        regions.push(region);
+        new_pages.push(*p);
       }
     }
+    if new_pages.is_empty() {
+      return Ok(());
+    }
+
+    let page_size = residency.page_size();
+    let tbl = self.get_table(memtype);
+    let mut tbl = tbl.lock().unwrap();
+    let blocks = tbl.bind_sparse(handle, &new_pages, page_size)?;
 
+    let binds = regions
+      .iter()
+      .zip(blocks.iter())
+      .map(|(r, b)| (*r, b.mem, b.beg + b.pad))
+      .collect::<Vec<_>>();
+    if let Err(e) = crate::sparse::queue_bind_sparse(queue, handle, &binds, fence) {
+      tbl.unbind_sparse(handle, &new_pages);
+      Err(e)?
+    }
+
+    for p in new_pages {
+      residency.make_resident(p)?;
+    }
+    Ok(())
+  }
+
+  /// Unbinds memory from pages of a sparse resource
+  ///
+  /// The unbinding is submitted with `vk::QueueBindSparse` to `queue`, `fence` is signaled when the unbinding is complete.
+  /// The backing memory is returned to the page table immediately, it is only safe to reuse it for other resources after `fence` has been signaled.
+  /// Pages that are not resident are skipped.
+  ///
+  /// # Arguments
+  /// * `queue` - queue with sparse binding support
+  /// * `handle` - the sparse resource
+  /// * `pages` - the pages that are evicted
+  /// * `fence` - fence to signal, may be `vk::NULL_HANDLE`
+  pub fn unbind_sparse(&self, queue: vk::Queue, handle: Handle<u64>, pages: &[SparsePage], fence: vk::Fence) -> Result<(), Error> {
+    let (memtype, _) = self.alloc.get_memtype(handle).ok_or(Error::NotBound)?;
+    let mut sparse = self.alloc.sparse.lock().unwrap();
+    let residency = sparse.get_mut(&handle).ok_or(Error::NotBound)?;
+
+    let mut binds = Vec::with_capacity(pages.len());
+    let mut old_pages = Vec::with_capacity(pages.len());
+    for p in pages.iter() {
+      if residency.is_resident(*p) && !old_pages.contains(p) {
+        binds.push((residency.region(*p)?, vk::NULL_HANDLE, 0));
+        old_pages.push(*p);
+      }
+    }
+    if old_pages.is_empty() {
+      return Ok(());
+    }
+
+    crate::sparse::queue_bind_sparse(queue, handle, &binds, fence)?;
+
+    for p in old_pages.iter() {
+      residency.evict(*p);
+    }
+    if let Some(tbl) = self.alloc.get_table(memtype) {
+      tbl.lock().unwrap().unbind_sparse(handle, &old_pages);
+    }
     Ok(())
   }
 
+  /// Gets the residency of a sparse resource
+  ///
+  /// # Returns
+  /// A copy of the residency bookkeeping or None, if `handle` is not a sparse resource bound to this allocator.
+  pub fn get_residency(&self, handle: Handle<u64>) -> Option<SparseResidency> {
+    self.alloc.sparse.lock().unwrap().get(&handle).cloned()
+  }
+
+  /// Enables forwarding of resource labels to the driver
+  ///
+  /// If enabled, [set_label](struct.Allocator.html#method.set_label) also names the resource with `vk::SetDebugUtilsObjectNameEXT`.
+  /// The extension `VK_EXT_debug_utils` has to be enabled on the instance, otherwise naming a resource panics.
+  pub fn debug_utils(&self, enable: bool) {
+    self.alloc.debug_utils.store(enable, Ordering::Relaxed);
+  }
+
+  /// Sets the debug label of a resource
+  ///
+  /// The label is shown in [print_stats](struct.Allocator.html#method.print_stats)
+  /// and forwarded to the driver as object name, if [debug_utils](struct.Allocator.html#method.debug_utils) are enabled.
+  ///
+  /// # Returns
+  /// * `Error::NotBound` - if `handle` is not bound to this allocator
+  pub fn set_label(&self, handle: Handle<u64>, label: Label) -> Result<(), Error> {
+    let (memtype, cached) = self.alloc.get_memtype(handle).ok_or(Error::NotBound)?;
+
+    if self.alloc.debug_utils.load(Ordering::Relaxed) && !label.is_empty() {
+      let name = std::ffi::CString::new(label.to_string().replace('\0', "")).unwrap();
+      let info = vk::DebugUtilsObjectNameInfoEXT {
+        sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+        pNext: std::ptr::null(),
+        objectType: match handle {
+          Handle::Buffer(_) => vk::OBJECT_TYPE_BUFFER,
+          Handle::Image(_) => vk::OBJECT_TYPE_IMAGE,
+        },
+        objectHandle: handle.get(),
+        pObjectName: name.as_ptr(),
+      };
+      vk_check!(vk::SetDebugUtilsObjectNameEXT(self.device, &info)).ok();
+    }
+
+    match cached {
+      true => {
+        if let Some(e) = self.alloc.shard(handle).get_mut(&handle) {
+          e.label = Some(label);
+        }
+      }
+      false => self.get_table(memtype).lock().unwrap().set_label(handle, label),
+    }
+    Ok(())
+  }
+
+  /// Gets the debug label of a resource
+  ///
+  /// # Returns
+  /// A copy of the label or None, if `handle` is not bound to this allocator or has no label.
+  pub fn get_label(&self, handle: Handle<u64>) -> Option<Label> {
+    let (memtype, label) = self.alloc.shard(handle).get(&handle).map(|e| (e.memtype, e.label.clone()))?;
+    label.or_else(|| {
+      self
+        .alloc
+        .get_table(memtype)
+        .and_then(|tbl| tbl.lock().unwrap().get_label(handle).cloned())
+    })
+  }
+
+  /// Sets how resources are reported, that are still bound when the allocator is dropped
+  ///
+  /// By default leaks are reported in debug builds and ignored in release builds, see [LeakMode](enum.LeakMode.html).
+  pub fn leak_mode(&self, mode: LeakMode) {
+    *self.alloc.leak_mode.lock().unwrap() = mode;
+  }
+
+  /// Lists all resources, that are currently bound to this allocator
+  ///
+  /// Backtraces of the bind calls are only captured, if the crate is compiled with the feature `backtrace`.
+  pub fn leak_report(&self) -> LeakReport {
+    self.alloc.leak_report()
+  }
+
   /// Destroys a resource, that has been bound to this allocator
   ///
   /// see [destroy_many](struct.Allocator.html#method.destroy_many)
-  pub fn destroy(&mut self, handle: Handle<u64>) {
+  pub fn destroy(&self, handle: Handle<u64>) {
     self.destroy_many(&[handle]);
   }
 
   /// Destroys resources, that have been bound to this allocator
   ///
   /// Destroys the buffer or image and makes their associated memory available again.
+  /// Memory that is aliased by other resources is only made available again, after the last alias has been destroyed.
   ///
   /// Freeing the memory is relatively expensive compared to allocation, which is why it should be preferred to use `destroy_many` over just `destroy`.
   /// When multiple resources are destroyed in bulk, merging blocks of free memory and padding together with the freed allocation has to be done only once.
@@ -605,7 +1280,7 @@ impl Allocator {
   /// #      transfer: true,
   /// #    }).create()
   /// #    .unwrap();
-  /// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+  /// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
   ///
   /// let mut buf = vk::NULL_HANDLE;
   /// let mut img = vk::NULL_HANDLE;
@@ -617,43 +1292,121 @@ impl Allocator {
   /// #   .image_type(vk::IMAGE_TYPE_2D)
   /// #   .size(123, 123, 1)
   /// #   .usage(vk::IMAGE_USAGE_SAMPLED_BIT)
-  /// #   .bind(&mut allocator, vkmem::BindType::Scatter)
+  /// #   .bind(&allocator, vkmem::BindType::Scatter)
   /// #   .unwrap();
   /// //... create, bind, use ...
   /// allocator.destroy_many(&[Handle::Buffer(buf), Handle::Image(img)]);
   /// # }
   /// ```
-  pub fn destroy_many(&mut self, handles: &[Handle<u64>]) {
-    Self::destroy_inner(self.device, &mut self.alloc.lock().unwrap(), handles);
-  }
+  pub fn destroy_many(&self, handles: &[Handle<u64>]) {
+    // unregister the handles, resources in a thread cache are returned to their cache immediately
+    let mut by_memtype = HashMap::new();
+    let mut destroyed = Vec::with_capacity(handles.len());
+    for h in handles.iter() {
+      let e = match self.alloc.shard(*h).remove(h) {
+        Some(e) => e,
+        None => continue,
+      };
+      destroyed.push(*h);
+      match e.cached {
+        Some((slot, cache)) => {
+          if let Some(id) = cache.lock().unwrap().free(slot) {
+            self.release(e.memtype, id);
+          }
+        }
+        None => by_memtype.entry(e.memtype).or_insert(Vec::new()).push(*h),
+      }
+    }
 
-  fn destroy_inner(device: vk::Device, alloc: &mut AllocatorImpl, handles: &[Handle<u64>]) {
-    let by_memtype = handles
-      .iter()
-      .filter_map(|h| alloc.handles.get(h).map(|mt| (*h, *mt)))
-      .fold(HashMap::new(), |mut acc, (h, mt)| {
-        acc.entry(mt).or_insert(Vec::new()).push(h);
-        acc
-      });
+    // destroying a sparse resource implicitly unbinds all of its pages
+    let sparse = {
+      let mut sparse = self.alloc.sparse.lock().unwrap();
+      destroyed
+        .iter()
+        .filter_map(|h| sparse.remove(h).map(|r| (*h, r)))
+        .collect::<HashMap<_, _>>()
+    };
 
     for (mt, hs) in by_memtype.iter() {
-      alloc.pagetbls.get_mut(mt).unwrap().unbind(hs);
-    }
-
-    for h in handles.iter() {
-      if alloc.handles.remove(h).is_some() {
-        match h {
-          Handle::Buffer(h) => vk::DestroyBuffer(device, *h, std::ptr::null()),
-          Handle::Image(h) => vk::DestroyImage(device, *h, std::ptr::null()),
+      if let Some(tbl) = self.alloc.get_table(*mt) {
+        let mut tbl = tbl.lock().unwrap();
+        tbl.unbind(hs);
+        for h in hs.iter() {
+          if let Some(residency) = sparse.get(h) {
+            tbl.unbind_sparse(*h, &residency.resident_pages());
+          }
         }
+        self.alloc.record(|| Event::Destroy(hs.clone()));
       }
     }
+
+    for h in destroyed.iter() {
+      self.backend.destroy(*h);
+    }
   }
 
   /// Frees memory of unused pages
-  pub fn free_unused(&mut self) {
-    for (_, tbl) in self.alloc.lock().unwrap().pagetbls.iter_mut() {
-      tbl.free_unused();
+  pub fn free_unused(&self) {
+    let caches = self
+      .alloc
+      .caches
+      .read()
+      .unwrap()
+      .iter()
+      .map(|(k, c)| (k.1, c.clone()))
+      .collect::<Vec<_>>();
+    for (memtype, cache) in caches {
+      let mut cache = cache.lock().unwrap();
+      for id in cache.free_unused() {
+        self.release(memtype, id);
+      }
+    }
+
+    let pagetbls = self.alloc.pagetbls.read().unwrap();
+    self.alloc.record(|| Event::FreeUnused);
+    for tbl in pagetbls.values() {
+      tbl.lock().unwrap().free_unused();
+    }
+  }
+
+  /// Records all subsequent bind, destroy and free_unused calls into a [Trace](struct.Trace.html)
+  ///
+  /// The trace is written to `writer` in a compact binary format and can be replayed without a device with [Trace::replay](struct.Trace.html#method.replay),
+  /// e.g. with the `replay` example of this crate.
+  /// Recording should be started right after the allocator has been created, since resources that are already bound are not part of the trace.
+  /// Pages of sparse resources are not recorded.
+  ///
+  /// Events are buffered and only flushed to `writer` by [stop_recording](struct.Allocator.html#method.stop_recording).
+  /// Recording is stopped, if an event could not be written. The error is returned by [stop_recording](struct.Allocator.html#method.stop_recording).
+  ///
+  /// # Returns
+  /// The io error, if the trace header could not be written.
+  pub fn record(&self, writer: Box<dyn std::io::Write + Send>) -> std::io::Result<()> {
+    let mut w = TraceWriter::new(writer)?;
+    let pagetbls = self.alloc.pagetbls.read().unwrap();
+    for memtype in pagetbls.keys() {
+      w.write(&Event::Table {
+        memtype: *memtype,
+        pagesize: self.sizes.get_pagesize(*memtype),
+      })?;
+    }
+    *self.alloc.trace_error.lock().unwrap() = None;
+    *self.alloc.trace.lock().unwrap() = Some(w);
+    Ok(())
+  }
+
+  /// Stops recording and flushes the trace, see [record](struct.Allocator.html#method.record)
+  ///
+  /// # Returns
+  /// The io error, that stopped the recording early, or that occurred while flushing the trace.
+  pub fn stop_recording(&self) -> std::io::Result<()> {
+    let writer = self.alloc.trace.lock().unwrap().take();
+    if let Some(e) = self.alloc.trace_error.lock().unwrap().take() {
+      Err(e)?
+    }
+    match writer {
+      Some(mut w) => w.flush(),
+      None => Ok(()),
     }
   }
 
@@ -667,17 +1420,37 @@ impl Allocator {
     self.device
   }
 
-  fn get_mem(&self, handle: Handle<u64>) -> Option<Block> {
-    let alloc = self.alloc.lock().unwrap();
-    alloc
-      .handles
+  /// Checks if the resource is bound to this allocator
+  ///
+  /// Sparse resources are bound from the call to [bind](struct.Allocator.html#method.bind) until they are destroyed, regardless of their resident pages.
+  pub fn is_bound(&self, handle: Handle<u64>) -> bool {
+    self.alloc.shard(handle).contains_key(&handle) || self.alloc.sparse.lock().unwrap().contains_key(&handle)
+  }
+
+  /// Blocks until the device has finished all submitted work
+  pub(crate) fn wait_idle(&self) {
+    self.backend.wait_idle();
+  }
+
+  fn get_mem(&self, handle: Handle<u64>) -> Option<(Block, Arc<Page>)> {
+    let (memtype, slot) = self
+      .alloc
+      .shard(handle)
       .get(&handle)
-      .and_then(|t| alloc.pagetbls.get(&t))
-      .and_then(|tbl| tbl.get_mem(handle))
+      .map(|e| (e.memtype, e.cached.as_ref().map(|(b, _)| *b)))?;
+    let tbl = self.alloc.get_table(memtype)?;
+    let tbl = tbl.lock().unwrap();
+    let block = slot.or_else(|| tbl.get_mem(handle))?;
+    tbl.get_page(block.mem).map(|page| (block, page))
   }
 
   /// Gets a [Mapped](mapped/struct.Mapped.html) of the spicified resource handle
   ///
+  /// Host visible pages are mapped persistently, so this does not call `vk::MapMemory`.
+  /// Multiple Mapped views of resources on the same page may be alive at the same time.
+  /// A view has exclusive access to its memory, returns None if a view on the same resource (or an aliasing one) is still alive.
+  /// The Mapped borrows the allocator and can not outlive it.
+  ///
   /// # Example
   /// Creates a uniform buffer, stores some values in it and reads them back
   ///```rust
@@ -701,7 +1474,7 @@ impl Allocator {
   /// #      transfer: true,
   /// #    }).create()
   /// #    .unwrap();
-  /// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+  /// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
   ///
   /// #[derive(Debug)]
   /// struct Ub { a: u32, b: u32, c: u32 }
@@ -711,7 +1484,7 @@ impl Allocator {
   ///   .size(std::mem::size_of::<Ub>() as vk::DeviceSize)
   ///   .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT)
   ///   .devicelocal(false)
-  ///   .bind(&mut allocator, vkmem::BindType::Scatter)
+  ///   .bind(&allocator, vkmem::BindType::Scatter)
   ///   .unwrap();
   ///
   /// {
@@ -728,12 +1501,14 @@ impl Allocator {
   /// }
   /// # }
   /// ```
-  pub fn get_mapped(&self, handle: Handle<u64>) -> Option<Mapped> {
-    self.get_mem(handle).and_then(|b| Mapped::new(self.device, b).ok())
+  pub fn get_mapped(&self, handle: Handle<u64>) -> Option<Mapped<'_>> {
+    self.get_mem(handle).and_then(|(b, page)| Mapped::new(page, b).ok())
   }
 
   /// Gets a [Mapped](mapped/struct.Mapped.html) of the spicified resource handle with an offset and size in bytes
   ///
+  /// Returns None if the region overlaps with a view, that is still alive, see [get_mapped](struct.Allocator.html#method.get_mapped).
+  ///
   /// # Example
   /// Creates a `u32` shader storage buffer, mappes it with offset and writes and reads values
   ///
@@ -758,14 +1533,14 @@ impl Allocator {
   /// #      transfer: true,
   /// #    }).create()
   /// #    .unwrap();
-  /// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+  /// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
   ///
   /// let mut buf = vk::NULL_HANDLE;
   /// vkmem::Buffer::new(&mut buf)
   ///   .size(123 * std::mem::size_of::<u32>() as vk::DeviceSize)
   ///   .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_STORAGE_BUFFER_BIT)
   ///   .devicelocal(false)
-  ///   .bind(&mut allocator, vkmem::BindType::Scatter)
+  ///   .bind(&allocator, vkmem::BindType::Scatter)
   ///   .unwrap();
   ///
   /// {
@@ -784,11 +1559,11 @@ impl Allocator {
   /// }
   /// # }
   /// ```
-  pub fn get_mapped_region(&self, handle: Handle<u64>, offset: vk::DeviceSize, size: vk::DeviceSize) -> Option<Mapped> {
-    self.get_mem(handle).and_then(|b| {
+  pub fn get_mapped_region(&self, handle: Handle<u64>, offset: vk::DeviceSize, size: vk::DeviceSize) -> Option<Mapped<'_>> {
+    self.get_mem(handle).and_then(|(b, page)| {
       let region = Block::new(b.mem, b.beg + b.pad + offset, b.beg + b.pad + offset + size, 0);
       match region.beg < b.end && region.end <= b.end {
-        true => Mapped::new(self.device, region).ok(),
+        true => Mapped::new(page, region).ok(),
         false => None,
       }
     })
@@ -796,14 +1571,378 @@ impl Allocator {
 
   /// Print staticstics for the Allocator in yaml format
   pub fn print_stats(&self) -> String {
-    let alloc = self.alloc.lock().unwrap();
+    let mut pagetbls = self
+      .alloc
+      .pagetbls
+      .read()
+      .unwrap()
+      .iter()
+      .map(|(k, t)| (*k, t.clone()))
+      .collect::<Vec<_>>();
+    pagetbls.sort_by_key(|(k, _)| *k);
+
     let mut s = String::new();
+    for (_, tbl) in pagetbls {
+      write!(s, "{}", tbl.lock().unwrap().print_stats()).unwrap();
+    }
 
-    let mut keys: Vec<_> = alloc.pagetbls.keys().collect();
-    keys.sort();
-    for k in keys {
-      write!(s, "{}", alloc.pagetbls[k].print_stats()).unwrap();
+    let caches = self.alloc.caches.read().unwrap();
+    if !caches.is_empty() {
+      let (slots, reserved) = caches.values().fold((0, 0), |(slots, reserved), c| {
+        let c = c.lock().unwrap();
+        (slots + c.len(), reserved + c.reserved_size())
+      });
+      writeln!(
+        s,
+        "Thread caches: {} caches, {} slots in use, {} bytes reserved",
+        caches.len(),
+        slots,
+        reserved
+      )
+      .unwrap();
     }
     s
   }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use crate::SimulatedBackend;
+
+  pub const HOST: vk::MemoryPropertyFlags = vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT;
+  pub const LOCAL: vk::MemoryPropertyFlags = vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT;
+
+  pub fn requirements(size: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::MemoryRequirements {
+    vk::MemoryRequirements {
+      size,
+      alignment,
+      memoryTypeBits: !0,
+    }
+  }
+
+  /// Allocator with 64KiB pages on a simulated backend
+  pub fn simulated(cache: Option<CacheSizes>) -> (Allocator, Arc<SimulatedBackend>) {
+    let backend = Arc::new(SimulatedBackend::new());
+    let mut sizes = AllocatorSizes::with_requirements(vk::NULL_HANDLE, requirements(0, 1), requirements(0, 1), 1 << 16);
+    sizes.cache = cache;
+    (Allocator::with_backend(vk::NULL_HANDLE, sizes, backend.clone()), backend)
+  }
+
+  /// Creates a buffer on the simulated backend and binds it to the allocator
+  pub fn bind_buffer(
+    alloc: &Allocator,
+    backend: &SimulatedBackend,
+    size: vk::DeviceSize,
+    properties: vk::MemoryPropertyFlags,
+  ) -> Handle<u64> {
+    let h = Handle::Buffer(backend.create_buffer(requirements(size, 16)));
+    alloc.bind(&[BindInfo::new(h, properties, true)], BindType::Scatter).unwrap();
+    h
+  }
+
+  #[test]
+  fn bind_destroy() {
+    let (alloc, backend) = simulated(None);
+    let buf = bind_buffer(&alloc, &backend, 256, HOST);
+    let img = Handle::Image(backend.create_image(requirements(1024, 256)));
+    alloc.bind(&[BindInfo::new(img, LOCAL, false)], BindType::Scatter).unwrap();
+
+    let (mem, _) = backend.get_binding(buf).unwrap();
+    assert_eq!(backend.get_allocation(mem), Some((1 << 16, 1)));
+    let (mem, offset) = backend.get_binding(img).unwrap();
+    assert_eq!(backend.get_allocation(mem), Some((1 << 16, 0)));
+    assert_eq!(offset % 256, 0);
+
+    alloc.destroy_many(&[buf, img]);
+    assert_eq!(backend.resource_count(), 0);
+    alloc.free_unused();
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  fn bind_aliased(
+    alloc: &Allocator,
+    backend: &SimulatedBackend,
+    requirements: &[vk::MemoryRequirements],
+  ) -> Result<Vec<Handle<u64>>, Error> {
+    let handles = requirements
+      .iter()
+      .map(|r| Handle::Image(backend.create_image(*r)))
+      .collect::<Vec<_>>();
+    let infos = handles.iter().map(|h| BindInfo::new(*h, LOCAL, false)).collect::<Vec<_>>();
+    alloc.bind(&infos, BindType::Alias).map(|_| handles)
+  }
+
+  #[test]
+  fn alias() {
+    let (alloc, backend) = simulated(None);
+    let h = bind_aliased(
+      &alloc,
+      &backend,
+      &[requirements(256, 64), requirements(1024, 256), requirements(512, 512)],
+    )
+    .unwrap();
+
+    // all resources share the block of the largest, that satisfies the largest alignment
+    let binding = backend.get_binding(h[0]).unwrap();
+    assert!(h.iter().all(|h| backend.get_binding(*h) == Some(binding)));
+    assert_eq!(binding.1 % 512, 0);
+
+    // the memory stays occupied until the last alias is destroyed
+    let other = bind_buffer(&alloc, &backend, 1 << 15, LOCAL);
+    alloc.destroy_many(&[h[1], h[2]]);
+    let a = bind_aliased(&alloc, &backend, &[requirements(1024, 256)]).unwrap()[0];
+    assert_ne!(backend.get_binding(a), Some(binding));
+
+    // aliasing an already bound resource
+    let b = Handle::Image(backend.create_image(requirements(128, 64)));
+    alloc.bind_alias(h[0], &[BindInfo::new(b, LOCAL, false)]).unwrap();
+    assert_eq!(backend.get_binding(b), Some(binding));
+    let c = Handle::Image(backend.create_image(requirements(2048, 64)));
+    assert!(match alloc.bind_alias(h[0], &[BindInfo::new(c, LOCAL, false)]) {
+      Err(Error::AliasMismatch) => true,
+      _ => false,
+    });
+
+    alloc.destroy_many(&[h[0], b, a, other]);
+    assert!(alloc.leak_report().is_empty());
+    alloc.free_unused();
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  #[test]
+  fn alias_mismatch() {
+    let (alloc, backend) = simulated(None);
+
+    // no common memory type
+    let mut host_only = requirements(256, 64);
+    host_only.memoryTypeBits = 0b10;
+    let mut local_only = requirements(256, 64);
+    local_only.memoryTypeBits = 0b01;
+    assert!(match bind_aliased(&alloc, &backend, &[host_only, local_only]) {
+      Err(Error::AliasMismatch) => true,
+      _ => false,
+    });
+
+    // different tiling
+    let buf = Handle::Buffer(backend.create_buffer(requirements(256, 64)));
+    let img = Handle::Image(backend.create_image(requirements(256, 64)));
+    let infos = [BindInfo::new(buf, LOCAL, true), BindInfo::new(img, LOCAL, false)];
+    assert!(match alloc.bind(&infos, BindType::Alias) {
+      Err(Error::AliasMismatch) => true,
+      _ => false,
+    });
+
+    assert!(alloc.leak_report().is_empty());
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  #[test]
+  fn alias_rollback() {
+    let (alloc, backend) = simulated(None);
+    let handles = (0..3)
+      .map(|_| Handle::Image(backend.create_image(requirements(256, 64))))
+      .collect::<Vec<_>>();
+    let infos = handles.iter().map(|h| BindInfo::new(*h, LOCAL, false)).collect::<Vec<_>>();
+    backend.fail_bind(handles[2]);
+
+    assert!(match alloc.bind(&infos, BindType::Alias) {
+      Err(Error::BindMemoryFailed) => true,
+      _ => false,
+    });
+
+    // none of the resources is registered and the memory block is free again
+    assert!(alloc.leak_report().is_empty());
+    assert!(handles.iter().all(|h| alloc.get_mem(*h).is_none()));
+    let tbl = alloc.alloc.get_table(Memtype { index: 0, linear: false }).unwrap();
+    let stats = tbl.lock().unwrap().get_stats();
+    assert_eq!(stats.free, stats.allocated);
+    alloc.free_unused();
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  #[test]
+  fn leak_report() {
+    let (alloc, backend) = simulated(None);
+    let buf = bind_buffer(&alloc, &backend, 256, HOST);
+    let img = Handle::Image(backend.create_image(requirements(1024, 256)));
+    alloc.bind(&[BindInfo::new(img, LOCAL, false)], BindType::Scatter).unwrap();
+    alloc.set_label(buf, Label::new("vertices", "mesh")).unwrap();
+    alloc.destroy(img);
+
+    let report = alloc.leak_report();
+    assert_eq!(report.leaks.len(), 1);
+    let leak = &report.leaks[0];
+    assert_eq!(leak.handle, buf);
+    assert_eq!(leak.size, 256);
+    assert_eq!(leak.memtype, Memtype { index: 1, linear: true });
+    assert_eq!(leak.label, Some(Label::new("vertices", "mesh")));
+    alloc.destroy(buf);
+  }
+
+  #[test]
+  #[should_panic(expected = "Leaked 1 resources with 256 bytes")]
+  fn leak_panic() {
+    let (alloc, backend) = simulated(None);
+    bind_buffer(&alloc, &backend, 256, HOST);
+    alloc.leak_mode(LeakMode::Panic);
+  }
+
+  #[test]
+  fn cached_size() {
+    let (alloc, backend) = simulated(Some(CacheSizes { slot_size: 256, slots: 4 }));
+    let buf = bind_buffer(&alloc, &backend, 100, HOST);
+    let big = bind_buffer(&alloc, &backend, 1024, HOST);
+
+    // the view covers the resource, not the slot
+    let mapped = alloc.get_mapped(buf).unwrap();
+    assert_eq!(mapped.get_size(), 100);
+    assert_eq!(mapped.try_as_slice::<u32>().unwrap().len(), 25);
+    assert!(alloc.get_mapped_region(buf, 96, 8).is_none());
+    drop(mapped);
+
+    let report = alloc.leak_report();
+    assert_eq!(report.size(), 1124);
+    alloc.destroy_many(&[buf, big]);
+  }
+
+  #[test]
+  fn threads() {
+    let (alloc, backend) = simulated(Some(CacheSizes { slot_size: 256, slots: 8 }));
+
+    let threads = (0..8u32)
+      .map(|t| {
+        let alloc = alloc.clone();
+        let backend = backend.clone();
+        std::thread::spawn(move || {
+          for i in 0..20u32 {
+            // small buffers are bound in the cache of the thread, large ones in the page table
+            let handles = (0..16)
+              .map(|j| bind_buffer(&alloc, &backend, if j % 4 == 0 { 1024 } else { 64 }, HOST))
+              .collect::<Vec<_>>();
+            let value = |j: usize| (t << 16) | (i << 8) | j as u32;
+            for (j, h) in handles.iter().enumerate() {
+              alloc.get_mapped(*h).unwrap().try_host_to_device_slice(&[value(j); 16]).unwrap();
+            }
+            std::thread::yield_now();
+            for (j, h) in handles.iter().enumerate() {
+              assert_eq!(alloc.get_mapped(*h).unwrap().try_as_slice::<u32>().unwrap()[..16], [value(j); 16]);
+            }
+            alloc.destroy_many(&handles);
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+
+    for t in threads {
+      t.join().unwrap();
+    }
+    assert!(alloc.leak_report().is_empty());
+    assert_eq!(backend.resource_count(), 0);
+    alloc.free_unused();
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  /// Writer into a shared buffer, that fails after `limit` bytes and counts its write calls
+  struct TraceSink {
+    buf: Arc<Mutex<Vec<u8>>>,
+    writes: Arc<Mutex<usize>>,
+    limit: usize,
+  }
+
+  impl std::io::Write for TraceSink {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+      *self.writes.lock().unwrap() += 1;
+      let mut buf = self.buf.lock().unwrap();
+      if buf.len() + data.len() > self.limit {
+        Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "trace sink is full"))?
+      }
+      buf.extend_from_slice(data);
+      Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn record_trace() {
+    let (alloc, backend) = simulated(None);
+    let bound = bind_buffer(&alloc, &backend, 256, HOST);
+
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let writes = Arc::new(Mutex::new(0));
+    alloc
+      .record(Box::new(TraceSink {
+        buf: buf.clone(),
+        writes: writes.clone(),
+        limit: usize::MAX,
+      }))
+      .unwrap();
+    let h = bind_buffer(&alloc, &backend, 256, HOST);
+    alloc.destroy(h);
+
+    // events are buffered and written at once, when recording is stopped
+    assert!(buf.lock().unwrap().is_empty());
+    alloc.stop_recording().unwrap();
+    assert_eq!(*writes.lock().unwrap(), 1);
+
+    let trace = crate::Trace::read(&mut buf.lock().unwrap().as_slice()).unwrap();
+    assert!(trace.events.iter().any(|e| match e {
+      Event::Destroy(handles) => handles == &[h],
+      _ => false,
+    }));
+    alloc.destroy(bound);
+  }
+
+  #[test]
+  fn record_error() {
+    let (alloc, backend) = simulated(None);
+    // the buffers are not destroyed, so that every bind records an event
+    alloc.leak_mode(LeakMode::Ignore);
+    bind_buffer(&alloc, &backend, 256, HOST);
+
+    // the events exceed the buffer while recording, which stops the recording
+    alloc
+      .record(Box::new(TraceSink {
+        buf: Default::default(),
+        writes: Default::default(),
+        limit: 64,
+      }))
+      .unwrap();
+    for _ in 0..512 {
+      bind_buffer(&alloc, &backend, 256, HOST);
+    }
+    assert!(alloc.alloc.trace.lock().unwrap().is_none());
+    let e = alloc.stop_recording().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::WriteZero);
+
+    // the error is only reported once
+    assert!(alloc.stop_recording().is_ok());
+
+    // the events fit into the buffer, the error is returned from flushing the trace
+    alloc
+      .record(Box::new(TraceSink {
+        buf: Default::default(),
+        writes: Default::default(),
+        limit: 64,
+      }))
+      .unwrap();
+    for _ in 0..8 {
+      bind_buffer(&alloc, &backend, 256, HOST);
+    }
+    let e = alloc.stop_recording().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::WriteZero);
+  }
+
+  #[test]
+  fn drop_destroys_resources() {
+    let (alloc, backend) = simulated(None);
+    bind_buffer(&alloc, &backend, 256, HOST);
+    alloc.leak_mode(LeakMode::Ignore);
+    drop(alloc);
+    assert_eq!(backend.resource_count(), 0);
+    assert_eq!(backend.allocation_count(), 0);
+  }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::Error;
+use crate::Handle;
+
+/// Device memory operations used by the [Allocator](struct.Allocator.html) and its page tables
+///
+/// The page tables only allocate, free, map and bind device memory through this trait.
+/// The allocator queries memory requirements and memory types and destroys bound resources through it.
+/// [VulkanBackend](struct.VulkanBackend.html) forwards the calls to the vulkan device and is used by default.
+/// [SimulatedBackend](struct.SimulatedBackend.html) keeps the memory on the host, so that the allocation logic can be tested without a device.
+pub trait Backend: std::fmt::Debug + Send + Sync {
+  /// Allocates `size` bytes of device memory with the memory type index `memtype`
+  ///
+  /// Fails with [AllocError](enum.Error.html) if the memory could not be allocated.
+  fn allocate(&self, memtype: u32, size: vk::DeviceSize) -> Result<vk::DeviceMemory, Error>;
+
+  /// Frees device memory, that has been allocated with [allocate](trait.Backend.html#tymethod.allocate)
+  fn free(&self, mem: vk::DeviceMemory);
+
+  /// Maps the whole device memory into host memory
+  ///
+  /// Fails with [MapError](enum.Error.html) if the memory could not be mapped.
+  fn map(&self, mem: vk::DeviceMemory) -> Result<*mut u8, Error>;
+
+  /// Unmaps device memory, that has been mapped with [map](trait.Backend.html#tymethod.map)
+  fn unmap(&self, mem: vk::DeviceMemory);
+
+  /// Binds a buffer or image to device memory at the specified offset
+  ///
+  /// Fails with [BindMemoryFailed](enum.Error.html) if the resource could not be bound.
+  fn bind(&self, handle: Handle<u64>, mem: vk::DeviceMemory, offset: vk::DeviceSize) -> Result<(), Error>;
+
+  /// Get the memory requirements of a buffer or image
+  fn requirements(&self, handle: Handle<u64>) -> vk::MemoryRequirements;
+
+  /// Destroys a buffer or image
+  fn destroy(&self, handle: Handle<u64>);
+
+  /// Get the property flags of all memory types of the physical device, indexed by the memory type index
+  fn memory_types(&self) -> Vec<vk::MemoryPropertyFlags>;
+
+  /// Blocks until the device has finished all submitted work
+  fn wait_idle(&self);
+}
+
+/// Backend that forwards all memory operations to a vulkan device
+#[derive(Debug, Clone, Copy)]
+pub struct VulkanBackend {
+  pdevice: vk::PhysicalDevice,
+  device: vk::Device,
+}
+
+impl VulkanBackend {
+  /// Creates the backend for the specified device
+  pub fn new(pdevice: vk::PhysicalDevice, device: vk::Device) -> Self {
+    Self { pdevice, device }
+  }
+
+  /// Get the property flags of all memory types of the physical device
+  pub fn get_memory_types(pdevice: vk::PhysicalDevice) -> Vec<vk::MemoryPropertyFlags> {
+    let mut properties = std::mem::MaybeUninit::uninit();
+    vk::GetPhysicalDeviceMemoryProperties(pdevice, properties.as_mut_ptr());
+    let properties = unsafe { properties.assume_init() };
+    properties.memoryTypes[..properties.memoryTypeCount as usize]
+      .iter()
+      .map(|t| t.propertyFlags)
+      .collect()
+  }
+}
+
+impl Backend for VulkanBackend {
+  fn allocate(&self, memtype: u32, size: vk::DeviceSize) -> Result<vk::DeviceMemory, Error> {
+    let alloc_info = vk::MemoryAllocateInfo {
+      sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+      pNext: std::ptr::null(),
+      allocationSize: size,
+      memoryTypeIndex: memtype,
+    };
+
+    let mut mem = vk::NULL_HANDLE;
+    vk_check!(vk::AllocateMemory(self.device, &alloc_info, std::ptr::null(), &mut mem)).map_err(|_| Error::AllocError)?;
+    assert!(mem != vk::NULL_HANDLE);
+    Ok(mem)
+  }
+
+  fn free(&self, mem: vk::DeviceMemory) {
+    vk::FreeMemory(self.device, mem, std::ptr::null());
+  }
+
+  fn map(&self, mem: vk::DeviceMemory) -> Result<*mut u8, Error> {
+    let mut ptr = std::ptr::null_mut();
+    vk_check!(vk::MapMemory(self.device, mem, 0, vk::WHOLE_SIZE, 0, &mut ptr)).map_err(|_| Error::MapError)?;
+    Ok(ptr as *mut u8)
+  }
+
+  fn unmap(&self, mem: vk::DeviceMemory) {
+    vk::UnmapMemory(self.device, mem);
+  }
+
+  fn bind(&self, handle: Handle<u64>, mem: vk::DeviceMemory, offset: vk::DeviceSize) -> Result<(), Error> {
+    match handle {
+      Handle::Buffer(h) => vk_check!(vk::BindBufferMemory(self.device, h, mem, offset)),
+      Handle::Image(h) => vk_check!(vk::BindImageMemory(self.device, h, mem, offset)),
+    }
+    .map(|_| ())
+    .map_err(|_| Error::BindMemoryFailed)
+  }
+
+  fn requirements(&self, handle: Handle<u64>) -> vk::MemoryRequirements {
+    let mut requirements = std::mem::MaybeUninit::uninit();
+    match handle {
+      Handle::Image(i) => vk::GetImageMemoryRequirements(self.device, i, requirements.as_mut_ptr()),
+      Handle::Buffer(b) => vk::GetBufferMemoryRequirements(self.device, b, requirements.as_mut_ptr()),
+    }
+    unsafe { requirements.assume_init() }
+  }
+
+  fn destroy(&self, handle: Handle<u64>) {
+    match handle {
+      Handle::Buffer(h) => vk::DestroyBuffer(self.device, h, std::ptr::null()),
+      Handle::Image(h) => vk::DestroyImage(self.device, h, std::ptr::null()),
+    }
+  }
+
+  fn memory_types(&self) -> Vec<vk::MemoryPropertyFlags> {
+    Self::get_memory_types(self.pdevice)
+  }
+
+  fn wait_idle(&self) {
+    vk_check!(vk::DeviceWaitIdle(self.device)).ok();
+  }
+}
+
+/// Host memory chunk with the alignment that vulkan guarantees for mapped memory (`minMemoryMapAlignment`)
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Chunk([u8; 64]);
+
+struct Allocation {
+  memtype: u32,
+  size: vk::DeviceSize,
+  data: Vec<Chunk>,
+  mapped: bool,
+}
+
+#[derive(Default)]
+struct SimulatedState {
+  next: vk::DeviceMemory,
+  allocations: HashMap<vk::DeviceMemory, Allocation>,
+  bindings: HashMap<Handle<u64>, (vk::DeviceMemory, vk::DeviceSize)>,
+  resources: HashMap<Handle<u64>, vk::MemoryRequirements>,
+  fail_bind: Option<Handle<u64>>,
+  idle: usize,
+}
+
+/// Backend that simulates device memory in host memory
+///
+/// Every allocation is backed by host memory, so that mapped pointers can be read and written.
+/// The backend records resource bindings and checks the usage of the memory handles:
+/// freeing, mapping or binding memory that is not allocated, mapping memory twice and binding outside of an allocation panics.
+///
+/// Buffers and images are created with [create_buffer](struct.SimulatedBackend.html#method.create_buffer) and [create_image](struct.SimulatedBackend.html#method.create_image),
+/// querying the memory requirements of or destroying a resource that has not been created panics.
+/// The simulated device has the memory types [SIMULATED_MEMORY_TYPES](constant.SIMULATED_MEMORY_TYPES.html).
+///
+/// An optional budget limits the total size of allocated memory, allocations exceeding it fail with [AllocError](enum.Error.html).
+#[derive(Default)]
+pub struct SimulatedBackend {
+  budget: Option<vk::DeviceSize>,
+  state: Mutex<SimulatedState>,
+}
+
+/// Memory types of the [SimulatedBackend](struct.SimulatedBackend.html), a device local and a host visible and coherent one
+pub const SIMULATED_MEMORY_TYPES: [vk::MemoryPropertyFlags; 2] = [
+  vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+  vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT,
+];
+
+impl std::fmt::Debug for SimulatedBackend {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "SimulatedBackend({} allocations, {} bytes)", self.allocation_count(), self.allocated_size())
+  }
+}
+
+impl SimulatedBackend {
+  /// Creates a simulated backend with unlimited memory
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Creates a simulated backend, that can allocate at most `budget` bytes at the same time
+  pub fn with_budget(budget: vk::DeviceSize) -> Self {
+    Self {
+      budget: Some(budget),
+      state: Default::default(),
+    }
+  }
+
+  /// Get the number of live allocations
+  pub fn allocation_count(&self) -> usize {
+    self.state.lock().unwrap().allocations.len()
+  }
+
+  /// Get the summed up size of all live allocations in bytes
+  pub fn allocated_size(&self) -> vk::DeviceSize {
+    self.state.lock().unwrap().allocations.values().map(|a| a.size).sum()
+  }
+
+  /// Get the size and memory type index of an allocation
+  ///
+  /// Returns None, if `mem` is not allocated.
+  pub fn get_allocation(&self, mem: vk::DeviceMemory) -> Option<(vk::DeviceSize, u32)> {
+    self.state.lock().unwrap().allocations.get(&mem).map(|a| (a.size, a.memtype))
+  }
+
+  /// Get the memory and offset the resource was last bound to
+  ///
+  /// Returns None, if the resource has never been bound.
+  pub fn get_binding(&self, handle: Handle<u64>) -> Option<(vk::DeviceMemory, vk::DeviceSize)> {
+    self.state.lock().unwrap().bindings.get(&handle).cloned()
+  }
+
+  /// Creates a buffer with the specified memory requirements
+  pub fn create_buffer(&self, requirements: vk::MemoryRequirements) -> vk::Buffer {
+    let mut state = self.state.lock().unwrap();
+    state.next += 1;
+    let handle = state.next;
+    state.resources.insert(Handle::Buffer(handle), requirements);
+    handle
+  }
+
+  /// Creates an image with the specified memory requirements
+  pub fn create_image(&self, requirements: vk::MemoryRequirements) -> vk::Image {
+    let mut state = self.state.lock().unwrap();
+    state.next += 1;
+    let handle = state.next;
+    state.resources.insert(Handle::Image(handle), requirements);
+    handle
+  }
+
+  /// Lets every following attempt to bind `handle` fail with [BindMemoryFailed](enum.Error.html)
+  pub fn fail_bind(&self, handle: Handle<u64>) {
+    self.state.lock().unwrap().fail_bind = Some(handle);
+  }
+
+  /// Get the number of buffers and images, that have been created and not yet destroyed
+  pub fn resource_count(&self) -> usize {
+    self.state.lock().unwrap().resources.len()
+  }
+
+  /// Get the number of times [wait_idle](trait.Backend.html#tymethod.wait_idle) has been called
+  pub fn idle_count(&self) -> usize {
+    self.state.lock().unwrap().idle
+  }
+}
+
+impl Backend for SimulatedBackend {
+  fn allocate(&self, memtype: u32, size: vk::DeviceSize) -> Result<vk::DeviceMemory, Error> {
+    let mut state = self.state.lock().unwrap();
+    let allocated = state.allocations.values().map(|a| a.size).sum::<vk::DeviceSize>();
+    if self.budget.map(|b| allocated + size > b).unwrap_or(false) {
+      Err(Error::AllocError)?
+    }
+
+    state.next += 1;
+    let mem = state.next;
+    let chunks = size.div_ceil(std::mem::size_of::<Chunk>() as vk::DeviceSize) as usize;
+    state.allocations.insert(
+      mem,
+      Allocation {
+        memtype,
+        size,
+        data: vec![Chunk([0; 64]); chunks],
+        mapped: false,
+      },
+    );
+    Ok(mem)
+  }
+
+  fn free(&self, mem: vk::DeviceMemory) {
+    let mut state = self.state.lock().unwrap();
+    let alloc = state.allocations.remove(&mem).expect("free of memory that is not allocated");
+    assert!(!alloc.mapped, "free of memory that is still mapped");
+    state.bindings.retain(|_, (m, _)| *m != mem);
+  }
+
+  fn map(&self, mem: vk::DeviceMemory) -> Result<*mut u8, Error> {
+    let mut state = self.state.lock().unwrap();
+    let alloc = state.allocations.get_mut(&mem).expect("map of memory that is not allocated");
+    assert!(!alloc.mapped, "memory is already mapped");
+    alloc.mapped = true;
+    Ok(alloc.data.as_mut_ptr() as *mut u8)
+  }
+
+  fn unmap(&self, mem: vk::DeviceMemory) {
+    let mut state = self.state.lock().unwrap();
+    let alloc = state.allocations.get_mut(&mem).expect("unmap of memory that is not allocated");
+    assert!(alloc.mapped, "unmap of memory that is not mapped");
+    alloc.mapped = false;
+  }
+
+  fn bind(&self, handle: Handle<u64>, mem: vk::DeviceMemory, offset: vk::DeviceSize) -> Result<(), Error> {
+    let mut state = self.state.lock().unwrap();
+    if state.fail_bind == Some(handle) {
+      Err(Error::BindMemoryFailed)?
+    }
+    let alloc = state.allocations.get(&mem).expect("bind to memory that is not allocated");
+    assert!(offset < alloc.size, "bind outside of the allocation");
+    state.bindings.insert(handle, (mem, offset));
+    Ok(())
+  }
+
+  fn requirements(&self, handle: Handle<u64>) -> vk::MemoryRequirements {
+    *self
+      .state
+      .lock()
+      .unwrap()
+      .resources
+      .get(&handle)
+      .expect("requirements of a resource that is not created")
+  }
+
+  fn destroy(&self, handle: Handle<u64>) {
+    let mut state = self.state.lock().unwrap();
+    state.resources.remove(&handle).expect("destroy of a resource that is not created");
+    state.bindings.remove(&handle);
+  }
+
+  fn memory_types(&self) -> Vec<vk::MemoryPropertyFlags> {
+    SIMULATED_MEMORY_TYPES.to_vec()
+  }
+
+  fn wait_idle(&self) {
+    self.state.lock().unwrap().idle += 1;
+  }
+}
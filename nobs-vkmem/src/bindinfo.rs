@@ -1,6 +1,10 @@
 use vk;
 
+use crate::backend::Backend;
+use crate::table::Key;
 use crate::Handle;
+use crate::SparseLayout;
+use crate::SparsePage;
 
 /// Bundles all information for the [Allocator](struct.Allocator.html) to perform a resource memory binding.
 ///
@@ -13,6 +17,10 @@ pub struct BindInfo {
   pub handle: Handle<u64>,
   pub properties: vk::MemoryPropertyFlags,
   pub linear: bool,
+  /// Layout of the resource, if it was created with sparse binding.
+  ///
+  /// Sparse resources are not bound to memory by the allocator, their pages are bound individually with [bind_sparse](struct.Allocator.html#method.bind_sparse).
+  pub sparse: Option<SparseLayout>,
 }
 
 impl BindInfo {
@@ -26,8 +34,15 @@ impl BindInfo {
       handle,
       properties,
       linear,
+      sparse: None,
     }
   }
+
+  /// Marks the resource as sparse resource with the specified layout
+  pub fn sparse(mut self, layout: SparseLayout) -> Self {
+    self.sparse = Some(layout);
+    self
+  }
 }
 
 /// Internal bind info used only by PageTable
@@ -36,21 +51,23 @@ impl BindInfo {
 pub struct BindInfoInner {
   pub handle: Handle<u64>,
   pub requirements: vk::MemoryRequirements,
+  pub page: Option<SparsePage>,
 }
 
 impl BindInfoInner {
-  pub fn new(info: &BindInfo, device: vk::Device) -> BindInfoInner {
-    let mut requirements = std::mem::MaybeUninit::uninit();
-    match info.handle {
-      Handle::Image(i) => vk::GetImageMemoryRequirements(device, i, requirements.as_mut_ptr()),
-      Handle::Buffer(b) => vk::GetBufferMemoryRequirements(device, b, requirements.as_mut_ptr()),
+  pub fn new(info: &BindInfo, backend: &dyn Backend) -> BindInfoInner {
+    Self {
+      handle: info.handle,
+      requirements: backend.requirements(info.handle),
+      page: None,
     }
-    let handle = info.handle;
-    let requirements = unsafe { requirements.assume_init() };
+  }
 
-    Self {
-      handle,
-      requirements,
+  /// Get the key with which the binding is identified in the page table
+  pub fn key(&self) -> Key {
+    match self.page {
+      Some(p) => Key::Sparse(self.handle, p),
+      None => Key::Resource(self.handle),
     }
   }
 }
@@ -8,6 +8,13 @@ pub enum BindType {
   /// The allocator is forced to bind all resources to a single continuous block of memory.
   /// If no such block exists a new page will be allocated.
   Block,
+  /// All resources are bound to the same block of memory and alias each other.
+  /// The block is as large as the largest resource and satisfies the alignment of all resources.
+  ///
+  /// The caller has to guarantee, that the lifetimes of the aliased resources do not overlap,
+  /// e.g. intermediate images of a post processing chain, that are never used at the same time.
+  /// The memory is only made available again, after the last alias has been destroyed.
+  Alias,
 }
 
 
@@ -3,6 +3,11 @@ use crate::Allocator;
 use crate::BindInfo;
 use crate::Error;
 use crate::Handle;
+use crate::Label;
+use crate::OwnedBuffer;
+use crate::OwnedImage;
+use crate::SparseLayout;
+use crate::Trash;
 use vk;
 
 /// Accumulator for buffer and image create infos
@@ -18,6 +23,18 @@ struct ResourceBuilder<'a> {
 }
 
 impl<'a> ResourceBuilder<'a> {
+  /// Checks that all images are supported by the physical device
+  ///
+  /// Fails with [UnsupportedImage](../enum.Error.html) for the first image that is not supported.
+  fn check_images(&self, pdevice: vk::PhysicalDevice) -> Result<(), Error> {
+    for (i, info) in self.infos.iter().enumerate() {
+      if let Handle::Image(j) = info {
+        self.images[*j].check(pdevice).map_err(|e| Error::UnsupportedImage(i as u32, e))?;
+      }
+    }
+    Ok(())
+  }
+
   /// Creates the buffers or images from their respective create infos
   ///
   /// Fails, if the vulkan command does not return successfully
@@ -31,17 +48,30 @@ impl<'a> ResourceBuilder<'a> {
           let info = &self.buffers[*j];
           let mut h = vk::NULL_HANDLE;
           vk_check!(vk::CreateBuffer(device, &info.info, std::ptr::null(), &mut h)).map_err(|_| Error::CreateBufferFailed(i as u32))?;
-          BindInfo::new(Handle::Buffer(h), info.properties, true)
+          let bindinfo = BindInfo::new(Handle::Buffer(h), info.properties, true);
+          match info.info.flags & vk::BUFFER_CREATE_SPARSE_BINDING_BIT {
+            0 => bindinfo,
+            _ => bindinfo.sparse(SparseLayout::Buffer),
+          }
         }
         Handle::Image(j) => {
           let info = &self.images[*j];
+          let create_info = info.get_info();
           let mut h = vk::NULL_HANDLE;
-          vk_check!(vk::CreateImage(device, &info.info, std::ptr::null(), &mut h)).map_err(|_| Error::CreateImageFailed(i as u32))?;
-          let linear = match info.info.tiling {
+          vk_check!(vk::CreateImage(device, &create_info, std::ptr::null(), &mut h)).map_err(|_| Error::CreateImageFailed(i as u32))?;
+          let linear = match create_info.tiling {
             vk::IMAGE_TILING_LINEAR | vk::IMAGE_TILING_DRM_FORMAT_MODIFIER_EXT => true,
             _ => false,
           };
-          BindInfo::new(Handle::Image(h), info.properties, linear)
+          let bindinfo = BindInfo::new(Handle::Image(h), info.properties, linear);
+          match create_info.flags & vk::IMAGE_CREATE_SPARSE_BINDING_BIT {
+            0 => bindinfo,
+            _ => bindinfo.sparse(SparseLayout::Image {
+              extent: create_info.extent,
+              mip_levels: create_info.mipLevels,
+              array_layers: create_info.arrayLayers,
+            }),
+          }
         }
       };
       bindinfos.push(bindinfo);
@@ -64,25 +94,46 @@ impl<'a> ResourceBuilder<'a> {
   }
 
   /// Copies the locally created handles to their output reference
-  fn copy_out_handles(&mut self, bindinfos: &[BindInfo]) -> Result<(), Error> {
+  fn copy_out_handles(&mut self, bindinfos: &[BindInfo]) -> Result<Vec<Handle<u64>>, Error> {
     self
       .handles
       .iter_mut()
       .zip(bindinfos.iter())
       .for_each(|(out, info)| **out = info.handle.get());
-    Ok(())
+    Ok(bindinfos.iter().map(|i| i.handle).collect())
   }
 
   /// Create all accumulated buffers and images and bind them to the `allocator`
-  fn bind(&mut self, allocator: &mut Allocator, bindtype: BindType) -> Result<(), Error> {
+  ///
+  /// Images are checked against the capabilities of the physical device before any resource is created.
+  ///
+  /// ## Returns
+  /// The handles of the created resources in the order they were added.
+  fn bind(&mut self, allocator: &Allocator, bindtype: BindType) -> Result<Vec<Handle<u64>>, Error> {
     let device = allocator.get_device();
+    self.check_images(allocator.get_physical_device())?;
     let bindinfos = self.create_bindinfos(device)?;
     allocator
       .bind(&bindinfos, bindtype)
       .or_else(|e| Err(self.delete_bindinfos(device, &bindinfos, e)))?;
+    self.set_labels(allocator, &bindinfos)?;
     self.copy_out_handles(&bindinfos)
   }
 
+  /// Sets the labels of all resources that have been configured with a name or tag
+  fn set_labels(&self, allocator: &Allocator, bindinfos: &[BindInfo]) -> Result<(), Error> {
+    for (info, bindinfo) in self.infos.iter().zip(bindinfos.iter()) {
+      let label = match info {
+        Handle::Buffer(j) => &self.buffers[*j].label,
+        Handle::Image(j) => &self.images[*j].label,
+      };
+      if !label.is_empty() {
+        allocator.set_label(bindinfo.handle, label.clone())?;
+      }
+    }
+    Ok(())
+  }
+
   /// Add the `buffer` the the accumulator
   ///
   /// The buffer will be created when bind is called
@@ -127,7 +178,7 @@ impl<'a> ResourceBuilder<'a> {
 /// #      transfer: true,
 /// #    }).create()
 /// #    .unwrap();
-/// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+/// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
 ///
 /// let mut handles = vec![vk::NULL_HANDLE, vk::NULL_HANDLE, vk::NULL_HANDLE];
 /// let mut builder = vkmem::Resource::new();
@@ -139,7 +190,7 @@ impl<'a> ResourceBuilder<'a> {
 ///     .usage(vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_STORAGE_BUFFER_BIT)
 ///     .submit()
 /// }
-/// builder.bind(&mut allocator, vkmem::BindType::Scatter);
+/// builder.bind(&allocator, vkmem::BindType::Scatter);
 ///
 /// assert!(handles[0] != vk::NULL_HANDLE);
 /// assert!(handles[1] != vk::NULL_HANDLE);
@@ -176,8 +227,16 @@ impl<'a> Resource<'a> {
   }
 
   /// Creates resources and binds them to the specified allocator
-  pub fn bind(mut self, alloc: &mut Allocator, ty: BindType) -> Result<(), Error> {
-    self.builder.bind(alloc, ty)
+  pub fn bind(mut self, alloc: &Allocator, ty: BindType) -> Result<(), Error> {
+    self.builder.bind(alloc, ty).map(|_| ())
+  }
+
+  /// Creates resources and binds them to the allocator of `trash`
+  ///
+  /// ## Returns
+  /// The handle of the resource, that was added last.
+  fn bind_last(mut self, trash: &Trash, ty: BindType) -> Result<Handle<u64>, Error> {
+    Ok(*self.builder.bind(&trash.get_allocator(), ty)?.last().unwrap())
   }
 }
 
@@ -186,6 +245,7 @@ struct BufferCreate {
   family_indices: Vec<u32>,
   info: vk::BufferCreateInfo,
   properties: vk::MemoryPropertyFlags,
+  label: Label,
 }
 
 /// Builder pattern for creating buffer resources
@@ -235,6 +295,7 @@ impl<'a> Buffer<'a> {
           pQueueFamilyIndices: std::ptr::null(),
         },
         properties: vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+        label: Default::default(),
       },
     }
   }
@@ -273,6 +334,20 @@ impl<'a> Buffer<'a> {
     self
   }
 
+  /// Creates the buffer as sparse resource
+  ///
+  /// Sets the flag `vk::BUFFER_CREATE_SPARSE_BINDING_BIT` and if `residency` is true also `vk::BUFFER_CREATE_SPARSE_RESIDENCY_BIT`.
+  ///
+  /// Sparse buffers are not bound to memory by the allocator,
+  /// instead their pages are bound individually with [bind_sparse](../struct.Allocator.html#method.bind_sparse).
+  pub fn sparse(mut self, residency: bool) -> Self {
+    self.buffer.info.flags |= vk::BUFFER_CREATE_SPARSE_BINDING_BIT;
+    if residency {
+      self.buffer.info.flags |= vk::BUFFER_CREATE_SPARSE_RESIDENCY_BIT;
+    }
+    self
+  }
+
   /// Setst the memory properties of the buffer
   ///
   /// Sets the properties to `vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT` if `local` is true.
@@ -350,6 +425,22 @@ impl<'a> Buffer<'a> {
       .size(size)
   }
 
+  /// Sets the debug name of the buffer
+  ///
+  /// See [Label](../struct.Label.html).
+  pub fn name(mut self, name: &str) -> Self {
+    self.buffer.label.name = name.to_owned();
+    self
+  }
+
+  /// Sets the user tag of the buffer
+  ///
+  /// See [Label](../struct.Label.html).
+  pub fn tag(mut self, tag: &str) -> Self {
+    self.buffer.label.tag = tag.to_owned();
+    self
+  }
+
   /// Finishes configuration of this buffer
   ///
   /// ## Returns
@@ -370,9 +461,80 @@ impl<'a> Buffer<'a> {
   }
 
   /// Short hand for [`submit()`](struct.Image.html#method.submit).[`bind(handle)`](struct.Resource.html#method.bind)
-  pub fn bind(self, allocator: &mut Allocator, bindtype: BindType) -> Result<(), Error> {
+  pub fn bind(self, allocator: &Allocator, bindtype: BindType) -> Result<(), Error> {
     self.submit().bind(allocator, bindtype)
   }
+
+  /// Same as [bind](struct.Buffer.html#method.bind) with the allocator of `trash`, but takes ownership of this buffer
+  ///
+  /// All other resources of the builder are bound as well and written to their handles.
+  pub fn bind_owned(self, trash: &Trash, bindtype: BindType) -> Result<OwnedBuffer, Error> {
+    let h = self.submit().bind_last(trash, bindtype)?;
+    Ok(OwnedBuffer::new(h.get(), trash.clone()))
+  }
+}
+
+/// Reason why an image configuration is not supported by the physical device
+///
+/// Limits are retrieved with `vk::GetPhysicalDeviceImageFormatProperties` for the combination of format, image type, tiling, usage and create flags.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageUnsupported {
+  /// The combination of format, image type, tiling, usage and create flags is not supported at all
+  Format,
+  /// The image is cube compatible, but is not a 2D image, is not square or its number of array layers is not a multiple of 6
+  Cube,
+  /// The image is multisampled, but is not a 2D image with optimal tiling and a single mip level, or it is cube compatible
+  Multisample,
+  /// The extent exceeds the wrapped maximum extent
+  Extent(vk::Extent3D),
+  /// The number of mip levels exceeds the wrapped maximum
+  MipLevels(u32),
+  /// The number of array layers exceeds the wrapped maximum
+  ArrayLayers(u32),
+  /// The sample count is not one of the wrapped supported sample counts
+  Samples(vk::SampleCountFlags),
+}
+
+/// Get the number of mip levels of a full mip chain
+///
+/// The chain ends with a level of size 1x1x1, so this is `floor(log2(max(width, height, depth))) + 1`.
+pub fn mip_chain_levels(extent: vk::Extent3D) -> u32 {
+  let max = u32::max(extent.width, u32::max(extent.height, extent.depth));
+  32 - u32::max(max, 1).leading_zeros()
+}
+
+/// Checks the image create info against the limits of the image format properties
+fn check_limits(info: &vk::ImageCreateInfo, properties: &vk::ImageFormatProperties) -> Result<(), ImageUnsupported> {
+  let (e, max) = (info.extent, properties.maxExtent);
+  if e.width > max.width || e.height > max.height || e.depth > max.depth {
+    Err(ImageUnsupported::Extent(max))?
+  }
+  if info.mipLevels > properties.maxMipLevels {
+    Err(ImageUnsupported::MipLevels(properties.maxMipLevels))?
+  }
+  if info.arrayLayers > properties.maxArrayLayers {
+    Err(ImageUnsupported::ArrayLayers(properties.maxArrayLayers))?
+  }
+  if info.samples & properties.sampleCounts == 0 {
+    Err(ImageUnsupported::Samples(properties.sampleCounts))?
+  }
+  Ok(())
+}
+
+/// Checks the image create info against the rules of the vulkan spec, that do not depend on the physical device
+// `is_multiple_of` would raise the minimum rust version to 1.87
+#[allow(clippy::manual_is_multiple_of)]
+fn check_config(info: &vk::ImageCreateInfo) -> Result<(), ImageUnsupported> {
+  let cube = info.flags & vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT != 0;
+  if cube && (info.imageType != vk::IMAGE_TYPE_2D || info.extent.width != info.extent.height || info.arrayLayers % 6 != 0) {
+    Err(ImageUnsupported::Cube)?
+  }
+  if info.samples != vk::SAMPLE_COUNT_1_BIT
+    && (info.mipLevels != 1 || info.imageType != vk::IMAGE_TYPE_2D || info.tiling != vk::IMAGE_TILING_OPTIMAL || cube)
+  {
+    Err(ImageUnsupported::Multisample)?
+  }
+  Ok(())
 }
 
 /// Image create info plus memory properties
@@ -380,6 +542,39 @@ struct ImageCreate {
   family_indices: Vec<u32>,
   info: vk::ImageCreateInfo,
   properties: vk::MemoryPropertyFlags,
+  full_mip_chain: bool,
+  label: Label,
+}
+
+impl ImageCreate {
+  /// Get the create info with the number of mip levels resolved, if a full mip chain is requested
+  fn get_info(&self) -> vk::ImageCreateInfo {
+    let mut info = self.info;
+    if self.full_mip_chain {
+      info.mipLevels = mip_chain_levels(info.extent);
+    }
+    info
+  }
+
+  /// Checks the image configuration against the capabilities of the physical device
+  fn check(&self, pdevice: vk::PhysicalDevice) -> Result<(), ImageUnsupported> {
+    let info = self.get_info();
+    check_config(&info)?;
+
+    let mut properties = std::mem::MaybeUninit::uninit();
+    vk::make_result(vk::GetPhysicalDeviceImageFormatProperties(
+      pdevice,
+      info.format,
+      info.imageType,
+      info.tiling,
+      info.usage,
+      info.flags,
+      properties.as_mut_ptr(),
+    ))
+    .map_err(|_| ImageUnsupported::Format)?;
+
+    check_limits(&info, unsafe { &properties.assume_init() })
+  }
 }
 
 /// Builder pattern for creating image resources
@@ -416,6 +611,8 @@ impl<'a> Image<'a> {
         family_indices: Default::default(),
         info: unsafe { std::mem::MaybeUninit::zeroed().assume_init() },
         properties: 0,
+        full_mip_chain: false,
+        label: Default::default(),
       },
     }
     .defaults()
@@ -504,6 +701,45 @@ impl<'a> Image<'a> {
     self.texture2d(w, h, format).usage(vk::IMAGE_USAGE_DEPTH_STENCIL_ATTACHMENT_BIT)
   }
 
+  /// Sets the configuration to be used as a multisampled color attachment
+  ///
+  /// This is basically a [color_attachment](struct.Image.html#method.color_attachment) with `samples` samples per pixel.
+  /// Multisampled images are usually resolved, so the usage is only `vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT | vk::IMAGE_USAGE_TRANSFER_SRC_BIT`.
+  pub fn multisample_attachment(self, w: u32, h: u32, format: vk::Format, samples: vk::SampleCountFlags) -> Self {
+    self
+      .color_attachment(w, h, format)
+      .samples(samples)
+      .usage(vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT | vk::IMAGE_USAGE_TRANSFER_SRC_BIT)
+  }
+
+  /// Sets the configuration to be used as a sampled 3D texture
+  ///
+  /// This is basically a [texture2D](struct.Image.html#method.texture2D) with
+  ///  - imageType: `vk::IMAGE_TYPE_3D`
+  ///  - depth: `d`
+  pub fn texture3d(self, w: u32, h: u32, d: u32, format: vk::Format) -> Self {
+    self.texture2d(w, h, format).image_type(vk::IMAGE_TYPE_3D).depth(d)
+  }
+
+  /// Sets the configuration to be used as a sampled cube map
+  ///
+  /// This is basically a [texture2D](struct.Image.html#method.texture2D) with
+  ///  - width = height: `size`
+  ///  - arrayLayers: 6
+  ///  - flags: `vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT`
+  pub fn cubemap(self, size: u32, format: vk::Format) -> Self {
+    self.cubemap_array(size, 1, format)
+  }
+
+  /// Sets the configuration to be used as a sampled cube map array
+  ///
+  /// Same as [cubemap](struct.Image.html#method.cubemap) but with `6 * count` array layers.
+  pub fn cubemap_array(mut self, size: u32, count: u32, format: vk::Format) -> Self {
+    self = self.texture2d(size, size, format).array_layers(6 * count);
+    self.image.info.flags |= vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT;
+    self
+  }
+
   /// Set the image type
   pub fn image_type(mut self, ty: vk::ImageType) -> Self {
     self.image.info.imageType = ty;
@@ -547,6 +783,16 @@ impl<'a> Image<'a> {
   /// Set the number of mip level
   pub fn mip_levels(mut self, levels: u32) -> Self {
     self.image.info.mipLevels = levels;
+    self.image.full_mip_chain = false;
+    self
+  }
+
+  /// Use a full mip chain down to a level of size 1x1x1
+  ///
+  /// The number of mip levels is computed from the extent with [mip_chain_levels](fn.mip_chain_levels.html), when the image is created.
+  /// This way the extent may be set after the mip chain has been requested.
+  pub fn full_mip_chain(mut self) -> Self {
+    self.image.full_mip_chain = true;
     self
   }
 
@@ -590,12 +836,32 @@ impl<'a> Image<'a> {
     self
   }
 
+  /// Sets the image create flags
+  pub fn flags(mut self, flags: vk::ImageCreateFlags) -> Self {
+    self.image.info.flags = flags;
+    self
+  }
+
   /// Sets the initial layout of the image
   pub fn layout(mut self, layout: vk::ImageLayout) -> Self {
     self.image.info.initialLayout = layout;
     self
   }
 
+  /// Creates the image as sparse resource
+  ///
+  /// Sets the flag `vk::IMAGE_CREATE_SPARSE_BINDING_BIT` and if `residency` is true also `vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT`.
+  ///
+  /// Sparse images are not bound to memory by the allocator,
+  /// instead their pages and tiles are bound individually with [bind_sparse](../struct.Allocator.html#method.bind_sparse).
+  pub fn sparse(mut self, residency: bool) -> Self {
+    self.image.info.flags |= vk::IMAGE_CREATE_SPARSE_BINDING_BIT;
+    if residency {
+      self.image.info.flags |= vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT;
+    }
+    self
+  }
+
   /// Sets the memory properties of the buffer
   pub fn mem_properties(mut self, properties: vk::MemoryPropertyFlags) -> Self {
     self.image.properties = properties;
@@ -614,6 +880,22 @@ impl<'a> Image<'a> {
     }
   }
 
+  /// Sets the debug name of the image
+  ///
+  /// See [Label](../struct.Label.html).
+  pub fn name(mut self, name: &str) -> Self {
+    self.image.label.name = name.to_owned();
+    self
+  }
+
+  /// Sets the user tag of the image
+  ///
+  /// See [Label](../struct.Label.html).
+  pub fn tag(mut self, tag: &str) -> Self {
+    self.image.label.tag = tag.to_owned();
+    self
+  }
+
   /// Finishes configuration of this image
   ///
   /// ## Returns
@@ -634,7 +916,104 @@ impl<'a> Image<'a> {
   }
 
   /// Short hand for [`submit()`](struct.Image.html#method.submit).[`bind(handle)`](struct.Resource.html#method.bind)
-  pub fn bind(self, allocator: &mut Allocator, bindtype: BindType) -> Result<(), Error> {
+  pub fn bind(self, allocator: &Allocator, bindtype: BindType) -> Result<(), Error> {
     self.submit().bind(allocator, bindtype)
   }
+
+  /// Same as [bind](struct.Image.html#method.bind) with the allocator of `trash`, but takes ownership of this image
+  ///
+  /// All other resources of the builder are bound as well and written to their handles.
+  pub fn bind_owned(self, trash: &Trash, bindtype: BindType) -> Result<OwnedImage, Error> {
+    let h = self.submit().bind_last(trash, bindtype)?;
+    Ok(OwnedImage::new(h.get(), trash.clone()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn extent(width: u32, height: u32, depth: u32) -> vk::Extent3D {
+    vk::Extent3D { width, height, depth }
+  }
+
+  #[test]
+  fn mip_chain() {
+    assert_eq!(mip_chain_levels(extent(1, 1, 1)), 1);
+    assert_eq!(mip_chain_levels(extent(256, 256, 1)), 9);
+    assert_eq!(mip_chain_levels(extent(300, 17, 1)), 9);
+    assert_eq!(mip_chain_levels(extent(4, 4, 64)), 7);
+    assert_eq!(mip_chain_levels(extent(0, 0, 0)), 1);
+  }
+
+  #[test]
+  fn limits() {
+    let properties = vk::ImageFormatProperties {
+      maxExtent: extent(1024, 1024, 1),
+      maxMipLevels: 11,
+      maxArrayLayers: 6,
+      sampleCounts: vk::SAMPLE_COUNT_1_BIT | vk::SAMPLE_COUNT_4_BIT,
+      maxResourceSize: 1 << 31,
+    };
+
+    let mut handle = vk::NULL_HANDLE;
+    let image = Image::new(&mut handle).cubemap(1024, vk::FORMAT_R8G8B8A8_UNORM).full_mip_chain();
+    let info = image.image.get_info();
+    assert_eq!(info.mipLevels, 11);
+    assert!(check_limits(&info, &properties).is_ok());
+
+    let image = image.size(2048, 2048, 1);
+    assert!(match check_limits(&image.image.get_info(), &properties) {
+      Err(ImageUnsupported::Extent(e)) => e.width == 1024,
+      _ => false,
+    });
+
+    let image = image.cubemap_array(512, 2, vk::FORMAT_R8G8B8A8_UNORM);
+    assert!(match check_limits(&image.image.get_info(), &properties) {
+      Err(ImageUnsupported::ArrayLayers(6)) => true,
+      _ => false,
+    });
+
+    let image = image.multisample_attachment(512, 512, vk::FORMAT_R8G8B8A8_UNORM, vk::SAMPLE_COUNT_8_BIT);
+    assert!(match check_limits(&image.image.get_info(), &properties) {
+      Err(ImageUnsupported::Samples(s)) => s == properties.sampleCounts,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn config() {
+    let mut handle = vk::NULL_HANDLE;
+    let is_multisample = |image: &Image| match check_config(&image.image.get_info()) {
+      Err(ImageUnsupported::Multisample) => true,
+      _ => false,
+    };
+    let is_cube = |image: &Image| match check_config(&image.image.get_info()) {
+      Err(ImageUnsupported::Cube) => true,
+      _ => false,
+    };
+
+    let image = Image::new(&mut handle).multisample_attachment(512, 512, vk::FORMAT_R8G8B8A8_UNORM, vk::SAMPLE_COUNT_4_BIT);
+    assert!(check_config(&image.image.get_info()).is_ok());
+    let image = image.full_mip_chain();
+    assert!(is_multisample(&image));
+    let image = image.mip_levels(1).image_type(vk::IMAGE_TYPE_3D);
+    assert!(is_multisample(&image));
+    let image = image.image_type(vk::IMAGE_TYPE_2D).tiling(vk::IMAGE_TILING_LINEAR);
+    assert!(is_multisample(&image));
+    let image = image
+      .tiling(vk::IMAGE_TILING_OPTIMAL)
+      .array_layers(6)
+      .flags(vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT);
+    assert!(is_multisample(&image));
+
+    let image = image.cubemap(512, vk::FORMAT_R8G8B8A8_UNORM);
+    assert!(check_config(&image.image.get_info()).is_ok());
+    let image = image.image_type(vk::IMAGE_TYPE_3D);
+    assert!(is_cube(&image));
+    let image = image.image_type(vk::IMAGE_TYPE_2D).size(512, 256, 1);
+    assert!(is_cube(&image));
+    let image = image.size(512, 512, 1).array_layers(4);
+    assert!(is_cube(&image));
+  }
 }
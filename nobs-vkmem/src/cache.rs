@@ -0,0 +1,184 @@
+use crate::block::Block;
+use crate::Error;
+
+/// Sizes of the per-thread caches of the [Allocator](struct.Allocator.html)
+///
+/// If enabled in the [AllocatorSizes](struct.AllocatorSizes.html), every thread gets its own cache of small blocks for every memory type.
+/// The cache reserves chunks of `slot_size * slots` bytes from the page table and hands out fixed size slots of these chunks.
+/// Binding and destroying resources in the cache only locks the cache of the thread, not the page table of the memory type.
+///
+/// Only resources bound with [BindType::Scatter](enum.BindType.html), whose size and alignment do not exceed `slot_size`, are bound in the cache.
+/// Resources in a cache can not be aliased.
+///
+/// Caches of threads, that have exited, are kept until the allocator is dropped. Their unused chunks are released with [free_unused](struct.Allocator.html#method.free_unused).
+#[derive(Debug, Clone, Copy)]
+pub struct CacheSizes {
+  /// Size of a slot in bytes, must be a power of two
+  pub slot_size: vk::DeviceSize,
+  /// Number of slots, that are reserved at once
+  pub slots: u32,
+}
+
+impl Default for CacheSizes {
+  /// Creates caches with 256 slots of 256 bytes
+  fn default() -> Self {
+    Self {
+      slot_size: 256,
+      slots: 256,
+    }
+  }
+}
+
+impl CacheSizes {
+  /// Checks if a resource with the memory requirements is bound in a cache
+  pub fn fits(&self, requirements: &vk::MemoryRequirements) -> bool {
+    requirements.size <= self.slot_size && requirements.alignment <= self.slot_size
+  }
+}
+
+/// Chunk of slots, that is reserved in a page table
+#[derive(Debug)]
+struct Chunk {
+  id: u64,
+  block: Block,
+  free: Vec<u32>,
+}
+
+impl Chunk {
+  fn base(&self) -> vk::DeviceSize {
+    self.block.beg + self.block.pad
+  }
+}
+
+/// Cache of small blocks for a single thread and memory type
+#[derive(Debug)]
+pub(crate) struct ThreadCache {
+  sizes: CacheSizes,
+  chunks: Vec<Chunk>,
+}
+
+impl ThreadCache {
+  pub fn new(sizes: CacheSizes) -> Self {
+    debug_assert!(sizes.slot_size.is_power_of_two(), "slot size must be a power of two");
+    Self {
+      sizes,
+      chunks: Default::default(),
+    }
+  }
+
+  /// Takes a free slot from the cache
+  ///
+  /// If all slots are taken, a new chunk is reserved with `reserve(size, alignment)`, that returns the id and block of the chunk.
+  pub fn alloc<F: FnOnce(vk::DeviceSize, vk::DeviceSize) -> Result<(u64, Block), Error>>(&mut self, reserve: F) -> Result<Block, Error> {
+    let size = self.sizes.slot_size;
+    if !self.chunks.iter().any(|c| !c.free.is_empty()) {
+      let (id, block) = reserve(size * self.sizes.slots as vk::DeviceSize, size)?;
+      self.chunks.push(Chunk {
+        id,
+        block,
+        free: (0..self.sizes.slots).rev().collect(),
+      });
+    }
+
+    let c = self.chunks.iter_mut().find(|c| !c.free.is_empty()).unwrap();
+    let slot = c.free.pop().unwrap() as vk::DeviceSize;
+    let beg = c.base() + slot * size;
+    Ok(Block::new(c.block.mem, beg, beg + size, 0))
+  }
+
+  /// Returns a slot to the cache
+  ///
+  /// The slot is identified by the memory and begin of `slot`, so any block starting at the slot may be passed.
+  /// Returns the id of a chunk, that is no longer used and should be released from the page table.
+  /// At most one unused chunk is kept in the cache.
+  pub fn free(&mut self, slot: Block) -> Option<u64> {
+    let size = self.sizes.slot_size;
+    let slots = self.sizes.slots as vk::DeviceSize;
+    let i = self
+      .chunks
+      .iter()
+      .position(|c| c.block.mem == slot.mem && slot.beg >= c.base() && slot.beg < c.base() + size * slots)?;
+
+    let c = &mut self.chunks[i];
+    c.free.push(((slot.beg - c.base()) / size) as u32);
+
+    let unused = |c: &Chunk| c.free.len() == slots as usize;
+    match unused(&self.chunks[i]) && self.chunks.iter().filter(|c| unused(c)).count() > 1 {
+      true => Some(self.chunks.remove(i).id),
+      false => None,
+    }
+  }
+
+  /// Removes all unused chunks from the cache
+  ///
+  /// Returns the ids of the removed chunks, that should be released from the page table.
+  pub fn free_unused(&mut self) -> Vec<u64> {
+    let slots = self.sizes.slots as usize;
+    let (unused, used) = self.chunks.drain(..).partition::<Vec<_>, _>(|c| c.free.len() == slots);
+    self.chunks = used;
+    unused.into_iter().map(|c| c.id).collect()
+  }
+
+  /// Get the number of slots, that are taken
+  pub fn len(&self) -> usize {
+    self.chunks.iter().map(|c| self.sizes.slots as usize - c.free.len()).sum()
+  }
+
+  /// Get the size of all reserved chunks in bytes
+  pub fn reserved_size(&self) -> vk::DeviceSize {
+    self.chunks.len() as vk::DeviceSize * self.sizes.slot_size * self.sizes.slots as vk::DeviceSize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::table::Table;
+  use crate::Memtype;
+  use crate::SimulatedBackend;
+  use std::sync::Arc;
+
+  #[test]
+  fn slots() {
+    let mut tbl = Table::new(
+      Arc::new(SimulatedBackend::new()),
+      Memtype { index: 0, linear: true },
+      1 << 16,
+      false,
+    );
+    let mut next = 0;
+    let mut reserve = |size, alignment| {
+      next += 1;
+      tbl.reserve(next, size, alignment).map(|b| (next, b))
+    };
+
+    let sizes = CacheSizes { slot_size: 64, slots: 4 };
+    let mut cache = ThreadCache::new(sizes);
+    let blocks = (0..9).map(|_| cache.alloc(&mut reserve).unwrap()).collect::<Vec<_>>();
+    assert_eq!(cache.len(), 9);
+    assert_eq!(cache.reserved_size(), 3 * 256);
+
+    for (i, b) in blocks.iter().enumerate() {
+      assert_eq!(b.size(), 64);
+      assert_eq!(b.beg % 64, 0);
+      assert!(
+        blocks[i + 1..].iter().all(|o| o.mem != b.mem || o.end <= b.beg || o.beg >= b.end),
+        "slots overlap"
+      );
+    }
+
+    // the first unused chunk is kept, the second is released
+    assert!(blocks[..4].iter().all(|b| cache.free(*b).is_none()));
+    assert_eq!(cache.free(blocks[8]), Some(3));
+    assert_eq!(cache.reserved_size(), 2 * 256);
+
+    // slots of the unused chunk are reused
+    let b = cache.alloc(|_, _| panic!("no chunk should be reserved")).unwrap();
+    assert!(blocks[..4].contains(&b));
+    cache.free(b);
+
+    assert_eq!(cache.free_unused(), vec![1]);
+    assert_eq!(cache.len(), 4);
+    assert_eq!(cache.reserved_size(), 256);
+  }
+}
@@ -0,0 +1,37 @@
+/// Debug name and user tag of a resource
+///
+/// Labels are shown in the [statistics](struct.Allocator.html#method.print_stats) of the allocator.
+/// If [debug utils](struct.Allocator.html#method.debug_utils) are enabled, they are also forwarded to the driver as object names,
+/// so that validation messages and graphics debuggers show the label instead of a raw handle.
+///
+/// The tag groups resources by their purpose (e.g. "mesh", "gui", "shadow"), while the name identifies a single resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Label {
+  pub name: String,
+  pub tag: String,
+}
+
+impl Label {
+  /// Creates a label from name and tag
+  pub fn new(name: &str, tag: &str) -> Self {
+    Self {
+      name: name.to_owned(),
+      tag: tag.to_owned(),
+    }
+  }
+
+  /// Checks if neither name nor tag are set
+  pub fn is_empty(&self) -> bool {
+    self.name.is_empty() && self.tag.is_empty()
+  }
+}
+
+impl std::fmt::Display for Label {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match (self.name.is_empty(), self.tag.is_empty()) {
+      (_, true) => write!(f, "{}", self.name),
+      (true, false) => write!(f, "[{}]", self.tag),
+      (false, false) => write!(f, "{} [{}]", self.name, self.tag),
+    }
+  }
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use crate::Handle;
+use crate::Label;
+use crate::Memtype;
+
+/// Backtrace, that is captured when a resource is bound
+///
+/// Backtraces are only captured, if the crate is compiled with the feature `backtrace`.
+pub type Backtrace = Arc<std::backtrace::Backtrace>;
+
+#[cfg(feature = "backtrace")]
+pub(crate) fn capture() -> Option<Backtrace> {
+  Some(Arc::new(std::backtrace::Backtrace::force_capture()))
+}
+
+#[cfg(not(feature = "backtrace"))]
+pub(crate) fn capture() -> Option<Backtrace> {
+  None
+}
+
+/// Defines how the [Allocator](struct.Allocator.html) reports resources, that are still bound when it is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeakMode {
+  /// Leaked resources are destroyed silently
+  Ignore,
+  /// The [LeakReport](struct.LeakReport.html) is printed to stderr
+  Report,
+  /// Panics with the [LeakReport](struct.LeakReport.html) as message, this is meant to be used in tests
+  ///
+  /// If the thread is already panicking, the report is only printed.
+  Panic,
+}
+
+impl Default for LeakMode {
+  /// Reports leaks in debug builds and ignores them in release builds
+  fn default() -> Self {
+    match cfg!(debug_assertions) {
+      true => LeakMode::Report,
+      false => LeakMode::Ignore,
+    }
+  }
+}
+
+/// A resource, that is still bound to the [Allocator](struct.Allocator.html)
+#[derive(Debug, Clone)]
+pub struct Leak {
+  pub handle: Handle<u64>,
+  /// Size of the memory block of the resource in bytes, for sparse resources the size of all resident pages
+  pub size: vk::DeviceSize,
+  pub memtype: Memtype,
+  pub label: Option<Label>,
+  /// Backtrace of the call that bound the resource, only available with the feature `backtrace`
+  pub backtrace: Option<Backtrace>,
+}
+
+/// List of all resources, that are still bound to the [Allocator](struct.Allocator.html)
+///
+/// Created on demand with [leak_report](struct.Allocator.html#method.leak_report) or automatically when the allocator is dropped, see [LeakMode](enum.LeakMode.html).
+#[derive(Debug, Clone, Default)]
+pub struct LeakReport {
+  pub leaks: Vec<Leak>,
+}
+
+impl LeakReport {
+  /// Checks if there are no leaked resources
+  pub fn is_empty(&self) -> bool {
+    self.leaks.is_empty()
+  }
+
+  /// Get the summed up size of all leaked resources in bytes
+  pub fn size(&self) -> vk::DeviceSize {
+    self.leaks.iter().map(|l| l.size).sum()
+  }
+}
+
+impl std::fmt::Display for LeakReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    writeln!(f, "Leaked {} resources with {} bytes:", self.leaks.len(), self.size())?;
+    for l in self.leaks.iter() {
+      write!(f, "  {:?} {} bytes {}", l.handle, l.size, l.memtype)?;
+      if let Some(label) = l.label.as_ref() {
+        write!(f, " {}", label)?;
+      }
+      writeln!(f)?;
+      if let Some(bt) = l.backtrace.as_ref() {
+        for line in bt.to_string().lines() {
+          writeln!(f, "      {}", line)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn report() {
+    let report = LeakReport {
+      leaks: vec![
+        Leak {
+          handle: Handle::Buffer(0x10),
+          size: 256,
+          memtype: Memtype { index: 1, linear: true },
+          label: Some(Label::new("vertices", "mesh")),
+          backtrace: None,
+        },
+        Leak {
+          handle: Handle::Image(0x20),
+          size: 1024,
+          memtype: Memtype { index: 0, linear: false },
+          label: None,
+          backtrace: None,
+        },
+      ],
+    };
+
+    assert_eq!(report.size(), 1280);
+    assert_eq!(
+      report.to_string(),
+      "Leaked 2 resources with 1280 bytes:\n  Buffer(16) 256 bytes memtype(1, linear) vertices [mesh]\n  Image(32) 1024 bytes memtype(0, non-linear)\n"
+    );
+  }
+
+  #[test]
+  fn leak_mode_default() {
+    // leaks are noticed in debug builds without opting in
+    match cfg!(debug_assertions) {
+      true => assert_eq!(LeakMode::default(), LeakMode::Report),
+      false => assert_eq!(LeakMode::default(), LeakMode::Ignore),
+    }
+  }
+}
@@ -11,7 +11,13 @@
 //! 1. Easy buffer and image creation with builder patterns.
 //! 2. Device memory is allocated in larger pages. The crate keeps track of free and used regions in a page.
 //! 3. Offers different allocation strategies for different purposes, including forcing the binding of several resources to a continuous block, or binding resources on private pages.
-//! 4. Easy mapping of host accessible buffers
+//! 4. Easy mapping of host accessible buffers, host visible pages stay mapped persistently
+//! 5. Aliasing of memory for transient resources, whose lifetimes do not overlap
+//! 6. Page granular binding of sparse buffers and images
+//! 7. Device memory is managed through a [Backend](trait.Backend.html), the allocation logic can be tested with a simulated backend without a device
+//! 8. Allocator calls can be recorded into a [Trace](struct.Trace.html) and replayed without a device to analyze peak usage and fragmentation
+//! 9. Owning [buffer](struct.OwnedBuffer.html) and [image](struct.OwnedImage.html) handles, that are destroyed deferred through the [Trash](struct.Trash.html) when dropped
+//! 10. The [Allocator](struct.Allocator.html) is internally synchronized with a lock per memory type and optional [per-thread caches](struct.CacheSizes.html) of small blocks
 //!
 //! Interfacing with this crate is mainly handled in [Allocator](struct.Allocator.html), with which buffers and images are bound to device memory.
 //!
@@ -20,27 +26,63 @@
 //! See [Allocator](struct.Allocator.html) to get a quick overview on how to use create vulkan buffers and images with this crate.
 #[macro_use]
 extern crate nobs_vk as vk;
+extern crate nobs_vkmath as vkm;
 
 mod allocator;
+mod backend;
 mod bindinfo;
 mod bindtype;
 mod block;
 mod builder;
+mod cache;
 mod handle;
+mod label;
+mod leak;
 mod mapped;
 mod memtype;
+mod owned;
+mod page;
+mod pod;
+mod sparse;
 mod table;
+mod trace;
 mod trash;
 
 pub use allocator::*;
+pub use backend::Backend;
+pub use backend::SimulatedBackend;
+pub use backend::SIMULATED_MEMORY_TYPES;
+pub use backend::VulkanBackend;
 pub use bindinfo::BindInfo;
 pub use bindtype::BindType;
 pub use builder::Buffer;
+pub use builder::mip_chain_levels;
 pub use builder::Image;
+pub use builder::ImageUnsupported;
 pub use builder::Resource;
+pub use cache::CacheSizes;
 pub use handle::Handle;
+pub use label::Label;
+pub use leak::Leak;
+pub use leak::LeakMode;
+pub use leak::LeakReport;
 pub use mapped::Mapped;
 pub use memtype::Memtype;
+pub use owned::OwnedBuffer;
+pub use owned::OwnedImage;
+pub use pod::assert_pod;
+pub use pod::Pod;
+pub use sparse::SparseLayout;
+pub use sparse::SparsePage;
+pub use sparse::SparseRegion;
+pub use sparse::SparseResidency;
+pub use trace::Event;
+pub use trace::ReplayReport;
+pub use trace::Sample;
+pub use trace::Trace;
+pub use trace::TraceResource;
+pub use trash::Garbage;
+pub use trash::Retire;
 pub use trash::Trash;
 
 /// Errors that can be occure when using this crate
@@ -62,12 +104,27 @@ pub enum Error {
   /// Indicates, that an image create returned unsuccessfull.
   /// The wrapped value is the index of the resource that could not be created.
   CreateImageFailed(u32),
+  /// Indicates, that an image configuration is not supported by the physical device.
+  /// The wrapped values are the index of the resource and the reason, why it is not supported.
+  UnsupportedImage(u32, ImageUnsupported),
   /// Indicates, that binding a buffer or image failed
   BindMemoryFailed,
   /// Indicates, that a resource was bound multiple times
   AlreadyBound,
+  /// Indicates, that a resource is not bound to the allocator
+  NotBound,
+  /// Indicates, that resources can not alias the same memory, because their memory requirements are not compatible
+  AliasMismatch,
+  /// Indicates, that a sparse page does not address a valid region of the sparse resource
+  InvalidSparsePage,
   /// indicates, that the requested memory region could not be mapped
   MapError,
+  /// Indicates, that the requested memory region overlaps with a [Mapped](struct.Mapped.html) view, that is still alive
+  AlreadyMapped,
+  /// Indicates, that a typed view on mapped memory does not satisfy the alignment of the type
+  UnalignedAccess,
+  /// Indicates, that the size of a mapped memory region does not match the size of a typed view
+  SizeMismatch,
 }
 
 /// Wrapper around an [Allocator](struct.Alloctator.html) and [Trash](struct.Trash.html).
@@ -1,40 +1,49 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
 use crate::block::Block;
+use crate::page::Page;
 use crate::Error;
+use crate::Pod;
 use vk;
 
 /// A mapped memory region
 ///
-/// Automatically unmapps the memory when the instance goes out of scope
+/// Host visible pages are mapped persistently by the [Allocator](struct.Allocator.html).
+/// A Mapped is only a view into such a page and holds a reference to it, so that the page's memory stays valid for as long as the view is alive,
+/// even if the resource is destroyed in the meantime.
+/// Creating and dropping a Mapped does not call `vk::MapMemory` or `vk::UnmapMemory`.
+///
+/// The view borrows the allocator it was created from, so that it can not outlive the allocator and with it the device, that frees the page.
+///
+/// A view has exclusive access to its memory region.
+/// As long as a Mapped is alive, no other view on an overlapping region of the same page can be created,
+/// so that slices handed out by [try_as_slice_mut](struct.Mapped.html#method.try_as_slice_mut) can not alias memory of another view.
 #[derive(Debug)]
-pub struct Mapped {
-  device: vk::Device,
+pub struct Mapped<'a> {
+  page: Arc<Page>,
   block: Block,
   ptr: *mut u8,
+  alloc: PhantomData<&'a ()>,
 }
 
-impl Drop for Mapped {
-  fn drop(&mut self) {
-    vk::UnmapMemory(self.device, self.block.mem);
-  }
-}
-
-impl Mapped {
-  /// Mapps the memory described by `block`
-  pub fn new(device: vk::Device, block: Block) -> Result<Mapped, Error> {
-    let mut ptr = std::ptr::null_mut();
-    vk_check!(vk::MapMemory(
-      device,
-      block.mem,
-      block.beg + block.pad,
-      block.size_padded(),
-      0,
-      &mut ptr
-    ))
-    .map_err(|_| Error::MapError)?;
+impl<'a> Mapped<'a> {
+  /// Creates a view on the memory described by `block`
+  ///
+  /// Fails with
+  ///  - [MapError](enum.Error.html) if the page is not mapped or `block` does not lie inside of `page`
+  ///  - [AlreadyMapped](enum.Error.html) if another view on an overlapping region of `page` is still alive
+  pub(crate) fn new(page: Arc<Page>, block: Block) -> Result<Mapped<'a>, Error> {
+    if block.mem != page.get_mem() || block.end > page.get_size() {
+      Err(Error::MapError)?
+    }
+    let ptr = page.get_ptr(block.beg + block.pad).ok_or(Error::MapError)?;
+    page.acquire_view(block.beg + block.pad, block.end)?;
     Ok(Mapped {
-      device,
+      page,
       block,
-      ptr: unsafe { std::mem::transmute(ptr) },
+      ptr,
+      alloc: PhantomData,
     })
   }
 
@@ -79,8 +88,249 @@ impl Mapped {
     unsafe { std::slice::from_raw_parts_mut(self.as_ptr_mut::<T>(), self.block.size_padded() as usize / std::mem::size_of::<T>()) }
   }
 
+  /// Checks that a slice of `T` can be viewed on this mapped region
+  ///
+  /// Returns the number of elements of the slice.
+  fn check_slice<T: Pod>(&self) -> Result<usize, Error> {
+    check_view::<T>(self.ptr, self.get_size())
+  }
+
+  /// Checks that a single `T` fits into this mapped region
+  fn check_size<T: Pod>(&self, count: usize) -> Result<(), Error> {
+    match ((count * std::mem::size_of::<T>()) as vk::DeviceSize) <= self.get_size() {
+      true => Ok(()),
+      false => Err(Error::SizeMismatch),
+    }
+  }
+
+  /// Copies memory from the mapped region on the device to a value of `T`
+  ///
+  /// Fails with [SizeMismatch](enum.Error.html) if `T` is larger than the mapped region.
+  pub fn try_device_to_host<T: Pod>(&self) -> Result<T, Error> {
+    self.check_size::<T>(1)?;
+    Ok(unsafe { std::ptr::read_unaligned(self.ptr as *const T) })
+  }
+  /// Copies memory from the mapped region on the device to `dst`
+  ///
+  /// Fails with [SizeMismatch](enum.Error.html) if `dst` is larger than the mapped region.
+  pub fn try_device_to_host_slice<T: Pod>(&self, dst: &mut [T]) -> Result<(), Error> {
+    self.check_size::<T>(dst.len())?;
+    unsafe { std::ptr::copy_nonoverlapping(self.ptr, dst.as_mut_ptr() as *mut u8, std::mem::size_of_val(dst)) };
+    Ok(())
+  }
+
+  /// Copies memory from `src` to the mapped region on the device
+  ///
+  /// Borrows the view mutably, so that no slice from [try_as_slice](struct.Mapped.html#method.try_as_slice) is alive while writing.
+  /// Fails with [SizeMismatch](enum.Error.html) if `T` is larger than the mapped region.
+  pub fn try_host_to_device<T: Pod>(&mut self, src: &T) -> Result<(), Error> {
+    self.try_host_to_device_slice(std::slice::from_ref(src))
+  }
+  /// Copies memory from `src` to the mapped region on the device
+  ///
+  /// Fails with [SizeMismatch](enum.Error.html) if `src` is larger than the mapped region.
+  pub fn try_host_to_device_slice<T: Pod>(&mut self, src: &[T]) -> Result<(), Error> {
+    self.check_size::<T>(src.len())?;
+    unsafe { std::ptr::copy_nonoverlapping(src.as_ptr() as *const u8, self.ptr, std::mem::size_of_val(src)) };
+    Ok(())
+  }
+
+  /// Get a typed slice from the mapped memory
+  ///
+  /// Fails with
+  ///  - [UnalignedAccess](enum.Error.html) if the mapped region does not satisfy the alignment of `T`
+  ///  - [SizeMismatch](enum.Error.html) if the size of the mapped region is not a multiple of the size of `T`
+  pub fn try_as_slice<T: Pod>(&self) -> Result<&[T], Error> {
+    let len = self.check_slice::<T>()?;
+    Ok(unsafe { std::slice::from_raw_parts(self.ptr as *const T, len) })
+  }
+
+  /// Get a typed mutable slice from the mapped memory
+  ///
+  /// Fails with the same errors as [try_as_slice](struct.Mapped.html#method.try_as_slice).
+  pub fn try_as_slice_mut<T: Pod>(&mut self) -> Result<&mut [T], Error> {
+    let len = self.check_slice::<T>()?;
+    Ok(unsafe { std::slice::from_raw_parts_mut(self.ptr as *mut T, len) })
+  }
+
   /// Get the size of the mapped reqion
   pub fn get_size(&self) -> vk::DeviceSize {
     self.block.size_padded()
   }
+
+  /// Get the device memory handle of the page this region is mapped from
+  pub fn get_mem(&self) -> vk::DeviceMemory {
+    self.page.get_mem()
+  }
+}
+
+impl<'a> Drop for Mapped<'a> {
+  fn drop(&mut self) {
+    self.page.release_view(self.block.beg + self.block.pad, self.block.end);
+  }
+}
+
+/// Checks that the memory at `ptr` with `size` bytes can be viewed as a slice of `T`
+///
+/// Returns the number of elements of the slice.
+// `is_multiple_of` would raise the minimum rust version to 1.87
+#[allow(clippy::manual_is_multiple_of)]
+fn check_view<T: Pod>(ptr: *const u8, size: vk::DeviceSize) -> Result<usize, Error> {
+  let elem = std::mem::size_of::<T>() as vk::DeviceSize;
+  if elem == 0 || size % elem != 0 {
+    Err(Error::SizeMismatch)?
+  }
+  if (ptr as usize) % std::mem::align_of::<T>() != 0 {
+    Err(Error::UnalignedAccess)?
+  }
+  Ok((size / elem) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::allocator::tests::*;
+  use crate::Handle;
+  use crate::SimulatedBackend;
+
+  #[test]
+  fn view_size() {
+    let mem = [0u64; 4];
+    let ptr = mem.as_ptr() as *const u8;
+    assert_eq!(check_view::<u32>(ptr, 32).unwrap(), 8);
+    assert_eq!(check_view::<vkm::Vec4<f32>>(ptr, 32).unwrap(), 2);
+    assert!(match check_view::<vkm::Vec3<f32>>(ptr, 32) {
+      Err(Error::SizeMismatch) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn view_alignment() {
+    let mem = [0u64; 4];
+    let ptr = mem.as_ptr() as *const u8;
+    assert!(check_view::<u8>(unsafe { ptr.add(1) }, 8).is_ok());
+    assert!(match check_view::<u32>(unsafe { ptr.add(2) }, 8) {
+      Err(Error::UnalignedAccess) => true,
+      _ => false,
+    });
+  }
+
+  crate::pod! {
+    struct Ub {
+      a: u32,
+      b: vkm::Vec3<f32>,
+      c: [f32; 4],
+    }
+  }
+
+  #[test]
+  fn page_views() {
+    let backend = Arc::new(SimulatedBackend::new());
+    let page = Arc::new(Page::new(backend.clone(), 1, 1024, true).unwrap());
+    let mem = page.get_mem();
+
+    let mut a = Mapped::new(page.clone(), Block::new(mem, 0, 64, 0)).unwrap();
+    let mut b = Mapped::new(page.clone(), Block::new(mem, 64, 256, 64)).unwrap();
+    assert_eq!(a.get_size(), 64);
+    assert_eq!(b.get_size(), 128);
+    assert_eq!(b.as_ptr::<u8>() as usize - a.as_ptr::<u8>() as usize, 128);
+
+    a.try_host_to_device_slice(&[1u32, 2, 3, 4]).unwrap();
+    b.try_host_to_device(&[5u32; 32]).unwrap();
+    assert_eq!(a.try_device_to_host::<[u32; 4]>().unwrap(), [1, 2, 3, 4]);
+    assert_eq!(b.try_as_slice::<u32>().unwrap(), &[5; 32][..]);
+    assert!(match a.try_host_to_device(&[0u32; 17]) {
+      Err(Error::SizeMismatch) => true,
+      _ => false,
+    });
+
+    // blocks outside of the page or on a different memory can not be viewed
+    assert!(Mapped::new(page.clone(), Block::new(mem, 512, 2048, 0)).is_err());
+    assert!(Mapped::new(page.clone(), Block::new(mem + 1, 0, 64, 0)).is_err());
+    assert!(match Mapped::new(page.clone(), Block::new(mem, 32, 96, 0)) {
+      Err(Error::AlreadyMapped) => true,
+      _ => false,
+    });
+
+    // the page stays mapped as long as a view is alive
+    drop(page);
+    assert_eq!(backend.allocation_count(), 1);
+    assert_eq!(b.try_as_slice::<u32>().unwrap()[31], 5);
+    drop(a);
+    drop(b);
+    assert_eq!(backend.allocation_count(), 0);
+  }
+
+  #[test]
+  fn unmapped_page() {
+    let page = Arc::new(Page::new(Arc::new(SimulatedBackend::new()), 0, 1024, false).unwrap());
+    let mem = page.get_mem();
+    assert!(match Mapped::new(page, Block::new(mem, 0, 64, 0)) {
+      Err(Error::MapError) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn allocator_views() {
+    let (alloc, backend) = simulated(None);
+    let buf = bind_buffer(&alloc, &backend, 256, HOST);
+    let local = bind_buffer(&alloc, &backend, 256, LOCAL);
+
+    let data = (0..64u32).collect::<Vec<_>>();
+    alloc.get_mapped(buf).unwrap().try_host_to_device_slice(&data).unwrap();
+    let region = alloc.get_mapped_region(buf, 16, 32).unwrap();
+    assert_eq!(region.get_size(), 32);
+    assert_eq!(region.try_as_slice::<u32>().unwrap(), &[4, 5, 6, 7, 8, 9, 10, 11]);
+
+    // regions have to lie inside of the resource, device local memory is not mapped
+    assert!(alloc.get_mapped_region(buf, 128, 256).is_none());
+    assert!(alloc.get_mapped_region(buf, 256, 0).is_none());
+    assert!(alloc.get_mapped(local).is_none());
+    assert!(alloc.get_mapped(Handle::Buffer(0xdead)).is_none());
+
+    // a view keeps the memory of a destroyed resource alive until it is dropped
+    alloc.destroy(buf);
+    alloc.free_unused();
+    assert_eq!(region.try_as_slice::<u32>().unwrap()[0], 4);
+    drop(region);
+    alloc.destroy(local);
+  }
+
+  #[test]
+  fn exclusive_views() {
+    let (alloc, backend) = simulated(None);
+    let buf = bind_buffer(&alloc, &backend, 256, HOST);
+    let other = bind_buffer(&alloc, &backend, 256, HOST);
+
+    // a second view on the same binding or an overlapping region can not be created while the first one is alive
+    let mut map = alloc.get_mapped(buf).unwrap();
+    assert!(alloc.get_mapped(buf).is_none());
+    assert!(alloc.get_mapped_region(buf, 128, 16).is_none());
+    assert!(alloc.get_mapped(other).is_some());
+    map.try_as_slice_mut::<u32>().unwrap()[0] = 1;
+    drop(map);
+
+    // disjoint regions of the same binding can be viewed at the same time
+    let mut a = alloc.get_mapped_region(buf, 0, 128).unwrap();
+    let b = alloc.get_mapped_region(buf, 128, 128).unwrap();
+    assert!(alloc.get_mapped_region(buf, 120, 16).is_none());
+    a.try_as_slice_mut::<u32>().unwrap()[1] = 2;
+    assert_eq!(b.try_as_slice::<u32>().unwrap().len(), 32);
+    drop(a);
+    drop(b);
+
+    assert_eq!(alloc.get_mapped(buf).unwrap().try_as_slice::<u32>().unwrap()[..2], [1, 2]);
+    alloc.destroy(buf);
+    alloc.destroy(other);
+  }
+
+  #[test]
+  fn pod_struct() {
+    let mem = [0u32; 16];
+    let ptr = mem.as_ptr() as *const u8;
+    assert_eq!(std::mem::size_of::<Ub>(), 32);
+    assert_eq!(check_view::<Ub>(ptr, 64).unwrap(), 2);
+  }
 }
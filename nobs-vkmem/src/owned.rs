@@ -0,0 +1,313 @@
+use crate::Allocator;
+use crate::Error;
+use crate::Handle;
+use crate::Label;
+use crate::Mapped;
+use crate::Trash;
+
+/// Owning handle of a buffer, that is bound to an [Allocator](struct.Allocator.html)
+///
+/// The buffer is pushed into the [Trash](struct.Trash.html) when the handle is dropped, so that it is destroyed after it is no longer used by the device.
+/// The handle keeps a reference to the trash and with it to the allocator, both live at least as long as the handle.
+///
+/// The raw buffer can be retrieved with [get](struct.OwnedBuffer.html#method.get) to record commands with it,
+/// but it must not be destroyed manually. Ownership is given up with [into_raw](struct.OwnedBuffer.html#method.into_raw) and taken again with [from_raw](struct.OwnedBuffer.html#method.from_raw).
+///
+/// Owned buffers are created directly from the builder with [Buffer::bind_owned](struct.Buffer.html#method.bind_owned).
+///
+/// ```rust,no_run
+/// # extern crate nobs_vk as vk;
+/// # extern crate nobs_vkmem as vkmem;
+/// # fn main() {
+/// #  let lib = vk::VkLib::new();
+/// #  let inst = vk::instance::new()
+/// #    .validate(vk::DEBUG_REPORT_ERROR_BIT_EXT | vk::DEBUG_REPORT_WARNING_BIT_EXT)
+/// #    .application("awesome app", 0)
+/// #    .create(lib)
+/// #    .unwrap();
+/// #  let (pdevice, device) = vk::device::PhysicalDevice::enumerate_all(inst.handle)
+/// #    .remove(0)
+/// #    .into_device()
+/// #    .add_queue(vk::device::QueueProperties {
+/// #      present: false,
+/// #      graphics: true,
+/// #      compute: true,
+/// #      transfer: true,
+/// #    }).create()
+/// #    .unwrap();
+/// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+/// let trash = vkmem::Trash::new(allocator.clone(), 2);
+///
+/// let mut buf = vk::NULL_HANDLE;
+/// let buf = vkmem::Buffer::new(&mut buf)
+///   .size(64)
+///   .usage(vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT)
+///   .devicelocal(false)
+///   .bind_owned(&trash, vkmem::BindType::Scatter)
+///   .unwrap();
+///
+/// buf.get_mapped().unwrap().host_to_device(&[1u32, 2, 3, 4]);
+///
+/// // buf is destroyed after the frames in flight
+/// drop(buf);
+/// trash.clean();
+/// # }
+/// ```
+pub struct OwnedBuffer {
+  buffer: vk::Buffer,
+  alloc: Allocator,
+  trash: Trash,
+}
+
+impl std::fmt::Debug for OwnedBuffer {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "OwnedBuffer({:x})", self.buffer)
+  }
+}
+
+impl OwnedBuffer {
+  /// Takes ownership of a buffer, that is bound to the allocator of `trash`
+  ///
+  /// In debug builds this panics, if the buffer is not bound to the allocator of `trash`.
+  pub fn new(buffer: vk::Buffer, trash: Trash) -> Self {
+    let alloc = trash.get_allocator();
+    debug_assert!(
+      alloc.is_bound(Handle::Buffer(buffer)),
+      "buffer {:x} is not bound to the allocator of the trash",
+      buffer
+    );
+    Self { buffer, alloc, trash }
+  }
+
+  /// Takes ownership of a buffer, that has been given up with [into_raw](struct.OwnedBuffer.html#method.into_raw)
+  ///
+  /// Fails with [NotBound](enum.Error.html), if the buffer is not bound to the allocator of `trash`.
+  pub fn from_raw(buffer: vk::Buffer, trash: Trash) -> Result<Self, Error> {
+    match trash.get_allocator().is_bound(Handle::Buffer(buffer)) {
+      true => Ok(Self::new(buffer, trash)),
+      false => Err(Error::NotBound),
+    }
+  }
+
+  /// Get the raw buffer handle
+  pub fn get(&self) -> vk::Buffer {
+    self.buffer
+  }
+
+  /// Get the allocator, the buffer is bound to
+  pub fn get_allocator(&self) -> Allocator {
+    self.alloc.clone()
+  }
+
+  /// Get the mapped memory of the buffer, see [Allocator::get_mapped](struct.Allocator.html#method.get_mapped)
+  pub fn get_mapped(&self) -> Option<Mapped<'_>> {
+    self.alloc.get_mapped(Handle::Buffer(self.buffer))
+  }
+
+  /// Get a mapped region of the buffer, see [Allocator::get_mapped_region](struct.Allocator.html#method.get_mapped_region)
+  pub fn get_mapped_region(&self, offset: vk::DeviceSize, size: vk::DeviceSize) -> Option<Mapped<'_>> {
+    self.alloc.get_mapped_region(Handle::Buffer(self.buffer), offset, size)
+  }
+
+  /// Get the label of the buffer, see [Allocator::get_label](struct.Allocator.html#method.get_label)
+  pub fn get_label(&self) -> Option<Label> {
+    self.alloc.get_label(Handle::Buffer(self.buffer))
+  }
+
+  /// Gives up ownership of the buffer
+  ///
+  /// The buffer has to be destroyed with the allocator or the trash again.
+  pub fn into_raw(mut self) -> vk::Buffer {
+    std::mem::replace(&mut self.buffer, vk::NULL_HANDLE)
+  }
+}
+
+impl Drop for OwnedBuffer {
+  fn drop(&mut self) {
+    if self.buffer != vk::NULL_HANDLE {
+      self.trash.push_buffer(self.buffer);
+    }
+  }
+}
+
+/// Owning handle of an image, that is bound to an [Allocator](struct.Allocator.html)
+///
+/// The image is pushed into the [Trash](struct.Trash.html) when the handle is dropped, see [OwnedBuffer](struct.OwnedBuffer.html).
+///
+/// Owned images are created directly from the builder with [Image::bind_owned](struct.Image.html#method.bind_owned).
+pub struct OwnedImage {
+  image: vk::Image,
+  alloc: Allocator,
+  trash: Trash,
+}
+
+impl std::fmt::Debug for OwnedImage {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "OwnedImage({:x})", self.image)
+  }
+}
+
+impl OwnedImage {
+  /// Takes ownership of an image, that is bound to the allocator of `trash`
+  ///
+  /// In debug builds this panics, if the image is not bound to the allocator of `trash`.
+  pub fn new(image: vk::Image, trash: Trash) -> Self {
+    let alloc = trash.get_allocator();
+    debug_assert!(
+      alloc.is_bound(Handle::Image(image)),
+      "image {:x} is not bound to the allocator of the trash",
+      image
+    );
+    Self { image, alloc, trash }
+  }
+
+  /// Takes ownership of an image, that has been given up with [into_raw](struct.OwnedImage.html#method.into_raw)
+  ///
+  /// Fails with [NotBound](enum.Error.html), if the image is not bound to the allocator of `trash`.
+  pub fn from_raw(image: vk::Image, trash: Trash) -> Result<Self, Error> {
+    match trash.get_allocator().is_bound(Handle::Image(image)) {
+      true => Ok(Self::new(image, trash)),
+      false => Err(Error::NotBound),
+    }
+  }
+
+  /// Get the raw image handle
+  pub fn get(&self) -> vk::Image {
+    self.image
+  }
+
+  /// Get the allocator, the image is bound to
+  pub fn get_allocator(&self) -> Allocator {
+    self.alloc.clone()
+  }
+
+  /// Get the label of the image, see [Allocator::get_label](struct.Allocator.html#method.get_label)
+  pub fn get_label(&self) -> Option<Label> {
+    self.alloc.get_label(Handle::Image(self.image))
+  }
+
+  /// Gives up ownership of the image
+  ///
+  /// The image has to be destroyed with the allocator or the trash again.
+  pub fn into_raw(mut self) -> vk::Image {
+    std::mem::replace(&mut self.image, vk::NULL_HANDLE)
+  }
+}
+
+impl Drop for OwnedImage {
+  fn drop(&mut self) {
+    if self.image != vk::NULL_HANDLE {
+      self.trash.push_image(self.image);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::allocator::tests::*;
+  use crate::BindInfo;
+  use crate::BindType;
+  use crate::LeakMode;
+
+  #[test]
+  fn drop_releases() {
+    let (alloc, backend) = simulated(None);
+    let trash = Trash::new(alloc.clone(), 2);
+
+    let buf = OwnedBuffer::new(bind_buffer(&alloc, &backend, 256, HOST).get(), trash.clone());
+    let img = Handle::Image(backend.create_image(requirements(1024, 256)));
+    alloc.bind(&[BindInfo::new(img, LOCAL, false)], BindType::Scatter).unwrap();
+    let img = OwnedImage::new(img.get(), trash.clone());
+    assert_eq!(backend.resource_count(), 2);
+
+    drop(buf);
+    drop(img);
+    trash.clean();
+    assert_eq!(backend.resource_count(), 2);
+    trash.clean();
+    assert_eq!(backend.resource_count(), 0);
+    assert!(alloc.leak_report().is_empty());
+  }
+
+  #[test]
+  fn raw() {
+    let (alloc, backend) = simulated(None);
+    let trash = Trash::new(alloc.clone(), 2);
+    let h = bind_buffer(&alloc, &backend, 256, HOST);
+
+    let raw = OwnedBuffer::new(h.get(), trash.clone()).into_raw();
+    assert_eq!(raw, h.get());
+    trash.clean();
+    trash.clean();
+    assert!(alloc.is_bound(h));
+
+    let buf = OwnedBuffer::from_raw(raw, trash.clone()).unwrap();
+    assert_eq!(buf.get(), raw);
+    assert!(buf.get_mapped().is_some());
+    assert!(match OwnedImage::from_raw(raw, trash.clone()) {
+      Err(Error::NotBound) => true,
+      _ => false,
+    });
+
+    alloc.destroy(Handle::Buffer(buf.into_raw()));
+    assert!(match OwnedBuffer::from_raw(raw, trash.clone()) {
+      Err(Error::NotBound) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn raw_image() {
+    let (alloc, backend) = simulated(None);
+    let trash = Trash::new(alloc.clone(), 2);
+    let h = Handle::Image(backend.create_image(requirements(1024, 256)));
+    alloc.bind(&[BindInfo::new(h, LOCAL, false)], BindType::Scatter).unwrap();
+
+    let raw = OwnedImage::new(h.get(), trash.clone()).into_raw();
+    assert_eq!(raw, h.get());
+    trash.clean();
+    trash.clean();
+    assert!(alloc.is_bound(h));
+
+    let img = OwnedImage::from_raw(raw, trash.clone()).unwrap();
+    assert_eq!(img.get(), raw);
+    assert!(img.get_allocator().is_bound(h));
+    assert!(match OwnedBuffer::from_raw(raw, trash.clone()) {
+      Err(Error::NotBound) => true,
+      _ => false,
+    });
+
+    alloc.destroy(Handle::Image(img.into_raw()));
+    assert!(match OwnedImage::from_raw(raw, trash.clone()) {
+      Err(Error::NotBound) => true,
+      _ => false,
+    });
+    assert_eq!(backend.resource_count(), 0);
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "is not bound to the allocator of the trash")]
+  fn foreign_allocator() {
+    let (alloc, backend) = simulated(None);
+    let (other, _) = simulated(None);
+    // the allocator is dropped with the bound resource while unwinding
+    alloc.leak_mode(LeakMode::Ignore);
+    let h = bind_buffer(&alloc, &backend, 256, HOST);
+    OwnedBuffer::new(h.get(), Trash::new(other, 2));
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "is not bound to the allocator of the trash")]
+  fn foreign_allocator_image() {
+    let (alloc, backend) = simulated(None);
+    let (other, _) = simulated(None);
+    // the allocator is dropped with the bound resource while unwinding
+    alloc.leak_mode(LeakMode::Ignore);
+    let h = Handle::Image(backend.create_image(requirements(1024, 256)));
+    alloc.bind(&[BindInfo::new(h, LOCAL, false)], BindType::Scatter).unwrap();
+    OwnedImage::new(h.get(), Trash::new(other, 2));
+  }
+}
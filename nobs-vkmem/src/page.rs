@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::Backend;
+use crate::Error;
+
+/// A single page of device memory
+///
+/// Owns the `vk::DeviceMemory` allocation and frees it, when the last reference to the page is dropped.
+/// Pages of host visible memory types are mapped once on creation and stay mapped for their whole lifetime.
+/// This way [Mapped](../struct.Mapped.html) views on a page are only pointer offsets and multiple views of different bindings on the same page may be alive at the same time.
+/// The page keeps track of the byte ranges of all live views, so that no two views on overlapping memory can exist at the same time.
+///
+/// Pages are shared through `Arc`, so that the memory of a page stays valid as long as a [Mapped](../struct.Mapped.html) view on it is alive,
+/// even if the page has already been released by the page table.
+/// Mapped views borrow the allocator, so that the last reference to a page is always dropped before the allocator.
+#[derive(Debug)]
+pub struct Page {
+  backend: Arc<dyn Backend>,
+  mem: vk::DeviceMemory,
+  size: vk::DeviceSize,
+  ptr: *mut u8,
+  views: Mutex<Vec<(vk::DeviceSize, vk::DeviceSize)>>,
+}
+
+unsafe impl Send for Page {}
+unsafe impl Sync for Page {}
+
+impl Drop for Page {
+  fn drop(&mut self) {
+    if !self.ptr.is_null() {
+      self.backend.unmap(self.mem);
+    }
+    self.backend.free(self.mem);
+  }
+}
+
+impl Page {
+  /// Allocates a new page of device memory from the backend
+  ///
+  /// If `mappable` is true, the whole page is mapped into host memory.
+  ///
+  /// Fails with [AllocError](../enum.Error.html) if the memory could not be allocated
+  /// and with [MapError](../enum.Error.html) if the memory could not be mapped.
+  pub fn new(backend: Arc<dyn Backend>, memtype: u32, size: vk::DeviceSize, mappable: bool) -> Result<Page, Error> {
+    let mem = backend.allocate(memtype, size)?;
+
+    let mut page = Page {
+      backend,
+      mem,
+      size,
+      ptr: std::ptr::null_mut(),
+      views: Default::default(),
+    };
+
+    if mappable {
+      page.ptr = page.backend.map(mem)?;
+    }
+
+    Ok(page)
+  }
+
+  /// Get the device memory handle of the page
+  pub fn get_mem(&self) -> vk::DeviceMemory {
+    self.mem
+  }
+
+  /// Get the size of the page in bytes
+  pub fn get_size(&self) -> vk::DeviceSize {
+    self.size
+  }
+
+  /// Checks if the page is mapped into host memory
+  pub fn is_mapped(&self) -> bool {
+    !self.ptr.is_null()
+  }
+
+  /// Get a pointer into the mapped page at the specified byte offset
+  ///
+  /// Returns None, if the page is not mapped or the offset lies outside of the page.
+  pub fn get_ptr(&self, offset: vk::DeviceSize) -> Option<*mut u8> {
+    match self.is_mapped() && offset <= self.size {
+      true => Some(unsafe { self.ptr.add(offset as usize) }),
+      false => None,
+    }
+  }
+
+  /// Registers a view on the byte range `[beg, end)` of the page
+  ///
+  /// Fails with [AlreadyMapped](../enum.Error.html) if the range overlaps with the range of a view, that is still alive.
+  pub fn acquire_view(&self, beg: vk::DeviceSize, end: vk::DeviceSize) -> Result<(), Error> {
+    let mut views = self.views.lock().unwrap();
+    if views.iter().any(|(b, e)| beg < *e && *b < end) {
+      Err(Error::AlreadyMapped)?
+    }
+    views.push((beg, end));
+    Ok(())
+  }
+
+  /// Releases a view, that has been registered with [acquire_view](struct.Page.html#method.acquire_view)
+  pub fn release_view(&self, beg: vk::DeviceSize, end: vk::DeviceSize) {
+    let mut views = self.views.lock().unwrap();
+    if let Some(i) = views.iter().position(|v| *v == (beg, end)) {
+      views.swap_remove(i);
+    }
+  }
+}
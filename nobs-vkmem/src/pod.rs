@@ -0,0 +1,91 @@
+use vkm::Mat3;
+use vkm::Mat4;
+use vkm::Vec2;
+use vkm::Vec3;
+use vkm::Vec4;
+
+/// Marker trait for plain old data
+///
+/// Types implementing Pod can be safely copied byte wise from and to mapped device memory:
+///  - every bit pattern is a valid value of the type
+///  - the type has no padding bytes
+///  - the type has no references, pointers or `Drop` implementation
+///
+/// Pod is implemented for all primitive integer and floating point types, arrays of Pod types and vectors and matrices of [nobs-vkmath](../nobs_vkmath/index.html).
+/// For user defined structs Pod is derived with the [pod!](macro.pod.html) macro, which checks the requirements at compile time.
+///
+/// # Safety
+/// Implementing this trait manually is only sound, if the type satisfies all of the requirements above and is `#[repr(C)]`.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+  ($($t:ty),*) => {
+    $(unsafe impl Pod for $t {})*
+  };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+unsafe impl<T: Pod> Pod for Vec2<T> {}
+unsafe impl<T: Pod> Pod for Vec3<T> {}
+unsafe impl<T: Pod> Pod for Vec4<T> {}
+unsafe impl<T: Pod> Pod for Mat3<T> {}
+unsafe impl<T: Pod> Pod for Mat4<T> {}
+
+/// Used by the [pod!](macro.pod.html) macro to check at compile time that all fields of a struct are Pod
+#[doc(hidden)]
+pub fn assert_pod<T: Pod>() {}
+
+/// Declares structs that implement [Pod](trait.Pod.html)
+///
+/// The macro takes one or more struct definitions with named fields, marks them `#[repr(C)]`, derives `Clone` and `Copy` and implements Pod for them.
+/// Other attributes (e.g. `#[derive(Debug)]`) are forwarded to the struct definition.
+///
+/// Compilation fails, if
+///  - a field type does not implement Pod
+///  - the struct contains implicit padding, padding has to be declared explicitly as a field
+///
+/// # Example
+/// ```rust
+/// #[macro_use] extern crate nobs_vkmem as vkmem;
+///
+/// vkmem::pod! {
+///   #[derive(Debug, Default)]
+///   pub struct Ub {
+///     pub a: u32,
+///     pub b: u32,
+///     pub c: u32,
+///     pub pad: u32,
+///   }
+/// }
+///
+/// # fn main() {
+/// fn upload<T: vkmem::Pod>(_: &T) {}
+/// upload(&Ub::default());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! pod {
+  ($($(#[$attr:meta])* $vis:vis struct $name:ident { $($fvis:vis $field:ident : $ty:ty),* $(,)? })*) => {
+    $(
+      $(#[$attr])*
+      #[repr(C)]
+      #[derive(Clone, Copy)]
+      $vis struct $name {
+        $($fvis $field: $ty),*
+      }
+
+      unsafe impl $crate::Pod for $name {}
+
+      const _: fn() = || {
+        $($crate::assert_pod::<$ty>();)*
+      };
+      const _: () = assert!(
+        std::mem::size_of::<$name>() == 0 $(+ std::mem::size_of::<$ty>())*,
+        concat!("pod struct ", stringify!($name), " has implicit padding")
+      );
+    )*
+  };
+}
@@ -0,0 +1,409 @@
+use std::collections::HashSet;
+
+use crate::Error;
+use crate::Handle;
+
+/// Describes the layout of a sparse resource
+///
+/// The allocator needs the layout of sparse images to compute the regions of individual tiles,
+/// since it can not be queried from the image handle.
+#[derive(Debug, Clone, Copy)]
+pub enum SparseLayout {
+  /// Sparse buffer, that is bound in opaque pages
+  Buffer,
+  /// Sparse image with its extent, number of mip levels and array layers
+  Image {
+    extent: vk::Extent3D,
+    mip_levels: u32,
+    array_layers: u32,
+  },
+}
+
+/// Identifies a page of a sparse resource
+///
+/// The size of a page is the sparse block size of the resource (`vk::MemoryRequirements::alignment`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SparsePage {
+  /// Page at index `i` of the opaque memory range of the resource.
+  ///
+  /// Buffers are always bound with opaque pages. For images opaque pages are used to bind the mip tail or non resident images.
+  Opaque(u64),
+  /// Tile of a sparse resident image.
+  ///
+  /// The tile coordinates `x`, `y`, `z` are in units of the sparse image granularity of the aspect.
+  Tile {
+    aspect: vk::ImageAspectFlags,
+    mip: u32,
+    layer: u32,
+    x: u32,
+    y: u32,
+    z: u32,
+  },
+}
+
+/// Region of a sparse resource that is covered by a [SparsePage](enum.SparsePage.html)
+#[derive(Debug, Clone, Copy)]
+pub enum SparseRegion {
+  /// Byte range in the opaque memory range of the resource
+  Opaque { offset: vk::DeviceSize, size: vk::DeviceSize },
+  /// Texel region of an image subresource
+  Tile {
+    subresource: vk::ImageSubresource,
+    offset: vk::Offset3D,
+    extent: vk::Extent3D,
+  },
+}
+
+#[derive(Debug, Clone)]
+struct SparseImage {
+  extent: vk::Extent3D,
+  mip_levels: u32,
+  array_layers: u32,
+  requirements: Vec<vk::SparseImageMemoryRequirements>,
+}
+
+/// Residency bookkeeping of a sparse resource
+///
+/// Keeps track of which pages of a sparse buffer or image are resident and validates page addresses against the layout of the resource.
+/// The backing memory of resident pages is managed by the page tables of the [Allocator](struct.Allocator.html).
+///
+/// The residency does not need a device and can be used on its own.
+#[derive(Debug, Clone)]
+pub struct SparseResidency {
+  size: vk::DeviceSize,
+  page_size: vk::DeviceSize,
+  image: Option<SparseImage>,
+  resident: HashSet<SparsePage>,
+}
+
+impl SparseResidency {
+  /// Creates the residency for a sparse buffer
+  ///
+  /// # Arguments
+  /// * `size` - size of the buffers memory requirements in bytes
+  /// * `page_size` - sparse block size (alignment of the buffers memory requirements)
+  pub fn buffer(size: vk::DeviceSize, page_size: vk::DeviceSize) -> Self {
+    Self {
+      size,
+      page_size,
+      image: None,
+      resident: Default::default(),
+    }
+  }
+
+  /// Creates the residency for a sparse image
+  ///
+  /// # Arguments
+  /// * `size` - size of the images memory requirements in bytes
+  /// * `page_size` - sparse block size (alignment of the images memory requirements)
+  /// * `extent` - extent of the image in texels
+  /// * `mip_levels` - number of mip levels of the image
+  /// * `array_layers` - number of array layers of the image
+  /// * `requirements` - sparse memory requirements retrieved with `vk::GetImageSparseMemoryRequirements`
+  pub fn image(
+    size: vk::DeviceSize,
+    page_size: vk::DeviceSize,
+    extent: vk::Extent3D,
+    mip_levels: u32,
+    array_layers: u32,
+    requirements: Vec<vk::SparseImageMemoryRequirements>,
+  ) -> Self {
+    Self {
+      size,
+      page_size,
+      image: Some(SparseImage {
+        extent,
+        mip_levels,
+        array_layers,
+        requirements,
+      }),
+      resident: Default::default(),
+    }
+  }
+
+  /// Get the size of a single page in bytes
+  pub fn page_size(&self) -> vk::DeviceSize {
+    self.page_size
+  }
+
+  /// Get the number of opaque pages of the resource
+  pub fn page_count(&self) -> u64 {
+    self.size.div_ceil(self.page_size)
+  }
+
+  /// Get the region of the resource that is covered by `page`
+  ///
+  /// Fails with [InvalidSparsePage](enum.Error.html) if the page lies outside of the resource,
+  /// or if a tile is addressed for a buffer, an aspect without sparse requirements, or a mip level inside the mip tail.
+  pub fn region(&self, page: SparsePage) -> Result<SparseRegion, Error> {
+    match page {
+      SparsePage::Opaque(i) => {
+        let offset = i.checked_mul(self.page_size).ok_or(Error::InvalidSparsePage)?;
+        match offset < self.size {
+          true => Ok(SparseRegion::Opaque {
+            offset,
+            size: vk::DeviceSize::min(self.page_size, self.size - offset),
+          }),
+          false => Err(Error::InvalidSparsePage),
+        }
+      }
+      SparsePage::Tile {
+        aspect,
+        mip,
+        layer,
+        x,
+        y,
+        z,
+      } => {
+        let img = self.image.as_ref().ok_or(Error::InvalidSparsePage)?;
+        let req = img
+          .requirements
+          .iter()
+          .find(|r| r.formatProperties.aspectMask & aspect == aspect)
+          .ok_or(Error::InvalidSparsePage)?;
+
+        if mip >= img.mip_levels || mip >= req.imageMipTailFirstLod || layer >= img.array_layers {
+          Err(Error::InvalidSparsePage)?
+        }
+
+        let g = req.formatProperties.imageGranularity;
+        let dim = |e: u32| u32::max(1, e.checked_shr(mip).unwrap_or(0));
+        let (w, h, d) = (dim(img.extent.width), dim(img.extent.height), dim(img.extent.depth));
+        let offset = |i: u32, g: u32| i.checked_mul(g).filter(|o| *o <= i32::MAX as u32).ok_or(Error::InvalidSparsePage);
+        let (ox, oy, oz) = (offset(x, g.width)?, offset(y, g.height)?, offset(z, g.depth)?);
+        if ox >= w || oy >= h || oz >= d {
+          Err(Error::InvalidSparsePage)?
+        }
+
+        Ok(SparseRegion::Tile {
+          subresource: vk::ImageSubresource {
+            aspectMask: aspect,
+            mipLevel: mip,
+            arrayLayer: layer,
+          },
+          offset: vk::Offset3D {
+            x: ox as i32,
+            y: oy as i32,
+            z: oz as i32,
+          },
+          extent: vk::Extent3D {
+            width: u32::min(g.width, w - ox),
+            height: u32::min(g.height, h - oy),
+            depth: u32::min(g.depth, d - oz),
+          },
+        })
+      }
+    }
+  }
+
+  /// Checks if the page is resident
+  pub fn is_resident(&self, page: SparsePage) -> bool {
+    self.resident.contains(&page)
+  }
+
+  /// Marks the page as resident
+  ///
+  /// # Returns
+  ///  - true, if the page was not resident before
+  ///  - [InvalidSparsePage](enum.Error.html), if the page does not address a valid region of the resource
+  pub fn make_resident(&mut self, page: SparsePage) -> Result<bool, Error> {
+    self.region(page)?;
+    Ok(self.resident.insert(page))
+  }
+
+  /// Marks the page as not resident
+  ///
+  /// # Returns
+  /// True, if the page was resident before.
+  pub fn evict(&mut self, page: SparsePage) -> bool {
+    self.resident.remove(&page)
+  }
+
+  /// Get all resident pages
+  pub fn resident_pages(&self) -> Vec<SparsePage> {
+    self.resident.iter().cloned().collect()
+  }
+
+  /// Get the size of all resident pages in bytes
+  pub fn resident_size(&self) -> vk::DeviceSize {
+    self.resident.len() as vk::DeviceSize * self.page_size
+  }
+}
+
+/// Submits sparse memory bindings of a single resource to a queue
+///
+/// Every region is bound to the memory at the specified offset. If the memory is `vk::NULL_HANDLE`, the region is unbound.
+pub(crate) fn queue_bind_sparse(
+  queue: vk::Queue,
+  handle: Handle<u64>,
+  regions: &[(SparseRegion, vk::DeviceMemory, vk::DeviceSize)],
+  fence: vk::Fence,
+) -> Result<(), Error> {
+  let mut opaque = Vec::new();
+  let mut tiles = Vec::new();
+  for (region, memory, memory_offset) in regions.iter().cloned() {
+    match region {
+      SparseRegion::Opaque { offset, size } => opaque.push(vk::SparseMemoryBind {
+        resourceOffset: offset,
+        size,
+        memory,
+        memoryOffset: memory_offset,
+        flags: 0,
+      }),
+      SparseRegion::Tile {
+        subresource,
+        offset,
+        extent,
+      } => tiles.push(vk::SparseImageMemoryBind {
+        subresource,
+        offset,
+        extent,
+        memory,
+        memoryOffset: memory_offset,
+        flags: 0,
+      }),
+    }
+  }
+
+  let buffer_binds = match handle {
+    Handle::Buffer(h) if !opaque.is_empty() => vec![vk::SparseBufferMemoryBindInfo {
+      buffer: h,
+      bindCount: opaque.len() as u32,
+      pBinds: opaque.as_ptr(),
+    }],
+    _ => vec![],
+  };
+  let opaque_binds = match handle {
+    Handle::Image(h) if !opaque.is_empty() => vec![vk::SparseImageOpaqueMemoryBindInfo {
+      image: h,
+      bindCount: opaque.len() as u32,
+      pBinds: opaque.as_ptr(),
+    }],
+    _ => vec![],
+  };
+  let image_binds = match handle {
+    Handle::Image(h) if !tiles.is_empty() => vec![vk::SparseImageMemoryBindInfo {
+      image: h,
+      bindCount: tiles.len() as u32,
+      pBinds: tiles.as_ptr(),
+    }],
+    Handle::Buffer(_) if !tiles.is_empty() => Err(Error::InvalidSparsePage)?,
+    _ => vec![],
+  };
+
+  let info = vk::BindSparseInfo {
+    sType: vk::STRUCTURE_TYPE_BIND_SPARSE_INFO,
+    pNext: std::ptr::null(),
+    waitSemaphoreCount: 0,
+    pWaitSemaphores: std::ptr::null(),
+    bufferBindCount: buffer_binds.len() as u32,
+    pBufferBinds: buffer_binds.as_ptr(),
+    imageOpaqueBindCount: opaque_binds.len() as u32,
+    pImageOpaqueBinds: opaque_binds.as_ptr(),
+    imageBindCount: image_binds.len() as u32,
+    pImageBinds: image_binds.as_ptr(),
+    signalSemaphoreCount: 0,
+    pSignalSemaphores: std::ptr::null(),
+  };
+
+  vk_check!(vk::QueueBindSparse(queue, 1, &info, fence))
+    .map(|_| ())
+    .map_err(|_| Error::BindMemoryFailed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn image() -> SparseResidency {
+    let req = vk::SparseImageMemoryRequirements {
+      formatProperties: vk::SparseImageFormatProperties {
+        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+        imageGranularity: vk::Extent3D {
+          width: 128,
+          height: 128,
+          depth: 1,
+        },
+        flags: 0,
+      },
+      imageMipTailFirstLod: 3,
+      imageMipTailSize: 1 << 16,
+      imageMipTailOffset: 1 << 20,
+      imageMipTailStride: 0,
+    };
+    let extent = vk::Extent3D {
+      width: 1000,
+      height: 512,
+      depth: 1,
+    };
+    SparseResidency::image(1 << 21, 1 << 16, extent, 10, 1, vec![req])
+  }
+
+  fn tile(mip: u32, x: u32, y: u32) -> SparsePage {
+    SparsePage::Tile {
+      aspect: vk::IMAGE_ASPECT_COLOR_BIT,
+      mip,
+      layer: 0,
+      x,
+      y,
+      z: 0,
+    }
+  }
+
+  #[test]
+  fn buffer_pages() {
+    let mut r = SparseResidency::buffer(1000, 256);
+    assert_eq!(r.page_count(), 4);
+    match r.region(SparsePage::Opaque(3)).unwrap() {
+      SparseRegion::Opaque { offset, size } => assert_eq!((offset, size), (768, 232)),
+      _ => panic!("expected opaque region"),
+    }
+    assert!(r.make_resident(SparsePage::Opaque(4)).is_err());
+    assert!(r.region(SparsePage::Opaque(u64::MAX)).is_err());
+    assert!(r.make_resident(tile(0, 0, 0)).is_err());
+
+    assert!(r.make_resident(SparsePage::Opaque(1)).unwrap());
+    assert!(!r.make_resident(SparsePage::Opaque(1)).unwrap());
+    assert!(r.is_resident(SparsePage::Opaque(1)));
+    assert_eq!(r.resident_size(), 256);
+    assert!(r.evict(SparsePage::Opaque(1)));
+    assert!(!r.evict(SparsePage::Opaque(1)));
+    assert_eq!(r.resident_size(), 0);
+  }
+
+  #[test]
+  fn image_tiles() {
+    let r = image();
+    match r.region(tile(0, 7, 3)).unwrap() {
+      SparseRegion::Tile { offset, extent, .. } => {
+        assert_eq!((offset.x, offset.y, offset.z), (896, 384, 0));
+        assert_eq!((extent.width, extent.height, extent.depth), (104, 128, 1));
+      }
+      _ => panic!("expected tile region"),
+    }
+    match r.region(tile(2, 1, 0)).unwrap() {
+      SparseRegion::Tile { offset, extent, .. } => {
+        assert_eq!((offset.x, offset.y), (128, 0));
+        assert_eq!((extent.width, extent.height), (122, 128));
+      }
+      _ => panic!("expected tile region"),
+    }
+    assert!(r.region(tile(0, 8, 0)).is_err());
+    assert!(r.region(tile(0, 0, 4)).is_err());
+    // coordinates, that overflow when scaled by the granularity
+    assert!(r.region(tile(0, u32::MAX, 0)).is_err());
+    assert!(r.region(tile(0, 0, u32::MAX / 64)).is_err());
+    // mip tail has to be bound opaque
+    assert!(r.region(tile(3, 0, 0)).is_err());
+    assert!(r
+      .region(SparsePage::Tile {
+        aspect: vk::IMAGE_ASPECT_DEPTH_BIT,
+        mip: 0,
+        layer: 0,
+        x: 0,
+        y: 0,
+        z: 0
+      })
+      .is_err());
+  }
+}
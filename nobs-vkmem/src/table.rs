@@ -1,13 +1,18 @@
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
+use std::sync::Arc;
 
+use crate::backend::Backend;
 use crate::bindinfo::BindInfoInner;
 use crate::bindtype::BindType;
 use crate::block::Block;
 use crate::block::BlockType;
 use crate::memtype::Memtype;
+use crate::page::Page;
 use crate::Error;
 use crate::Handle;
+use crate::Label;
+use crate::SparsePage;
 
 #[derive(Debug, Clone)]
 struct Node {
@@ -15,44 +20,91 @@ struct Node {
   next: Option<BlockType>,
 }
 
+/// Identifies a binding in the page table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+  /// Buffer or image that is bound with `vk::Bind*Memory`
+  Resource(Handle<u64>),
+  /// Page of a sparse resource, that is bound with `vk::QueueBindSparse`
+  Sparse(Handle<u64>, SparsePage),
+  /// Memory reserved for a [thread cache](../struct.CacheSizes.html), that is not bound to any resource
+  Cache(u64),
+}
+
 #[derive(Debug, Clone)]
 struct Binding {
-  handle: Handle<u64>,
+  key: Key,
   node: Node,
 }
 
+/// A resource that aliases the memory of another resource
+#[derive(Debug, Clone, Copy)]
+struct Alias {
+  handle: Handle<u64>,
+  size: vk::DeviceSize,
+}
+
+/// Summed up usage of all pages in a [Table](struct.Table.html)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableStats {
+  pub pages: usize,
+  pub allocated: vk::DeviceSize,
+  pub free: vk::DeviceSize,
+  pub free_blocks: usize,
+  pub largest_free: vk::DeviceSize,
+}
+
+impl TableStats {
+  /// Fragmentation of the free memory, 0 if all free memory is in a single block and approaches 1 the more the free memory is scattered
+  pub fn fragmentation(&self) -> f64 {
+    match self.free {
+      0 => 0.0,
+      free => 1.0 - self.largest_free as f64 / free as f64,
+    }
+  }
+}
+
 pub struct Table {
-  device: vk::Device,
+  backend: Arc<dyn Backend>,
   memtype: Memtype,
+  mappable: bool,
 
   pagesize: vk::DeviceSize,
 
+  memory: HashMap<vk::DeviceMemory, Arc<Page>>,
   pages: HashMap<vk::DeviceMemory, HashMap<Block, Binding>>,
-  bindings: HashMap<Handle<u64>, Block>,
+  bindings: HashMap<Key, Block>,
   free: BTreeMap<Block, Node>,
-}
 
-impl Drop for Table {
-  fn drop(&mut self) {
-    for (mem, _) in self.pages.iter() {
-      vk::FreeMemory(self.device, *mem, std::ptr::null());
-    }
-  }
+  aliases: HashMap<Handle<u64>, Vec<Alias>>,
+  alias_of: HashMap<Handle<u64>, Handle<u64>>,
+
+  labels: HashMap<Handle<u64>, Label>,
 }
 
 impl Table {
   /// Creates a new page table with the desired page size.
   ///
   /// We do not need to check for the minimum page size, since [Allocator](../struct.Allocator.html) already does that, and we don't leak this type.
-  pub fn new(device: vk::Device, memtype: Memtype, pagesize: vk::DeviceSize) -> Self {
+  ///
+  /// If `mappable` is true, every page of the table will be persistently mapped into host memory.
+  /// Pages are allocated, mapped and bound through `backend`.
+  pub fn new(backend: Arc<dyn Backend>, memtype: Memtype, pagesize: vk::DeviceSize, mappable: bool) -> Self {
     Self {
-      device,
+      backend,
       memtype,
+      mappable,
       pagesize,
 
+      memory: Default::default(),
       pages: Default::default(),
       bindings: Default::default(),
       free: Default::default(),
+
+      aliases: Default::default(),
+      alias_of: Default::default(),
+
+      labels: Default::default(),
     }
   }
 
@@ -66,49 +118,54 @@ impl Table {
   /// Allocates device memory
   ///
   /// Returns a block with the allocated size and the new device memory handle.
-  /// Fails with [AllockError](../enum.Error.html) if the vulkan command failed.
+  /// Fails with [AllockError](../enum.Error.html) if the backend could not allocate the memory.
+  /// If the table is mappable, the new page is mapped persistently.
   fn allocate_page(&mut self, pagesize: vk::DeviceSize) -> Result<Block, Error> {
-    let alloc_info = vk::MemoryAllocateInfo {
-      sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
-      pNext: std::ptr::null(),
-      allocationSize: pagesize,
-      memoryTypeIndex: self.memtype.index,
-    };
-
-    let mut handle = vk::NULL_HANDLE;
-
-    vk_check!(vk::AllocateMemory(self.device, &alloc_info, std::ptr::null(), &mut handle)).map_err(|_| Error::AllocError)?;
-    assert!(handle != vk::NULL_HANDLE);
+    let page = Page::new(self.backend.clone(), self.memtype.index, pagesize, self.mappable)?;
+    let handle = page.get_mem();
 
     let b = Block::new(handle, 0, pagesize, 0);
     self.free.insert(b, Node { prev: None, next: None });
     self.pages.insert(handle, Default::default());
+    self.memory.insert(handle, Arc::new(page));
     Ok(b)
   }
 
-  fn scan_bindinfos(infos: &[BindInfoInner], maxsize: Option<vk::DeviceSize>) -> (vk::DeviceSize, vk::DeviceSize, usize) {
+  /// Computes the alignment and size of a group of resources, that are bound consecutively starting at `beg`
+  ///
+  /// If `end` is specified, only as many resources are considered as fit before `end`.
+  /// Returns the largest alignment of all resources, the size of the bound resources including paddings and the number of bound resources.
+  fn scan_bindinfos(infos: &[BindInfoInner], beg: vk::DeviceSize, end: Option<vk::DeviceSize>) -> (vk::DeviceSize, vk::DeviceSize, usize) {
     // use the largest alignment for all resources
     let alignment = infos
       .iter()
       .fold(0, |align, i| vk::DeviceSize::max(align, i.requirements.alignment));
 
     let mut count = 0;
-    let mut size = 0;
-    let maxsize = maxsize.unwrap_or(vk::DeviceSize::max_value());
+    let mut offset = beg;
+    let end = end.unwrap_or(vk::DeviceSize::max_value());
 
     for i in infos.iter() {
-      let pad = Self::get_padding(size, i.requirements.alignment);
-      if size + pad + i.requirements.size > maxsize {
+      let pad = Self::get_padding(offset, i.requirements.alignment);
+      if offset + pad + i.requirements.size > end {
         break;
       }
-      size += pad + i.requirements.size; 
+      offset += pad + i.requirements.size;
       count += 1;
     }
 
-    (alignment, size, count)
+    (alignment, offset - beg, count)
   }
 
   pub fn bind(&mut self, bindinfos: &[BindInfoInner], bindtype: BindType) -> Result<(), Error> {
+    let keys = bindinfos.iter().map(|i| i.key()).collect::<Vec<_>>();
+    self.bind_keys(bindinfos, &keys, bindtype)
+  }
+
+  /// Binds the memory requirements of `bindinfos` with the specified keys
+  ///
+  /// Only keys of [Resource](enum.Key.html) are bound to the memory with the backend.
+  fn bind_keys(&mut self, bindinfos: &[BindInfoInner], keys: &[Key], bindtype: BindType) -> Result<(), Error> {
     #[derive(Debug)]
     struct Group {
       b: usize,
@@ -124,16 +181,17 @@ impl Table {
     };
 
     // find groups of bindinfos, that we can bind together to a free block of memory
-    loop {
+    while g.b < g.e {
       let infos = &bindinfos[g.b..g.e];
-      let (alignment, size, _) = Self::scan_bindinfos(infos, None);
+      let (alignment, size, _) = Self::scan_bindinfos(infos, 0, None);
+      let used = |b: &Block| groups.iter().any(|g: &Group| *b == g.block);
 
       if let Some((b, _)) = self
         .free
         // find Blocks that are large enough
         .range(Block::new(0, 0, size, 0)..)
         // skip Blocks that are to small when padding is added in front
-        .skip_while(|(b, _)| groups.iter().any(|g: &Group| **b == g.block) || b.size() - Self::get_padding(b.beg, alignment) < size)
+        .skip_while(|(b, _)| used(b) || b.size() < size + Self::get_padding(b.beg, alignment))
         .next()
         .map(|(b, n)| (*b, n.clone()))
       {
@@ -142,9 +200,9 @@ impl Table {
       } else {
         // Only if we are allowed to split,
         // find the biggest block and bind as many handles as possible there.
-        if let BindType::Scatter {} = bindtype {
-          if let Some((b, _)) = self.free.iter().next().map(|(b, n)| (*b, n.clone())) {
-            let (_, _, c) = Self::scan_bindinfos(infos, Some(b.size()));
+        if let BindType::Scatter = bindtype {
+          if let Some((b, _)) = self.free.iter().find(|(b, _)| !used(b)).map(|(b, n)| (*b, n.clone())) {
+            let (_, _, c) = Self::scan_bindinfos(infos, b.beg, Some(b.end));
             // Only if we find a free block that is large enough, we continue the loop
             // If not, we allocate a new page and put everything there
             if c != 0 {
@@ -154,7 +212,7 @@ impl Table {
                 block: b,
               });
 
-              g.b = g.b + c;
+              g.b += c;
               continue;
             }
           }
@@ -178,16 +236,13 @@ impl Table {
       let mut offset = g.block.beg;
       let mem = g.block.mem;
 
-      for i in infos.iter() {
+      for (i, k) in infos.iter().zip(keys[g.b..g.e].iter()) {
         let pad = Self::get_padding(offset, i.requirements.alignment);
 
         blocks.push(Block::new(mem, offset, offset + pad + i.requirements.size, pad));
-
-        match i.handle {
-          Handle::Buffer(h) => vk_check!(vk::BindBufferMemory(self.device, h, mem, offset + pad)),
-          Handle::Image(h) => vk_check!(vk::BindImageMemory(self.device, h, mem, offset + pad)),
+        if let Key::Resource(h) = k {
+          self.backend.bind(*h, mem, offset + pad)?;
         }
-        .map_err(|_| Error::BindMemoryFailed)?;
 
         offset = offset + pad + i.requirements.size;
       }
@@ -195,15 +250,15 @@ impl Table {
 
     // insert free/occupied blocks
     for g in groups.iter() {
-      let infos = &bindinfos[g.b..g.e];
+      let keys = &keys[g.b..g.e];
       let blocks = &blocks[g.b..g.e];
 
       if let Some(n) = self.free.remove(&g.block) {
         // insert the first block always with the node of the group
         // prev reference will be good,
         // next reference will get fixed by subsequent blocks
-        self.insert(BlockType::Occupied(blocks[0]), n.clone(), Some(infos[0].handle));
-        self.bindings.insert(infos[0].handle, blocks[0]);
+        self.insert(BlockType::Occupied(blocks[0]), n.clone(), Some(keys[0]));
+        self.bindings.insert(keys[0], blocks[0]);
 
         // insert middle blocks, same as in the first one, next references will be fixed by subsequent blocks
         if blocks.len() > 2 {
@@ -214,9 +269,9 @@ impl Table {
                 prev: Some(BlockType::Occupied(blocks[i - 1])),
                 next: Some(BlockType::Occupied(blocks[i + 1])),
               },
-              Some(infos[i].handle),
+              Some(keys[i]),
             );
-            self.bindings.insert(infos[i].handle, blocks[i]);
+            self.bindings.insert(keys[i], blocks[i]);
           }
         }
 
@@ -229,9 +284,9 @@ impl Table {
               prev: Some(BlockType::Occupied(blocks[l - 1])),
               next: n.next,
             },
-            Some(infos[l].handle),
+            Some(keys[l]),
           );
-          self.bindings.insert(infos[l].handle, blocks[l]);
+          self.bindings.insert(keys[l], blocks[l]);
         }
 
         // fix last block
@@ -253,16 +308,152 @@ impl Table {
     Ok(())
   }
 
+  /// Binds resources to the same memory as `target`
+  ///
+  /// The resources alias the memory of `target` and of each other.
+  /// The memory block of `target` is only freed, after `target` and all of its aliases have been unbound.
+  ///
+  /// Fails with
+  ///  - [NotBound](../enum.Error.html) if `target` is not bound in this table
+  ///  - [AliasMismatch](../enum.Error.html) if a resource is larger than the block of `target` or its alignment is not satisfied
+  ///  - [BindMemoryFailed](../enum.Error.html) if a resource could not be bound
+  ///
+  /// The aliases are only recorded after all resources have been bound, if binding fails none of the resources is an alias of `target`.
+  pub fn bind_alias(&mut self, target: Handle<u64>, bindinfos: &[BindInfoInner]) -> Result<(), Error> {
+    let target = *self.alias_of.get(&target).unwrap_or(&target);
+    let block = self.bindings.get(&Key::Resource(target)).cloned().ok_or(Error::NotBound)?;
+    let offset = block.beg + block.pad;
+
+    if bindinfos
+      .iter()
+      .any(|i| i.requirements.size > block.size_padded() || Self::get_padding(offset, i.requirements.alignment) != 0)
+    {
+      Err(Error::AliasMismatch)?
+    }
+
+    for i in bindinfos.iter() {
+      self.backend.bind(i.handle, block.mem, offset)?;
+    }
+
+    for i in bindinfos.iter() {
+      self.alias_of.insert(i.handle, target);
+      self.aliases.entry(target).or_default().push(Alias {
+        handle: i.handle,
+        size: i.requirements.size,
+      });
+    }
+    Ok(())
+  }
+
+  /// Removes aliases from `handles`
+  ///
+  /// Returns the handles whose memory blocks actually need to be freed.
+  /// If a resource is unbound that still has aliases, one of the aliases takes over the memory block.
+  fn unbind_aliases(&mut self, handles: &[Handle<u64>]) -> Vec<Handle<u64>> {
+    let mut unbind = Vec::with_capacity(handles.len());
+    for h in handles {
+      if let Some(target) = self.alias_of.remove(h) {
+        if let Some(aliases) = self.aliases.get_mut(&target) {
+          aliases.retain(|a| a.handle != *h);
+          if aliases.is_empty() {
+            self.aliases.remove(&target);
+          }
+        }
+      } else if let Some(mut aliases) = self.aliases.remove(h) {
+        // promote the first alias to the owner of the memory block
+        let owner = aliases.remove(0).handle;
+        self.alias_of.remove(&owner);
+        for a in aliases.iter() {
+          self.alias_of.insert(a.handle, owner);
+        }
+        if !aliases.is_empty() {
+          self.aliases.insert(owner, aliases);
+        }
+
+        if let Some(b) = self.bindings.remove(&Key::Resource(*h)) {
+          if let Some(binding) = self.pages.get_mut(&b.mem).and_then(|p| p.get_mut(&b)) {
+            binding.key = Key::Resource(owner);
+          }
+          self.bindings.insert(Key::Resource(owner), b);
+        }
+      } else {
+        unbind.push(*h);
+      }
+    }
+    unbind
+  }
+
   /// Frees the allocated blocks of the specified handles
   ///
   /// Removes the mappings of all resources and merges the allocated and padding blocks back into the free list.
+  /// Blocks that are aliased by other resources stay allocated, until the last alias is unbound.
   ///
   /// Does NOT reshuffel the memory to maximize contiuous free blocks,
   /// because vulkan does not allow to rebind buffers/images.
   pub fn unbind(&mut self, handles: &[Handle<u64>]) {
-    let mut blocks = Vec::with_capacity(handles.len());
-    for h in handles {
-      if let Some(b) = self.bindings.remove(h) {
+    for h in handles.iter() {
+      self.labels.remove(h);
+    }
+    let keys = self.unbind_aliases(handles).into_iter().map(Key::Resource).collect::<Vec<_>>();
+    self.unbind_keys(&keys);
+  }
+
+  /// Binds backing memory for pages of a sparse resource
+  ///
+  /// Every page gets its own block of `page_size` bytes, the memory is NOT bound to the resource.
+  /// This has to be done with `vk::QueueBindSparse`.
+  ///
+  /// Returns the blocks of the pages in the same order as `pages`.
+  pub fn bind_sparse(&mut self, handle: Handle<u64>, pages: &[SparsePage], page_size: vk::DeviceSize) -> Result<Vec<Block>, Error> {
+    let infos = pages
+      .iter()
+      .map(|p| BindInfoInner {
+        handle,
+        requirements: vk::MemoryRequirements {
+          size: page_size,
+          alignment: page_size,
+          memoryTypeBits: 1 << self.memtype.index,
+        },
+        page: Some(*p),
+      })
+      .collect::<Vec<_>>();
+
+    self.bind(&infos, BindType::Scatter)?;
+    Ok(infos.iter().map(|i| self.bindings[&i.key()]).collect())
+  }
+
+  /// Reserves a block of memory for a thread cache
+  ///
+  /// The block is not bound to any resource, it is identified by `id` until it is released again.
+  pub fn reserve(&mut self, id: u64, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Result<Block, Error> {
+    let info = BindInfoInner {
+      handle: Handle::Buffer(vk::NULL_HANDLE),
+      requirements: vk::MemoryRequirements {
+        size,
+        alignment,
+        memoryTypeBits: 1 << self.memtype.index,
+      },
+      page: None,
+    };
+    self.bind_keys(&[info], &[Key::Cache(id)], BindType::Block)?;
+    Ok(self.bindings[&Key::Cache(id)])
+  }
+
+  /// Releases a block, that has been reserved with [reserve](struct.Table.html#method.reserve)
+  pub fn release(&mut self, id: u64) {
+    self.unbind_keys(&[Key::Cache(id)]);
+  }
+
+  /// Frees the backing memory of pages of a sparse resource
+  pub fn unbind_sparse(&mut self, handle: Handle<u64>, pages: &[SparsePage]) {
+    let keys = pages.iter().map(|p| Key::Sparse(handle, *p)).collect::<Vec<_>>();
+    self.unbind_keys(&keys);
+  }
+
+  fn unbind_keys(&mut self, keys: &[Key]) {
+    let mut blocks = Vec::with_capacity(keys.len());
+    for k in keys.iter() {
+      if let Some(b) = self.bindings.remove(k) {
         blocks.push(b);
       }
     }
@@ -335,8 +526,8 @@ impl Table {
         group.block.end = next.beg + next.pad;
         if let Some(binding) = self.pages.get_mut(&next.mem).and_then(|p| p.remove(&next)) {
           let b = Block::new(next.mem, next.beg + next.pad, next.end, 0);
-          self.bindings.insert(binding.handle, b);
-          self.insert(BlockType::Occupied(b), binding.node, Some(binding.handle));
+          self.bindings.insert(binding.key, b);
+          self.insert(BlockType::Occupied(b), binding.node, Some(binding.key));
           group.node.next = Some(BlockType::Occupied(b));
         }
 
@@ -369,7 +560,7 @@ impl Table {
     }
   }
 
-  fn insert(&mut self, b: BlockType, n: Node, h: Option<Handle<u64>>) {
+  fn insert(&mut self, b: BlockType, n: Node, k: Option<Key>) {
     if let Some(n) = n.prev.and_then(|prev| self.get_node(prev)) {
       n.next = Some(b);
     }
@@ -386,10 +577,7 @@ impl Table {
         self.pages.get_mut(&b.mem).and_then(|p| {
           p.insert(
             b,
-            Binding {
-              handle: h.unwrap(),
-              node: n,
-            },
+            Binding { key: k.unwrap(), node: n },
           )
         });
       }
@@ -404,6 +592,8 @@ impl Table {
   }
 
   /// Frees up pages with no allocation.
+  ///
+  /// The device memory of a page is released as soon as the last [Mapped](../struct.Mapped.html) view on it is dropped.
   pub fn free_unused(&mut self) {
     let empty = self
       .pages
@@ -412,7 +602,7 @@ impl Table {
       .collect::<Vec<_>>();
 
     for mem in empty {
-      vk::FreeMemory(self.device, mem, std::ptr::null());
+      self.memory.remove(&mem);
 
       if let Some(b) = self
         .free
@@ -429,8 +619,39 @@ impl Table {
   /// Get the block of the specified resourcs.
   ///
   /// If the handle does not have a mapped block in this PageTable, returns None.
+  /// Aliases resolve to the block of the resource they alias.
   pub fn get_mem(&self, handle: Handle<u64>) -> Option<Block> {
-    self.bindings.get(&handle).cloned()
+    self.bindings.get(&Key::Resource(*self.alias_of.get(&handle).unwrap_or(&handle))).cloned()
+  }
+
+  /// Sets the debug label of a resource
+  ///
+  /// The label is removed, when the resource is unbound.
+  pub fn set_label(&mut self, handle: Handle<u64>, label: Label) {
+    self.labels.insert(handle, label);
+  }
+
+  /// Get the debug label of a resource
+  pub fn get_label(&self, handle: Handle<u64>) -> Option<&Label> {
+    self.labels.get(&handle)
+  }
+
+  /// Get the page of the specified device memory
+  ///
+  /// If the memory is not a page of this PageTable, returns None.
+  pub fn get_page(&self, mem: vk::DeviceMemory) -> Option<Arc<Page>> {
+    self.memory.get(&mem).cloned()
+  }
+
+  /// Get the summed up usage of all pages
+  pub fn get_stats(&self) -> TableStats {
+    TableStats {
+      pages: self.pages.len(),
+      allocated: self.memory.values().map(|p| p.get_size()).sum(),
+      free: self.free.keys().map(|b| b.size()).sum(),
+      free_blocks: self.free.len(),
+      largest_free: self.free.keys().map(|b| b.size()).max().unwrap_or(0),
+    }
   }
 
   /// Print stats abount all pages in yaml format
@@ -455,10 +676,40 @@ impl Table {
       blocks.sort_by_key(|b| b.get().beg);
 
       for b in blocks {
-        write!(s, "    {}\n", b).unwrap();
+        match b {
+          BlockType::Occupied(o) => {
+            let key = self.pages[mem].get(&o).map(|binding| binding.key);
+            let (handle, aliases) = match key {
+              Some(Key::Resource(h)) => (Some(h), self.aliases.get(&h)),
+              Some(Key::Sparse(h, _)) => (Some(h), None),
+              Some(Key::Cache(_)) | None => (None, None),
+            };
+            write!(s, "    {}", b).unwrap();
+            if let Some(Key::Cache(_)) = key {
+              write!(s, " [thread cache]").unwrap();
+            }
+            if let Some(label) = handle.and_then(|h| self.labels.get(&h)).filter(|l| !l.is_empty()) {
+              write!(s, " {}", label).unwrap();
+            }
+            match aliases {
+              Some(aliases) => writeln!(s, " [{} aliases]", aliases.len()).unwrap(),
+              None => writeln!(s).unwrap(),
+            }
+          }
+          BlockType::Free(_) => writeln!(s, "    {}", b).unwrap(),
+        }
       }
     }
 
+    if !self.aliases.is_empty() {
+      let (n, saved) = self
+        .aliases
+        .values()
+        .flatten()
+        .fold((0, 0), |(n, saved), a| (n + 1, saved + a.size));
+      writeln!(s, "  Aliases: {} resources on {} blocks, saved {} bytes", n, self.aliases.len(), saved).unwrap();
+    }
+
     //  write!(s, "    - SumAlloc   = {}\n", sum_alloc).unwrap();
     //  write!(s, "    - SumFree    = {}\n", sum_free).unwrap();
     //  write!(s, "    - SumPadding = {}\n", sum_paddings).unwrap();
@@ -534,6 +785,272 @@ impl Table {
       }
     }
 
-    allblocks.iter().any(|(_, b)| *b == true)
+    allblocks.is_empty() || allblocks.values().any(|b| *b)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SimulatedBackend;
+  use proptest::prelude::*;
+
+  const PAGESIZE: vk::DeviceSize = 1 << 12;
+
+  #[derive(Debug, Clone)]
+  enum Op {
+    Bind {
+      linear: bool,
+      block: bool,
+      resources: Vec<(vk::DeviceSize, u32)>,
+    },
+    Unbind(Vec<usize>),
+    FreeUnused,
+  }
+
+  fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+      3 => (any::<bool>(), any::<bool>(), prop::collection::vec((1..3 * PAGESIZE / 2, 0..9u32), 1..8))
+        .prop_map(|(linear, block, resources)| Op::Bind { linear, block, resources }),
+      2 => prop::collection::vec(any::<usize>(), 1..6).prop_map(Op::Unbind),
+      1 => Just(Op::FreeUnused),
+    ]
+  }
+
+  #[derive(Debug, Clone, Copy)]
+  struct Resource {
+    size: vk::DeviceSize,
+    alignment: vk::DeviceSize,
+    linear: bool,
+  }
+
+  /// Page tables for linear and non-linear resources of the same memory type index, that share a simulated backend
+  struct Fixture {
+    backend: Arc<SimulatedBackend>,
+    linear: Table,
+    optimal: Table,
+    live: HashMap<Handle<u64>, Resource>,
+    next: u64,
+  }
+
+  impl Fixture {
+    fn new(backend: SimulatedBackend) -> Self {
+      let backend = Arc::new(backend);
+      Self {
+        linear: Table::new(backend.clone(), Memtype { index: 0, linear: true }, PAGESIZE, true),
+        optimal: Table::new(backend.clone(), Memtype { index: 0, linear: false }, PAGESIZE, true),
+        backend,
+        live: Default::default(),
+        next: 0,
+      }
+    }
+
+    fn table(&mut self, linear: bool) -> &mut Table {
+      match linear {
+        true => &mut self.linear,
+        false => &mut self.optimal,
+      }
+    }
+
+    fn bind(&mut self, linear: bool, bindtype: BindType, resources: &[(vk::DeviceSize, u32)]) -> Result<Vec<Handle<u64>>, Error> {
+      let infos = resources
+        .iter()
+        .map(|(size, align)| {
+          self.next += 1;
+          BindInfoInner {
+            handle: match linear {
+              true => Handle::Buffer(self.next),
+              false => Handle::Image(self.next),
+            },
+            requirements: vk::MemoryRequirements {
+              size: *size,
+              alignment: 1 << align,
+              memoryTypeBits: 1,
+            },
+            page: None,
+          }
+        })
+        .collect::<Vec<_>>();
+
+      self.table(linear).bind(&infos, bindtype)?;
+
+      for i in infos.iter() {
+        self.live.insert(
+          i.handle,
+          Resource {
+            size: i.requirements.size,
+            alignment: i.requirements.alignment,
+            linear,
+          },
+        );
+      }
+      Ok(infos.iter().map(|i| i.handle).collect())
+    }
+
+    fn unbind(&mut self, handles: &[Handle<u64>]) {
+      for linear in [true, false].iter() {
+        let handles = handles
+          .iter()
+          .filter(|h| self.live.get(h).map(|r| r.linear == *linear).unwrap_or(false))
+          .cloned()
+          .collect::<Vec<_>>();
+        self.table(*linear).unbind(&handles);
+      }
+      for h in handles {
+        self.live.remove(h);
+      }
+    }
+
+    fn check(&self) {
+      let mut by_mem = HashMap::<vk::DeviceMemory, Vec<(vk::DeviceSize, Resource)>>::new();
+
+      for (h, r) in self.live.iter() {
+        let (mem, offset) = self.backend.get_binding(*h).expect("resource is not bound");
+        let table = match r.linear {
+          true => &self.linear,
+          false => &self.optimal,
+        };
+        let block = table.get_mem(*h).expect("resource has no block");
+        assert_eq!((block.mem, block.beg + block.pad), (mem, offset), "block does not match the binding");
+        assert_eq!(block.size_padded(), r.size, "block does not match the resource size");
+        assert_eq!(offset % r.alignment, 0, "binding is not aligned");
+
+        let (size, _) = self.backend.get_allocation(mem).expect("resource is bound to freed memory");
+        assert!(offset + r.size <= size, "binding exceeds the allocation");
+        by_mem.entry(mem).or_default().push((offset, *r));
+      }
+
+      for (_, mut bindings) in by_mem {
+        // linear and non-linear resources are bound in separate tables and never share a page,
+        // this is how bufferImageGranularity is satisfied, so there is nothing to check within a page
+        assert!(
+          bindings.iter().all(|(_, r)| r.linear == bindings[0].1.linear),
+          "linear and non-linear resources share a page"
+        );
+        bindings.sort_by_key(|(offset, _)| *offset);
+        for w in bindings.windows(2) {
+          let ((o0, r0), (o1, _)) = (w[0], w[1]);
+          assert!(o0 + r0.size <= o1, "bindings overlap");
+        }
+      }
+    }
+
+    /// Unbinds everything and checks that all memory is returned to the backend
+    fn reclaim(mut self) {
+      let handles = self.live.keys().cloned().collect::<Vec<_>>();
+      self.unbind(&handles);
+
+      for t in [&self.linear, &self.optimal].iter() {
+        assert!(t.bindings.is_empty());
+        assert!(t.pages.values().all(|p| p.is_empty()));
+        assert_eq!(t.free.len(), t.pages.len(), "free blocks are not merged");
+        assert!(t.free.keys().all(|b| b.beg == 0 && b.end == t.memory[&b.mem].get_size()));
+      }
+
+      self.linear.free_unused();
+      self.optimal.free_unused();
+      assert_eq!(self.backend.allocation_count(), 0);
+      assert_eq!(self.backend.allocated_size(), 0);
+    }
+  }
+
+  proptest! {
+    #[test]
+    fn invariants(ops in prop::collection::vec(op(), 1..40)) {
+      let mut f = Fixture::new(SimulatedBackend::new());
+
+      for op in ops {
+        match op {
+          Op::Bind { linear, block, resources } => {
+            let bindtype = match block {
+              true => BindType::Block,
+              false => BindType::Scatter,
+            };
+            let handles = f.bind(linear, bindtype, &resources).unwrap();
+            if block {
+              let mem = f.backend.get_binding(handles[0]).unwrap().0;
+              prop_assert!(handles.iter().all(|h| f.backend.get_binding(*h).unwrap().0 == mem), "block binding on multiple pages");
+            }
+          }
+          Op::Unbind(sel) => {
+            let mut live = f.live.keys().cloned().collect::<Vec<_>>();
+            live.sort_by_key(|h| h.get());
+            if !live.is_empty() {
+              let handles = sel.iter().map(|i| live[i % live.len()]).collect::<Vec<_>>();
+              f.unbind(&handles);
+            }
+          }
+          Op::FreeUnused => {
+            f.linear.free_unused();
+            f.optimal.free_unused();
+          }
+        }
+        f.check();
+      }
+
+      f.reclaim();
+    }
+  }
+
+  #[test]
+  fn out_of_budget() {
+    let mut f = Fixture::new(SimulatedBackend::with_budget(2 * PAGESIZE));
+
+    let a = f.bind(true, BindType::Scatter, &[(PAGESIZE, 0)]).unwrap();
+    f.bind(true, BindType::Scatter, &[(PAGESIZE, 0)]).unwrap();
+    assert!(match f.bind(true, BindType::Scatter, &[(PAGESIZE / 2, 0)]) {
+      Err(Error::AllocError) => true,
+      _ => false,
+    });
+    f.check();
+
+    f.unbind(&a);
+    f.bind(true, BindType::Scatter, &[(PAGESIZE / 2, 0), (PAGESIZE / 2, 4)]).unwrap();
+    f.check();
+    f.reclaim();
+  }
+
+  #[test]
+  fn labels() {
+    let mut f = Fixture::new(SimulatedBackend::new());
+
+    let h = f.bind(true, BindType::Block, &[(256, 0), (256, 0)]).unwrap();
+    f.linear.set_label(h[0], Label::new("vertices", "mesh"));
+    f.linear.set_label(h[1], Label::new("", "gui"));
+    assert_eq!(f.linear.get_label(h[0]).map(|l| l.to_string()), Some("vertices [mesh]".to_owned()));
+
+    let stats = f.linear.print_stats();
+    assert!(stats.contains("(256) vertices [mesh]\n"));
+    assert!(stats.contains("(256) [gui]\n"));
+
+    f.unbind(&h[..1]);
+    assert!(f.linear.get_label(h[0]).is_none());
+    f.reclaim();
+  }
+
+  #[test]
+  fn aliases() {
+    let mut f = Fixture::new(SimulatedBackend::new());
+
+    let owner = f.bind(true, BindType::Block, &[(256, 8)]).unwrap()[0];
+    let alias = Handle::Buffer(1000);
+    let info = BindInfoInner {
+      handle: alias,
+      requirements: vk::MemoryRequirements {
+        size: 128,
+        alignment: 64,
+        memoryTypeBits: 1,
+      },
+      page: None,
+    };
+    f.linear.bind_alias(owner, &[info]).unwrap();
+    assert_eq!(f.backend.get_binding(alias), f.backend.get_binding(owner));
+
+    f.linear.unbind(&[owner]);
+    f.live.remove(&owner);
+    assert_eq!(f.linear.get_mem(alias).map(|b| b.beg + b.pad), f.backend.get_binding(alias).map(|(_, o)| o));
+
+    f.linear.unbind(&[alias]);
+    f.reclaim();
   }
 }
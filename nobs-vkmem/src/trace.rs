@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::bindinfo::BindInfoInner;
+use crate::table::Table;
+use crate::table::TableStats;
+use crate::BindType;
+use crate::Error;
+use crate::Handle;
+use crate::Memtype;
+use crate::SimulatedBackend;
+
+const MAGIC: &[u8; 4] = b"VKMT";
+const VERSION: u32 = 1;
+
+/// Memory requirements of a single resource in a [Trace](struct.Trace.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceResource {
+  pub handle: Handle<u64>,
+  pub size: vk::DeviceSize,
+  pub alignment: vk::DeviceSize,
+}
+
+/// Allocator call recorded in a [Trace](struct.Trace.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+  /// A page table has been created for the memory type with the page size
+  Table { memtype: Memtype, pagesize: vk::DeviceSize },
+  /// Resources have been bound to a page table, `block` is true for [BindType::Block](enum.BindType.html) and false for [BindType::Scatter](enum.BindType.html)
+  Bind {
+    memtype: Memtype,
+    block: bool,
+    resources: Vec<TraceResource>,
+  },
+  /// Resources have been bound as aliases of `target`
+  Alias {
+    target: Handle<u64>,
+    resources: Vec<TraceResource>,
+  },
+  /// Resources have been destroyed
+  Destroy(Vec<Handle<u64>>),
+  /// Unused pages have been freed
+  FreeUnused,
+  /// A chunk has been reserved for a thread cache
+  Reserve {
+    memtype: Memtype,
+    id: u64,
+    size: vk::DeviceSize,
+    alignment: vk::DeviceSize,
+  },
+  /// A chunk of a thread cache has been released
+  Release { memtype: Memtype, id: u64 },
+}
+
+/// Recorded sequence of allocator calls
+///
+/// A trace is recorded with [record](struct.Allocator.html#method.record) and contains every bind, destroy and free_unused call with the memory requirements of the resources.
+/// Pages of sparse resources are not recorded.
+/// Resources in [thread caches](struct.CacheSizes.html) are not recorded either, only the chunks that are reserved and released by the caches.
+///
+/// Traces are stored in a compact binary format and can be replayed without a device with [replay](struct.Trace.html#method.replay).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+  pub events: Vec<Event>,
+}
+
+fn write_u8(w: &mut dyn Write, v: u8) -> std::io::Result<()> {
+  w.write_all(&[v])
+}
+fn write_u32(w: &mut dyn Write, v: u32) -> std::io::Result<()> {
+  w.write_all(&v.to_le_bytes())
+}
+fn write_u64(w: &mut dyn Write, v: u64) -> std::io::Result<()> {
+  w.write_all(&v.to_le_bytes())
+}
+fn read_u8(r: &mut dyn Read) -> std::io::Result<u8> {
+  let mut b = [0; 1];
+  r.read_exact(&mut b)?;
+  Ok(b[0])
+}
+fn read_u32(r: &mut dyn Read) -> std::io::Result<u32> {
+  let mut b = [0; 4];
+  r.read_exact(&mut b)?;
+  Ok(u32::from_le_bytes(b))
+}
+fn read_u64(r: &mut dyn Read) -> std::io::Result<u64> {
+  let mut b = [0; 8];
+  r.read_exact(&mut b)?;
+  Ok(u64::from_le_bytes(b))
+}
+
+/// Converts the resources of a bind call
+pub(crate) fn resources(infos: &[BindInfoInner]) -> Vec<TraceResource> {
+  infos
+    .iter()
+    .map(|i| TraceResource {
+      handle: i.handle,
+      size: i.requirements.size,
+      alignment: i.requirements.alignment,
+    })
+    .collect()
+}
+
+fn invalid(what: &str) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid trace: {}", what))
+}
+
+fn write_memtype(w: &mut dyn Write, memtype: Memtype) -> std::io::Result<()> {
+  write_u32(w, memtype.index)?;
+  write_u8(w, memtype.linear as u8)
+}
+fn read_memtype(r: &mut dyn Read) -> std::io::Result<Memtype> {
+  let index = read_u32(r)?;
+  let linear = read_u8(r)? != 0;
+  Ok(Memtype { index, linear })
+}
+
+fn write_handle(w: &mut dyn Write, h: Handle<u64>) -> std::io::Result<()> {
+  write_u8(
+    w,
+    match h {
+      Handle::Buffer(_) => 0,
+      Handle::Image(_) => 1,
+    },
+  )?;
+  write_u64(w, h.get())
+}
+fn read_handle(r: &mut dyn Read) -> std::io::Result<Handle<u64>> {
+  match read_u8(r)? {
+    0 => Ok(Handle::Buffer(read_u64(r)?)),
+    1 => Ok(Handle::Image(read_u64(r)?)),
+    _ => Err(invalid("handle type")),
+  }
+}
+
+fn write_resources(w: &mut dyn Write, resources: &[TraceResource]) -> std::io::Result<()> {
+  write_u32(w, resources.len() as u32)?;
+  for r in resources.iter() {
+    write_handle(w, r.handle)?;
+    write_u64(w, r.size)?;
+    write_u64(w, r.alignment)?;
+  }
+  Ok(())
+}
+fn read_resources(r: &mut dyn Read) -> std::io::Result<Vec<TraceResource>> {
+  (0..read_u32(r)?)
+    .map(|_| {
+      Ok(TraceResource {
+        handle: read_handle(r)?,
+        size: read_u64(r)?,
+        alignment: read_u64(r)?,
+      })
+    })
+    .collect()
+}
+
+impl Event {
+  /// Writes the event in the binary trace format
+  pub fn write(&self, w: &mut dyn Write) -> std::io::Result<()> {
+    match self {
+      Event::Table { memtype, pagesize } => {
+        write_u8(w, 0)?;
+        write_memtype(w, *memtype)?;
+        write_u64(w, *pagesize)
+      }
+      Event::Bind { memtype, block, resources } => {
+        write_u8(w, 1)?;
+        write_memtype(w, *memtype)?;
+        write_u8(w, *block as u8)?;
+        write_resources(w, resources)
+      }
+      Event::Alias { target, resources } => {
+        write_u8(w, 2)?;
+        write_handle(w, *target)?;
+        write_resources(w, resources)
+      }
+      Event::Destroy(handles) => {
+        write_u8(w, 3)?;
+        write_u32(w, handles.len() as u32)?;
+        handles.iter().try_for_each(|h| write_handle(w, *h))
+      }
+      Event::FreeUnused => write_u8(w, 4),
+      Event::Reserve {
+        memtype,
+        id,
+        size,
+        alignment,
+      } => {
+        write_u8(w, 5)?;
+        write_memtype(w, *memtype)?;
+        write_u64(w, *id)?;
+        write_u64(w, *size)?;
+        write_u64(w, *alignment)
+      }
+      Event::Release { memtype, id } => {
+        write_u8(w, 6)?;
+        write_memtype(w, *memtype)?;
+        write_u64(w, *id)
+      }
+    }
+  }
+
+  /// Reads an event in the binary trace format
+  ///
+  /// Returns None at the end of the stream.
+  pub fn read(r: &mut dyn Read) -> std::io::Result<Option<Event>> {
+    let tag = match read_u8(r) {
+      Ok(tag) => tag,
+      Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+      Err(e) => Err(e)?,
+    };
+    let e = match tag {
+      0 => Event::Table {
+        memtype: read_memtype(r)?,
+        pagesize: read_u64(r)?,
+      },
+      1 => Event::Bind {
+        memtype: read_memtype(r)?,
+        block: read_u8(r)? != 0,
+        resources: read_resources(r)?,
+      },
+      2 => Event::Alias {
+        target: read_handle(r)?,
+        resources: read_resources(r)?,
+      },
+      3 => Event::Destroy((0..read_u32(r)?).map(|_| read_handle(r)).collect::<std::io::Result<_>>()?),
+      4 => Event::FreeUnused,
+      5 => Event::Reserve {
+        memtype: read_memtype(r)?,
+        id: read_u64(r)?,
+        size: read_u64(r)?,
+        alignment: read_u64(r)?,
+      },
+      6 => Event::Release {
+        memtype: read_memtype(r)?,
+        id: read_u64(r)?,
+      },
+      _ => Err(invalid("event type"))?,
+    };
+    Ok(Some(e))
+  }
+}
+
+/// Writes a trace header followed by events as they are recorded
+///
+/// Events are buffered, write errors of the underlying writer may only show up when the buffer is flushed.
+pub(crate) struct TraceWriter {
+  writer: std::io::BufWriter<Box<dyn Write + Send>>,
+}
+
+impl TraceWriter {
+  pub fn new(writer: Box<dyn Write + Send>) -> std::io::Result<Self> {
+    let mut writer = std::io::BufWriter::new(writer);
+    writer.write_all(MAGIC)?;
+    write_u32(&mut writer, VERSION)?;
+    Ok(Self { writer })
+  }
+
+  pub fn write(&mut self, e: &Event) -> std::io::Result<()> {
+    e.write(&mut self.writer)
+  }
+
+  pub fn flush(&mut self) -> std::io::Result<()> {
+    self.writer.flush()
+  }
+}
+
+impl Drop for TraceWriter {
+  fn drop(&mut self) {
+    self.flush().ok();
+  }
+}
+
+/// Usage of all page tables after a replayed event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sample {
+  /// Index of the event in the trace
+  pub event: usize,
+  /// Number of allocated pages
+  pub pages: usize,
+  /// Size of all allocated pages in bytes
+  pub allocated: vk::DeviceSize,
+  /// Size of all bound resources including paddings in bytes
+  pub used: vk::DeviceSize,
+  /// Number of free blocks
+  pub free_blocks: usize,
+  /// Fragmentation of the free memory `1 - largest_free_block / free`, the maximum over all page tables
+  pub fragmentation: f64,
+}
+
+/// Result of a [replay](struct.Trace.html#method.replay)
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+  /// Usage after every event of the trace
+  pub samples: Vec<Sample>,
+}
+
+impl ReplayReport {
+  /// Get the first sample with the most allocated memory
+  pub fn peak(&self) -> Sample {
+    self
+      .samples
+      .iter()
+      .max_by_key(|s| (s.allocated, std::cmp::Reverse(s.event)))
+      .cloned()
+      .unwrap_or_default()
+  }
+
+  /// Get the largest number of allocated pages
+  pub fn peak_pages(&self) -> usize {
+    self.samples.iter().map(|s| s.pages).max().unwrap_or(0)
+  }
+
+  /// Get the largest fragmentation
+  pub fn peak_fragmentation(&self) -> f64 {
+    self.samples.iter().map(|s| s.fragmentation).fold(0.0, f64::max)
+  }
+}
+
+impl std::fmt::Display for ReplayReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let peak = self.peak();
+    writeln!(f, "Events: {}", self.samples.len())?;
+    writeln!(f, "Peak:")?;
+    writeln!(f, "  - Event         = {}", peak.event)?;
+    writeln!(f, "  - Allocated     = {}", peak.allocated)?;
+    writeln!(f, "  - Used          = {}", peak.used)?;
+    writeln!(f, "  - Pages         = {}", self.peak_pages())?;
+    writeln!(f, "  - Fragmentation = {:.3}", self.peak_fragmentation())?;
+    writeln!(f, "Samples:")?;
+    for s in self.samples.iter() {
+      writeln!(
+        f,
+        "  - [{}, {}, {}, {}, {}, {:.3}]",
+        s.event, s.pages, s.allocated, s.used, s.free_blocks, s.fragmentation
+      )?;
+    }
+    Ok(())
+  }
+}
+
+impl Trace {
+  /// Reads a trace in the binary format
+  pub fn read(r: &mut dyn Read) -> std::io::Result<Trace> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC || read_u32(r)? != VERSION {
+      Err(invalid("header"))?
+    }
+
+    let mut events = Vec::new();
+    while let Some(e) = Event::read(r)? {
+      events.push(e);
+    }
+    Ok(Trace { events })
+  }
+
+  /// Writes the trace in the binary format
+  pub fn write(&self, w: Box<dyn Write + Send>) -> std::io::Result<()> {
+    let mut writer = TraceWriter::new(w)?;
+    for e in self.events.iter() {
+      writer.write(e)?;
+    }
+    writer.flush()
+  }
+
+  /// Replays the trace against the page tables with a [SimulatedBackend](struct.SimulatedBackend.html)
+  ///
+  /// Page tables that are not created with an [Event::Table](enum.Event.html) use `default_pagesize`.
+  ///
+  /// Fails with the first error of a page table, e.g. [NotBound](enum.Error.html) if the trace is incomplete.
+  pub fn replay(&self, default_pagesize: vk::DeviceSize) -> Result<ReplayReport, Error> {
+    let backend = Arc::new(SimulatedBackend::new());
+    let mut tables = HashMap::<Memtype, Table>::new();
+    let mut handles = HashMap::<Handle<u64>, Memtype>::new();
+    let mut report = ReplayReport::default();
+
+    let infos = |resources: &[TraceResource]| {
+      resources
+        .iter()
+        .map(|r| BindInfoInner {
+          handle: r.handle,
+          requirements: vk::MemoryRequirements {
+            size: r.size,
+            alignment: r.alignment,
+            memoryTypeBits: !0,
+          },
+          page: None,
+        })
+        .collect::<Vec<_>>()
+    };
+
+    for (i, e) in self.events.iter().enumerate() {
+      match e {
+        Event::Table { memtype, pagesize } => {
+          tables.insert(*memtype, Table::new(backend.clone(), *memtype, *pagesize, false));
+        }
+        Event::Bind { memtype, block, resources } => {
+          let bindtype = match block {
+            true => BindType::Block,
+            false => BindType::Scatter,
+          };
+          tables
+            .entry(*memtype)
+            .or_insert_with(|| Table::new(backend.clone(), *memtype, default_pagesize, false))
+            .bind(&infos(resources), bindtype)?;
+          handles.extend(resources.iter().map(|r| (r.handle, *memtype)));
+        }
+        Event::Alias { target, resources } => {
+          let memtype = *handles.get(target).ok_or(Error::NotBound)?;
+          tables
+            .get_mut(&memtype)
+            .ok_or(Error::NotBound)?
+            .bind_alias(*target, &infos(resources))?;
+          handles.extend(resources.iter().map(|r| (r.handle, memtype)));
+        }
+        Event::Destroy(hs) => {
+          let mut by_memtype = HashMap::<Memtype, Vec<Handle<u64>>>::new();
+          for h in hs.iter() {
+            if let Some(memtype) = handles.remove(h) {
+              by_memtype.entry(memtype).or_default().push(*h);
+            }
+          }
+          for (memtype, hs) in by_memtype {
+            if let Some(tbl) = tables.get_mut(&memtype) {
+              tbl.unbind(&hs);
+            }
+          }
+        }
+        Event::FreeUnused => tables.values_mut().for_each(|t| t.free_unused()),
+        Event::Reserve {
+          memtype,
+          id,
+          size,
+          alignment,
+        } => {
+          tables
+            .entry(*memtype)
+            .or_insert_with(|| Table::new(backend.clone(), *memtype, default_pagesize, false))
+            .reserve(*id, *size, *alignment)?;
+        }
+        Event::Release { memtype, id } => {
+          if let Some(tbl) = tables.get_mut(memtype) {
+            tbl.release(*id);
+          }
+        }
+      }
+
+      let stats = tables.values().map(|t| t.get_stats()).collect::<Vec<_>>();
+      let sum = |f: fn(&TableStats) -> vk::DeviceSize| stats.iter().map(f).sum::<vk::DeviceSize>();
+      report.samples.push(Sample {
+        event: i,
+        pages: stats.iter().map(|s| s.pages).sum(),
+        allocated: sum(|s| s.allocated),
+        used: sum(|s| s.allocated - s.free),
+        free_blocks: stats.iter().map(|s| s.free_blocks).sum(),
+        fragmentation: stats.iter().map(|s| s.fragmentation()).fold(0.0, f64::max),
+      });
+    }
+
+    Ok(report)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn trace() -> Trace {
+    let memtype = Memtype { index: 1, linear: true };
+    let resource = |h, size, alignment| TraceResource {
+      handle: Handle::Buffer(h),
+      size,
+      alignment,
+    };
+    Trace {
+      events: vec![
+        Event::Table { memtype, pagesize: 1024 },
+        Event::Bind {
+          memtype,
+          block: false,
+          resources: vec![resource(1, 256, 256), resource(2, 256, 16), resource(3, 512, 256)],
+        },
+        Event::Alias {
+          target: Handle::Buffer(3),
+          resources: vec![resource(4, 100, 4)],
+        },
+        Event::Destroy(vec![Handle::Buffer(2)]),
+        Event::Bind {
+          memtype: Memtype { index: 0, linear: false },
+          block: true,
+          resources: vec![TraceResource {
+            handle: Handle::Image(5),
+            size: 2048,
+            alignment: 1024,
+          }],
+        },
+        Event::Destroy(vec![Handle::Buffer(1), Handle::Buffer(3), Handle::Buffer(4), Handle::Image(5)]),
+        Event::FreeUnused,
+        Event::Reserve {
+          memtype,
+          id: 1,
+          size: 512,
+          alignment: 256,
+        },
+        Event::Release { memtype, id: 1 },
+        Event::FreeUnused,
+      ],
+    }
+  }
+
+  #[test]
+  fn read_write() {
+    let trace = trace();
+    let mut w = Vec::new();
+    w.extend_from_slice(MAGIC);
+    w.extend_from_slice(&VERSION.to_le_bytes());
+    for e in trace.events.iter() {
+      e.write(&mut w).unwrap();
+    }
+    assert_eq!(Trace::read(&mut w.as_slice()).unwrap(), trace);
+    assert!(Trace::read(&mut &w[1..]).is_err());
+  }
+
+  #[test]
+  fn replay() {
+    let report = trace().replay(4096).unwrap();
+    assert_eq!(report.samples.len(), 10);
+
+    let s = report.samples[3];
+    assert_eq!((s.pages, s.allocated, s.used, s.free_blocks), (1, 1024, 768, 1));
+    assert!((s.fragmentation - 0.0).abs() < 1e-9);
+
+    let peak = report.peak();
+    assert_eq!((peak.event, peak.pages, peak.allocated), (4, 2, 1024 + 4096));
+    assert_eq!(report.peak_pages(), 2);
+
+    let s = report.samples[7];
+    assert_eq!((s.pages, s.allocated, s.used), (1, 1024, 512));
+
+    let last = report.samples.last().unwrap();
+    assert_eq!((last.pages, last.allocated, last.used), (0, 0, 0));
+  }
+}
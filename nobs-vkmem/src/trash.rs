@@ -1,13 +1,190 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use crate::Allocator;
 use crate::Handle;
 
+/// Objects that can be destroyed by the [Trash](struct.Trash.html)
+pub enum Garbage {
+  /// Buffer or image, that is destroyed by the allocator
+  Resource(Handle<u64>),
+  ImageView(vk::ImageView),
+  Sampler(vk::Sampler),
+  Framebuffer(vk::Framebuffer),
+  RenderPass(vk::RenderPass),
+  Pipeline(vk::Pipeline),
+  PipelineLayout(vk::PipelineLayout),
+  ShaderModule(vk::ShaderModule),
+  DescriptorSetLayout(vk::DescriptorSetLayout),
+  DescriptorPool(vk::DescriptorPool),
+  /// Descriptor sets, that are freed from their pool
+  DescriptorSets(vk::DescriptorPool, Vec<vk::DescriptorSet>),
+  /// Command buffers, that are freed from their pool
+  CommandBuffers(vk::CommandPool, Vec<vk::CommandBuffer>),
+  /// Arbitrary destroy closure
+  Fn(Box<dyn FnOnce() + Send>),
+}
+
+impl std::fmt::Debug for Garbage {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Garbage::Resource(h) => write!(f, "Resource({:?})", h),
+      Garbage::ImageView(h) => write!(f, "ImageView({:x})", h),
+      Garbage::Sampler(h) => write!(f, "Sampler({:x})", h),
+      Garbage::Framebuffer(h) => write!(f, "Framebuffer({:x})", h),
+      Garbage::RenderPass(h) => write!(f, "RenderPass({:x})", h),
+      Garbage::Pipeline(h) => write!(f, "Pipeline({:x})", h),
+      Garbage::PipelineLayout(h) => write!(f, "PipelineLayout({:x})", h),
+      Garbage::ShaderModule(h) => write!(f, "ShaderModule({:x})", h),
+      Garbage::DescriptorSetLayout(h) => write!(f, "DescriptorSetLayout({:x})", h),
+      Garbage::DescriptorPool(h) => write!(f, "DescriptorPool({:x})", h),
+      Garbage::DescriptorSets(p, s) => write!(f, "DescriptorSets({:x}, {:x?})", p, s),
+      Garbage::CommandBuffers(p, c) => write!(f, "CommandBuffers({:x}, {:x?})", p, c),
+      Garbage::Fn(_) => write!(f, "Fn"),
+    }
+  }
+}
+
+impl From<Handle<u64>> for Garbage {
+  fn from(h: Handle<u64>) -> Self {
+    Garbage::Resource(h)
+  }
+}
+
+impl Garbage {
+  /// Destroys the object, resources are collected in `resources` to be destroyed in bulk by the allocator
+  fn destroy(self, device: vk::Device, resources: &mut Vec<Handle<u64>>) {
+    let null = std::ptr::null();
+    match self {
+      Garbage::Resource(h) => resources.push(h),
+      Garbage::ImageView(h) => vk::DestroyImageView(device, h, null),
+      Garbage::Sampler(h) => vk::DestroySampler(device, h, null),
+      Garbage::Framebuffer(h) => vk::DestroyFramebuffer(device, h, null),
+      Garbage::RenderPass(h) => vk::DestroyRenderPass(device, h, null),
+      Garbage::Pipeline(h) => vk::DestroyPipeline(device, h, null),
+      Garbage::PipelineLayout(h) => vk::DestroyPipelineLayout(device, h, null),
+      Garbage::ShaderModule(h) => vk::DestroyShaderModule(device, h, null),
+      Garbage::DescriptorSetLayout(h) => vk::DestroyDescriptorSetLayout(device, h, null),
+      Garbage::DescriptorPool(h) => vk::DestroyDescriptorPool(device, h, null),
+      Garbage::DescriptorSets(pool, sets) => {
+        vk_check!(vk::FreeDescriptorSets(device, pool, sets.len() as u32, sets.as_ptr())).ok();
+      }
+      Garbage::CommandBuffers(pool, cbs) => vk::FreeCommandBuffers(device, pool, cbs.len() as u32, cbs.as_ptr()),
+      Garbage::Fn(f) => f(),
+    }
+  }
+}
+
+/// Defines when garbage in the [Trash](struct.Trash.html) may be destroyed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retire {
+  /// After the number of frames in flight, that is specified when creating the Trash
+  Frame,
+  /// As soon as the fence is signaled
+  Fence(vk::Fence),
+  /// As soon as the submission id has been [completed](struct.Trash.html#method.complete)
+  Submission(u64),
+}
+
+/// Bookkeeping of garbage by its retirement
+///
+/// Does not know how to destroy garbage, [clean](struct.Bins.html#method.clean) only returns everything that is retired.
+struct Bins<T> {
+  ring_buffer: Vec<Vec<T>>,
+  ring_index: usize,
+  fences: Vec<(vk::Fence, Vec<T>)>,
+  submissions: VecDeque<(u64, Vec<T>)>,
+  completed: Option<u64>,
+}
+
+impl<T> Bins<T> {
+  fn new(inflight: usize) -> Self {
+    let mut ring_buffer = Vec::with_capacity(inflight);
+    ring_buffer.resize_with(inflight, Default::default);
+    Self {
+      ring_buffer,
+      ring_index: 0,
+      fences: Default::default(),
+      submissions: Default::default(),
+      completed: None,
+    }
+  }
+
+  fn push(&mut self, retire: Retire, t: T) {
+    match retire {
+      Retire::Frame => self.ring_buffer[self.ring_index].push(t),
+      Retire::Fence(fence) => match self.fences.iter_mut().find(|(f, _)| *f == fence) {
+        Some((_, ts)) => ts.push(t),
+        None => self.fences.push((fence, vec![t])),
+      },
+      Retire::Submission(id) => {
+        // submission ids are increasing, so that we can retire them in order
+        let i = self.submissions.iter().position(|(s, _)| *s >= id).unwrap_or(self.submissions.len());
+        match self.submissions.get_mut(i) {
+          Some((s, ts)) if *s == id => ts.push(t),
+          _ => self.submissions.insert(i, (id, vec![t])),
+        }
+      }
+    }
+  }
+
+  fn complete(&mut self, id: u64) {
+    self.completed = Some(self.completed.map_or(id, |c| u64::max(c, id)));
+  }
+
+  /// Advances the ring buffer and collects everything, that is retired
+  fn clean(&mut self, is_signaled: impl Fn(vk::Fence) -> bool) -> Vec<T> {
+    let next = (self.ring_index + 1) % self.ring_buffer.len();
+    let mut retired = std::mem::take(&mut self.ring_buffer[next]);
+    self.ring_index = next;
+
+    let (signaled, pending) = std::mem::take(&mut self.fences).into_iter().partition(|(f, _)| is_signaled(*f));
+    self.fences = pending;
+    retired.extend(signaled.into_iter().flat_map(|(_, ts): (_, Vec<T>)| ts));
+
+    while let Some(true) = self.submissions.front().map(|(id, _)| Some(*id) <= self.completed) {
+      retired.extend(self.submissions.pop_front().unwrap().1);
+    }
+
+    retired
+  }
+
+  /// Collects all garbage regardless of its retirement
+  fn drain(&mut self) -> Vec<T> {
+    let mut all = self.ring_buffer.iter_mut().flat_map(|r| r.drain(..)).collect::<Vec<_>>();
+    all.extend(self.fences.drain(..).flat_map(|(_, ts)| ts));
+    all.extend(self.submissions.drain(..).flat_map(|(_, ts)| ts));
+    all
+  }
+}
+
 struct TrashImpl {
   alloc: Allocator,
-  ring_buffer: Vec<Vec<Handle<u64>>>,
-  ring_index: usize,
+  bins: Bins<Garbage>,
+}
+
+/// Destroys garbage, buffers and images are destroyed in bulk with the allocator
+fn destroy(alloc: &Allocator, garbage: Vec<Garbage>) {
+  let device = alloc.get_device();
+  let mut resources = Vec::new();
+  for g in garbage {
+    g.destroy(device, &mut resources);
+  }
+  if !resources.is_empty() {
+    alloc.destroy_many(&resources);
+  }
+}
+
+impl Drop for TrashImpl {
+  /// Waits for the device to be idle and destroys all remaining garbage, even if it is not retired yet
+  fn drop(&mut self) {
+    let garbage = self.bins.drain();
+    if !garbage.is_empty() {
+      self.alloc.wait_idle();
+      destroy(&self.alloc, garbage);
+    }
+  }
 }
 
 /// Keeps track of unused resources and deletes them
@@ -17,7 +194,14 @@ struct TrashImpl {
 ///
 /// The main idea is to call [clean](struct.Trash.html#method.clean) once every frame. Dependeing on the number of frames that can be in flight we wait N frames before deleting the resources.
 ///
+/// Besides buffers and images of the allocator, the Trash accepts any vulkan object as [Garbage](enum.Garbage.html), including arbitrary destroy closures.
+/// Garbage is retired in one of the following ways, see [Retire](enum.Retire.html):
+///  - after a fixed number of frames (calls to [clean](struct.Trash.html#method.clean)), this is what the `push*` methods do
+///  - as soon as a fence is signaled, e.g. for resources that are freed mid frame and are used by a specific submission
+///  - as soon as a submission id is [completed](struct.Trash.html#method.complete), e.g. for tracking submissions with a timeline
+///
 /// The Trash can be used concurrently, hazards are resolved internally.
+/// When the last reference to the Trash is dropped, it waits for the device to be idle, so that no garbage is in use by a frame in flight, and destroys all remaining garbage.
 ///
 /// The example shows the basic usage of [Trash](struct.Trash.html). 
 ///
@@ -55,8 +239,8 @@ struct TrashImpl {
 ///
 /// // crate the allocator and trash
 /// // the inflight parameter of Trash::new() controlls the delay of resource deletion
-/// let mut allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
-/// let mut trash = vkmem::Trash::new(allocator.clone(), 2);
+/// let allocator = vkmem::Allocator::new(pdevice.handle, device.handle);
+/// let trash = vkmem::Trash::new(allocator.clone(), 2);
 ///
 /// // create some resources
 /// let mut buf = vk::NULL_HANDLE;
@@ -71,7 +255,7 @@ struct TrashImpl {
 /// #  .size(123, 123, 1)
 /// #  .usage(vk::IMAGE_USAGE_SAMPLED_BIT)
 /// #  .devicelocal(true)
-/// #  .bind(&mut allocator, vkmem::BindType::Scatter)
+/// #  .bind(&allocator, vkmem::BindType::Scatter)
 /// #  .unwrap();
 ///
 /// // do some thing ...
@@ -85,6 +269,17 @@ struct TrashImpl {
 /// // suppose we finished our draw call (and synced with the cpu)
 /// // now clean will delete img
 /// trash.clean();
+///
+/// // any vulkan object can be pushed, descriptor sets are only freed, after the fence of their submission is signaled
+/// # let pool = vk::NULL_HANDLE;
+/// # let set = vk::NULL_HANDLE;
+/// # let fence = vk::NULL_HANDLE;
+/// trash.push_retire(vkmem::Retire::Fence(fence), vkmem::Garbage::DescriptorSets(pool, vec![set]));
+///
+/// // arbitrary cleanup closures are delayed as well, e.g. to return an index to a free list
+/// let free_list = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+/// let fl = free_list.clone();
+/// trash.push_fn(move || fl.lock().unwrap().push(42u32));
 /// # }
 /// ```
 #[derive(Clone)]
@@ -102,22 +297,22 @@ impl Trash {
   /// # Returns
   /// The Trash object
   pub fn new(alloc: Allocator, inflight: usize) -> Self {
-    let mut ring_buffer = Vec::with_capacity(inflight);
-    ring_buffer.resize_with(inflight, || Default::default());
     Self {
       imp: Arc::new(Mutex::new(TrashImpl {
         alloc,
-        ring_buffer,
-        ring_index: 0,
+        bins: Bins::new(inflight),
       })),
     }
   }
 
+  /// Get the allocator, that destroys the tracked resources
+  pub fn get_allocator(&self) -> Allocator {
+    self.imp.lock().unwrap().alloc.clone()
+  }
+
   /// Mark the given resource for deletion
   pub fn push(&self, resource: Handle<u64>) {
-    let mut imp = self.imp.lock().unwrap();
-    let i = imp.ring_index;
-    imp.ring_buffer[i].push(resource);
+    self.push_garbage(Garbage::Resource(resource));
   }
   /// Mark the specified image for deletion
   pub fn push_image(&self, img: vk::Image) {
@@ -128,18 +323,116 @@ impl Trash {
     self.push(Handle::Buffer(buf));
   }
 
+  /// Mark the given object for deletion
+  ///
+  /// The object is destroyed after the number of frames in flight.
+  pub fn push_garbage(&self, garbage: Garbage) {
+    self.push_retire(Retire::Frame, garbage);
+  }
+
+  /// Mark the destroy closure for execution
+  ///
+  /// The closure is called after the number of frames in flight.
+  pub fn push_fn<F: FnOnce() + Send + 'static>(&self, f: F) {
+    self.push_garbage(Garbage::Fn(Box::new(f)));
+  }
+
+  /// Mark the given object for deletion, as soon as it is retired
+  ///
+  /// Retired objects are destroyed with the next call to [clean](struct.Trash.html#method.clean).
+  pub fn push_retire(&self, retire: Retire, garbage: Garbage) {
+    self.imp.lock().unwrap().bins.push(retire, garbage);
+  }
+
+  /// Marks all submissions up to and including `id` as completed
+  ///
+  /// Garbage pushed with [Retire::Submission](enum.Retire.html) for these submissions is destroyed with the next call to [clean](struct.Trash.html#method.clean).
+  pub fn complete(&self, id: u64) {
+    self.imp.lock().unwrap().bins.complete(id);
+  }
+
   /// Cleans up resources that can be deleted safely
+  ///
+  /// Advances the frame ring buffer and destroys all garbage, that is retired.
+  /// The garbage is destroyed without holding the lock of the Trash, so destroy closures may push new garbage.
+  ///
+  /// # Returns
+  /// True, if any garbage has been destroyed.
   pub fn clean(&self) -> bool {
-    let mut imp = self.imp.lock().unwrap();
-    let next = (imp.ring_index + 1) % imp.ring_buffer.len();
-    let free = !imp.ring_buffer[next].is_empty();
+    let (alloc, garbage) = {
+      let mut imp = self.imp.lock().unwrap();
+      let device = imp.alloc.get_device();
+      let garbage = imp.bins.clean(|fence| vk::GetFenceStatus(device, fence) == vk::SUCCESS);
+      (imp.alloc.clone(), garbage)
+    };
+    let free = !garbage.is_empty();
+    destroy(&alloc, garbage);
+    free
+  }
+}
 
-    if !imp.ring_buffer[next].is_empty() {
-      imp.alloc.clone().destroy_many(&imp.ring_buffer[next]);
-      imp.ring_buffer[next].clear();
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::allocator::tests::*;
 
-    imp.ring_index = next;
-    free
+  #[test]
+  fn frames() {
+    let mut bins = Bins::new(2);
+    bins.push(Retire::Frame, 1);
+    assert!(bins.clean(|_| false).is_empty());
+    bins.push(Retire::Frame, 2);
+    assert_eq!(bins.clean(|_| false), vec![1]);
+    assert_eq!(bins.clean(|_| false), vec![2]);
+    assert!(bins.clean(|_| false).is_empty());
+  }
+
+  #[test]
+  fn fences() {
+    let mut bins = Bins::new(3);
+    bins.push(Retire::Fence(1), 1);
+    bins.push(Retire::Fence(2), 2);
+    bins.push(Retire::Fence(1), 3);
+    assert!(bins.clean(|_| false).is_empty());
+    assert_eq!(bins.clean(|f| f == 1), vec![1, 3]);
+    assert_eq!(bins.clean(|_| true), vec![2]);
+  }
+
+  #[test]
+  fn submissions() {
+    let mut bins = Bins::new(3);
+    bins.push(Retire::Submission(5), 5);
+    bins.push(Retire::Submission(2), 2);
+    bins.push(Retire::Submission(7), 7);
+    bins.push(Retire::Submission(5), 6);
+    assert!(bins.clean(|_| true).is_empty());
+
+    bins.complete(5);
+    bins.complete(3);
+    assert_eq!(bins.clean(|_| true), vec![2, 5, 6]);
+    bins.complete(7);
+    bins.push(Retire::Frame, 1);
+    let mut all = bins.drain();
+    all.sort();
+    assert_eq!(all, vec![1, 7]);
+  }
+
+  #[test]
+  fn drop_waits_idle() {
+    let (alloc, backend) = simulated(None);
+
+    let trash = Trash::new(alloc.clone(), 2);
+    drop(trash);
+    assert_eq!(backend.idle_count(), 0);
+
+    let trash = Trash::new(alloc.clone(), 2);
+    trash.push(bind_buffer(&alloc, &backend, 256, HOST));
+    trash.push_retire(Retire::Submission(1), Garbage::Resource(bind_buffer(&alloc, &backend, 256, HOST)));
+    assert!(!trash.clean());
+    assert_eq!(backend.resource_count(), 2);
+
+    drop(trash);
+    assert_eq!(backend.idle_count(), 1);
+    assert_eq!(backend.resource_count(), 0);
   }
 }
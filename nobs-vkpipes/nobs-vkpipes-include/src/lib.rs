@@ -0,0 +1,139 @@
+//! Resolves includes of glsl sources
+//!
+//! This crate is shared between the `shader` macro and the runtime compilation of nobs-vkpipes,
+//! so that includes are resolved the same way at compile time and at runtime.
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Finds the file of an include
+///
+/// - `#include "file"` is searched relative to the including file
+/// - `#include <file>` is searched in the include directories in order, absolute paths are not allowed
+///
+/// Returns the canonical path of the included file.
+pub fn find_include<P: AsRef<Path>>(
+  includee: &str,
+  include_type: shaderc::IncludeType,
+  includer: &str,
+  include_dirs: &[P],
+) -> Result<PathBuf, String> {
+  let resolved = match include_type {
+    shaderc::IncludeType::Relative => Path::new(includer).parent().map(|p| p.join(includee)).filter(|p| p.is_file()),
+    shaderc::IncludeType::Standard => match Path::new(includee).is_absolute() {
+      true => None,
+      false => include_dirs.iter().map(|dir| dir.as_ref().join(includee)).find(|p| p.is_file()),
+    },
+  };
+
+  resolved
+    .and_then(|p| p.canonicalize().ok())
+    .ok_or(format!("Include `{}` is not a file, included from `{}`", includee, includer))
+}
+
+/// Finds and reads the file of an include
+///
+/// Fails if the file can not be read, or its path is not valid unicode.
+pub fn resolve_include<P: AsRef<Path>>(
+  includee: &str,
+  include_type: shaderc::IncludeType,
+  includer: &str,
+  include_dirs: &[P],
+) -> Result<shaderc::ResolvedInclude, String> {
+  let resolved = find_include(includee, include_type, includer, include_dirs)?;
+  let resolved_name = resolved
+    .to_str()
+    .ok_or(format!(
+      "Include `{}` resolves to `{}`, which is not valid unicode",
+      includee,
+      resolved.display()
+    ))?
+    .to_string();
+  let content = std::fs::read_to_string(&resolved).map_err(|e| format!("Could not read {}: {}", resolved_name, e))?;
+  Ok(shaderc::ResolvedInclude { resolved_name, content })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Creates a directory tree with a root source, a relative include and an include in an include directory
+  fn tree(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("nobs-vkpipes-include-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("shaders/common")).unwrap();
+    std::fs::create_dir_all(root.join("include")).unwrap();
+    std::fs::write(root.join("shaders/main.comp"), "#include \"common/util.glsl\"").unwrap();
+    std::fs::write(root.join("shaders/common/util.glsl"), "// util").unwrap();
+    std::fs::write(root.join("include/lib.glsl"), "// lib").unwrap();
+    std::fs::write(root.join("shaders/lib.glsl"), "// shadowed lib").unwrap();
+    root.canonicalize().unwrap()
+  }
+
+  #[test]
+  fn relative() {
+    let root = tree("relative");
+    let main = root.join("shaders/main.comp");
+    let main = main.to_str().unwrap();
+    let none: &[PathBuf] = &[];
+
+    let util = find_include("common/util.glsl", shaderc::IncludeType::Relative, main, none).unwrap();
+    assert_eq!(util, root.join("shaders/common/util.glsl"));
+
+    // includes of an include are relative to the include
+    let util = util.to_str().unwrap();
+    assert_eq!(
+      find_include("../main.comp", shaderc::IncludeType::Relative, util, none).unwrap(),
+      root.join("shaders/main.comp")
+    );
+
+    assert!(find_include("missing.glsl", shaderc::IncludeType::Relative, main, none)
+      .unwrap_err()
+      .contains("Include `missing.glsl` is not a file"));
+    // relative includes are not searched in the include directories
+    assert!(find_include("lib.glsl", shaderc::IncludeType::Relative, util, &[root.join("include")]).is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn standard() {
+    let root = tree("standard");
+    let main = root.join("shaders/main.comp");
+    let main = main.to_str().unwrap();
+
+    // include directories are searched in order
+    let dirs = [root.join("include"), root.join("shaders")];
+    assert_eq!(
+      find_include("lib.glsl", shaderc::IncludeType::Standard, main, &dirs).unwrap(),
+      root.join("include/lib.glsl")
+    );
+    let dirs = [root.join("missing"), root.join("shaders"), root.join("include")];
+    assert_eq!(
+      find_include("lib.glsl", shaderc::IncludeType::Standard, main, &dirs).unwrap(),
+      root.join("shaders/lib.glsl")
+    );
+
+    // standard includes are not searched relative to the includer
+    assert!(find_include("common/util.glsl", shaderc::IncludeType::Standard, main, &[root.join("include")]).is_err());
+    // absolute paths are not allowed
+    let lib = root.join("include/lib.glsl");
+    assert!(find_include(lib.to_str().unwrap(), shaderc::IncludeType::Standard, main, &[root.join("include")]).is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn resolve() {
+    let root = tree("resolve");
+    let main = root.join("shaders/main.comp");
+
+    let include = resolve_include(
+      "lib.glsl",
+      shaderc::IncludeType::Standard,
+      main.to_str().unwrap(),
+      &[root.join("include")],
+    )
+    .unwrap();
+    assert_eq!(include.resolved_name, root.join("include/lib.glsl").to_str().unwrap());
+    assert_eq!(include.content, "// lib");
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -1,3 +1,5 @@
+use block;
+use std::collections::HashMap;
 use vk;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -8,18 +10,28 @@ pub struct Binding {
   pub desctype: vk::DescriptorType,
   pub arrayelems: u32,
   pub stageflags: vk::ShaderStageFlagBits,
+  pub flags: vk::DescriptorBindingFlagsEXT,
+  pub immutable_sampler: bool,
 }
 
+/// Number of descriptors of runtime arrays, if not specified otherwise
+pub const DEFAULT_RUNTIME_ARRAY_LEN: u32 = 4096;
+
+/// Binding flags for runtime sized descriptor arrays
+pub const RUNTIME_ARRAY_FLAGS: vk::DescriptorBindingFlagsEXT =
+  vk::DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT_EXT | vk::DESCRIPTOR_BINDING_PARTIALLY_BOUND_BIT_EXT;
+
 impl Binding {
   pub fn to_binding_string(&self) -> String {
     format!(
-      "Binding {{ name: &\"{name}\", binding: {binding}, descset: {descset}, desctype: {desctype}, arrayelems: {arrayelems}, stageflags: {stageflags} }},\n",
+      "Binding {{ name: &\"{name}\", binding: {binding}, descset: {descset}, desctype: {desctype}, arrayelems: {arrayelems}, stageflags: {stageflags}, flags: {flags} }},\n",
       name = self.name,
       binding = self.binding,
       descset = self.descset,
       desctype = self.desctype,
       arrayelems = self.arrayelems,
       stageflags = self.stageflags,
+      flags = self.flags,
     )
   }
 
@@ -33,7 +45,8 @@ impl Binding {
       vk::DESCRIPTOR_TYPE_SAMPLER
       | vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER
       | vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE
-      | vk::DESCRIPTOR_TYPE_STORAGE_IMAGE => ("image", "vk::DescriptorImageInfo"),
+      | vk::DESCRIPTOR_TYPE_STORAGE_IMAGE
+      | vk::DESCRIPTOR_TYPE_INPUT_ATTACHMENT => ("image", "vk::DescriptorImageInfo"),
 
       vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER | vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER => ("bufferview", "vk::BufferView"),
 
@@ -44,6 +57,10 @@ impl Binding {
   pub fn to_dset_write_string(&self, dset_name: &str) -> String {
     let (setter, desctype) = self.get_desctype_str();
 
+    if self.immutable_sampler {
+      return self.to_dset_write_immutable_string(dset_name);
+    }
+
     if self.arrayelems == 1 {
       format!(
         "
@@ -52,7 +69,7 @@ impl Binding {
           self
         }}
         ",
-        name = self.name,
+        name = block::ident(&self.name),
         dset_name = dset_name,
         binding = self.binding,
         ty = self.desctype,
@@ -62,7 +79,7 @@ impl Binding {
     } else {
       format!(
         "
-        pub fn {name}_elem(mut self, array_elem: u32, info: {desctype}) -> {dset_name} {{
+        pub fn {elem}(mut self, array_elem: u32, info: {desctype}) -> {dset_name} {{
           self.inner = self.inner.{setter}({binding}, array_elem, {ty}, info);
           self
         }}
@@ -71,7 +88,8 @@ impl Binding {
           self
         }}
         ",
-        name = self.name,
+        name = block::ident(&self.name),
+        elem = block::ident(&format!("{}_elem", self.name)),
         dset_name = dset_name,
         binding = self.binding,
         ty = self.desctype,
@@ -81,8 +99,286 @@ impl Binding {
     }
   }
 
+  /// Writes the setters for bindings with immutable samplers
+  ///
+  /// Samplers can not be written at all, combined image samplers are only written with layout and image view.
+  fn to_dset_write_immutable_string(&self, dset_name: &str) -> String {
+    if self.desctype == vk::DESCRIPTOR_TYPE_SAMPLER {
+      return String::new();
+    }
+
+    if self.arrayelems == 1 {
+      format!(
+        "
+        pub fn {name}(mut self, layout: vk::ImageLayout, view: vk::ImageView) -> {dset_name} {{
+          let info = vk::DescriptorImageInfo {{ imageLayout: layout, imageView: view, sampler: vk::NULL_HANDLE }};
+          self.inner = self.inner.image({binding}, 0, {ty}, info);
+          self
+        }}
+        ",
+        name = block::ident(&self.name),
+        dset_name = dset_name,
+        binding = self.binding,
+        ty = self.desctype,
+      )
+    } else {
+      format!(
+        "
+        pub fn {elem}(mut self, array_elem: u32, layout: vk::ImageLayout, view: vk::ImageView) -> {dset_name} {{
+          let info = vk::DescriptorImageInfo {{ imageLayout: layout, imageView: view, sampler: vk::NULL_HANDLE }};
+          self.inner = self.inner.image({binding}, array_elem, {ty}, info);
+          self
+        }}
+        pub fn {name}(mut self, layout: vk::ImageLayout, views: &[vk::ImageView]) -> {dset_name} {{
+          let infos = views
+            .iter()
+            .map(|v| vk::DescriptorImageInfo {{ imageLayout: layout, imageView: *v, sampler: vk::NULL_HANDLE }})
+            .collect::<Vec<_>>();
+          self.inner = self.inner.images({binding}, 0, {ty}, &infos);
+          self
+        }}
+        ",
+        name = block::ident(&self.name),
+        elem = block::ident(&format!("{}_elem", self.name)),
+        dset_name = dset_name,
+        binding = self.binding,
+        ty = self.desctype,
+      )
+    }
+  }
+
+  /// Checks if the binding is part of the descriptor update template of its set
+  ///
+  /// Runtime arrays are excluded, since their descriptors are written individually.
+  /// Samplers with immutable samplers are excluded, since they must not be written.
+  pub fn in_template(&self) -> bool {
+    self.flags & vk::DESCRIPTOR_BINDING_PARTIALLY_BOUND_BIT_EXT == 0
+      && !(self.immutable_sampler && self.desctype == vk::DESCRIPTOR_TYPE_SAMPLER)
+  }
+
+  pub fn to_template_field_string(&self) -> String {
+    let (_, desctype) = self.get_desctype_str();
+    match self.arrayelems {
+      1 => format!("pub {}: {},\n", block::ident(&self.name), desctype),
+      n => format!("pub {}: [{}; {}],\n", block::ident(&self.name), desctype, n),
+    }
+  }
+
+  pub fn to_template_entry_string(&self) -> String {
+    let (_, desctype) = self.get_desctype_str();
+    format!(
+      "vk::DescriptorUpdateTemplateEntry {{ dstBinding: {binding}, dstArrayElement: 0, descriptorCount: {arrayelems}, descriptorType: {ty}, offset: std::mem::offset_of!(Template, {name}), stride: std::mem::size_of::<{desctype}>() }},\n",
+      binding = self.binding,
+      arrayelems = self.arrayelems,
+      ty = self.desctype,
+      name = block::ident(&self.name),
+      desctype = desctype,
+    )
+  }
+
   /// Comparecs everything for equality except `stageflags`
   pub fn same_stage(a: &Self, b: &Self) -> bool {
     a.name == b.name && a.binding == b.binding && a.descset == b.descset && a.desctype == b.desctype && a.arrayelems == b.arrayelems
   }
+
+  /// Adds a binding of a shader stage to the bindings of a pipeline
+  ///
+  /// Bindings at the same location `(descset, binding)` of different stages are merged, if they are the same except for `stageflags`.
+  /// Within a descriptor set bindings at the same binding must have the same name and bindings at different bindings must have different names.
+  /// Bindings in different descriptor sets may have the same name.
+  /// Bindings at the same location with a different descriptor type or number of descriptors (e.g. a runtime array with a different `runtime_array_len` in each stage) are rejected.
+  pub fn merge(bindings: &mut Vec<Binding>, b: &Binding, stage_bit: vk::ShaderStageFlagBits) -> Result<(), String> {
+    let location = |bind: &Binding| (bind.descset, bind.binding);
+    if bindings
+      .iter()
+      .any(|bind| bind.descset == b.descset && (bind.binding == b.binding) != (bind.name == b.name))
+    {
+      Err(format!("binding name collision for binding {:?}", b))?;
+    }
+
+    // if the binding already exists add the shader stage to binding
+    // else we create a new binding for the pipeline
+    match bindings.iter().position(|bind| location(bind) == location(b)) {
+      Some(p) if Binding::same_stage(&bindings[p], b) => bindings[p].stageflags |= stage_bit,
+      Some(p) => Err(format!(
+        "binding {} in set {} at binding {} is declared with descriptor type {} and {} descriptors, but with descriptor type {} and {} descriptors in another stage",
+        b.name, b.descset, b.binding, b.desctype, b.arrayelems, bindings[p].desctype, bindings[p].arrayelems
+      ))?,
+      None => bindings.push(b.clone()),
+    }
+    Ok(())
+  }
+
+  /// Marks the sampler or combined image sampler binding with the specified name to use an immutable sampler
+  ///
+  /// Samplers are specified by binding name only, so the name must identify exactly one binding over all descriptor sets.
+  pub fn set_immutable_sampler(bindings: &mut [Binding], name: &str) -> Result<(), String> {
+    let mut found = bindings.iter_mut().filter(|b| b.name == name);
+    let b = match (found.next(), found.next()) {
+      (Some(b), None) => b,
+      (Some(b), Some(other)) => Err(format!(
+        "immutable sampler specified for binding {}, that exists in descriptor set {} and {}",
+        name, b.descset, other.descset
+      ))?,
+      (None, _) => Err(format!("immutable sampler specified for binding {}, that does not exist", name))?,
+    };
+    if b.desctype != vk::DESCRIPTOR_TYPE_SAMPLER && b.desctype != vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER {
+      Err(format!(
+        "immutable sampler specified for binding {}, that is neither a sampler nor a combined image sampler",
+        name
+      ))?;
+    }
+    b.immutable_sampler = true;
+    Ok(())
+  }
+
+  /// Sets the number of descriptors for runtime arrays
+  ///
+  /// Runtime arrays are reflected with zero array elements.
+  pub fn set_runtime_array_len(bindings: &mut [Binding], len: u32) {
+    bindings.iter_mut().filter(|b| b.arrayelems == 0).for_each(|b| b.arrayelems = len);
+  }
+
+  /// Marks the runtime array with the highest binding in every descriptor set with a variable descriptor count
+  ///
+  /// Only the last binding of a descriptor set may have a variable descriptor count.
+  pub fn set_variable_count(bindings: &mut [Binding]) {
+    bindings
+      .iter_mut()
+      .for_each(|b| b.flags &= !vk::DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT);
+
+    let last = bindings.iter().enumerate().fold(HashMap::new(), |mut acc, (i, b)| {
+      {
+        let l = acc.entry(b.descset).or_insert(i);
+        if bindings[*l].binding < b.binding {
+          *l = i;
+        }
+      }
+      acc
+    });
+
+    for i in last.values() {
+      if bindings[*i].flags & vk::DESCRIPTOR_BINDING_PARTIALLY_BOUND_BIT_EXT != 0 {
+        bindings[*i].flags |= vk::DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn binding(name: &str, binding: u32, desctype: vk::DescriptorType, arrayelems: u32) -> Binding {
+    Binding {
+      name: name.to_owned(),
+      binding,
+      descset: 0,
+      desctype,
+      arrayelems,
+      stageflags: vk::SHADER_STAGE_COMPUTE_BIT,
+      flags: 0,
+      immutable_sampler: false,
+    }
+  }
+
+  #[test]
+  fn template_fields() {
+    let b = binding("ub", 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER, 1);
+    assert_eq!(b.to_template_field_string(), "pub ub: vk::DescriptorBufferInfo,\n");
+    let b = binding("textures", 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 4);
+    assert_eq!(b.to_template_field_string(), "pub textures: [vk::DescriptorImageInfo; 4],\n");
+    let b = binding("texels", 2, vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER, 1);
+    assert_eq!(b.to_template_field_string(), "pub texels: vk::BufferView,\n");
+    // input attachments are written with image infos
+    let b = binding("attachment", 4, vk::DESCRIPTOR_TYPE_INPUT_ATTACHMENT, 1);
+    assert_eq!(b.to_template_field_string(), "pub attachment: vk::DescriptorImageInfo,\n");
+
+    // keywords are raw identifiers
+    let b = binding("type", 3, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER, 1);
+    assert_eq!(b.to_template_field_string(), "pub r#type: vk::DescriptorBufferInfo,\n");
+    let b = binding("self", 3, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER, 2);
+    assert_eq!(b.to_template_field_string(), "pub self_: [vk::DescriptorBufferInfo; 2],\n");
+  }
+
+  #[test]
+  fn template_entries() {
+    let b = binding("textures", 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 4);
+    assert_eq!(
+      b.to_template_entry_string(),
+      format!(
+        "vk::DescriptorUpdateTemplateEntry {{ dstBinding: 1, dstArrayElement: 0, descriptorCount: 4, descriptorType: {}, offset: std::mem::offset_of!(Template, textures), stride: std::mem::size_of::<vk::DescriptorImageInfo>() }},\n",
+        vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER
+      )
+    );
+
+    let b = binding("type", 3, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER, 1);
+    assert!(b
+      .to_template_entry_string()
+      .contains("offset: std::mem::offset_of!(Template, r#type), stride: std::mem::size_of::<vk::DescriptorBufferInfo>()"));
+  }
+
+  #[test]
+  fn keyword_setters() {
+    let b = binding("type", 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER, 1);
+    assert!(b.to_dset_write_string("Write").contains("pub fn r#type(mut self, info: vk::DescriptorBufferInfo) -> Write"));
+
+    let b = binding("ref", 1, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER, 4);
+    let s = b.to_dset_write_string("Write");
+    assert!(s.contains("pub fn ref_elem(mut self, array_elem: u32, info: vk::DescriptorBufferInfo) -> Write"));
+    assert!(s.contains("pub fn r#ref(mut self, infos: &[vk::DescriptorBufferInfo]) -> Write"));
+
+    let mut b = binding("in", 2, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 1);
+    b.immutable_sampler = true;
+    assert!(b.to_dset_write_string("Write").contains("pub fn r#in(mut self, layout: vk::ImageLayout, view: vk::ImageView) -> Write"));
+
+    let mut b = binding("self", 3, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 2);
+    b.immutable_sampler = true;
+    let s = b.to_dset_write_string("Write");
+    assert!(s.contains("pub fn self_elem(mut self, array_elem: u32, layout: vk::ImageLayout, view: vk::ImageView) -> Write"));
+    assert!(s.contains("pub fn self_(mut self, layout: vk::ImageLayout, views: &[vk::ImageView]) -> Write"));
+  }
+
+  #[test]
+  fn in_template() {
+    assert!(binding("ub", 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER, 1).in_template());
+
+    let mut runtime_array = binding("textures", 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 4096);
+    runtime_array.flags = RUNTIME_ARRAY_FLAGS;
+    assert!(!runtime_array.in_template());
+
+    let mut sampler = binding("smp", 2, vk::DESCRIPTOR_TYPE_SAMPLER, 1);
+    assert!(sampler.in_template());
+    sampler.immutable_sampler = true;
+    assert!(!sampler.in_template());
+
+    // combined image samplers with immutable samplers still need the image
+    let mut combined = binding("tex", 3, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 1);
+    combined.immutable_sampler = true;
+    assert!(combined.in_template());
+  }
+
+  #[test]
+  fn immutable_sampler() {
+    let mut bindings = vec![
+      binding("ub", 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER, 1),
+      binding("tex", 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 1),
+      binding("smp", 2, vk::DESCRIPTOR_TYPE_SAMPLER, 1),
+    ];
+    Binding::set_immutable_sampler(&mut bindings, "tex").unwrap();
+    Binding::set_immutable_sampler(&mut bindings, "smp").unwrap();
+    assert_eq!(bindings.iter().filter(|b| b.immutable_sampler).count(), 2);
+
+    assert!(Binding::set_immutable_sampler(&mut bindings, "ub").is_err());
+    assert!(Binding::set_immutable_sampler(&mut bindings, "missing").is_err());
+
+    // a name in two descriptor sets is ambiguous and does not silently pick the first binding
+    let mut other = binding("tex", 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER, 1);
+    other.descset = 1;
+    bindings.push(other);
+    bindings.iter_mut().for_each(|b| b.immutable_sampler = false);
+    let err = Binding::set_immutable_sampler(&mut bindings, "tex").unwrap_err();
+    assert!(err.contains("descriptor set 0 and 1"));
+    assert!(bindings.iter().all(|b| !b.immutable_sampler));
+  }
 }
@@ -0,0 +1,341 @@
+use spec_constants::snake_case;
+
+const KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum", "extern", "false",
+  "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+  "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can not be used as raw identifiers
+const PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Makes an identifier from a glsl name
+///
+/// Rust keywords are written as raw identifiers, `crate`, `self`, `Self` and `super` can not be raw identifiers and get a trailing underscore.
+pub fn ident(name: &str) -> String {
+  match (KEYWORDS.contains(&name), PATH_KEYWORDS.contains(&name)) {
+    (true, _) => format!("r#{}", name),
+    (_, true) => format!("{}_", name),
+    _ => name.to_string(),
+  }
+}
+
+/// Member of a struct with offset and size in bytes
+#[derive(Debug, PartialEq, Clone)]
+pub struct Member {
+  pub name: String,
+  pub offset: u32,
+  pub size: u32,
+  pub align: u32,
+  pub ty: String,
+}
+
+/// Runtime array at the end of a storage block
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeArray {
+  pub name: String,
+  pub offset: u32,
+  pub stride: u32,
+  pub ty: String,
+}
+
+/// Writes the fields of a `#[repr(C)]` struct
+///
+/// Gaps between members and the gap between the last member and `end` are filled with hidden padding fields.
+/// Offsets of the members are relative to `base`.
+pub fn write_fields(members: &[Member], base: u32, end: u32) -> String {
+  let mut pos = base;
+  let mut fields = String::new();
+  for (i, m) in members.iter().enumerate() {
+    if m.offset > pos {
+      fields = format!("{}#[doc(hidden)]\npub _pad{}: [u8; {}],\n", fields, i, m.offset - pos);
+    }
+    fields = format!("{}pub {}: {},\n", fields, ident(&m.name), m.ty);
+    pos = m.offset + m.size;
+  }
+  if end > pos {
+    fields = format!("{}#[doc(hidden)]\npub _pad{}: [u8; {}],\n", fields, members.len(), end - pos);
+  }
+  fields
+}
+
+/// Writes compile time assertions for the size and the member offsets of a struct
+pub fn write_asserts(name: &str, members: &[Member], base: u32, size: u32) -> String {
+  members.iter().fold(
+    format!("const _: () = assert!(std::mem::size_of::<{}>() == {});\n", name, size),
+    |acc, m| {
+      format!(
+        "{}const _: () = assert!(std::mem::offset_of!({}, {}) == {});\n",
+        acc,
+        name,
+        ident(&m.name),
+        m.offset - base
+      )
+    },
+  )
+}
+
+/// Hashes the layout of a struct with FNV-1a
+///
+/// The hash only depends on the names, types and offsets of the members and the size of the struct,
+/// so that it is the same for every shader stage and compilation that uses the same layout.
+pub fn layout_hash(members: &[Member], runtime_array: &Option<RuntimeArray>, size: u32) -> u32 {
+  let mut layout = members
+    .iter()
+    .fold(String::new(), |acc, m| format!("{}{}:{}@{};", acc, m.name, m.ty, m.offset));
+  if let Some(a) = runtime_array {
+    layout = format!("{}{}:{}@{}+{};", layout, a.name, a.ty, a.offset, a.stride);
+  }
+  format!("{}{}", layout, size)
+    .bytes()
+    .fold(0x811c_9dc5, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Rounds `size` up to a multiple of `align`
+pub fn align_up(size: u32, align: u32) -> u32 {
+  (size + align - 1) / align * align
+}
+
+/// Layout of a struct in a uniform, storage or push constant block
+#[derive(Debug, PartialEq, Clone)]
+pub struct Struct {
+  pub name: String,
+  pub members: Vec<Member>,
+  pub runtime_array: Option<RuntimeArray>,
+  pub size: u32,
+  pub align: u32,
+}
+
+impl Struct {
+  /// Writes a `#[repr(C)]` struct with the layout of the shader
+  ///
+  /// A runtime array is not part of the struct, its offset and stride are written as associated constants named in upper snake case.
+  pub fn to_struct_string(&self) -> String {
+    let runtime_array = match self.runtime_array {
+      Some(ref a) => format!(
+        "
+        impl {name} {{
+          /// Offset of the runtime array `{array}` of `{ty}` in bytes
+          pub const {upper}_OFFSET: usize = {offset};
+          /// Stride of the runtime array `{array}` of `{ty}` in bytes
+          pub const {upper}_STRIDE: usize = {stride};
+        }}
+        ",
+        name = self.name,
+        array = a.name,
+        upper = snake_case(&a.name).to_uppercase(),
+        ty = a.ty,
+        offset = a.offset,
+        stride = a.stride,
+      ),
+      None => String::new(),
+    };
+
+    format!(
+      "
+      #[repr(C)]
+      #[derive(Debug, Clone, Copy)]
+      pub struct {name} {{
+        {fields}
+      }}
+
+      impl Default for {name} {{
+        fn default() -> Self {{
+          unsafe {{ std::mem::zeroed() }}
+        }}
+      }}
+
+      {runtime_array}
+
+      {asserts}
+      ",
+      name = self.name,
+      fields = write_fields(&self.members, 0, self.size),
+      runtime_array = runtime_array,
+      asserts = write_asserts(&self.name, &self.members, 0, self.size),
+    )
+  }
+}
+
+/// Writes the module `blocks` with the structs of all uniform, storage and push constant blocks
+pub fn write_blocks(structs: &[Struct]) -> String {
+  if structs.is_empty() {
+    return String::new();
+  }
+
+  format!(
+    "
+    #[allow(non_camel_case_types)]
+    pub mod blocks {{
+      #[allow(unused_imports)]
+      use super::blocks;
+
+      {}
+    }}
+    ",
+    structs
+      .iter()
+      .fold(String::new(), |acc, s| format!("{}{}", acc, s.to_struct_string()))
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use spirv::Spirv;
+
+  fn member(name: &str, offset: u32, size: u32, ty: &str) -> Member {
+    Member {
+      name: name.to_owned(),
+      offset,
+      size,
+      align: 4,
+      ty: ty.to_owned(),
+    }
+  }
+
+  fn structs() -> Vec<Struct> {
+    let bytes = include_bytes!("../../../nobs-spirv/fixtures/layout.spv");
+    let words = bytes
+      .chunks(4)
+      .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+      .collect::<Vec<_>>();
+    Spirv::from_binary(&words).unwrap().get_structs().unwrap()
+  }
+
+  #[test]
+  fn idents() {
+    assert_eq!(ident("color"), "color");
+    assert_eq!(ident("type"), "r#type");
+    assert_eq!(ident("async"), "r#async");
+    assert_eq!(ident("yield"), "r#yield");
+    assert_eq!(ident("crate"), "crate_");
+    assert_eq!(ident("self"), "self_");
+    assert_eq!(ident("Self"), "Self_");
+    assert_eq!(ident("super"), "super_");
+  }
+
+  #[test]
+  fn padding() {
+    let members = vec![member("a", 0, 12, "[f32; 3]"), member("b", 16, 4, "f32")];
+    assert_eq!(
+      write_fields(&members, 0, 32),
+      "pub a: [f32; 3],\n#[doc(hidden)]\npub _pad1: [u8; 4],\npub b: f32,\n#[doc(hidden)]\npub _pad2: [u8; 12],\n"
+    );
+    assert_eq!(write_fields(&[member("type", 8, 4, "u32")], 8, 12), "pub r#type: u32,\n");
+    assert_eq!(
+      write_asserts("S", &[member("type", 8, 4, "u32")], 8, 4),
+      "const _: () = assert!(std::mem::size_of::<S>() == 4);\nconst _: () = assert!(std::mem::offset_of!(S, r#type) == 0);\n"
+    );
+    assert_eq!(align_up(13, 4), 16);
+    assert_eq!(align_up(16, 4), 16);
+  }
+
+  #[test]
+  fn std140_std430() {
+    let structs = structs();
+    assert_eq!(structs.len(), 4);
+
+    let light140 = &structs[0];
+    let light430 = &structs[2];
+    assert!(light140.name.starts_with("Light_") && light430.name.starts_with("Light_"));
+    assert_ne!(light140.name, light430.name);
+    assert_eq!(
+      light140.members,
+      vec![
+        Member {
+          name: "pos".to_owned(),
+          offset: 0,
+          size: 12,
+          align: 4,
+          ty: "[f32; 3]".to_owned()
+        },
+        Member {
+          name: "w".to_owned(),
+          offset: 16,
+          size: 32,
+          align: 4,
+          ty: "[[f32; 4]; 2]".to_owned()
+        },
+      ]
+    );
+    assert_eq!(light140.size, 48);
+    assert_eq!(
+      light430.members,
+      vec![
+        Member {
+          name: "pos".to_owned(),
+          offset: 0,
+          size: 12,
+          align: 4,
+          ty: "[f32; 3]".to_owned()
+        },
+        Member {
+          name: "w".to_owned(),
+          offset: 12,
+          size: 8,
+          align: 4,
+          ty: "[f32; 2]".to_owned()
+        },
+      ]
+    );
+    assert_eq!(light430.size, 20);
+
+    let ubo = &structs[1];
+    assert_eq!(ubo.name, "Ubo");
+    assert_eq!(ubo.members[0].ty, format!("blocks::{}", light140.name));
+    assert_eq!(ubo.members.iter().map(|m| m.offset).collect::<Vec<_>>(), vec![0, 48, 56]);
+    assert_eq!(ubo.size, 60);
+
+    let ssbo = &structs[3];
+    assert_eq!(ssbo.name, "Ssbo");
+    assert_eq!(ssbo.members[0].ty, format!("blocks::{}", light430.name));
+    assert_eq!(ssbo.members.iter().map(|m| m.offset).collect::<Vec<_>>(), vec![0, 32, 40]);
+    assert_eq!(ssbo.size, 44);
+
+    let s = ssbo.to_struct_string();
+    assert!(s.contains("pub light: blocks::Light_"));
+    assert!(s.contains("#[doc(hidden)]\npub _pad1: [u8; 12],\npub uv: [f32; 2],\npub r#type: u32,\n"));
+    assert!(s.contains("const _: () = assert!(std::mem::size_of::<Ssbo>() == 44);"));
+    assert!(s.contains("const _: () = assert!(std::mem::offset_of!(Ssbo, uv) == 32);"));
+    assert!(s.contains("const _: () = assert!(std::mem::offset_of!(Ssbo, r#type) == 40);"));
+  }
+
+  #[test]
+  fn stable_names() {
+    let structs = structs();
+    let light = &structs[2];
+    assert_eq!(
+      light.name,
+      format!("Light_{:08x}", layout_hash(&light.members, &light.runtime_array, light.size))
+    );
+
+    // the hash does not depend on the ids, only on the layout
+    let mut moved = light.members.clone();
+    assert_eq!(
+      layout_hash(&moved, &None, 20),
+      layout_hash(&light.members, &light.runtime_array, light.size)
+    );
+    moved[1].offset = 16;
+    assert_ne!(layout_hash(&moved, &None, 20), layout_hash(&light.members, &None, 20));
+  }
+
+  #[test]
+  fn runtime_array_consts() {
+    let s = Struct {
+      name: "Lights".to_owned(),
+      members: vec![member("count", 0, 4, "u32")],
+      runtime_array: Some(RuntimeArray {
+        name: "lightData".to_owned(),
+        offset: 16,
+        stride: 32,
+        ty: "blocks::Light".to_owned(),
+      }),
+      size: 16,
+      align: 4,
+    }
+    .to_struct_string();
+    assert!(s.contains("pub const LIGHT_DATA_OFFSET: usize = 16;"));
+    assert!(s.contains("pub const LIGHT_DATA_STRIDE: usize = 32;"));
+  }
+}
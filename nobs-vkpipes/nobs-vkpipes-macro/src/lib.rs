@@ -48,26 +48,27 @@
 //!     }
 //!   }
 //!
-//!   // The code generation will not create types for e.g. the uniform buffer
-//!   // If we want this, we need to do it our selves
-//!   pub struct ub {
-//!     pub num_elems: u32,
-//!     pub i_first: u32,
-//!     pub i_step: u32,
-//!   }
+//!   // The code generation creates #[repr(C)] structs for the uniform and storage blocks in the module blocks,
+//!   // e.g. blocks::ub with the members num_elems, i_first and i_step
 //! }
 //! ```
 
+extern crate nobs_spirv;
 extern crate nobs_vk as vk;
+extern crate nobs_vkpipes_include as include;
 extern crate proc_macro;
 
 mod binding;
-mod enums;
+mod block;
 mod parse;
 mod pipeline;
+mod push_constants;
+mod sampler;
 mod shader;
+mod spec_constants;
 mod spirv;
 mod usings;
+mod vertex_input;
 
 use proc_macro::TokenStream;
 
@@ -92,12 +93,23 @@ macro_rules! consume_err {
 /// | --------- | ---- | --------- | ----------- | ------- |
 /// | `ty` | `str` | yes | Type of the shader, must be one of ["comp", "vert", "tesc", "tese", "geom", "frag"]. The pipeline type (compute or graphics) is inferred from the specified stages. | `ty = "comp"`|
 /// | `glsl` | `str` | yes* | Specifies either a shader file, or actual glsl shader code. If glsl is specified spv must not be specified. | `glsl = "src/shader.comp"`|
+/// | `hlsl` | `str` | yes* | Specifies either a shader file, or actual hlsl shader code. Use `entry` to set the name of the entry point. If hlsl is specified neither glsl nor spv must be specified. | `hlsl = "src/shader.hlsl"`|
 /// | `spv` | `str` | yes* | Specifies a compiled shader file. If spv is specified glsl must not be specified. | `glsl = "src/shader.spv"`|
 /// | `include` | `[str]` | no | List of strings specifying include directories if the shader is specified as glsl file. | `include = ["src/global", "src/util"]`|
+/// | `define` | `[str]` | no | List of preprocessor macros, either as name or as name and value separated by '='. | `define = ["USE_SHADOWS", "NUM_LIGHTS=4"]`|
+/// | `optimize` | `str` | no | Optimization level, must be one of ["zero", "size", "performance"]. Set to "zero" by default. | `optimize = "performance"`|
+/// | `debug` | `bool` | no | Generates debug information in the SPIR-V. Set to false by default. | `debug = true`|
+/// | `target_env` | `str` | no | Vulkan version the SPIR-V is compiled for, must be one of ["vulkan1.0", "vulkan1.1"]. The SPIR-V version follows from the Vulkan version (1.0 and 1.3 respectively). Set to "vulkan1.0" by default. | `target_env = "vulkan1.1"`|
+/// | `runtime_array_len` | `u32` | no | Number of descriptors of runtime sized descriptor arrays. Set to 4096 by default. | `runtime_array_len = 1024`|
 /// | `vk_alias` | `str` | no | Alias of the import of the nobs-vk crate. Set to "vk" by default. | `vk_alias = "othervk"`|
 /// | `vkpipes_alias` | `str` | no | Alias of the import of the nobs-vkpipes crate. Set to "vk::pipes" by default. | `vkpipes_alias = "otherpipes"`|
 /// | `dump` | `str` | no | Filename to which the output of the code generation will be written. | `dump = "dump/my_pipeline.rs"`|
 ///
+/// The options `define`, `optimize`, `debug` and `target_env` are forwarded to shaderc and only apply, if the shader is compiled from glsl or hlsl.
+/// Optimization may strip the names of variables and block members from the SPIR-V, in which case the generated bindings and
+/// struct members are enumerated. Specify `debug = true` to keep the names.
+/// Names in SPIR-V generated from hlsl, e.g. `type.StructuredBuffer.Light` or `$Globals`, are converted into valid rust identifiers (`StructuredBuffer_Light`, `Globals`).
+///
 /// See [here] for whole pipeline configuration.
 #[proc_macro]
 pub fn shader(input: TokenStream) -> TokenStream {
@@ -121,6 +133,10 @@ pub fn shader(input: TokenStream) -> TokenStream {
 /// | `stage` | `{...}` | yes | Specifies a shader stage with parameters. Can be used multiple times to specify multiple stages. | see [shader](macro.shader.html) |
 /// | `include` | `[str]` | no | List of strings specifying include directories for the compilation of glsl shader files. Include directories defined in the pipeline are used for all stages. | `include = ["src/global", "src/util"]`|
 /// | `dset_name[i32]` | `[str]` | no | Rename descriptor set with index 0. Since we can not specify descriptor set names in glsl, they are enumerated with Dset0, Dset1, Dset2.. if no name is specified for a descriptor set index. | `dset_name[0] = "per_frame"`|
+/// | `vertex_binding[u32]` | `[u32]` | no | Maps the vertex shader inputs with the listed locations to the vertex binding with index 0. The inputs are interleaved in the binding in the listed order. If specified, every input needs to be mapped to exactly one binding. By default all inputs are interleaved in binding 0. | `vertex_binding[0] = [0, 1]`|
+/// | `vertex_rate[u32]` | `str` | no | Input rate of the vertex binding with index 0, must be one of ["vertex", "instance"]. Set to "vertex" by default. | `vertex_rate[1] = "instance"`|
+/// | `runtime_array_len` | `u32` | no | Number of descriptors of runtime sized descriptor arrays in all stages. A value specified in a stage takes precedence, stages sharing a runtime array must agree on its length. Set to 4096 by default. | `runtime_array_len = 1024`|
+/// | `sampler[str]` | `{...}` | no | Immutable sampler for the sampler or combined image sampler binding with the specified name. See below for the sampler parameters. | `sampler["tex"] = { mag_filter = "linear", address_mode = "repeat" }`|
 /// | `vk_alias` | `str` | no | Alias of the import of the nobs-vk crate. Set to "vk" by default. | `vk_alias = "othervk"`|
 /// | `vkpipes_alias` | `str` | no | Alias of the import of the nobs-vkpipes crate. Set to "vk::pipes" by default. | `vkpipes_alias = "otherpipes"`|
 /// | `dump` | `str` | no | Filename to which the output of the code generation will be written. | `dump = "dump/my_pipeline.rs"`|
@@ -129,6 +145,64 @@ pub fn shader(input: TokenStream) -> TokenStream {
 /// and if the nobs-vkpipes crate was imported with an alias e.g.: 'extern crate nobs-vkpipes as pipes;'.
 /// In these cases the code generation can not resolve the dependencies for the generated module code.
 ///
+/// If any stage declares a push constant block, the blocks of all stages are merged into a single push constant range `PUSH_CONSTANTS`,
+/// that is added to the pipeline layout. The stage flags of the range contain every stage, that declares the block.
+/// Members at the same offset in different stages need to have the same name and type.
+/// For the merged block a `#[repr(C)]` struct `PushConstants` is generated, with padding fields so that the member offsets match the shader.
+/// Its `push(layout)` method creates the command, that records `vkCmdPushConstants` with the range's offset and stage flags.
+///
+/// For every uniform and storage block a `#[repr(C)]` struct with the layout of the block is generated in the module `blocks`,
+/// together with the structs nested in the blocks. Gaps between members are filled with hidden padding fields and
+/// compile time assertions check the size and the member offsets of the structs.
+/// Matrices are arrays of columns, arrays of scalars and vectors with larger array stride (e.g. `float[]` in std140) are arrays of padded arrays.
+/// Runtime arrays are not part of the structs, their offset and stride are associated constants.
+/// Structs that have the same name but different layouts (e.g. the same glsl struct in a std140 and a std430 block) are suffixed
+/// with a hash of their layout, e.g. `Light_1f2e3d4c`. Members named like rust keywords are raw identifiers, `self`, `Self`, `super`
+/// and `crate` get a trailing underscore.
+///
+/// For the inputs of the vertex shader a `#[repr(C)]` vertex struct is generated for every vertex binding in the module `vertices`.
+/// The struct is named `Vertex` if there is only one binding, otherwise they are enumerated with `Vertex0`, `Vertex1`...
+/// Matrices are arrays of columns, inputs that share a location with the `component` qualifier are not supported.
+/// The vertex input state with all bindings and attributes is created with `vertices::vertex_input()`
+/// and is preset in the builder of the pipeline.
+///
+/// Runtime sized descriptor arrays, e.g. `uniform sampler2D textures[]`, are reflected with `runtime_array_len` array elements and
+/// the binding flags `DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT_EXT` and `DESCRIPTOR_BINDING_PARTIALLY_BOUND_BIT_EXT`.
+/// If the array is the binding with the highest index in its descriptor set, it also gets `DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT`.
+///
+/// Every descriptor set module contains a `#[repr(C)]` struct `Template` with a field for the descriptor infos of every binding,
+/// together with the entries of a descriptor update template, that reference the fields. `create_template(device, layout)` creates
+/// a [DescriptorTemplate](../nobs_vkpipes/struct.DescriptorTemplate.html), that writes all descriptors of a set with a single call.
+/// Runtime arrays are not part of the template, their elements are written with the `Write` builder of the set.
+///
+/// Immutable samplers are specified with `sampler["name"] = {...}` for a binding and baked into the descriptor set layout.
+/// The parameters are `mag_filter`, `min_filter` and `mipmap_mode` (one of ["nearest", "linear"]), `address_mode` or `address_mode_u/v/w`
+/// (one of ["repeat", "mirrored_repeat", "clamp_to_edge", "clamp_to_border"]), `border_color` (e.g. "float_opaque_black"),
+/// `compare_op` (e.g. "less_or_equal"), the booleans `anisotropy`, `compare` and `unnormalized_coordinates`
+/// and the floats `max_anisotropy`, `mip_lodbias`, `min_lod` and `max_lod`. Parameters that are not specified are taken from `nobs_vkpipes::builder::DEFAULT_SAMPLER`.
+/// Samplers are looked up by binding name, so the name must not be used for bindings in more than one descriptor set.
+/// For every sampler a constant `SAMPLER_<NAME>` with the `vk::SamplerCreateInfo` is generated and set in the pipeline builder, `<NAME>` is the name in upper snake case.
+/// The setters of the `Write` builder for combined image samplers with immutable sampler only take the image layout and view,
+/// samplers with immutable sampler have no setter at all.
+/// ```rust,ignore
+/// vk::pipes::pipeline! {
+///   sampler["tex"] = { mag_filter = "linear", min_filter = "linear", address_mode = "repeat" }
+///   stage = { ty = "frag", glsl = "src/textured.frag" }
+/// }
+/// ```
+///
+/// For every specialization constant of the stages a constant `SPEC_<NAME>` with its id and default value is generated, `<NAME>` is the name in upper snake case.
+/// Specialization constants with the same id in different stages need to have the same name and type.
+/// The trait `Specialize` adds a typed setter `spec_<name>(value)` for every specialization constant to the pipeline builders,
+/// so that one shader can be compiled into several variants. Names are converted to snake case, e.g. `groupSize` has the setter `spec_group_size`.
+/// Unnamed constants of the workgroup size (`local_size_x_id`...) are named `local_size_x`, `local_size_y` and `local_size_z`.
+/// ```rust,ignore
+/// // layout(local_size_x_id = 0) in;
+/// // layout(constant_id = 1) const uint groupCount = 64;
+/// use my_pipeline::Specialize;
+/// let p = my_pipeline::new(device).spec_local_size_x(64).spec_group_count(256).create().unwrap();
+/// ```
+///
 /// See the [module level documentation](index.html) for a complete example
 #[proc_macro]
 pub fn pipeline(input: TokenStream) -> TokenStream {
@@ -49,6 +49,27 @@ pub fn parse_string_vec(tokens: &mut Tokens) -> Result<Vec<String>, String> {
   v
 }
 
+pub fn parse_u32_vec(tokens: &mut Tokens) -> Result<Vec<u32>, String> {
+  let v = parse_group(tokens, |g| {
+    g.into_iter()
+      .filter(|tok| match tok {
+        TokenTree::Literal(_) => true,
+        _ => false,
+      }).map(|tok| tok.to_string().parse::<u32>().map_err(|e| format!("{}", e)))
+      .collect()
+  });
+  tokens.next();
+  v
+}
+
+pub fn parse_u32(tokens: &mut Tokens) -> Result<u32, String> {
+  parse(tokens).parse::<u32>().map_err(|e| format!("{}", e))
+}
+
+pub fn parse_bool(tokens: &mut Tokens) -> Result<bool, String> {
+  parse(tokens).parse::<bool>().map_err(|e| format!("{}", e))
+}
+
 pub fn parse_array_index(tokens: &mut Tokens) -> Result<u32, String> {
   parse_group(tokens, |g| match g.into_iter().next() {
     Some(tok) => match tok {
@@ -58,3 +79,13 @@ pub fn parse_array_index(tokens: &mut Tokens) -> Result<u32, String> {
     None => Err("Expected TokenTree:Literal with u32")?,
   })
 }
+
+pub fn parse_array_name(tokens: &mut Tokens) -> Result<String, String> {
+  parse_group(tokens, |g| match g.into_iter().next() {
+    Some(tok) => match tok {
+      TokenTree::Literal(_) => Ok(trim_quote(&tok.to_string())),
+      _ => Err("Expected TokenTree:Literal with str")?,
+    },
+    None => Err("Expected TokenTree:Literal with str")?,
+  })
+}
@@ -5,22 +5,46 @@ use std::io::prelude::*;
 use std::process::Command;
 
 use binding::Binding;
+use block;
+use block::Struct;
 use parse;
+use push_constants::PushConstants;
+use sampler;
+use sampler::Sampler;
 use shader::stage_from_stirng;
+use shader::write_push_constants;
+use shader::write_spec_constants;
 use shader::Builder as ShaderBuilder;
 use shader::Shader;
+use spec_constants::SpecConstant;
 use std::collections::HashMap;
 use usings::Usings;
+use vertex_input;
+use vertex_input::VertexBinding;
+use vk;
 
 #[derive(Default, Debug)]
 pub struct Builder {
   pub usings: Usings,
   pub stages: Vec<ShaderBuilder>,
   pub dset_names: HashMap<u32, String>,
+  pub vertex_bindings: HashMap<u32, Vec<u32>>,
+  pub vertex_rates: HashMap<u32, vk::VertexInputRate>,
+  pub runtime_array_len: Option<u32>,
+  pub samplers: HashMap<String, Sampler>,
   pub dump: String,
 }
 
-const ARG_TYPES: &[&str] = &["dset_name[N]", "dump", "inlude", "stage"];
+const ARG_TYPES: &[&str] = &[
+  "dset_name[N]",
+  "vertex_binding[N]",
+  "vertex_rate[N]",
+  "runtime_array_len",
+  "sampler[str]",
+  "dump",
+  "inlude",
+  "stage",
+];
 
 impl Builder {
   pub fn from_tokens(input: TokenStream) -> Result<Builder, String> {
@@ -33,10 +57,17 @@ impl Builder {
           let s = i.to_string();
 
           let idx = match s.as_ref() {
-            "dset_name" => parse::parse_array_index(&mut tokens).map_err(|e| format!("after argument dset_name: {}", e))?,
+            "dset_name" | "vertex_binding" | "vertex_rate" => {
+              parse::parse_array_index(&mut tokens).map_err(|e| format!("after argument {}: {}", s, e))?
+            }
             _ => 0,
           };
 
+          let name = match s.as_ref() {
+            "sampler" => parse::parse_array_name(&mut tokens).map_err(|e| format!("after argument {}: {}", s, e))?,
+            _ => String::new(),
+          };
+
           tokens.next();
           match s.as_ref() {
             "nobs_vkpipes_alias" => b.usings.pipes = Some(parse::parse_string(&mut tokens)),
@@ -46,6 +77,30 @@ impl Builder {
             "dset_name" => {
               b.dset_names.insert(idx, parse::parse_string(&mut tokens));
             }
+            "vertex_binding" => {
+              let locations = parse::parse_u32_vec(&mut tokens).map_err(|e| format!("after argument vertex_binding: {}", e))?;
+              b.vertex_bindings.insert(idx, locations);
+            }
+            "vertex_rate" => {
+              let rate = match parse::parse_string(&mut tokens).as_ref() {
+                "vertex" => vk::VERTEX_INPUT_RATE_VERTEX,
+                "instance" => vk::VERTEX_INPUT_RATE_INSTANCE,
+                r => Err(format!(
+                  "vertex_rate \"{}\" not recognized, use one of [\"vertex\", \"instance\"]",
+                  r
+                ))?,
+              };
+              b.vertex_rates.insert(idx, rate);
+            }
+            "runtime_array_len" => {
+              let len = parse::parse_u32(&mut tokens).map_err(|e| format!("after argument runtime_array_len: {}", e))?;
+              b.runtime_array_len = Some(len);
+            }
+            "sampler" => {
+              let sampler = parse::parse_group(&mut tokens, Sampler::from_tokens)
+                .map_err(|e| format!("after argument sampler[\"{}\"]: {}", name, e))?;
+              b.samplers.insert(name, sampler);
+            }
             "dump" => b.dump = parse::parse_string(&mut tokens),
             _ => Err(format!("expected one of {:?}, found {}", ARG_TYPES, &s))?,
           }
@@ -72,8 +127,24 @@ impl Builder {
       Err("compute pipelines must only contain a single compute stage and can not be mixed with shaders of different stage types")?;
     }
 
+    if (!b.vertex_bindings.is_empty() || !b.vertex_rates.is_empty()) && !b.stages.iter().any(|s| s.stage == "vert") {
+      Err("vertex bindings can only be specified for pipelines with a vertex shader stage")?;
+    }
+
+    let rate_unmapped = |k: &&u32| match b.vertex_bindings.is_empty() {
+      true => **k != 0,
+      false => !b.vertex_bindings.contains_key(k),
+    };
+    if let Some(binding) = b.vertex_rates.keys().find(rate_unmapped) {
+      Err(format!(
+        "vertex_rate specified for binding {}, that is not listed in vertex_binding",
+        binding
+      ))?;
+    }
+
     for s in b.stages.iter_mut() {
       s.usings = b.usings.clone();
+      s.runtime_array_len = s.runtime_array_len.or(b.runtime_array_len);
     }
 
     Ok(b)
@@ -89,23 +160,61 @@ impl Builder {
     for s in stages.iter() {
       let stage_bit = stage_from_stirng(&s.stage).unwrap();
       for b in s.bindings.iter() {
-        // if the binding id is different we also require a different name
-        if bindings.iter().any(|bind| bind.binding == b.binding && bind.name != b.name)
-          || bindings.iter().any(|bind| bind.binding != b.binding && bind.name == b.name)
-        {
-          Err(format!("binding name collision for binding {:?}", b))?;
-        }
+        Binding::merge(&mut bindings, b, stage_bit)?;
+      }
+    }
 
-        // if the binding already exists add the shader stage to binding
-        // else we create a new binding for the pipeline
-        if let Some(p) = bindings.iter().position(|bind| Binding::same_stage(bind, b)) {
-          bindings[p].stageflags |= stage_bit;
-        } else {
-          bindings.push(b.clone());
-        }
+    // bindings of other stages may follow a runtime array in the same set
+    Binding::set_variable_count(&mut bindings);
+
+    for name in self.samplers.keys() {
+      Binding::set_immutable_sampler(&mut bindings, name)?;
+    }
+
+    let samplers = bindings
+      .iter()
+      .filter(|b| b.immutable_sampler)
+      .map(|b| (b.descset, b.binding, b.name.clone(), self.samplers[&b.name].clone()))
+      .collect();
+
+    let mut push_constants: Option<PushConstants> = None;
+    for pc in stages.iter().filter_map(|s| s.push_constants.as_ref()) {
+      match push_constants {
+        Some(ref mut merged) => merged.merge(pc)?,
+        None => push_constants = Some(pc.clone()),
+      }
+    }
+
+    let mut spec_constants: Vec<SpecConstant> = Vec::new();
+    for c in stages.iter().flat_map(|s| s.spec_constants.iter()) {
+      // the same id requires the same name and type, different ids must have different setters
+      if spec_constants.iter().any(|sc| {
+        (sc.id == c.id) != (sc.name == c.name)
+          || (sc.id == c.id && !SpecConstant::same_constant(sc, c))
+          || (sc.id != c.id && sc.ident() == c.ident())
+      }) {
+        Err(format!("specialization constant collision for {:?}", c))?;
+      }
+
+      if !spec_constants.iter().any(|sc| sc.id == c.id) {
+        spec_constants.push(c.clone());
       }
     }
 
+    let mut structs: Vec<Struct> = Vec::new();
+    for st in stages.iter().flat_map(|s| s.structs.iter()) {
+      match structs.iter().find(|s| s.name == st.name) {
+        Some(s) if s != st => Err(format!("struct {} has a different layout in different stages", st.name))?,
+        Some(_) => (),
+        None => structs.push(st.clone()),
+      }
+    }
+
+    let vertex_bindings = match stages.iter().find(|s| s.stage == "vert") {
+      Some(s) => VertexBinding::from_inputs(&s.inputs, &self.vertex_bindings, &self.vertex_rates)?,
+      None => Vec::new(),
+    };
+
     let mut dset_names = bindings
       .iter()
       .fold(std::collections::HashSet::new(), |mut acc, b| {
@@ -130,6 +239,11 @@ impl Builder {
       stages,
       dset_names,
       bindings,
+      push_constants,
+      spec_constants,
+      structs,
+      vertex_bindings,
+      samplers,
     })
   }
 }
@@ -139,6 +253,11 @@ pub struct Pipeline {
   pub stages: Vec<Shader>,
   pub dset_names: HashMap<u32, String>,
   pub bindings: Vec<Binding>,
+  pub push_constants: Option<PushConstants>,
+  pub spec_constants: Vec<SpecConstant>,
+  pub structs: Vec<Struct>,
+  pub vertex_bindings: Vec<VertexBinding>,
+  pub samplers: Vec<(u32, u32, String, Sampler)>,
 }
 
 impl Pipeline {
@@ -156,6 +275,44 @@ impl Pipeline {
     )
   }
 
+  fn write_template(&self, bindings: &[Binding]) -> String {
+    let bindings = bindings.iter().filter(|b| b.in_template()).collect::<Vec<_>>();
+    if bindings.is_empty() {
+      return String::new();
+    }
+
+    format!(
+      "
+      /// Payload for the descriptor update template of the descriptor set
+      #[repr(C)]
+      #[derive(Clone, Copy)]
+      pub struct Template {{
+        {fields}
+      }}
+
+      pub const TEMPLATE_ENTRIES: [vk::DescriptorUpdateTemplateEntry; {len}] = [
+        {entries}
+      ];
+
+      /// Creates the descriptor update template for descriptor sets with `layout`
+      pub fn create_template(
+        device: vk::Device,
+        layout: vk::DescriptorSetLayout,
+      ) -> Result<{vkpipes_alias}::DescriptorTemplate<Template>, {vkpipes_alias}::Error> {{
+        {vkpipes_alias}::DescriptorTemplate::new(device, layout, &TEMPLATE_ENTRIES)
+      }}
+      ",
+      vkpipes_alias = self.usings.get_pipes(),
+      fields = bindings
+        .iter()
+        .fold(String::new(), |acc, b| format!("{}{}", acc, b.to_template_field_string())),
+      len = bindings.len(),
+      entries = bindings
+        .iter()
+        .fold(String::new(), |acc, b| format!("{}{}", acc, b.to_template_entry_string())),
+    )
+  }
+
   fn write_descriptors(&self, descriptors: &HashMap<u32, Vec<Binding>>) -> String {
     descriptors.iter().fold(String::new(), |acc, (set, b)| {
       format!(
@@ -173,6 +330,8 @@ impl Pipeline {
 
           {bindings}
 
+          {template}
+
           pub fn write(device: vk::Device, dset: vk::DescriptorSet) -> Write {{
             Write::new(device, dset)
           }}
@@ -200,6 +359,7 @@ impl Pipeline {
         vk_alias = self.usings.get_vk(),
         vkpipes_alias = self.usings.get_pipes(),
         bindings = self.write_bindings(b),
+        template = self.write_template(b),
         name = self.dset_names[set],
         setter = b
           .iter()
@@ -208,13 +368,77 @@ impl Pipeline {
     })
   }
 
+  fn write_specialize(&self) -> String {
+    if self.spec_constants.is_empty() {
+      return String::new();
+    }
+
+    let decls = self
+      .spec_constants
+      .iter()
+      .fold(String::new(), |acc, c| format!("{}{}", acc, c.to_setter_decl_string()));
+    let impls = self
+      .spec_constants
+      .iter()
+      .fold(String::new(), |acc, c| format!("{}{}", acc, c.to_setter_impl_string()));
+
+    format!(
+      "
+      {consts}
+
+      /// Typed setters for the specialization constants of the pipeline
+      pub trait Specialize {{
+        {decls}
+      }}
+
+      impl Specialize for BuilderComp {{
+        {impls}
+      }}
+
+      impl Specialize for BuilderGraphics {{
+        {impls}
+      }}
+      ",
+      consts = write_spec_constants(&self.spec_constants, &self.usings.get_pipes()),
+      decls = decls,
+      impls = impls,
+    )
+  }
+
+  fn write_samplers(&self) -> String {
+    self.samplers.iter().fold(String::new(), |acc, (_, _, name, s)| {
+      format!(
+        "{}{}",
+        acc,
+        s.to_const_string(&sampler::const_name(name), &self.usings.get_pipes())
+      )
+    })
+  }
+
+  fn write_immutable_samplers(&self) -> String {
+    self.samplers.iter().fold(String::new(), |acc, (set, binding, name, _)| {
+      format!(
+        "{}.immutable_sampler({}, {}, &{})\n",
+        acc,
+        set,
+        binding,
+        sampler::const_name(name)
+      )
+    })
+  }
+
   fn write_compute(&self) -> String {
-    "pub fn new(device: vk::Device) -> BuilderComp {{
+    format!(
+      "pub fn new(device: vk::Device) -> BuilderComp {{
         let mut b = BuilderComp::from_device(device);
-        b.bindings(&BINDINGS).comp(&comp::create_module(device));
+        b.bindings(&BINDINGS)
+        .push_constants(&PUSH_CONSTANTS)
+        {samplers}
+        .comp(&comp::create_module(device));
         b
-      }}"
-      .to_string()
+      }}",
+      samplers = self.write_immutable_samplers(),
+    )
   }
 
   fn write_graphics(&self) -> String {
@@ -222,9 +446,17 @@ impl Pipeline {
       "pub fn new(device: vk::Device, pass: vk::RenderPass, subpass: u32) -> BuilderGraphics {{
         let mut b = BuilderGraphics::from_pass(device, pass, subpass);
         b.bindings(&BINDINGS)
+        .push_constants(&PUSH_CONSTANTS)
+        {samplers}
+        {vertex_input}
         {stages};
         b
       }}",
+      samplers = self.write_immutable_samplers(),
+      vertex_input = match self.vertex_bindings.is_empty() {
+        true => "",
+        false => ".vertex_input(vertices::vertex_input())",
+      },
       stages = self.stages.iter().fold(String::new(), |acc, s| format!(
         "{acc}.{name}(&{name}::create_module(device))\n",
         acc = acc,
@@ -273,6 +505,16 @@ impl Pipeline {
 
       pub const NUM_SETS: u32 = {num_sets};
 
+      {push_constants}
+
+      {samplers}
+
+      {specialize}
+
+      {blocks}
+
+      {vertices}
+
       {build}
 
       {descriptors}
@@ -282,6 +524,11 @@ impl Pipeline {
       bindings = self.write_bindings(&self.bindings),
       build = build,
       num_sets = descriptors.len(),
+      push_constants = write_push_constants(&self.push_constants, &self.usings.get_pipes()),
+      samplers = self.write_samplers(),
+      specialize = self.write_specialize(),
+      blocks = block::write_blocks(&self.structs),
+      vertices = vertex_input::write_vertices(&self.vertex_bindings, &self.usings.get_pipes()),
       stages = stages,
       descriptors = self.write_descriptors(&descriptors),
     )
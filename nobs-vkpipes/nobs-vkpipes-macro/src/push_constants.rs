@@ -0,0 +1,197 @@
+use block;
+use block::Member;
+use vk;
+
+/// Push constant block of one or more shader stages
+#[derive(Debug, PartialEq, Clone)]
+pub struct PushConstants {
+  pub name: String,
+  pub members: Vec<Member>,
+  pub stageflags: vk::ShaderStageFlagBits,
+}
+
+impl PushConstants {
+  /// Offset of the first member in the block
+  pub fn offset(&self) -> u32 {
+    self.members.iter().map(|m| m.offset).min().unwrap_or(0)
+  }
+
+  /// Size of the block from the first to the end of the last member, rounded up to a multiple of 4
+  pub fn size(&self) -> u32 {
+    let end = self.members.iter().map(|m| m.offset + m.size).max().unwrap_or(0);
+    (end - self.offset() + 3) & !3
+  }
+
+  /// Merges the members and stage flags of the push constant block of another stage
+  ///
+  /// Members with the same offset need to have the same name and type, members must not overlap otherwise.
+  pub fn merge(&mut self, other: &PushConstants) -> Result<(), String> {
+    for m in other.members.iter() {
+      if self.members.iter().any(|s| s == m) {
+        continue;
+      }
+      if let Some(s) = self
+        .members
+        .iter()
+        .find(|s| s.offset < m.offset + m.size && m.offset < s.offset + s.size)
+      {
+        Err(format!("push constant member collision for {:?} and {:?}", s, m))?;
+      }
+      self.members.push(m.clone());
+    }
+    self.members.sort_by_key(|m| m.offset);
+    self.stageflags |= other.stageflags;
+    Ok(())
+  }
+
+  pub fn to_range_string(&self) -> String {
+    format!(
+      "vk::PushConstantRange {{ stageFlags: {stageflags}, offset: {offset}, size: {size} }},\n",
+      stageflags = self.stageflags,
+      offset = self.offset(),
+      size = self.size(),
+    )
+  }
+
+  /// Writes a `#[repr(C)]` struct with the layout of the push constant block
+  ///
+  /// Gaps between members are filled with hidden padding fields, so that the member offsets match the shader.
+  pub fn to_struct_string(&self, vkpipes_alias: &str) -> String {
+    let align = self.members.iter().map(|m| m.align).max().unwrap_or(4);
+    let size = block::align_up(self.size(), align);
+
+    format!(
+      "
+      /// Push constants of the block `{name}`
+      #[repr(C)]
+      #[derive(Debug, Clone, Copy)]
+      pub struct PushConstants {{
+        {fields}
+      }}
+
+      {asserts}
+
+      // gaps between members are explicit padding fields, the asserts check that the struct has no implicit padding
+      unsafe impl {vkpipes_alias}::pipeline::PushValue for PushConstants {{}}
+
+      impl Default for PushConstants {{
+        fn default() -> Self {{
+          unsafe {{ std::mem::zeroed() }}
+        }}
+      }}
+
+      impl PushConstants {{
+        pub const RANGE: vk::PushConstantRange = {range};
+
+        /// Creates the command to push the constants to a pipeline layout
+        pub fn push(&self, layout: vk::PipelineLayout) -> {vkpipes_alias}::pipeline::PushConstants {{
+          {vkpipes_alias}::pipeline::PushConstants::new(layout, Self::RANGE, self)
+        }}
+      }}
+      ",
+      name = self.name,
+      fields = block::write_fields(&self.members, self.offset(), self.offset() + size),
+      asserts = block::write_asserts("PushConstants", &self.members, self.offset(), size),
+      range = self.to_range_string().trim_end_matches(",\n"),
+      vkpipes_alias = vkpipes_alias,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use spirv::Spirv;
+
+  fn reflect(bytes: &[u8], stage: vk::ShaderStageFlagBits) -> PushConstants {
+    let words = bytes
+      .chunks(4)
+      .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+      .collect::<Vec<_>>();
+    Spirv::from_binary(&words).unwrap().get_push_constants(stage).unwrap().unwrap()
+  }
+
+  fn vertex() -> PushConstants {
+    reflect(include_bytes!("../../../nobs-spirv/fixtures/push_vertex.spv"), vk::SHADER_STAGE_VERTEX_BIT)
+  }
+
+  fn fragment() -> PushConstants {
+    reflect(include_bytes!("../../../nobs-spirv/fixtures/push_fragment.spv"), vk::SHADER_STAGE_FRAGMENT_BIT)
+  }
+
+  /// Checks that the generated struct has the size of the reflected push constant range
+  fn assert_struct_size(pc: &PushConstants) {
+    let s = pc.to_struct_string("vk::pipes");
+    assert!(s.contains(&format!("assert!(std::mem::size_of::<PushConstants>() == {})", pc.size())));
+    assert!(s.contains(&format!("offset: {}, size: {}", pc.offset(), pc.size())));
+    assert!(s.contains("unsafe impl vk::pipes::pipeline::PushValue for PushConstants {}"));
+  }
+
+  #[test]
+  fn reflected() {
+    let pc = vertex();
+    assert_eq!(pc.name, "Push");
+    assert_eq!(
+      pc.members.iter().map(|m| (m.name.as_str(), m.offset, m.size, m.align, m.ty.as_str())).collect::<Vec<_>>(),
+      [("mvp", 0, 64, 4, "[[f32; 4]; 4]"), ("scale", 64, 4, 4, "f32")]
+    );
+
+    // vectors have the alignment of their components
+    let pc = fragment();
+    assert_eq!(
+      pc.members.iter().map(|m| (m.name.as_str(), m.offset, m.size, m.align, m.ty.as_str())).collect::<Vec<_>>(),
+      [("color", 80, 16, 4, "[f32; 4]"), ("ids", 96, 8, 4, "[u32; 2]")]
+    );
+  }
+
+  #[test]
+  fn merge() {
+    let mut pc = vertex();
+    pc.merge(&fragment()).unwrap();
+    assert_eq!(pc.stageflags, vk::SHADER_STAGE_VERTEX_BIT | vk::SHADER_STAGE_FRAGMENT_BIT);
+    assert_eq!(pc.members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["mvp", "scale", "color", "ids"]);
+    assert_eq!((pc.offset(), pc.size()), (0, 104));
+
+    // merging the same block again does not change it
+    let mut again = pc.clone();
+    again.merge(&vertex()).unwrap();
+    assert_eq!(again, pc);
+
+    // members at the same offset must match, other members must not overlap
+    let mut renamed = vertex();
+    renamed.members[0].name = "model".to_owned();
+    assert!(pc.clone().merge(&renamed).is_err());
+    let mut moved = fragment();
+    moved.members.iter_mut().for_each(|m| m.offset -= 20);
+    assert!(vertex().merge(&moved).is_err());
+  }
+
+  #[test]
+  fn size() {
+    let pc = fragment();
+    assert_eq!((pc.offset(), pc.size()), (80, 24));
+    assert!(pc.to_range_string().contains("offset: 80, size: 24"));
+    assert_struct_size(&pc);
+    assert_struct_size(&vertex());
+  }
+
+  #[test]
+  fn struct_padding() {
+    let mut pc = vertex();
+    pc.merge(&fragment()).unwrap();
+    let s = pc.to_struct_string("vk::pipes");
+
+    // gaps between members of different stages are padded
+    assert!(s.contains("pub mvp: [[f32; 4]; 4],\npub scale: f32,\n#[doc(hidden)]\npub _pad2: [u8; 12],\npub color: [f32; 4],\npub ids: [u32; 2],\n"));
+    assert!(!s.contains("_pad4"));
+    assert!(s.contains("assert!(std::mem::offset_of!(PushConstants, color) == 80)"));
+    assert_struct_size(&pc);
+
+    // the struct of a single stage starts at its first member
+    let s = fragment().to_struct_string("vk::pipes");
+    assert!(s.contains("pub color: [f32; 4],\npub ids: [u32; 2],\n"));
+    assert!(!s.contains("_pad"));
+    assert!(s.contains("assert!(std::mem::offset_of!(PushConstants, color) == 0)"));
+    assert!(s.contains("assert!(std::mem::offset_of!(PushConstants, ids) == 16)"));
+  }
+}
@@ -0,0 +1,261 @@
+use proc_macro::TokenStream;
+use proc_macro::TokenTree;
+
+use parse;
+use spec_constants::snake_case;
+use vk;
+
+/// Description of an immutable sampler
+///
+/// Only the specified parameters are stored, all others are taken from `DEFAULT_SAMPLER` of the nobs-vkpipes builder module,
+/// which is also the default of the `SamplerBuilder` of nobs-vulkanism.
+#[derive(Debug, Clone, Default)]
+pub struct Sampler {
+  pub mag_filter: Option<vk::Filter>,
+  pub min_filter: Option<vk::Filter>,
+  pub mipmap_mode: Option<vk::SamplerMipmapMode>,
+  pub address_mode: [Option<vk::SamplerAddressMode>; 3],
+  pub anisotropy: Option<bool>,
+  pub max_anisotropy: Option<f32>,
+  pub border_color: Option<vk::BorderColor>,
+  pub unnormalized_coordinates: Option<bool>,
+  pub compare: Option<bool>,
+  pub compare_op: Option<vk::CompareOp>,
+  pub mip_lodbias: Option<f32>,
+  pub min_lod: Option<f32>,
+  pub max_lod: Option<f32>,
+}
+
+const ARG_TYPES: &[&str] = &[
+  "mag_filter",
+  "min_filter",
+  "mipmap_mode",
+  "address_mode",
+  "address_mode_u",
+  "address_mode_v",
+  "address_mode_w",
+  "anisotropy",
+  "max_anisotropy",
+  "border_color",
+  "unnormalized_coordinates",
+  "compare",
+  "compare_op",
+  "mip_lodbias",
+  "min_lod",
+  "max_lod",
+];
+
+fn parse_filter(s: &str) -> Result<vk::Filter, String> {
+  match s {
+    "nearest" => Ok(vk::FILTER_NEAREST),
+    "linear" => Ok(vk::FILTER_LINEAR),
+    _ => Err(format!("filter \"{}\" not recognized, use one of [\"nearest\", \"linear\"]", s)),
+  }
+}
+
+fn parse_mipmap_mode(s: &str) -> Result<vk::SamplerMipmapMode, String> {
+  match s {
+    "nearest" => Ok(vk::SAMPLER_MIPMAP_MODE_NEAREST),
+    "linear" => Ok(vk::SAMPLER_MIPMAP_MODE_LINEAR),
+    _ => Err(format!(
+      "mipmap mode \"{}\" not recognized, use one of [\"nearest\", \"linear\"]",
+      s
+    )),
+  }
+}
+
+fn parse_address_mode(s: &str) -> Result<vk::SamplerAddressMode, String> {
+  match s {
+    "repeat" => Ok(vk::SAMPLER_ADDRESS_MODE_REPEAT),
+    "mirrored_repeat" => Ok(vk::SAMPLER_ADDRESS_MODE_MIRRORED_REPEAT),
+    "clamp_to_edge" => Ok(vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE),
+    "clamp_to_border" => Ok(vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_BORDER),
+    _ => Err(format!(
+      "address mode \"{}\" not recognized, use one of [\"repeat\", \"mirrored_repeat\", \"clamp_to_edge\", \"clamp_to_border\"]",
+      s
+    )),
+  }
+}
+
+fn parse_border_color(s: &str) -> Result<vk::BorderColor, String> {
+  match s {
+    "float_transparent_black" => Ok(vk::BORDER_COLOR_FLOAT_TRANSPARENT_BLACK),
+    "int_transparent_black" => Ok(vk::BORDER_COLOR_INT_TRANSPARENT_BLACK),
+    "float_opaque_black" => Ok(vk::BORDER_COLOR_FLOAT_OPAQUE_BLACK),
+    "int_opaque_black" => Ok(vk::BORDER_COLOR_INT_OPAQUE_BLACK),
+    "float_opaque_white" => Ok(vk::BORDER_COLOR_FLOAT_OPAQUE_WHITE),
+    "int_opaque_white" => Ok(vk::BORDER_COLOR_INT_OPAQUE_WHITE),
+    _ => Err(format!("border color \"{}\" not recognized", s)),
+  }
+}
+
+fn parse_compare_op(s: &str) -> Result<vk::CompareOp, String> {
+  match s {
+    "never" => Ok(vk::COMPARE_OP_NEVER),
+    "less" => Ok(vk::COMPARE_OP_LESS),
+    "equal" => Ok(vk::COMPARE_OP_EQUAL),
+    "less_or_equal" => Ok(vk::COMPARE_OP_LESS_OR_EQUAL),
+    "greater" => Ok(vk::COMPARE_OP_GREATER),
+    "not_equal" => Ok(vk::COMPARE_OP_NOT_EQUAL),
+    "greater_or_equal" => Ok(vk::COMPARE_OP_GREATER_OR_EQUAL),
+    "always" => Ok(vk::COMPARE_OP_ALWAYS),
+    _ => Err(format!("compare op \"{}\" not recognized", s)),
+  }
+}
+
+fn parse_bool(s: &str) -> Result<bool, String> {
+  s.parse::<bool>().map_err(|_| format!("expected true or false, found {}", s))
+}
+
+fn parse_f32(s: &str) -> Result<f32, String> {
+  s.parse::<f32>()
+    .map_err(|_| format!("expected a floating point number, found {}", s))
+}
+
+fn bool32(b: bool) -> &'static str {
+  match b {
+    true => "vk::TRUE",
+    false => "vk::FALSE",
+  }
+}
+
+impl Sampler {
+  pub fn from_tokens(input: TokenStream) -> Result<Sampler, String> {
+    let mut s = Sampler::default();
+    let mut tokens = input.clone().into_iter();
+    while let Some(tok) = tokens.next() {
+      match tok {
+        TokenTree::Ident(i) => {
+          tokens.next();
+          let arg = i.to_string();
+          let value = parse::parse(&mut tokens);
+          let value = value.trim_matches('"');
+          match arg.as_ref() {
+            "mag_filter" => s.mag_filter = Some(parse_filter(value)?),
+            "min_filter" => s.min_filter = Some(parse_filter(value)?),
+            "mipmap_mode" => s.mipmap_mode = Some(parse_mipmap_mode(value)?),
+            "address_mode" => s.address_mode = [Some(parse_address_mode(value)?); 3],
+            "address_mode_u" => s.address_mode[0] = Some(parse_address_mode(value)?),
+            "address_mode_v" => s.address_mode[1] = Some(parse_address_mode(value)?),
+            "address_mode_w" => s.address_mode[2] = Some(parse_address_mode(value)?),
+            "anisotropy" => s.anisotropy = Some(parse_bool(value)?),
+            "max_anisotropy" => s.max_anisotropy = Some(parse_f32(value)?),
+            "border_color" => s.border_color = Some(parse_border_color(value)?),
+            "unnormalized_coordinates" => s.unnormalized_coordinates = Some(parse_bool(value)?),
+            "compare" => s.compare = Some(parse_bool(value)?),
+            "compare_op" => s.compare_op = Some(parse_compare_op(value)?),
+            "mip_lodbias" => s.mip_lodbias = Some(parse_f32(value)?),
+            "min_lod" => s.min_lod = Some(parse_f32(value)?),
+            "max_lod" => s.max_lod = Some(parse_f32(value)?),
+            _ => Err(format!("expected one of {:?}, found {}", ARG_TYPES, &arg))?,
+          }
+        }
+        TokenTree::Group(_) => Err("expected TokenTree::Ident, found TokenTree::Group")?,
+        TokenTree::Literal(_) => Err("expected TokenTree::Ident, found TokenTree::Literal")?,
+        TokenTree::Punct(_) => Err("expected TokenTree::Ident, found TokenTree::Punct")?,
+      }
+    }
+    Ok(s)
+  }
+
+  /// Writes the sampler description as constant `vk::SamplerCreateInfo`
+  ///
+  /// Parameters that are not specified are initialized from `DEFAULT_SAMPLER` of the nobs-vkpipes builder module.
+  pub fn to_const_string(&self, name: &str, pipes: &str) -> String {
+    let mut fields = Vec::new();
+    {
+      let mut field = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+          fields.push(format!("{}: {},", name, value));
+        }
+      };
+      field("magFilter", self.mag_filter.map(|v| v.to_string()));
+      field("minFilter", self.min_filter.map(|v| v.to_string()));
+      field("mipmapMode", self.mipmap_mode.map(|v| v.to_string()));
+      field("addressModeU", self.address_mode[0].map(|v| v.to_string()));
+      field("addressModeV", self.address_mode[1].map(|v| v.to_string()));
+      field("addressModeW", self.address_mode[2].map(|v| v.to_string()));
+      field("mipLodBias", self.mip_lodbias.map(|v| format!("{:?}", v)));
+      field("anisotropyEnable", self.anisotropy.map(|v| bool32(v).to_string()));
+      field("maxAnisotropy", self.max_anisotropy.map(|v| format!("{:?}", v)));
+      field("compareEnable", self.compare.map(|v| bool32(v).to_string()));
+      field("compareOp", self.compare_op.map(|v| v.to_string()));
+      field("minLod", self.min_lod.map(|v| format!("{:?}", v)));
+      field("maxLod", self.max_lod.map(|v| format!("{:?}", v)));
+      field("borderColor", self.border_color.map(|v| v.to_string()));
+      field(
+        "unnormalizedCoordinates",
+        self.unnormalized_coordinates.map(|v| bool32(v).to_string()),
+      );
+    }
+
+    format!(
+      "pub const {name}: vk::SamplerCreateInfo = vk::SamplerCreateInfo {{
+        {fields}
+        ..{pipes}::builder::DEFAULT_SAMPLER
+      }};\n",
+      name = name,
+      fields = fields.join("\n        "),
+      pipes = pipes,
+    )
+  }
+}
+
+/// Name of the generated constant with the `vk::SamplerCreateInfo` of the sampler for the binding `name`
+///
+/// Uses the same upper snake case as the constants of specialization constants, e.g. `albedoTex` becomes `SAMPLER_ALBEDO_TEX`.
+pub fn const_name(name: &str) -> String {
+  format!("SAMPLER_{}", snake_case(name).to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn defaults() {
+    // nothing specified, everything is initialized from the default sampler
+    let s = Sampler::default().to_const_string("SAMPLER_TEX", "pipes");
+    assert!(s.starts_with("pub const SAMPLER_TEX: vk::SamplerCreateInfo = vk::SamplerCreateInfo {"));
+    assert!(s.contains("..pipes::builder::DEFAULT_SAMPLER"));
+    assert!(!s.contains(','));
+  }
+
+  #[test]
+  fn specified() {
+    let s = Sampler {
+      mag_filter: Some(parse_filter("linear").unwrap()),
+      address_mode: [None, Some(parse_address_mode("repeat").unwrap()), None],
+      anisotropy: Some(true),
+      max_lod: Some(parse_f32("4").unwrap()),
+      ..Default::default()
+    }
+    .to_const_string("SAMPLER_TEX", "vk::pipes");
+
+    assert!(s.contains(&format!("magFilter: {},", vk::FILTER_LINEAR)));
+    assert!(s.contains(&format!("addressModeV: {},", vk::SAMPLER_ADDRESS_MODE_REPEAT)));
+    assert!(s.contains("anisotropyEnable: vk::TRUE,"));
+    assert!(s.contains("maxLod: 4.0,"));
+    assert!(!s.contains("minFilter"));
+    assert!(!s.contains("addressModeU"));
+    assert!(s.contains("..vk::pipes::builder::DEFAULT_SAMPLER"));
+  }
+
+  #[test]
+  fn const_names() {
+    assert_eq!(const_name("tex"), "SAMPLER_TEX");
+    assert_eq!(const_name("albedoTex"), "SAMPLER_ALBEDO_TEX");
+    assert_eq!(const_name("shadow_map"), "SAMPLER_SHADOW_MAP");
+  }
+
+  #[test]
+  fn parse_values() {
+    assert_eq!(parse_mipmap_mode("nearest"), Ok(vk::SAMPLER_MIPMAP_MODE_NEAREST));
+    assert_eq!(parse_border_color("float_opaque_white"), Ok(vk::BORDER_COLOR_FLOAT_OPAQUE_WHITE));
+    assert_eq!(parse_compare_op("less_or_equal"), Ok(vk::COMPARE_OP_LESS_OR_EQUAL));
+    assert_eq!(parse_bool("false"), Ok(false));
+    assert!(parse_filter("cubic").is_err());
+    assert!(parse_address_mode("clamp").is_err());
+    assert!(parse_f32("one").is_err());
+  }
+}
@@ -5,10 +5,19 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::process::Command;
 
+use binding;
 use binding::Binding;
+use block;
+use block::Struct;
+use include;
 use parse;
+use push_constants::PushConstants;
+use spec_constants::SpecConstant;
 use spirv;
 use usings::Usings;
+use vertex_input;
+use vertex_input::Input;
+use vertex_input::VertexBinding;
 use vk;
 
 #[derive(Default, Debug)]
@@ -20,7 +29,14 @@ pub struct Builder {
   pub path_spv: String,
   pub src_glsl: String,
   pub src_spv: Vec<u32>,
+  /// `src_glsl` contains hlsl instead of glsl
+  pub hlsl: bool,
   pub includes: Vec<String>,
+  pub defines: Vec<(String, Option<String>)>,
+  pub optimize: String,
+  pub debug: bool,
+  pub target_env: String,
+  pub runtime_array_len: Option<u32>,
   pub dump: String,
 }
 
@@ -30,11 +46,19 @@ const ARG_TYPES: &[&str] = &[
   "ty",
   "entry",
   "glsl",
+  "hlsl",
   "spv",
   "include",
+  "define",
+  "optimize",
+  "debug",
+  "target_env",
+  "runtime_array_len",
   "dump",
 ];
 const STAGE_TYPES: &[&str] = &["vert", "tesc", "tese", "geom", "frag", "comp"];
+const OPTIMIZATION_LEVELS: &[&str] = &["zero", "size", "performance"];
+const TARGET_ENVS: &[&str] = &["vulkan1.0", "vulkan1.1"];
 
 pub fn stage_from_stirng(stage: &str) -> Result<vk::ShaderStageFlagBits, String> {
   match stage.as_ref() {
@@ -48,7 +72,57 @@ pub fn stage_from_stirng(stage: &str) -> Result<vk::ShaderStageFlagBits, String>
   }
 }
 
+fn optimization_level_from_string(level: &str) -> Result<shaderc::OptimizationLevel, String> {
+  match level {
+    "zero" => Ok(shaderc::OptimizationLevel::Zero),
+    "size" => Ok(shaderc::OptimizationLevel::Size),
+    "performance" => Ok(shaderc::OptimizationLevel::Performance),
+    _ => Err(format!(
+      "optimization level \"{}\" not recognized, use one of {:?}",
+      level, OPTIMIZATION_LEVELS
+    ))?,
+  }
+}
+
+fn target_env_from_string(env: &str) -> Result<u32, String> {
+  match env {
+    "vulkan1.0" => Ok(vk::make_version!(1, 0, 0)),
+    "vulkan1.1" => Ok(vk::make_version!(1, 1, 0)),
+    _ => Err(format!(
+      "target environment \"{}\" not recognized, use one of {:?}",
+      env, TARGET_ENVS
+    ))?,
+  }
+}
+
 impl Builder {
+  /// Sets the path or inline source of a glsl or hlsl shader
+  ///
+  /// Fails if a glsl or hlsl source has already been specified.
+  fn set_source(&mut self, arg: &str, src: String) -> Result<(), String> {
+    let hlsl = arg == "hlsl";
+    if !self.path_glsl.is_empty() {
+      match self.hlsl == hlsl {
+        true => Err(format!("\"{}\" has been specified more than once", arg))?,
+        false => Err("Both \"glsl\" and \"hlsl\" have been specified for shader source")?,
+      }
+    }
+    self.path_glsl = src;
+    self.hlsl = hlsl;
+    Ok(())
+  }
+
+  /// Checks that a spv shader is not specified together with a glsl or hlsl source
+  fn check_sources(&self) -> Result<(), String> {
+    if !self.path_glsl.is_empty() && !self.path_spv.is_empty() {
+      match self.hlsl {
+        true => Err("Both \"hlsl\" and \"spv\" have been specified for shader source")?,
+        false => Err("Both \"glsl\" and \"spv\" have been specified for shader source")?,
+      }
+    }
+    Ok(())
+  }
+
   pub fn from_tokens(input: TokenStream) -> Result<Builder, String> {
     let mut b = Builder::default();
     let mut tokens = input.clone().into_iter();
@@ -62,9 +136,26 @@ impl Builder {
             "nobs_vk_alias" => b.usings.vk = Some(parse::parse_string(&mut tokens)),
             "ty" => b.stage = parse::parse_string(&mut tokens),
             "entry" => b.entry = parse::parse_string(&mut tokens),
-            "glsl" => b.path_glsl = parse::parse_string(&mut tokens),
+            "glsl" | "hlsl" => b.set_source(&s, parse::parse_string(&mut tokens))?,
+            "spv" if !b.path_spv.is_empty() => Err("\"spv\" has been specified more than once")?,
             "spv" => b.path_spv = parse::parse_string(&mut tokens),
             "include" => b.includes = parse::parse_string_vec(&mut tokens).map_err(|e| format!("after argument include: {}", e))?,
+            "define" => {
+              b.defines = parse::parse_string_vec(&mut tokens)
+                .map_err(|e| format!("after argument define: {}", e))?
+                .iter()
+                .map(|d| match d.find('=') {
+                  Some(i) => (d[..i].to_string(), Some(d[i + 1..].to_string())),
+                  None => (d.to_string(), None),
+                })
+                .collect()
+            }
+            "optimize" => b.optimize = parse::parse_string(&mut tokens),
+            "debug" => b.debug = parse::parse_bool(&mut tokens).map_err(|e| format!("after argument debug: {}", e))?,
+            "target_env" => b.target_env = parse::parse_string(&mut tokens),
+            "runtime_array_len" => {
+              b.runtime_array_len = Some(parse::parse_u32(&mut tokens).map_err(|e| format!("after argument runtime_array_len: {}", e))?)
+            }
             "dump" => b.dump = parse::parse_string(&mut tokens),
             _ => Err(format!("expected one of {:?}, found {}", ARG_TYPES, &s))?,
           }
@@ -75,9 +166,7 @@ impl Builder {
       }
     }
 
-    if !b.path_glsl.is_empty() && !b.src_spv.is_empty() {
-      Err("Both \"glsl\" and \"spv\" have been specified for shader source")?
-    }
+    b.check_sources()?;
 
     // first try to read the src from a spcified spv file
     if !b.path_spv.is_empty() {
@@ -106,7 +195,7 @@ impl Builder {
 
     // At this point we got to have either of those specified
     if b.src_spv.is_empty() && b.src_glsl.is_empty() {
-      Err("Neither \"glsl\" nor \"spv\" have been specified for shader source")?
+      Err("Neither \"glsl\", \"hlsl\" nor \"spv\" have been specified for shader source")?
     }
 
     // our entry point by default is main
@@ -122,6 +211,12 @@ impl Builder {
     }
 
     stage_from_stirng(&b.stage)?;
+    if !b.optimize.is_empty() {
+      optimization_level_from_string(&b.optimize)?;
+    }
+    if !b.target_env.is_empty() {
+      target_env_from_string(&b.target_env)?;
+    }
 
     Ok(b)
   }
@@ -130,7 +225,7 @@ impl Builder {
     // make include dirs absolute
     let includes = self.includes.iter().filter_map(|i| Self::make_abs_path(i)).collect::<Vec<_>>();
 
-    // if we have glsl source we comile it with shaderc
+    // if we have glsl or hlsl source we comile it with shaderc
     // if not we use the spv from the file
     let binary = if !self.src_glsl.is_empty() {
       let mut compiler = shaderc::Compiler::new().unwrap();
@@ -140,6 +235,22 @@ impl Builder {
         Self::find_include(includer, include_type, includee, depth, &includes, !self.path_glsl.is_empty())
       });
 
+      if self.hlsl {
+        options.set_source_language(shaderc::SourceLanguage::HLSL);
+      }
+      for (name, value) in self.defines.iter() {
+        options.add_macro_definition(name, value.as_ref().map(|v| v.as_str()));
+      }
+      if !self.optimize.is_empty() {
+        options.set_optimization_level(optimization_level_from_string(&self.optimize)?);
+      }
+      if self.debug {
+        options.set_generate_debug_info();
+      }
+      if !self.target_env.is_empty() {
+        options.set_target_env(shaderc::TargetEnv::Vulkan, target_env_from_string(&self.target_env)?);
+      }
+
       let shader_kind = match self.stage.as_ref() {
         "vert" => shaderc::ShaderKind::Vertex,
         "tesc" => shaderc::ShaderKind::TessControl,
@@ -171,11 +282,26 @@ impl Builder {
     // parse the spirv to get uniforms
     let spirv = spirv::Spirv::from_binary(&binary)?;
 
+    let mut bindings = spirv.get_bindings(stage_from_stirng(&self.stage)?)?;
+    Binding::set_runtime_array_len(&mut bindings, self.runtime_array_len.unwrap_or(binding::DEFAULT_RUNTIME_ARRAY_LEN));
+    Binding::set_variable_count(&mut bindings);
+
+    let inputs = match self.stage.as_ref() {
+      "vert" => spirv.get_inputs()?,
+      _ => Vec::new(),
+    };
+    let vertices = VertexBinding::from_inputs(&inputs, &Default::default(), &Default::default())?;
+
     Ok(Shader {
       usings: self.usings.clone(),
       stage: self.stage.clone(),
       entry: self.entry.clone(),
-      bindings: spirv.get_bindings(stage_from_stirng(&self.stage)?),
+      bindings,
+      push_constants: spirv.get_push_constants(stage_from_stirng(&self.stage)?)?,
+      spec_constants: spirv.get_spec_constants()?,
+      structs: spirv.get_structs()?,
+      inputs,
+      vertices,
       binary: binary,
     })
   }
@@ -203,72 +329,50 @@ impl Builder {
     include_dirs: &[String],
     root_source_has_path: bool,
   ) -> Result<shaderc::ResolvedInclude, String> {
-    let resolved = match include_type {
-      shaderc::IncludeType::Relative => {
-        if !root_source_has_path && depth == 1 {
-          return Err("Can not find includes with relative path to the root source file, when using embedded GLSL.".to_string());
-        }
-
-        let parent_dir = Path::new(includer).parent().unwrap();
-        let resolved = parent_dir.join(includee);
-
-        if !resolved.is_file() {
-          return Err(format!("Include `{}` is not a file, included from `{}`", includee, includer));
-        }
-        resolved
-      }
-
-      shaderc::IncludeType::Standard => {
-        let includee_path = Path::new(includee);
-
-        if includee_path.is_absolute() {
-          return Err(format!("Include `{}` is not a file, included from `{}`", includee, includer));
-        }
-
-        let mut found = None;
-
-        for dir in include_dirs {
-          let path = Path::new(dir)
-            .canonicalize()
-            .unwrap_or_else(|_| panic!("`{}` is not a valid include directory.", dir));
-
-          if let Ok(resolved) = path.join(includee).canonicalize() {
-            if resolved.is_file() {
-              found = Some(resolved);
-              break;
-            }
-          }
-        }
-
-        if found.is_none() {
-          return Err(format!("Include `{}` is not a file, included from `{}`", includee, includer));
-        }
-
-        found.unwrap()
+    if let shaderc::IncludeType::Relative = include_type {
+      if !root_source_has_path && depth == 1 {
+        return Err("Can not find includes with relative path to the root source file, when using embedded GLSL.".to_string());
       }
     }
-    .canonicalize()
-    .unwrap();
-
-
-    let resolved_name = resolved.to_str().unwrap().to_string();
+    include::resolve_include(includee, include_type, includer, include_dirs)
+  }
+}
 
-    let content = {
-      let mut buffer = String::new();
-      let mut f = std::fs::File::open(&resolved_name).unwrap();
-      f.read_to_string(&mut buffer).unwrap();
-      buffer
-    };
+/// Writes the push constant ranges and the typed push constant struct, if the block is specified
+pub fn write_push_constants(push_constants: &Option<PushConstants>, vkpipes_alias: &str) -> String {
+  match push_constants {
+    Some(pc) => format!(
+      "
+      pub const PUSH_CONSTANTS: [vk::PushConstantRange; 1] = [
+        {}
+      ];
 
-    Ok(shaderc::ResolvedInclude { resolved_name, content })
+      {}
+      ",
+      pc.to_range_string(),
+      pc.to_struct_string(vkpipes_alias)
+    ),
+    None => "pub const PUSH_CONSTANTS: [vk::PushConstantRange; 0] = [];".to_string(),
   }
 }
 
+/// Writes a constant with id and default value for every specialization constant
+pub fn write_spec_constants(spec_constants: &[SpecConstant], vkpipes_alias: &str) -> String {
+  spec_constants
+    .iter()
+    .fold(String::new(), |acc, c| format!("{}{}", acc, c.to_const_string(vkpipes_alias)))
+}
+
 pub struct Shader {
   pub usings: Usings,
   pub stage: String,
   pub entry: String,
   pub bindings: Vec<Binding>,
+  pub push_constants: Option<PushConstants>,
+  pub spec_constants: Vec<SpecConstant>,
+  pub structs: Vec<Struct>,
+  pub inputs: Vec<Input>,
+  pub vertices: Vec<VertexBinding>,
   pub binary: Vec<u32>,
 }
 
@@ -288,6 +392,10 @@ impl Shader {
     )
   }
 
+  fn write_push_constants(&self) -> String {
+    write_push_constants(&self.push_constants, &self.usings.get_pipes())
+  }
+
   pub fn write_module(&self) -> String {
     format!(
       "
@@ -322,6 +430,14 @@ impl Shader {
       }}
 
       {bindings}
+
+      {push_constants}
+
+      {spec_constants}
+
+      {blocks}
+
+      {vertices}
       ",
       vk_alias = self.usings.get_vk(),
       vkpipes_alias = self.usings.get_pipes(),
@@ -329,6 +445,10 @@ impl Shader {
       shader_entry = self.entry,
       spirv = self.binary.iter().fold(String::new(), |s, w| format!("{} {},", s, w)),
       bindings = self.write_bindings(),
+      push_constants = self.write_push_constants(),
+      spec_constants = write_spec_constants(&self.spec_constants, &self.usings.get_pipes()),
+      blocks = block::write_blocks(&self.structs),
+      vertices = vertex_input::write_vertices(&self.vertices, &self.usings.get_pipes()),
     )
   }
 
@@ -345,3 +465,66 @@ impl Shader {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn is_err(r: Result<(), String>, msg: &str) -> bool {
+    match r {
+      Err(e) => e == msg,
+      Ok(_) => false,
+    }
+  }
+
+  #[test]
+  fn sources() {
+    let mut b = Builder::default();
+    assert!(b.set_source("glsl", "a.comp".to_string()).is_ok());
+    assert!(!b.hlsl);
+    assert!(is_err(
+      b.set_source("glsl", "b.comp".to_string()),
+      "\"glsl\" has been specified more than once"
+    ));
+    assert!(is_err(
+      b.set_source("hlsl", "b.hlsl".to_string()),
+      "Both \"glsl\" and \"hlsl\" have been specified for shader source"
+    ));
+    assert_eq!(b.path_glsl, "a.comp");
+
+    let mut b = Builder::default();
+    assert!(b.set_source("hlsl", "a.hlsl".to_string()).is_ok());
+    assert!(b.hlsl);
+    assert!(is_err(
+      b.set_source("hlsl", "b.hlsl".to_string()),
+      "\"hlsl\" has been specified more than once"
+    ));
+    assert!(is_err(
+      b.set_source("glsl", "b.comp".to_string()),
+      "Both \"glsl\" and \"hlsl\" have been specified for shader source"
+    ));
+    assert!(b.hlsl);
+  }
+
+  #[test]
+  fn spv_sources() {
+    // the spv file is checked before it is loaded
+    let mut b = Builder::default();
+    b.path_spv = "a.spv".to_string();
+    assert!(b.check_sources().is_ok());
+
+    b.set_source("glsl", "a.comp".to_string()).unwrap();
+    assert!(is_err(
+      b.check_sources(),
+      "Both \"glsl\" and \"spv\" have been specified for shader source"
+    ));
+
+    let mut b = Builder::default();
+    b.path_spv = "a.spv".to_string();
+    b.set_source("hlsl", "a.hlsl".to_string()).unwrap();
+    assert!(is_err(
+      b.check_sources(),
+      "Both \"hlsl\" and \"spv\" have been specified for shader source"
+    ));
+  }
+}
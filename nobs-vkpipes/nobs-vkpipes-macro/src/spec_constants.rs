@@ -0,0 +1,143 @@
+/// Specialization constant of a shader
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpecConstant {
+  pub name: String,
+  pub id: u32,
+  pub ty: String,
+  pub default: String,
+}
+
+/// Converts a camel case name into snake case, e.g. `groupSize` and `GroupSize` into `group_size`
+///
+/// An acronym is kept as one word, `maxLODBias` becomes `max_lod_bias`.
+pub fn snake_case(name: &str) -> String {
+  let chars = name.chars().collect::<Vec<_>>();
+  let mut snake = String::with_capacity(name.len() + 4);
+  for (i, c) in chars.iter().enumerate() {
+    if c.is_uppercase() && i > 0 {
+      let prev = chars[i - 1];
+      let next_lower = chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false);
+      if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+        snake.push('_');
+      }
+    }
+    snake.extend(c.to_lowercase());
+  }
+  snake
+}
+
+impl SpecConstant {
+  /// Name of the constant in snake case, that is used for the generated constant and setter
+  pub fn ident(&self) -> String {
+    snake_case(&self.name)
+  }
+
+  pub fn to_const_string(&self, vkpipes_alias: &str) -> String {
+    format!(
+      "pub const SPEC_{upper}: {vkpipes_alias}::pipeline::SpecConstant<{ty}> = {vkpipes_alias}::pipeline::SpecConstant {{ name: &\"{name}\", id: {id}, default: {default} }};\n",
+      vkpipes_alias = vkpipes_alias,
+      upper = self.ident().to_uppercase(),
+      name = self.name,
+      id = self.id,
+      ty = self.ty,
+      default = self.default,
+    )
+  }
+
+  pub fn to_setter_decl_string(&self) -> String {
+    format!(
+      "
+      /// Sets the specialization constant `{name}` with id {id}
+      fn spec_{ident}(&mut self, value: {ty}) -> &mut Self;
+      ",
+      name = self.name,
+      ident = self.ident(),
+      id = self.id,
+      ty = self.ty,
+    )
+  }
+
+  pub fn to_setter_impl_string(&self) -> String {
+    format!(
+      "
+      fn spec_{ident}(&mut self, value: {ty}) -> &mut Self {{
+        self.specialize({id}, value{cast})
+      }}
+      ",
+      ident = self.ident(),
+      id = self.id,
+      ty = self.ty,
+      cast = if self.ty == "bool" { " as vk::Bool32" } else { "" },
+    )
+  }
+
+  /// Checks if the constants have the same id and type
+  pub fn same_constant(a: &Self, b: &Self) -> bool {
+    a.id == b.id && a.ty == b.ty
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use spirv::Spirv;
+
+  fn constant(name: &str, id: u32, ty: &str, default: &str) -> SpecConstant {
+    SpecConstant {
+      name: name.to_owned(),
+      id,
+      ty: ty.to_owned(),
+      default: default.to_owned(),
+    }
+  }
+
+  #[test]
+  fn snake() {
+    assert_eq!(snake_case("group_size"), "group_size");
+    assert_eq!(snake_case("groupSize"), "group_size");
+    assert_eq!(snake_case("GroupSize"), "group_size");
+    assert_eq!(snake_case("maxLODBias"), "max_lod_bias");
+    assert_eq!(snake_case("LOD"), "lod");
+    assert_eq!(snake_case("tap4Weights"), "tap4_weights");
+    assert_eq!(snake_case("local_size_x"), "local_size_x");
+  }
+
+  #[test]
+  fn setters() {
+    let c = constant("groupSize", 2, "u32", "32");
+    assert!(c.to_const_string("vk::pipes").starts_with(
+      "pub const SPEC_GROUP_SIZE: vk::pipes::pipeline::SpecConstant<u32> = vk::pipes::pipeline::SpecConstant { name: &\"groupSize\", id: 2, default: 32 };"
+    ));
+    assert!(c
+      .to_setter_decl_string()
+      .contains("fn spec_group_size(&mut self, value: u32) -> &mut Self;"));
+    assert!(c.to_setter_impl_string().contains("self.specialize(2, value)"));
+
+    let c = constant("enabled", 0, "bool", "true");
+    assert!(c
+      .to_setter_impl_string()
+      .contains("fn spec_enabled(&mut self, value: bool) -> &mut Self"));
+    assert!(c.to_setter_impl_string().contains("self.specialize(0, value as vk::Bool32)"));
+
+    assert!(SpecConstant::same_constant(&c, &constant("other", 0, "bool", "false")));
+    assert!(!SpecConstant::same_constant(&c, &constant("enabled", 0, "u32", "1")));
+  }
+
+  #[test]
+  fn workgroup_size() {
+    let bytes = include_bytes!("../../../nobs-spirv/fixtures/workgroup.spv");
+    let words = bytes
+      .chunks(4)
+      .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+      .collect::<Vec<_>>();
+    let constants = Spirv::from_binary(&words).unwrap().get_spec_constants().unwrap();
+    assert_eq!(
+      constants.iter().map(|c| (c.ident(), c.id, c.default.as_str())).collect::<Vec<_>>(),
+      vec![
+        ("local_size_x".to_owned(), 0, "1"),
+        ("local_size_y".to_owned(), 1, "1"),
+        ("group_size".to_owned(), 2, "32")
+      ]
+    );
+  }
+}
@@ -1,583 +1,651 @@
-// Copyright (c) 2016 The vulkano developers
-// Licensed under the Apache License, Version 2.0
-// <LICENSE-APACHE or
-// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
-// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
-// at your option. All files in the project carrying such
-// notice may not be copied, modified, or distributed except
-// according to those terms.
-
+use binding;
 use binding::Binding;
-use enums::*;
+use block;
+use block::Member;
+use block::RuntimeArray;
+use block::Struct;
+use nobs_spirv::enums::Decoration;
+use nobs_spirv::enums::StorageClass;
+use nobs_spirv::DescriptorKind;
+use nobs_spirv::Instruction;
+use nobs_spirv::Module;
+use nobs_spirv::Type;
+use push_constants::PushConstants;
+use spec_constants::SpecConstant;
+use vertex_input;
+use vertex_input::Attribute;
+use vertex_input::Input;
 use vk;
 
+/// Code generation on top of the reflection of [nobs_spirv](https://docs.rs/nobs-spirv)
 #[derive(Debug, Clone)]
 pub struct Spirv {
-  pub version: (u8, u8),
-  pub bound: u32,
-  pub instructions: Vec<Instruction>,
-}
-
-#[derive(Debug, Clone)]
-pub enum Instruction {
-  Unknown(u16, Vec<u32>),
-  Nop,
-  Name {
-    target_id: u32,
-    name: String,
-  },
-  MemberName {
-    target_id: u32,
-    member: u32,
-    name: String,
-  },
-  ExtInstImport {
-    result_id: u32,
-    name: String,
-  },
-  MemoryModel(AddressingModel, MemoryModel),
-  EntryPoint {
-    execution: ExecutionModel,
-    id: u32,
-    name: String,
-    interface: Vec<u32>,
-  },
-  ExecutionMode {
-    target_id: u32,
-    mode: ExecutionMode,
-    optional_literals: Vec<u32>,
-  },
-  Capability(Capability),
-  TypeVoid {
-    result_id: u32,
-  },
-  TypeBool {
-    result_id: u32,
-  },
-  TypeInt {
-    result_id: u32,
-    width: u32,
-    signedness: bool,
-  },
-  TypeFloat {
-    result_id: u32,
-    width: u32,
-  },
-  TypeVector {
-    result_id: u32,
-    component_id: u32,
-    count: u32,
-  },
-  TypeMatrix {
-    result_id: u32,
-    column_type_id: u32,
-    column_count: u32,
-  },
-  TypeImage {
-    result_id: u32,
-    sampled_type_id: u32,
-    dim: Dim,
-    depth: Option<bool>,
-    arrayed: bool,
-    ms: bool,
-    sampled: Option<bool>,
-    format: ImageFormat,
-    access: Option<AccessQualifier>,
-  },
-  TypeSampler {
-    result_id: u32,
-  },
-  TypeSampledImage {
-    result_id: u32,
-    image_type_id: u32,
-  },
-  TypeArray {
-    result_id: u32,
-    type_id: u32,
-    length_id: u32,
-  },
-  TypeRuntimeArray {
-    result_id: u32,
-    type_id: u32,
-  },
-  TypeStruct {
-    result_id: u32,
-    member_types: Vec<u32>,
-  },
-  TypeOpaque {
-    result_id: u32,
-    name: String,
-  },
-  TypePointer {
-    result_id: u32,
-    storage_class: StorageClass,
-    type_id: u32,
-  },
-  Constant {
-    result_type_id: u32,
-    result_id: u32,
-    data: Vec<u32>,
-  },
-  SpecConstantTrue {
-    result_type_id: u32,
-    result_id: u32,
-  },
-  SpecConstantFalse {
-    result_type_id: u32,
-    result_id: u32,
-  },
-  SpecConstant {
-    result_type_id: u32,
-    result_id: u32,
-    data: Vec<u32>,
-  },
-  SpecConstantComposite {
-    result_type_id: u32,
-    result_id: u32,
-    data: Vec<u32>,
-  },
-  FunctionEnd,
-  Variable {
-    result_type_id: u32,
-    result_id: u32,
-    storage_class: StorageClass,
-    initializer: Option<u32>,
-  },
-  Decorate {
-    target_id: u32,
-    decoration: Decoration,
-    params: Vec<u32>,
-  },
-  MemberDecorate {
-    target_id: u32,
-    member: u32,
-    decoration: Decoration,
-    params: Vec<u32>,
-  },
-  DecorationGroup {
-    result_id: u32,
-  },
-  GroupDecorate {
-    decoration_group: u32,
-    targets: Vec<u32>,
-  },
-  GroupMemberDecorate {
-    decoration_group: u32,
-    targets: Vec<(u32, u32)>,
-  },
-  Label {
-    result_id: u32,
-  },
-  Branch {
-    result_id: u32,
-  },
-  Kill,
-  Return,
+  pub module: Module,
 }
 
 impl Spirv {
   /// Parses a SPIR-V document from a list of words.
   pub fn from_binary(i: &[u32]) -> Result<Spirv, String> {
-    fn parse_instruction(i: &[u32]) -> Result<(Instruction, &[u32]), String> {
-      assert!(i.len() >= 1);
+    Ok(Spirv {
+      module: Module::from_words(i).map_err(|e| format!("Parse spv: {:?}", e))?,
+    })
+  }
 
-      let word_count = (i[0] >> 16) as usize;
-      assert!(word_count >= 1);
-      let opcode = (i[0] & 0xffff) as u16;
+  pub fn get_bindings(&self, stagebit: vk::ShaderStageFlagBits) -> Result<Vec<Binding>, String> {
+    let bindings = self
+      .module
+      .descriptor_bindings()
+      .map_err(|e| format!("could not reflect descriptor bindings: {:?}", e))?;
+    Ok(
+      bindings
+        .into_iter()
+        .map(|b| Binding {
+          name: self
+            .module
+            .name(b.id)
+            .and_then(to_identifier)
+            .or_else(|| self.get_pointee_name(b.id))
+            .unwrap_or_else(|| format!("binding_{}", b.binding)),
+          binding: b.binding,
+          descset: b.set,
+          desctype: get_descriptor_type(b.kind),
+          // runtime arrays have an unknown number of descriptors
+          arrayelems: b.count.unwrap_or(0),
+          stageflags: stagebit,
+          flags: match b.count {
+            None => binding::RUNTIME_ARRAY_FLAGS,
+            Some(_) => 0,
+          },
+          immutable_sampler: false,
+        })
+        .collect(),
+    )
+  }
 
-      if i.len() < word_count {
-        Err("Parse spv: incomplete instruction")?
-      }
+  /// Get the name of the type, that a variable points to
+  ///
+  /// Used for variables without a name, e.g. uniform blocks of glsl compilers or resources of hlsl compilers.
+  fn get_pointee_name(&self, id: u32) -> Option<String> {
+    let ty = self.module.instructions.iter().find_map(|i| match i {
+      Instruction::Variable {
+        result_type_id, result_id, ..
+      } if *result_id == id => Some(*result_type_id),
+      _ => None,
+    })?;
+    let pointee = self.module.instructions.iter().find_map(|i| match i {
+      Instruction::TypePointer { result_id, type_id, .. } if *result_id == ty => Some(*type_id),
+      _ => None,
+    })?;
+    self.module.name(pointee).and_then(to_identifier)
+  }
 
-      let opcode = decode_instruction(opcode, &i[1..word_count])?;
-      Ok((opcode, &i[word_count..]))
-    }
+  pub fn get_push_constants(&self, stagebit: vk::ShaderStageFlagBits) -> Result<Option<PushConstants>, String> {
+    let var = match self.module.push_constants().map_err(|e| format!("{:?}", e))?.into_iter().next() {
+      Some(var) => var,
+      None => return Ok(None),
+    };
 
-    fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, String> {
-      Ok(match opcode {
-        0 => Instruction::Nop,
-        5 => Instruction::Name {
-          target_id: operands[0],
-          name: parse_string(&operands[1..]).0,
-        },
-        6 => Instruction::MemberName {
-          target_id: operands[0],
-          member: operands[1],
-          name: parse_string(&operands[2..]).0,
-        },
-        11 => Instruction::ExtInstImport {
-          result_id: operands[0],
-          name: parse_string(&operands[1..]).0,
-        },
-        14 => Instruction::MemoryModel(AddressingModel::from_num(operands[0])?, MemoryModel::from_num(operands[1])?),
-        15 => {
-          let (n, r) = parse_string(&operands[2..]);
-          Instruction::EntryPoint {
-            execution: ExecutionModel::from_num(operands[0])?,
-            id: operands[1],
-            name: n,
-            interface: r.to_owned(),
-          }
-        }
-        16 => Instruction::ExecutionMode {
-          target_id: operands[0],
-          mode: ExecutionMode::from_num(operands[1])?,
-          optional_literals: operands[2..].to_vec(),
-        },
-        17 => Instruction::Capability(Capability::from_num(operands[0])?),
-        19 => Instruction::TypeVoid { result_id: operands[0] },
-        20 => Instruction::TypeBool { result_id: operands[0] },
-        21 => Instruction::TypeInt {
-          result_id: operands[0],
-          width: operands[1],
-          signedness: operands[2] != 0,
-        },
-        22 => Instruction::TypeFloat {
-          result_id: operands[0],
-          width: operands[1],
-        },
-        23 => Instruction::TypeVector {
-          result_id: operands[0],
-          component_id: operands[1],
-          count: operands[2],
-        },
-        24 => Instruction::TypeMatrix {
-          result_id: operands[0],
-          column_type_id: operands[1],
-          column_count: operands[2],
-        },
-        25 => Instruction::TypeImage {
-          result_id: operands[0],
-          sampled_type_id: operands[1],
-          dim: Dim::from_num(operands[2])?,
-          depth: match operands[3] {
-            0 => Some(false),
-            1 => Some(true),
-            2 => None,
-            _ => unreachable!(),
-          },
-          arrayed: operands[4] != 0,
-          ms: operands[5] != 0,
-          sampled: match operands[6] {
-            0 => None,
-            1 => Some(true),
-            2 => Some(false),
-            _ => unreachable!(),
-          },
-          format: ImageFormat::from_num(operands[7])?,
-          access: if operands.len() >= 9 {
-            Some(AccessQualifier::from_num(operands[8])?)
-          } else {
-            None
-          },
-        },
-        26 => Instruction::TypeSampler { result_id: operands[0] },
-        27 => Instruction::TypeSampledImage {
-          result_id: operands[0],
-          image_type_id: operands[1],
-        },
-        28 => Instruction::TypeArray {
-          result_id: operands[0],
-          type_id: operands[1],
-          length_id: operands[2],
-        },
-        29 => Instruction::TypeRuntimeArray {
-          result_id: operands[0],
-          type_id: operands[1],
-        },
-        30 => Instruction::TypeStruct {
-          result_id: operands[0],
-          member_types: operands[1..].to_owned(),
-        },
-        31 => Instruction::TypeOpaque {
-          result_id: operands[0],
-          name: parse_string(&operands[1..]).0,
-        },
-        32 => Instruction::TypePointer {
-          result_id: operands[0],
-          storage_class: StorageClass::from_num(operands[1])?,
-          type_id: operands[2],
-        },
-        43 => Instruction::Constant {
-          result_type_id: operands[0],
-          result_id: operands[1],
-          data: operands[2..].to_owned(),
-        },
-        48 => Instruction::SpecConstantTrue {
-          result_type_id: operands[0],
-          result_id: operands[1],
-        },
-        49 => Instruction::SpecConstantFalse {
-          result_type_id: operands[0],
-          result_id: operands[1],
-        },
-        50 => Instruction::SpecConstant {
-          result_type_id: operands[0],
-          result_id: operands[1],
-          data: operands[2..].to_owned(),
-        },
-        51 => Instruction::SpecConstantComposite {
-          result_type_id: operands[0],
-          result_id: operands[1],
-          data: operands[2..].to_owned(),
-        },
-        56 => Instruction::FunctionEnd,
-        59 => Instruction::Variable {
-          result_type_id: operands[0],
-          result_id: operands[1],
-          storage_class: StorageClass::from_num(operands[2])?,
-          initializer: operands.get(3).map(|&v| v),
-        },
-        71 => Instruction::Decorate {
-          target_id: operands[0],
-          decoration: Decoration::from_num(operands[1])?,
-          params: operands[2..].to_owned(),
-        },
-        72 => Instruction::MemberDecorate {
-          target_id: operands[0],
-          member: operands[1],
-          decoration: Decoration::from_num(operands[2])?,
-          params: operands[3..].to_owned(),
-        },
-        73 => Instruction::DecorationGroup { result_id: operands[0] },
-        74 => Instruction::GroupDecorate {
-          decoration_group: operands[0],
-          targets: operands[1..].to_owned(),
-        },
-        75 => Instruction::GroupMemberDecorate {
-          decoration_group: operands[0],
-          targets: operands.chunks(2).map(|x| (x[0], x[1])).collect(),
-        },
-        248 => Instruction::Label { result_id: operands[0] },
-        249 => Instruction::Branch { result_id: operands[0] },
-        252 => Instruction::Kill,
-        253 => Instruction::Return,
-        _ => Instruction::Unknown(opcode, operands.to_owned()),
-      })
+    let block = match var.ty {
+      Type::Struct(s) => s,
+      _ => Err("push constants are not a block")?,
+    };
+    let (members, runtime_array) = self.get_members(&block)?;
+    if runtime_array.is_some() {
+      Err("push constant block must not contain a runtime array")?
     }
 
-    fn parse_string(data: &[u32]) -> (String, &[u32]) {
-      let bytes = data
-        .iter()
-        .flat_map(|&n| {
-          let b1 = (n & 0xff) as u8;
-          let b2 = ((n >> 8) & 0xff) as u8;
-          let b3 = ((n >> 16) & 0xff) as u8;
-          let b4 = ((n >> 24) & 0xff) as u8;
-          vec![b1, b2, b3, b4].into_iter()
-        })
-        .take_while(|&b| b != 0)
-        .collect::<Vec<u8>>();
-
-      let r = 1 + bytes.len() / 4;
-      let s = String::from_utf8(bytes).expect("Shader content is not UTF-8");
+    Ok(Some(PushConstants {
+      name: block
+        .name
+        .as_ref()
+        .and_then(|n| to_identifier(n))
+        .unwrap_or_else(|| "push_constants".to_string()),
+      members,
+      stageflags: stagebit,
+    }))
+  }
 
-      (s, &data[r..])
+  pub fn get_spec_constants(&self) -> Result<Vec<SpecConstant>, String> {
+    // unnamed constants of the workgroup size are named after the glsl layout qualifiers
+    let workgroup = self.module.workgroup_size_ids();
+    let mut constants = Vec::new();
+    for c in self.module.spec_constants().map_err(|e| format!("{:?}", e))? {
+      let name = c
+        .name
+        .as_ref()
+        .and_then(|n| to_identifier(n))
+        .or_else(|| {
+          let i = workgroup?.iter().position(|id| *id == c.id)?;
+          Some(format!("local_size_{}", ["x", "y", "z"][i]))
+        })
+        .unwrap_or(format!("constant_{}", c.spec_id));
+      let ty = match c.ty {
+        Type::Bool => "bool".to_string(),
+        _ => {
+          self
+            .get_rust_type(&c.ty, None, false)
+            .map_err(|e| format!("specialization constant {}: {}", name, e))?
+            .0
+        }
+      };
+
+      let low = *c
+        .default
+        .first()
+        .ok_or(format!("specialization constant {} has no default value", name))?;
+      let bits = low as u64 | c.default.get(1).map(|&w| (w as u64) << 32).unwrap_or(0);
+      let default = match ty.as_ref() {
+        "bool" => (bits != 0).to_string(),
+        "u16" => (bits as u16).to_string(),
+        "i16" => (bits as i16).to_string(),
+        "u32" => (bits as u32).to_string(),
+        "i32" => (bits as i32).to_string(),
+        "u64" => bits.to_string(),
+        "i64" => (bits as i64).to_string(),
+        "f32" if f32::from_bits(bits as u32).is_finite() => format!("{:?}", f32::from_bits(bits as u32)),
+        "f64" if f64::from_bits(bits).is_finite() => format!("{:?}", f64::from_bits(bits)),
+        "f32" => format!("f32::from_bits({:#x})", bits as u32),
+        "f64" => format!("f64::from_bits({:#x})", bits),
+        _ => Err(format!("specialization constant {} has unsupported type {}", name, ty))?,
+      };
+
+      constants.push(SpecConstant {
+        name,
+        id: c.spec_id,
+        ty,
+        default,
+      });
     }
+    Ok(constants)
+  }
 
-    if i.len() < 5 {
-      Err("Parse spv: missing header")?
+  /// Get the layouts of the uniform and storage blocks and of all structs nested in uniform, storage and push constant blocks
+  ///
+  /// Nested structs come before the structs they are used in. Structs with the same name and layout are only returned once.
+  pub fn get_structs(&self) -> Result<Vec<Struct>, String> {
+    let mut found = Vec::new();
+    for v in self.module.variables().map_err(|e| format!("{:?}", e))? {
+      match v.storage_class {
+        StorageClass::StorageClassUniform | StorageClass::StorageClassStorageBuffer => collect_structs(&v.ty, true, &mut found),
+        StorageClass::StorageClassPushConstant => collect_structs(&v.ty, false, &mut found),
+        _ => (),
+      }
     }
 
-    if i[0] != 0x07230203 {
-      Err("Parse spv: wrong header")?
+    let mut structs = Vec::with_capacity(found.len());
+    for s in found {
+      let (members, runtime_array) = self.get_members(&s)?;
+      let (size, align) = self.get_struct_size(&s)?;
+      let name = self.get_struct_name(&s)?;
+      if structs.iter().any(|t: &Struct| t.name == name) {
+        continue;
+      }
+      structs.push(Struct {
+        name,
+        members,
+        runtime_array,
+        size,
+        align,
+      });
     }
+    Ok(structs)
+  }
 
-    let version = (((i[1] & 0x00ff0000) >> 16) as u8, ((i[1] & 0x0000ff00) >> 8) as u8);
-
-    let instructions = {
-      let mut ret = Vec::new();
-      let mut i = &i[5..];
-      while i.len() >= 1 {
-        let (instruction, rest) = parse_instruction(i)?;
-        ret.push(instruction);
-        i = rest;
+  /// Get the members of a struct with their offsets and rust types
+  ///
+  /// If the last member is a runtime array, it is returned separately.
+  fn get_members(&self, s: &nobs_spirv::Struct) -> Result<(Vec<Member>, Option<RuntimeArray>), String> {
+    let struct_name = s.name.clone().unwrap_or(format!("struct{}", s.id));
+    let mut members = Vec::with_capacity(s.members.len());
+    let mut runtime_array = None;
+    for (m, member) in s.members.iter().enumerate() {
+      let name = member
+        .name
+        .as_ref()
+        .and_then(|n| to_identifier(n))
+        .unwrap_or(format!("member{}", m));
+      let offset = member.offset().ok_or(format!("member {}.{} has no offset", struct_name, name))?;
+      let matrix_stride = member.decorations.matrix_stride();
+      let row_major = member.decorations.row_major();
+
+      if let Type::RuntimeArray { element, stride } = &member.ty {
+        let stride = stride.ok_or(format!("member {}.{} has no array stride", struct_name, name))?;
+        let (ty, _, _) = self
+          .get_rust_type(element, matrix_stride, row_major)
+          .map_err(|e| format!("member {}.{}: {}", struct_name, name, e))?;
+        runtime_array = Some(RuntimeArray { name, offset, stride, ty });
+        continue;
       }
-      ret
-    };
 
-    Ok(Spirv {
-      version: version,
-      bound: i[3],
-      instructions: instructions,
-    })
-  }
+      let (ty, size, align) = self
+        .get_rust_type(&member.ty, matrix_stride, row_major)
+        .map_err(|e| format!("member {}.{}: {}", struct_name, name, e))?;
+      members.push(Member {
+        name,
+        offset,
+        size,
+        align,
+        ty,
+      });
+    }
 
-  pub fn get_bindings(&self, stagebit: vk::ShaderStageFlagBits) -> Vec<Binding> {
-    self.instructions.iter().fold(Vec::new(), |mut acc, i| match i {
-      Instruction::Decorate {
-        target_id,
-        decoration,
-        ref params,
-      } if *decoration == Decoration::DecorationBinding => {
-        acc.push(Binding {
-          name: match self.get_name(*target_id) {
-            Ok(name) => name,
-            Err(_) => format!("binding_{}", params[0]),
-          },
-          binding: params[0],
-          descset: match self.get_descriptor_id(*target_id) {
-            Ok(id) => id,
-            Err(_) => 0,
-          },
-          desctype: match self.get_descriptor_type(*target_id) {
-            Ok(ty) => ty,
-            Err(_) => panic!(
-              "could not find descriptor type\n\n{}",
-              self.instructions.iter().fold(String::new(), |acc, i| format!("{}\n{:?}", acc, i))
-            ),
-          },
-          arrayelems: self.get_arraylen(*target_id),
-          stageflags: stagebit,
-        });
-        acc
-      }
-      _ => acc,
-    })
+    Ok((members, runtime_array))
   }
 
-  pub fn get_decoration(&self, id: u32, kind: Decoration) -> Option<&Instruction> {
-    self.instructions.iter().find(|i| match i {
-      Instruction::Decorate { target_id, decoration, .. } if *target_id == id && *decoration == kind => true,
-      _ => false,
-    })
+  /// Get the size and alignment of a struct in bytes
+  ///
+  /// The size is padded to the array stride, if the struct is used as element of an array.
+  fn get_struct_size(&self, s: &nobs_spirv::Struct) -> Result<(u32, u32), String> {
+    let (members, _) = self.get_members(s)?;
+    let end = members.iter().map(|m| m.offset + m.size).max().unwrap_or(0);
+    let align = members.iter().map(|m| m.align).max().unwrap_or(1);
+    let stride = self
+      .module
+      .instructions
+      .iter()
+      .filter_map(|i| match i {
+        Instruction::TypeArray { result_id, type_id, .. } if *type_id == s.id => self.module.decorations(*result_id).array_stride(),
+        _ => None,
+      })
+      .max()
+      .unwrap_or(0);
+    Ok((block::align_up(u32::max(end, stride), align), align))
   }
 
-  pub fn get_name(&self, id: u32) -> Result<String, ()> {
-    let name_of_id = |id| {
-      let opname = self.instructions.iter().find(|i| match i {
-        Instruction::Name { target_id, .. } if *target_id == id => true,
+  /// Get the name of the rust struct for a struct
+  ///
+  /// Structs with the same name, e.g. the same glsl struct used with std140 and std430 layout, and unnamed structs
+  /// get the [layout hash](../block/fn.layout_hash.html) as suffix, so that the name does not depend on the SPIR-V ids.
+  /// Names, that are rust keywords, are made an identifier with [ident](../block/fn.ident.html).
+  fn get_struct_name(&self, s: &nobs_spirv::Struct) -> Result<String, String> {
+    let name = s.name.as_ref().and_then(|n| to_identifier(n));
+    let duplicate = name.is_none()
+      || self.module.instructions.iter().any(|i| match i {
+        Instruction::TypeStruct { result_id, .. } => *result_id != s.id && self.module.name(*result_id).and_then(to_identifier) == name,
         _ => false,
       });
+    match duplicate {
+      true => {
+        let (members, runtime_array) = self.get_members(s)?;
+        let (size, _) = self.get_struct_size(s)?;
+        let hash = block::layout_hash(&members, &runtime_array, size);
+        Ok(format!("{}_{:08x}", name.unwrap_or_else(|| "struct".to_string()), hash))
+      }
+      false => Ok(block::ident(&name.unwrap())),
+    }
+  }
+
+  /// Get the input variables with a location, sorted by location
+  ///
+  /// Built-in inputs are skipped.
+  pub fn get_inputs(&self) -> Result<Vec<Input>, String> {
+    let mut inputs = Vec::new();
+    for v in self.module.inputs().map_err(|e| format!("{:?}", e))? {
+      let location = match v.decorations.location() {
+        Some(location) => location,
+        None => continue,
+      };
+
+      let name = v
+        .name
+        .as_ref()
+        .and_then(|n| to_identifier(n))
+        .unwrap_or(format!("location{}", location));
+      if v.decorations.contains(Decoration::DecorationComponent) {
+        Err(format!(
+          "input {} at location {} has a component decoration, inputs that share a location are not supported",
+          name, location
+        ))?;
+      }
+      let (ty, _, attributes) = self.get_input_type(&v.ty).map_err(|e| format!("input {}: {}", name, e))?;
+      inputs.push(Input {
+        name,
+        location,
+        ty,
+        attributes,
+      });
+    }
+    inputs.sort_by_key(|i| i.location);
+    Ok(inputs)
+  }
+
+  /// Get the rust type, size in bytes and vertex attributes of an input
+  ///
+  /// Matrices have an attribute for every column and arrays for every element.
+  fn get_input_type(&self, ty: &Type) -> Result<(String, u32, Vec<Attribute>), String> {
+    // number of locations of the attributes, 64 bit vectors with three or four components take two locations
+    let locations = |attributes: &[Attribute]| {
+      attributes
+        .iter()
+        .map(|a| match a.format {
+          vk::FORMAT_R64G64B64_UINT..=vk::FORMAT_R64G64B64A64_SFLOAT => 2,
+          _ => 1,
+        })
+        .sum::<u32>()
+    };
 
-      match opname {
-        Some(Instruction::Name { ref name, .. }) if !name.is_empty() => Ok(name.to_string()),
-        _ => Err(()),
+    let (elem, len) = match ty {
+      Type::Bool | Type::Int { .. } | Type::Float { .. } | Type::Vector { .. } => {
+        let (scalar, size) = self.get_scalar(ty).ok_or("unsupported scalar type")?;
+        let count = match ty {
+          Type::Vector { count, .. } => *count,
+          _ => 1,
+        };
+        let format = vertex_input::get_format(&scalar[..1], size * 8, count)?;
+        let ty = match count {
+          1 => scalar,
+          _ => format!("[{}; {}]", scalar, count),
+        };
+        return Ok((
+          ty,
+          size * count,
+          vec![Attribute {
+            location: 0,
+            format,
+            offset: 0,
+          }],
+        ));
       }
+      Type::Matrix { column, columns } => (column, *columns),
+      Type::Array { spec_id: Some(_), .. } => Err("array length is not a constant")?,
+      Type::Array { element, len, .. } => (element, *len),
+      ty => Err(format!("unsupported input type {:?}", ty))?,
     };
 
-    let n = name_of_id(id);
+    let (ty, size, attributes) = self.get_input_type(elem)?;
+    let n = locations(&attributes);
+    let attributes = (0..len)
+      .flat_map(|k| {
+        attributes.iter().map(move |a| Attribute {
+          location: a.location + k * n,
+          format: a.format,
+          offset: a.offset + k * size,
+        })
+      })
+      .collect();
+    Ok((format!("[{}; {}]", ty, len), size * len, attributes))
+  }
 
-    if n.is_err() {
-      name_of_id(self.get_pointet_ty(id))
-    } else {
-      n
+  /// Get the scalar type and size in bytes of a scalar or vector type
+  fn get_scalar(&self, ty: &Type) -> Option<(String, u32)> {
+    match ty {
+      Type::Vector { component, .. } => self.get_scalar(component),
+      Type::Bool | Type::Int { .. } | Type::Float { .. } => self.get_rust_type(ty, None, false).ok().map(|(ty, size, _)| (ty, size)),
+      _ => None,
     }
   }
 
-  pub fn get_descriptor_id(&self, id: u32) -> Result<u32, ()> {
-    match self.get_decoration(id, Decoration::DecorationDescriptorSet) {
-      Some(Instruction::Decorate { ref params, .. }) => Ok(params[0]),
-      _ => Err(()),
+  /// Get the rust type, size and alignment in bytes of a type in a uniform, storage or push constant block
+  ///
+  /// Matrices are written as array of columns, or as array of rows if they are decorated row major.
+  /// Structs are referenced in the module `blocks`.
+  /// Arrays of scalars and vectors with an array stride larger than the element, e.g. `float[]` with std140 layout,
+  /// are written as arrays of padded arrays.
+  fn get_rust_type(&self, ty: &Type, matrix_stride: Option<u32>, row_major: bool) -> Result<(String, u32, u32), String> {
+    match ty {
+      Type::Bool => Ok(("u32".to_string(), 4, 4)),
+      Type::Int { width, signed } => match width {
+        16 | 32 | 64 => Ok((format!("{}{}", if *signed { "i" } else { "u" }, width), width / 8, width / 8)),
+        _ => Err(format!("unsupported integer width {}", width)),
+      },
+      Type::Float { width } => match width {
+        32 | 64 => Ok((format!("f{}", width), width / 8, width / 8)),
+        _ => Err(format!("unsupported float width {}", width)),
+      },
+      Type::Vector { component, count } => {
+        let (ty, size, align) = self.get_rust_type(component, None, false)?;
+        Ok((format!("[{}; {}]", ty, count), size * count, align))
+      }
+      Type::Matrix { column, columns } => {
+        let (rows, component) = match column.as_ref() {
+          Type::Vector { component, count } => (*count, component),
+          _ => Err("matrix column is not a vector")?,
+        };
+        let (ty, size, align) = self.get_rust_type(component, None, false)?;
+        let stride = matrix_stride.ok_or("matrix has no matrix stride")?;
+        let count = if row_major { rows } else { *columns };
+        Ok((format!("[[{}; {}]; {}]", ty, stride / size, count), stride * count, align))
+      }
+      Type::Array { spec_id: Some(_), .. } => Err("array length is not a constant")?,
+      Type::Array { element, len, stride, .. } => {
+        let stride = stride.ok_or("array has no array stride")?;
+        let (ty, size, align) = self.get_rust_type(element, matrix_stride, row_major)?;
+        let ty = match (stride == size, self.get_scalar(element)) {
+          (true, _) => ty,
+          (false, Some((scalar, scalar_size))) if stride > size && stride % scalar_size == 0 => {
+            format!("[{}; {}]", scalar, stride / scalar_size)
+          }
+          _ => Err(format!("array stride {} does not match the element size {}", stride, size))?,
+        };
+        Ok((format!("[{}; {}]", ty, len), stride * len, align))
+      }
+      Type::RuntimeArray { .. } => Err("runtime arrays are only supported as last member of a block")?,
+      Type::Struct(s) => {
+        let (size, align) = self.get_struct_size(s)?;
+        Ok((format!("blocks::{}", self.get_struct_name(s)?), size, align))
+      }
+      ty => Err(format!("unsupported type {:?}", ty)),
     }
   }
+}
 
-  pub fn get_descriptor_type(&self, id: u32) -> Result<vk::DescriptorType, ()> {
-    fn get_type_recursive(spirv: &Spirv, id: u32, combined_sampler: bool) -> Result<vk::DescriptorType, ()> {
-      for i in spirv.instructions.iter() {
-        match i {
-          Instruction::TypeStruct { result_id, .. } if *result_id == id => {
-            let is_buffer_block = spirv.get_decoration(id, Decoration::DecorationBufferBlock).is_some();
-            let is_block = spirv.get_decoration(id, Decoration::DecorationBlock).is_some();
-
-            if is_buffer_block == is_block {
-              return Err(());
-            } else if is_buffer_block && !is_block {
-              return Ok(vk::DESCRIPTOR_TYPE_STORAGE_BUFFER);
-            } else {
-              return Ok(vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER);
-            }
-          }
-          Instruction::TypeImage {
-            result_id,
-            ref dim,
-            sampled,
-            ..
-          } if *result_id == id => {
-            let sampled = sampled.expect("OpTypeImage needs to have a Sampled operand of 1 or 2");
-
-            match dim {
-              Dim::DimSubpassData => return Err(()),
-              Dim::DimBuffer => return Ok(vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER),
-              _ => {
-                if combined_sampler {
-                  return Ok(vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER);
-                } else if sampled {
-                  return Ok(vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE);
-                } else {
-                  return Ok(vk::DESCRIPTOR_TYPE_STORAGE_IMAGE);
-                }
-              }
-            }
-          }
-          Instruction::TypeSampledImage { result_id, image_type_id } if *result_id == id => {
-            return get_type_recursive(spirv, *image_type_id, true);
-          }
-          Instruction::TypePointer { result_id, type_id, .. } if *result_id == id => {
-            return get_type_recursive(spirv, *type_id, combined_sampler);
-          }
-          Instruction::TypeArray { result_id, type_id, .. } if *result_id == id => {
-            return get_type_recursive(spirv, *type_id, combined_sampler);
-          }
-          Instruction::TypeSampler { result_id, .. } if *result_id == id => return Ok(vk::DESCRIPTOR_TYPE_SAMPLER),
-          _ => (),
-          // TODO: maybe more things in the future
-        }
+/// Collects all structs used in a type, including the type itself if `include` is set
+fn collect_structs(ty: &Type, include: bool, found: &mut Vec<nobs_spirv::Struct>) {
+  match ty {
+    Type::Struct(s) => {
+      for m in s.members.iter() {
+        collect_structs(&m.ty, true, found);
+      }
+      if include && !found.iter().any(|f| f.id == s.id) {
+        found.push(s.clone());
       }
+    }
+    Type::Array { element, .. } | Type::RuntimeArray { element, .. } => collect_structs(element, include, found),
+    _ => (),
+  }
+}
 
-      Err(())
-    };
+fn get_descriptor_type(kind: DescriptorKind) -> vk::DescriptorType {
+  match kind {
+    DescriptorKind::Sampler => vk::DESCRIPTOR_TYPE_SAMPLER,
+    DescriptorKind::CombinedImageSampler => vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+    DescriptorKind::SampledImage => vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE,
+    DescriptorKind::StorageImage => vk::DESCRIPTOR_TYPE_STORAGE_IMAGE,
+    DescriptorKind::UniformTexelBuffer => vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER,
+    DescriptorKind::StorageTexelBuffer => vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER,
+    DescriptorKind::UniformBuffer => vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER,
+    DescriptorKind::StorageBuffer => vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
+    DescriptorKind::InputAttachment => vk::DESCRIPTOR_TYPE_INPUT_ATTACHMENT,
+  }
+}
 
-    get_type_recursive(&self, self.get_pointet_ty(id), false)
+/// Converts a debug name into a valid rust identifier
+///
+/// Names generated by hlsl compilers contain characters, that are not allowed in identifiers,
+/// e.g. `type.StructuredBuffer.Light`, `in.var.POSITION`, `$Globals` or `@data`.
+/// Prefixes of types and stage inputs are removed, the remaining parts are joined with '_'.
+/// Returns `None` for empty names.
+fn to_identifier(name: &str) -> Option<String> {
+  let name = ["type.", "in.var.", "out.var."]
+    .iter()
+    .find(|p| name.starts_with(*p))
+    .map(|p| &name[p.len()..])
+    .unwrap_or(name);
+
+  let ident = name
+    .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+    .filter(|s| !s.is_empty())
+    .collect::<Vec<_>>()
+    .join("_");
+
+  match ident.chars().next() {
+    None => None,
+    Some(c) if c.is_ascii_digit() => Some(format!("_{}", ident)),
+    Some(_) => Some(ident),
   }
+}
 
-  pub fn get_arraylen(&self, id: u32) -> u32 {
-    let ptrty_id = self.get_pointet_ty(id);
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-    match self
-      .instructions
+  fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+      .chunks(4)
+      .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+      .collect()
+  }
+
+  fn spirv(bytes: &[u8]) -> Spirv {
+    Spirv::from_binary(&words(bytes)).unwrap()
+  }
+
+  #[test]
+  fn spec_constant_defaults() {
+    let compute = include_bytes!("../../../nobs-spirv/fixtures/compute.spv");
+    let constants = spirv(compute).get_spec_constants().unwrap();
+    let defaults = constants
       .iter()
-      .filter_map(|i| match i {
-        &Instruction::TypeArray { result_id, length_id, .. } if result_id == ptrty_id => Some(length_id),
-        _ => None,
-      })
-      .next()
-      .and_then(|len_id| {
-        self
-          .instructions
-          .iter()
-          .filter_map(|i| match i {
-            &Instruction::Constant { result_id, ref data, .. } if result_id == len_id => Some(data[0]),
-            _ => None,
-          })
-          .next()
-      }) {
-      Some(len) => len,
-      None => 1,
+      .map(|c| (c.name.as_str(), c.ty.as_str(), c.default.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      defaults,
+      vec![("count", "u32", "16"), ("enabled", "bool", "true"), ("scale", "f32", "0.5")]
+    );
+
+    // strip the default value of the first OpSpecConstant
+    let mut words = words(compute);
+    let mut i = 5;
+    while words[i] & 0xffff != 50 {
+      i += (words[i] >> 16) as usize;
     }
+    let len = (words[i] >> 16) as usize;
+    words.remove(i + len - 1);
+    words[i] -= 1 << 16;
+
+    let err = Spirv::from_binary(&words).unwrap().get_spec_constants().unwrap_err();
+    assert!(err.contains("specialization constant count has no default value"));
   }
 
-  pub fn get_pointet_ty(&self, id: u32) -> u32 {
-    let var_ty = self
-      .instructions
-      .iter()
-      .filter_map(|i| match i {
-        &Instruction::Variable {
-          result_type_id, result_id, ..
-        } if result_id == id => Some(result_type_id),
-        _ => None,
-      })
-      .next()
+  fn find<'a>(bindings: &'a [Binding], name: &str) -> &'a Binding {
+    bindings.iter().find(|b| b.name == name).unwrap()
+  }
+
+  #[test]
+  fn identifiers() {
+    assert_eq!(to_identifier("color"), Some("color".to_string()));
+    assert_eq!(
+      to_identifier("type.StructuredBuffer.Light"),
+      Some("StructuredBuffer_Light".to_string())
+    );
+    assert_eq!(to_identifier("in.var.POSITION"), Some("POSITION".to_string()));
+    assert_eq!(to_identifier("out.var.SV_Target"), Some("SV_Target".to_string()));
+    assert_eq!(to_identifier("$Globals"), Some("Globals".to_string()));
+    assert_eq!(to_identifier("counter.var.counters"), Some("counter_var_counters".to_string()));
+    assert_eq!(to_identifier("@data"), Some("data".to_string()));
+    assert_eq!(to_identifier("0uv"), Some("_0uv".to_string()));
+    assert_eq!(to_identifier(""), None);
+    assert_eq!(to_identifier("$."), None);
+  }
+
+  #[test]
+  fn hlsl_bindings() {
+    let bindings = spirv(include_bytes!("../../../nobs-spirv/fixtures/hlsl.spv"))
+      .get_bindings(vk::SHADER_STAGE_FRAGMENT_BIT)
       .unwrap();
+    assert_eq!(bindings.len(), 5);
+
+    // structured buffers live in the storage buffer storage class
+    let lights = find(&bindings, "lights");
+    assert_eq!((lights.descset, lights.binding), (1, 0));
+    assert_eq!(lights.desctype, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER);
+    assert_eq!(lights.arrayelems, 1);
+    assert_eq!(find(&bindings, "counters").desctype, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER);
+    assert_eq!(find(&bindings, "counter_var_counters").desctype, vk::DESCRIPTOR_TYPE_STORAGE_BUFFER);
+
+    // the global constant buffer is a uniform buffer
+    let globals = find(&bindings, "Globals");
+    assert_eq!((globals.descset, globals.binding), (0, 2));
+    assert_eq!(globals.desctype, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER);
+
+    // typed buffers are texel buffers
+    let texels = find(&bindings, "texels");
+    assert_eq!((texels.descset, texels.binding), (0, 4));
+    assert_eq!(texels.desctype, vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER);
+  }
 
-    self
-      .instructions
-      .iter()
-      .filter_map(|i| match i {
-        &Instruction::TypePointer { result_id, type_id, .. } if result_id == var_ty => Some(type_id),
-        _ => None,
-      })
-      .next()
-      .unwrap()
+  #[test]
+  fn image_bindings() {
+    let bindings = spirv(include_bytes!("../../../nobs-spirv/fixtures/images.spv"))
+      .get_bindings(vk::SHADER_STAGE_FRAGMENT_BIT)
+      .unwrap();
+    assert_eq!(find(&bindings, "tex").desctype, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER);
+    assert_eq!(find(&bindings, "separate").desctype, vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE);
+    assert_eq!(find(&bindings, "samp").desctype, vk::DESCRIPTOR_TYPE_SAMPLER);
+    assert_eq!(find(&bindings, "img").desctype, vk::DESCRIPTOR_TYPE_STORAGE_IMAGE);
+    assert_eq!(find(&bindings, "texels").desctype, vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER);
+    assert_eq!(find(&bindings, "storage_texels").desctype, vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER);
+    assert_eq!(find(&bindings, "attachment").desctype, vk::DESCRIPTOR_TYPE_INPUT_ATTACHMENT);
+
+    // runtime arrays
+    let textures = find(&bindings, "textures");
+    assert_eq!(textures.arrayelems, 0);
+    assert_eq!(textures.flags, binding::RUNTIME_ARRAY_FLAGS);
+  }
+
+  #[test]
+  fn merge_bindings() {
+    let frag = spirv(include_bytes!("../../../nobs-spirv/fixtures/images.spv"))
+      .get_bindings(vk::SHADER_STAGE_FRAGMENT_BIT)
+      .unwrap();
+
+    // the runtime array at binding 0 of set 1 does not collide with `tex` at binding 0 of set 0
+    let mut bindings = Vec::new();
+    for b in frag.iter() {
+      Binding::merge(&mut bindings, b, vk::SHADER_STAGE_FRAGMENT_BIT).unwrap();
+    }
+    assert_eq!(bindings.len(), frag.len());
+    assert_eq!((find(&bindings, "tex").descset, find(&bindings, "tex").binding), (0, 0));
+    assert_eq!((find(&bindings, "textures").descset, find(&bindings, "textures").binding), (1, 0));
+
+    // the same bindings in another stage are merged
+    for b in frag.iter() {
+      Binding::merge(&mut bindings, b, vk::SHADER_STAGE_VERTEX_BIT).unwrap();
+    }
+    assert_eq!(bindings.len(), frag.len());
+    assert_eq!(
+      find(&bindings, "textures").stageflags,
+      vk::SHADER_STAGE_FRAGMENT_BIT | vk::SHADER_STAGE_VERTEX_BIT
+    );
+
+    // a different name at the same location collides
+    let mut renamed = find(&frag, "textures").clone();
+    renamed.name = "images".to_string();
+    assert!(Binding::merge(&mut bindings, &renamed, vk::SHADER_STAGE_VERTEX_BIT).is_err());
+
+    // the same name at a different binding of the same set collides
+    let mut moved = find(&frag, "textures").clone();
+    moved.binding = 7;
+    assert!(Binding::merge(&mut bindings, &moved, vk::SHADER_STAGE_VERTEX_BIT).is_err());
+
+    // runtime arrays with a different length in another stage would end up twice in the set layout
+    let mut stage = frag.clone();
+    Binding::set_runtime_array_len(&mut stage, 1024);
+    assert!(Binding::merge(&mut bindings, find(&stage, "textures"), vk::SHADER_STAGE_COMPUTE_BIT).is_err());
+    let mut retyped = find(&frag, "tex").clone();
+    retyped.desctype = vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE;
+    assert!(Binding::merge(&mut bindings, &retyped, vk::SHADER_STAGE_COMPUTE_BIT).is_err());
+    assert_eq!(bindings.len(), frag.len());
+
+    // the same name in another descriptor set is a different binding
+    let mut other = find(&frag, "tex").clone();
+    other.descset = 2;
+    Binding::merge(&mut bindings, &other, vk::SHADER_STAGE_VERTEX_BIT).unwrap();
+    assert_eq!(bindings.len(), frag.len() + 1);
+    assert_eq!(bindings.iter().filter(|b| b.name == "tex").count(), 2);
+
+    // which makes an immutable sampler for the name ambiguous
+    let err = Binding::set_immutable_sampler(&mut bindings, "tex").unwrap_err();
+    assert!(err.contains("descriptor set 0 and 2"));
   }
 }
@@ -0,0 +1,375 @@
+use block;
+use std::collections::HashMap;
+use vk;
+
+/// Attribute of a vertex shader input
+///
+/// Location and offset are relative to the location and offset of the input.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attribute {
+  pub location: u32,
+  pub format: vk::Format,
+  pub offset: u32,
+}
+
+/// Input variable of a vertex shader
+///
+/// Matrices and arrays occupy several consecutive locations, with one attribute for every column or element.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Input {
+  pub name: String,
+  pub location: u32,
+  pub ty: String,
+  pub attributes: Vec<Attribute>,
+}
+
+/// Get the attribute format for a vector of `count` scalars
+///
+/// `kind` is one of "u", "i", "f" for unsigned, signed and floating point scalars.
+pub fn get_format(kind: &str, width: u32, count: u32) -> Result<vk::Format, String> {
+  let formats = match width {
+    32 => [
+      [vk::FORMAT_R32_UINT, vk::FORMAT_R32_SINT, vk::FORMAT_R32_SFLOAT],
+      [vk::FORMAT_R32G32_UINT, vk::FORMAT_R32G32_SINT, vk::FORMAT_R32G32_SFLOAT],
+      [vk::FORMAT_R32G32B32_UINT, vk::FORMAT_R32G32B32_SINT, vk::FORMAT_R32G32B32_SFLOAT],
+      [
+        vk::FORMAT_R32G32B32A32_UINT,
+        vk::FORMAT_R32G32B32A32_SINT,
+        vk::FORMAT_R32G32B32A32_SFLOAT,
+      ],
+    ],
+    64 => [
+      [vk::FORMAT_R64_UINT, vk::FORMAT_R64_SINT, vk::FORMAT_R64_SFLOAT],
+      [vk::FORMAT_R64G64_UINT, vk::FORMAT_R64G64_SINT, vk::FORMAT_R64G64_SFLOAT],
+      [vk::FORMAT_R64G64B64_UINT, vk::FORMAT_R64G64B64_SINT, vk::FORMAT_R64G64B64_SFLOAT],
+      [
+        vk::FORMAT_R64G64B64A64_UINT,
+        vk::FORMAT_R64G64B64A64_SINT,
+        vk::FORMAT_R64G64B64A64_SFLOAT,
+      ],
+    ],
+    _ => Err(format!("unsupported vertex attribute width {}", width))?,
+  };
+
+  let k = match kind {
+    "u" => 0,
+    "i" => 1,
+    "f" => 2,
+    _ => Err(format!("unsupported vertex attribute type {}", kind))?,
+  };
+
+  match count {
+    1..=4 => Ok(formats[count as usize - 1][k]),
+    _ => Err(format!("unsupported vertex attribute component count {}", count)),
+  }
+}
+
+/// Vertex binding with the inputs, that are interleaved in the binding
+#[derive(Debug, Clone)]
+pub struct VertexBinding {
+  pub binding: u32,
+  pub rate: vk::VertexInputRate,
+  pub inputs: Vec<Input>,
+}
+
+impl VertexBinding {
+  /// Maps the inputs to vertex bindings
+  ///
+  /// `locations` lists the locations of the inputs for every binding, with the order of the members in the vertex struct.
+  /// If no bindings are specified, all inputs are interleaved in binding 0.
+  pub fn from_inputs(
+    inputs: &[Input],
+    locations: &HashMap<u32, Vec<u32>>,
+    rates: &HashMap<u32, vk::VertexInputRate>,
+  ) -> Result<Vec<VertexBinding>, String> {
+    if inputs.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let mut locations = locations.clone();
+    if locations.is_empty() {
+      let mut l = inputs.iter().map(|i| i.location).collect::<Vec<_>>();
+      l.sort();
+      locations.insert(0, l);
+    }
+
+    let mut bindings = locations
+      .iter()
+      .map(|(binding, locations)| {
+        let inputs = locations
+          .iter()
+          .map(|l| {
+            inputs.iter().find(|i| i.location == *l).cloned().ok_or(format!(
+              "vertex binding {} lists location {}, that is not a vertex shader input",
+              binding, l
+            ))
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(VertexBinding {
+          binding: *binding,
+          rate: *rates.get(binding).unwrap_or(&vk::VERTEX_INPUT_RATE_VERTEX),
+          inputs,
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    bindings.sort_by_key(|b| b.binding);
+
+    for i in inputs.iter() {
+      match bindings
+        .iter()
+        .flat_map(|b| b.inputs.iter())
+        .filter(|bi| bi.location == i.location)
+        .count()
+      {
+        0 => Err(format!(
+          "vertex shader input {} at location {} is not mapped to a vertex binding",
+          i.name, i.location
+        ))?,
+        1 => (),
+        _ => Err(format!(
+          "vertex shader input {} at location {} is mapped to several vertex bindings",
+          i.name, i.location
+        ))?,
+      }
+    }
+
+    Ok(bindings)
+  }
+
+  pub fn get_name(&self, single: bool) -> String {
+    match single {
+      true => "Vertex".to_string(),
+      false => format!("Vertex{}", self.binding),
+    }
+  }
+
+  pub fn to_struct_string(&self, name: &str) -> String {
+    format!(
+      "
+      /// Vertex of binding {binding}
+      #[repr(C)]
+      #[derive(Debug, Clone, Copy)]
+      pub struct {name} {{
+        {fields}
+      }}
+
+      impl Default for {name} {{
+        fn default() -> Self {{
+          unsafe {{ std::mem::zeroed() }}
+        }}
+      }}
+      ",
+      binding = self.binding,
+      name = name,
+      fields = self
+        .inputs
+        .iter()
+        .fold(String::new(), |acc, i| format!("{}pub {}: {},\n", acc, block::ident(&i.name), i.ty)),
+    )
+  }
+
+  pub fn to_builder_string(&self, name: &str) -> String {
+    let attributes = self.inputs.iter().fold(String::new(), |acc, i| {
+      i.attributes.iter().fold(acc, |acc, a| {
+        format!(
+          "{acc}.push_attribute(AttributeBuilder::default().binding({binding}).location({location}).format({format}).offset(std::mem::offset_of!({name}, {field}) as u32 + {offset}))\n",
+          acc = acc,
+          binding = self.binding,
+          location = i.location + a.location,
+          format = a.format,
+          name = name,
+          field = block::ident(&i.name),
+          offset = a.offset,
+        )
+      })
+    });
+
+    format!(
+      ".push_binding(BindingBuilder::default().binding({binding}).stride(std::mem::size_of::<{name}>() as u32).input_rate({rate}))\n{attributes}",
+      binding = self.binding,
+      name = name,
+      rate = self.rate,
+      attributes = attributes,
+    )
+  }
+}
+
+/// Writes the module `vertices` with a vertex struct for every binding and the vertex input state with all bindings and attributes
+pub fn write_vertices(bindings: &[VertexBinding], vkpipes_alias: &str) -> String {
+  if bindings.is_empty() {
+    return String::new();
+  }
+
+  let single = bindings.len() == 1;
+  format!(
+    "
+    pub mod vertices {{
+      use {vkpipes_alias}::pipeline::builder::graphics::vertex_input::AttributeBuilder;
+      use {vkpipes_alias}::pipeline::builder::graphics::vertex_input::BindingBuilder;
+      use {vkpipes_alias}::VertexInput;
+
+      {structs}
+
+      /// Creates the vertex input state with the vertex bindings and attributes of the vertex shader
+      pub fn vertex_input() -> VertexInput {{
+        VertexInput::default()
+        {builder}
+      }}
+    }}
+    ",
+    vkpipes_alias = vkpipes_alias,
+    structs = bindings.iter().fold(String::new(), |acc, b| format!(
+      "{}{}",
+      acc,
+      b.to_struct_string(&b.get_name(single))
+    )),
+    builder = bindings.iter().fold(String::new(), |acc, b| format!(
+      "{}{}",
+      acc,
+      b.to_builder_string(&b.get_name(single))
+    )),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nobs_spirv::enums::Decoration;
+  use nobs_spirv::Instruction;
+  use spirv::Spirv;
+
+  fn spirv() -> Spirv {
+    let bytes = include_bytes!("../../../nobs-spirv/fixtures/inputs.spv");
+    let words = bytes
+      .chunks(4)
+      .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+      .collect::<Vec<_>>();
+    Spirv::from_binary(&words).unwrap()
+  }
+
+  fn attribute(location: u32, format: vk::Format, offset: u32) -> Attribute {
+    Attribute { location, format, offset }
+  }
+
+  #[test]
+  fn format() {
+    assert_eq!(get_format("f", 32, 1).unwrap(), vk::FORMAT_R32_SFLOAT);
+    assert_eq!(get_format("u", 32, 2).unwrap(), vk::FORMAT_R32G32_UINT);
+    assert_eq!(get_format("i", 32, 4).unwrap(), vk::FORMAT_R32G32B32A32_SINT);
+    assert_eq!(get_format("f", 64, 3).unwrap(), vk::FORMAT_R64G64B64_SFLOAT);
+    assert_eq!(get_format("u", 64, 4).unwrap(), vk::FORMAT_R64G64B64A64_UINT);
+    assert!(get_format("f", 16, 1).is_err());
+    assert!(get_format("b", 32, 1).is_err());
+    assert!(get_format("f", 32, 0).is_err());
+    assert!(get_format("f", 32, 5).is_err());
+  }
+
+  #[test]
+  fn locations() {
+    let inputs = spirv().get_inputs().unwrap();
+    assert_eq!(
+      inputs
+        .iter()
+        .map(|i| (i.name.as_str(), i.location, i.ty.as_str()))
+        .collect::<Vec<_>>(),
+      vec![
+        ("position", 0, "[f64; 3]"),
+        ("weights", 2, "[[f64; 4]; 2]"),
+        ("normal", 6, "[[f32; 3]; 3]"),
+        ("uv", 9, "[f32; 2]"),
+      ]
+    );
+
+    // 64 bit vectors with three or four components take two locations
+    assert_eq!(inputs[0].attributes, vec![attribute(0, vk::FORMAT_R64G64B64_SFLOAT, 0)]);
+    assert_eq!(
+      inputs[1].attributes,
+      vec![
+        attribute(0, vk::FORMAT_R64G64B64A64_SFLOAT, 0),
+        attribute(2, vk::FORMAT_R64G64B64A64_SFLOAT, 32),
+      ]
+    );
+    // matrices have an attribute for every column
+    assert_eq!(
+      inputs[2].attributes,
+      vec![
+        attribute(0, vk::FORMAT_R32G32B32_SFLOAT, 0),
+        attribute(1, vk::FORMAT_R32G32B32_SFLOAT, 12),
+        attribute(2, vk::FORMAT_R32G32B32_SFLOAT, 24),
+      ]
+    );
+    assert_eq!(inputs[3].attributes, vec![attribute(0, vk::FORMAT_R32G32_SFLOAT, 0)]);
+  }
+
+  #[test]
+  fn component() {
+    let mut spirv = spirv();
+    let uv = spirv
+      .module
+      .instructions
+      .iter()
+      .find_map(|i| match i {
+        Instruction::Name { target_id, name } if name == "uv" => Some(*target_id),
+        _ => None,
+      })
+      .unwrap();
+    spirv.module.instructions.push(Instruction::Decorate {
+      target_id: uv,
+      decoration: Decoration::DecorationComponent,
+      params: vec![2],
+    });
+    assert!(spirv.get_inputs().unwrap_err().contains("component decoration"));
+  }
+
+  #[test]
+  fn bindings() {
+    let inputs = spirv().get_inputs().unwrap();
+
+    let bindings = VertexBinding::from_inputs(&inputs, &Default::default(), &Default::default()).unwrap();
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[0].binding, 0);
+    assert_eq!(bindings[0].rate, vk::VERTEX_INPUT_RATE_VERTEX);
+    assert_eq!(bindings[0].inputs, inputs);
+
+    let mut locations = HashMap::new();
+    locations.insert(1, vec![6, 2]);
+    locations.insert(0, vec![9, 0]);
+    let mut rates = HashMap::new();
+    rates.insert(1, vk::VERTEX_INPUT_RATE_INSTANCE);
+    let bindings = VertexBinding::from_inputs(&inputs, &locations, &rates).unwrap();
+    assert_eq!(
+      bindings
+        .iter()
+        .map(|b| (b.binding, b.rate, b.inputs.iter().map(|i| i.location).collect::<Vec<_>>()))
+        .collect::<Vec<_>>(),
+      vec![
+        (0, vk::VERTEX_INPUT_RATE_VERTEX, vec![9, 0]),
+        (1, vk::VERTEX_INPUT_RATE_INSTANCE, vec![6, 2]),
+      ]
+    );
+    assert_eq!(bindings[1].get_name(false), "Vertex1");
+    assert!(bindings[1]
+      .to_builder_string("Vertex1")
+      .contains(".location(4).format(121).offset(std::mem::offset_of!(Vertex1, weights) as u32 + 32)"));
+
+    let mut missing = HashMap::new();
+    missing.insert(0, vec![0, 2, 6]);
+    assert!(VertexBinding::from_inputs(&inputs, &missing, &rates)
+      .unwrap_err()
+      .contains("uv at location 9 is not mapped"));
+
+    let mut twice = locations.clone();
+    twice.insert(2, vec![9]);
+    assert!(VertexBinding::from_inputs(&inputs, &twice, &rates)
+      .unwrap_err()
+      .contains("mapped to several vertex bindings"));
+
+    let mut unknown = locations.clone();
+    unknown.insert(2, vec![1]);
+    assert!(VertexBinding::from_inputs(&inputs, &unknown, &rates)
+      .unwrap_err()
+      .contains("lists location 1, that is not a vertex shader input"));
+
+    assert!(VertexBinding::from_inputs(&[], &locations, &rates).unwrap().is_empty());
+  }
+}
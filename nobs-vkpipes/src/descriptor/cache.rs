@@ -0,0 +1,396 @@
+use crate::pipeline::Binding;
+use crate::DescriptorLayout;
+use crate::Error;
+use vk;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Identifies a descriptor set layout by its bindings
+///
+/// Names and the set index of the bindings do not contribute to the layout, bindings are sorted by their binding point.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct LayoutKey(
+  Vec<(u32, vk::DescriptorType, u32, vk::ShaderStageFlags, vk::DescriptorBindingFlagsEXT)>,
+  Vec<(u32, vk::Sampler)>,
+);
+
+impl LayoutKey {
+  fn from_bindings(bindings: &[Binding], samplers: &[(u32, vk::Sampler)]) -> Self {
+    let mut key = bindings
+      .iter()
+      .map(|b| (b.binding, b.desctype, b.arrayelems, b.stageflags, b.flags))
+      .collect::<Vec<_>>();
+    key.sort();
+    let mut samplers = samplers.to_vec();
+    samplers.sort();
+    LayoutKey(key, samplers)
+  }
+}
+
+/// Creates a descriptor set layout with immutable samplers
+type CreateLayout = fn(vk::Device, &[Binding], &[(u32, vk::Sampler)]) -> DescriptorLayout;
+
+/// Destroys a descriptor set layout
+type DestroyLayout = fn(vk::Device, vk::DescriptorSetLayout);
+
+fn destroy_layout(device: vk::Device, layout: vk::DescriptorSetLayout) {
+  vk::DestroyDescriptorSetLayout(device, layout, std::ptr::null());
+}
+
+#[derive(Debug)]
+struct CacheImpl {
+  device: vk::Device,
+  create: CreateLayout,
+  destroy: DestroyLayout,
+  layouts: HashMap<LayoutKey, (DescriptorLayout, usize)>,
+  handles: HashMap<vk::DescriptorSetLayout, LayoutKey>,
+  declared: HashMap<u32, DescriptorLayout>,
+}
+
+impl Drop for CacheImpl {
+  fn drop(&mut self) {
+    for (l, _) in self.layouts.values() {
+      (self.destroy)(self.device, l.layout);
+    }
+  }
+}
+
+impl CacheImpl {
+  fn acquire(&mut self, bindings: &[Binding], samplers: &[(u32, vk::Sampler)]) -> DescriptorLayout {
+    let key = LayoutKey::from_bindings(bindings, samplers);
+    if let Some((l, refs)) = self.layouts.get_mut(&key) {
+      *refs += 1;
+      return l.clone();
+    }
+
+    let l = (self.create)(self.device, bindings, samplers);
+    self.handles.insert(l.layout, key.clone());
+    self.layouts.insert(key, (l.clone(), 1));
+    l
+  }
+}
+
+/// Cache for descriptor set layouts
+///
+/// Pipelines, that are created with the same cache (see [ComputeBuilder::layout_cache](../struct.ComputeBuilder.html#method.layout_cache)
+/// and [GraphicsBuilder::layout_cache](../struct.GraphicsBuilder.html#method.layout_cache)), share one `vk::DescriptorSetLayout` for
+/// descriptor sets with identical bindings.
+/// Layouts are reference counted and destroyed, when the last pipeline using them is dropped.
+///
+/// Identical layouts are only created for identical bindings, e.g. a camera uniform buffer that is used in the vertex stage in one
+/// pipeline and in the vertex and fragment stage in another results in two different layouts.
+/// With [declare](struct.DescriptorLayoutCache.html#method.declare) a layout can be shared explicitly for a set index.
+/// All pipelines created with the cache then use this layout for the set, so that a descriptor set only needs to be bound once for all of them.
+///
+/// The cache is internally synchronized and can be cloned, clones reference the same cache.
+#[derive(Debug, Clone)]
+pub struct DescriptorLayoutCache {
+  cache: Arc<Mutex<CacheImpl>>,
+}
+
+impl DescriptorLayoutCache {
+  /// Creates an empty cache
+  pub fn new(device: vk::Device) -> Self {
+    Self::with_fns(device, DescriptorLayout::from_bindings_with_samplers, destroy_layout)
+  }
+
+  fn with_fns(device: vk::Device, create: CreateLayout, destroy: DestroyLayout) -> Self {
+    Self {
+      cache: Arc::new(Mutex::new(CacheImpl {
+        device,
+        create,
+        destroy,
+        layouts: Default::default(),
+        handles: Default::default(),
+        declared: Default::default(),
+      })),
+    }
+  }
+
+  /// Declares a shared layout for the descriptor set with index `set`
+  ///
+  /// Every pipeline created with this cache uses the declared layout for `set`.
+  /// The bindings of the pipeline in that set must be a subset of the declared bindings, see [is_compatible](struct.DescriptorLayoutCache.html#method.is_compatible).
+  /// The declared layout is kept alive until the cache is dropped.
+  ///
+  /// Declaring a layout for a set twice replaces the previous declaration for pipelines created afterwards.
+  ///
+  /// # Returns
+  /// The layout, that can be used to allocate descriptor sets from a [DescriptorPool](struct.DescriptorPool.html).
+  pub fn declare(&self, set: u32, bindings: &[Binding]) -> DescriptorLayout {
+    let mut c = self.cache.lock().unwrap();
+    let l = c.acquire(bindings, &[]);
+    if let Some(prev) = c.declared.insert(set, l.clone()) {
+      Self::release_impl(&mut c, prev.layout);
+    }
+    l
+  }
+
+  /// Get the declared layout of a set
+  pub fn get_declared(&self, set: u32) -> Option<DescriptorLayout> {
+    self.cache.lock().unwrap().declared.get(&set).cloned()
+  }
+
+  /// Get the layout for the bindings of a descriptor set
+  ///
+  /// If `set` has a declared layout, the declared layout is returned, otherwise a layout matching the bindings is looked up
+  /// or created if there is none. The returned layout must be handed back with [release](struct.DescriptorLayoutCache.html#method.release).
+  ///
+  /// Layouts with immutable samplers are only shared, if they use the same sampler handles. `samplers` maps a binding to its sampler.
+  ///
+  /// Fails with `Error::IncompatibleDescriptorLayout`, if the bindings do not fit into the declared layout,
+  /// or if immutable samplers are requested for a set with a declared layout.
+  pub fn get(&self, set: u32, bindings: &[Binding], samplers: &[(u32, vk::Sampler)]) -> Result<DescriptorLayout, Error> {
+    let mut c = self.cache.lock().unwrap();
+    let declared = c.declared.get(&set).cloned();
+    match declared {
+      Some(d) => {
+        if !samplers.is_empty() || !Self::is_compatible(&d.bindings, bindings) {
+          Err(Error::IncompatibleDescriptorLayout(set))?
+        }
+        Ok(c.acquire(&d.bindings, &[]))
+      }
+      None => Ok(c.acquire(bindings, samplers)),
+    }
+  }
+
+  /// Releases a layout, that was retrieved with [get](struct.DescriptorLayoutCache.html#method.get)
+  ///
+  /// The layout is destroyed, if it is no longer used.
+  pub fn release(&self, layout: vk::DescriptorSetLayout) {
+    Self::release_impl(&mut self.cache.lock().unwrap(), layout);
+  }
+
+  /// Checks if bindings can be used with a layout
+  ///
+  /// Every binding must exist in the layout with the same descriptor type, at least the same number of array elements and
+  /// all of the binding's shader stages.
+  pub fn is_compatible(layout: &[Binding], bindings: &[Binding]) -> bool {
+    bindings.iter().all(|b| {
+      layout.iter().any(|l| {
+        l.binding == b.binding && l.desctype == b.desctype && l.arrayelems >= b.arrayelems && l.stageflags & b.stageflags == b.stageflags
+      })
+    })
+  }
+
+  fn release_impl(c: &mut CacheImpl, layout: vk::DescriptorSetLayout) {
+    let key = match c.handles.get(&layout) {
+      Some(key) => key.clone(),
+      None => return,
+    };
+
+    let destroy = match c.layouts.get_mut(&key) {
+      Some((_, refs)) => {
+        *refs -= 1;
+        *refs == 0
+      }
+      None => false,
+    };
+
+    if destroy {
+      c.layouts.remove(&key);
+      c.handles.remove(&layout);
+      (c.destroy)(c.device, layout);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::DescriptorSizes;
+  use std::cell::Cell;
+  use std::cell::RefCell;
+
+  thread_local! {
+    static CREATED: Cell<u64> = Cell::new(0);
+    static DESTROYED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+  }
+
+  fn create(_device: vk::Device, bindings: &[Binding], _samplers: &[(u32, vk::Sampler)]) -> DescriptorLayout {
+    CREATED.with(|c| c.set(c.get() + 1));
+    DescriptorLayout {
+      layout: CREATED.with(|c| c.get()),
+      sizes: DescriptorSizes::from_bindings(bindings),
+      bindings: bindings.to_vec(),
+    }
+  }
+
+  fn destroy(_device: vk::Device, layout: vk::DescriptorSetLayout) {
+    DESTROYED.with(|d| d.borrow_mut().push(layout));
+  }
+
+  fn created() -> u64 {
+    CREATED.with(|c| c.get())
+  }
+
+  fn destroyed() -> Vec<u64> {
+    DESTROYED.with(|d| d.borrow().clone())
+  }
+
+  fn cache() -> DescriptorLayoutCache {
+    CREATED.with(|c| c.set(0));
+    DESTROYED.with(|d| d.borrow_mut().clear());
+    DescriptorLayoutCache::with_fns(vk::NULL_HANDLE, create, destroy)
+  }
+
+  fn binding(name: &'static str, binding: u32, desctype: vk::DescriptorType, stageflags: vk::ShaderStageFlagBits) -> Binding {
+    Binding {
+      name,
+      binding,
+      descset: 0,
+      desctype,
+      arrayelems: 1,
+      stageflags,
+      flags: 0,
+    }
+  }
+
+  fn camera() -> Binding {
+    binding("camera", 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER, vk::SHADER_STAGE_VERTEX_BIT)
+  }
+
+  fn texture() -> Binding {
+    binding(
+      "texture",
+      1,
+      vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+      vk::SHADER_STAGE_FRAGMENT_BIT,
+    )
+  }
+
+  #[test]
+  fn key_order() {
+    let mut renamed = camera();
+    renamed.name = "cam";
+    renamed.descset = 2;
+    assert_eq!(
+      LayoutKey::from_bindings(&[camera(), texture()], &[]),
+      LayoutKey::from_bindings(&[texture(), renamed], &[])
+    );
+    assert_eq!(
+      LayoutKey::from_bindings(&[texture()], &[(1, 7), (0, 8)]),
+      LayoutKey::from_bindings(&[texture()], &[(0, 8), (1, 7)])
+    );
+
+    assert_ne!(
+      LayoutKey::from_bindings(&[camera()], &[]),
+      LayoutKey::from_bindings(&[camera(), texture()], &[])
+    );
+    assert_ne!(
+      LayoutKey::from_bindings(&[texture()], &[]),
+      LayoutKey::from_bindings(&[texture()], &[(1, 7)])
+    );
+    let mut stages = camera();
+    stages.stageflags |= vk::SHADER_STAGE_FRAGMENT_BIT;
+    assert_ne!(LayoutKey::from_bindings(&[camera()], &[]), LayoutKey::from_bindings(&[stages], &[]));
+  }
+
+  #[test]
+  fn refcount() {
+    let cache = cache();
+
+    // the same bindings in a different order share the layout
+    let a = cache.get(0, &[camera(), texture()], &[]).unwrap();
+    let b = cache.get(1, &[texture(), camera()], &[]).unwrap();
+    assert_eq!(a.layout, b.layout);
+    assert_eq!(created(), 1);
+
+    let c = cache.get(0, &[camera()], &[]).unwrap();
+    assert_ne!(a.layout, c.layout);
+    assert_eq!(created(), 2);
+
+    cache.release(a.layout);
+    assert!(destroyed().is_empty());
+    cache.release(b.layout);
+    assert_eq!(destroyed(), vec![a.layout]);
+
+    // released layouts are created again
+    let a = cache.get(0, &[camera(), texture()], &[]).unwrap();
+    assert_eq!(created(), 3);
+
+    // unknown layouts are ignored
+    cache.release(42);
+    assert_eq!(destroyed().len(), 1);
+
+    // the remaining layouts are destroyed with the cache
+    drop(cache);
+    let mut d = destroyed();
+    d.sort();
+    assert_eq!(d, vec![1, c.layout, a.layout]);
+  }
+
+  #[test]
+  fn declare() {
+    let cache = cache();
+    assert!(cache.get_declared(0).is_none());
+
+    let declared = cache.declare(0, &[camera(), texture()]);
+    assert_eq!(cache.get_declared(0).unwrap().layout, declared.layout);
+
+    // bindings, that are a subset of the declared layout, get the declared layout
+    let a = cache.get(0, &[texture()], &[]).unwrap();
+    let b = cache.get(0, &[camera()], &[]).unwrap();
+    assert_eq!(a.layout, declared.layout);
+    assert_eq!(b.layout, declared.layout);
+    assert_eq!(created(), 1);
+
+    // other sets are not affected
+    let other = cache.get(1, &[texture()], &[]).unwrap();
+    assert_ne!(other.layout, declared.layout);
+
+    // the declared layout is kept alive by the declaration
+    cache.release(a.layout);
+    cache.release(b.layout);
+    assert!(destroyed().is_empty());
+
+    // declaring again releases the previous declaration
+    let redeclared = cache.declare(0, &[camera()]);
+    assert_ne!(redeclared.layout, declared.layout);
+    assert_eq!(destroyed(), vec![declared.layout]);
+    assert_eq!(cache.get_declared(0).unwrap().layout, redeclared.layout);
+  }
+
+  #[test]
+  fn incompatible() {
+    let cache = cache();
+    cache.declare(0, &[camera()]);
+
+    let is_incompatible = |r: Result<DescriptorLayout, Error>| match r {
+      Err(Error::IncompatibleDescriptorLayout(0)) => true,
+      _ => false,
+    };
+    assert!(is_incompatible(cache.get(0, &[camera(), texture()], &[])));
+    let mut stages = camera();
+    stages.stageflags |= vk::SHADER_STAGE_FRAGMENT_BIT;
+    assert!(is_incompatible(cache.get(0, &[stages], &[])));
+    // immutable samplers can not be used with a declared layout
+    assert!(is_incompatible(cache.get(0, &[camera()], &[(0, 7)])));
+    assert_eq!(created(), 1);
+  }
+
+  #[test]
+  fn compatible() {
+    let layout = [camera(), texture()];
+    assert!(DescriptorLayoutCache::is_compatible(&layout, &[]));
+    assert!(DescriptorLayoutCache::is_compatible(&layout, &[texture()]));
+
+    let mut fewer = texture();
+    fewer.arrayelems = 0;
+    assert!(DescriptorLayoutCache::is_compatible(&layout, &[fewer]));
+
+    let mut more = texture();
+    more.arrayelems = 2;
+    assert!(!DescriptorLayoutCache::is_compatible(&layout, &[more]));
+
+    let mut desctype = texture();
+    desctype.desctype = vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE;
+    assert!(!DescriptorLayoutCache::is_compatible(&layout, &[desctype]));
+
+    let mut moved = texture();
+    moved.binding = 2;
+    assert!(!DescriptorLayoutCache::is_compatible(&layout, &[moved]));
+  }
+}
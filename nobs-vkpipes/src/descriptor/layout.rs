@@ -14,12 +14,37 @@ pub struct DescriptorLayout {
 
 impl DescriptorLayout {
   pub fn from_bindings(device: vk::Device, bindings: &[Binding]) -> Self {
+    Self::from_bindings_with_samplers(device, bindings, &[])
+  }
+
+  /// Creates the layout with immutable samplers
+  ///
+  /// `samplers` maps a binding to the sampler, that is used for all of its array elements.
+  pub fn from_bindings_with_samplers(device: vk::Device, bindings: &[Binding], samplers: &[(u32, vk::Sampler)]) -> Self {
     let mut b : Vec<_> = bindings.into();
     b.sort_by_key(|a| a.binding);
     Self {
-      layout: create_descriptor_layout(device, bindings),
+      layout: create_descriptor_layout_with_samplers(device, bindings, samplers),
       sizes: DescriptorSizes::from_bindings(bindings),
       bindings: b,
     }
   }
+
+  /// Checks if descriptor sets with this layout need to be allocated from a pool with `DESCRIPTOR_POOL_CREATE_UPDATE_AFTER_BIND_BIT_EXT`
+  pub fn is_update_after_bind(&self) -> bool {
+    self
+      .bindings
+      .iter()
+      .any(|b| b.flags & vk::DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT_EXT != 0)
+  }
+
+  /// Get the binding with a variable descriptor count, if there is one
+  ///
+  /// The `arrayelems` of the binding is the upper bound of the descriptor count.
+  pub fn get_variable_binding(&self) -> Option<&Binding> {
+    self
+      .bindings
+      .iter()
+      .find(|b| b.flags & vk::DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT != 0)
+  }
 }
@@ -1,8 +1,14 @@
+pub mod cache;
 pub mod layout;
 pub mod pool;
 pub mod sizes;
+pub mod slots;
+pub mod template;
 pub mod writes;
 
 pub use pool::DescriptorPool;
 pub use sizes::DescriptorSizes;
+pub use slots::DescriptorSlots;
+pub use template::DescriptorTemplate;
 pub use layout::DescriptorLayout;
+pub use cache::DescriptorLayoutCache;
@@ -4,6 +4,7 @@ use crate::Error;
 use vk;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -35,6 +36,7 @@ struct PoolImpl {
   capacity: DescriptorSizes,
   capacity_vec: Vec<vk::DescriptorPoolSize>,
   pools: HashMap<vk::DescriptorPool, DescriptorSizes>,
+  update_after_bind: HashSet<vk::DescriptorPool>,
   dsets: HashMap<vk::DescriptorSet, (vk::DescriptorPool, usize)>,
   dset_types: HashMap<DescriptorSizes, usize>,
 }
@@ -68,24 +70,54 @@ impl DescriptorPool {
         capacity,
         capacity_vec,
         pools: Default::default(),
+        update_after_bind: Default::default(),
         dsets: Default::default(),
         dset_types: Default::default(),
       })),
     }
   }
 
+  /// Allocates a descriptor set
+  ///
+  /// If the layout has a binding with `DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT`, the binding gets all of its array elements,
+  /// e.g. 4096 descriptors for a runtime sized array with the default `runtime_array_len` of the pipeline macro.
+  /// Use [new_dset_variable](struct.DescriptorPool.html#method.new_dset_variable) to allocate only as many descriptors as needed.
   pub fn new_dset(&self, layout: &DescriptorLayout) -> Result<vk::DescriptorSet, crate::Error> {
+    match layout.get_variable_binding() {
+      Some(b) => self.new_dset_variable(layout, b.arrayelems),
+      None => self.new_dset_variable(layout, 0),
+    }
+  }
+
+  /// Allocates a descriptor set with a variable descriptor count
+  ///
+  /// The binding of `layout` with `DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT` gets `count` descriptors.
+  /// The count must not exceed the array elements of the binding. It is ignored if the layout does not have a variable binding.
+  ///
+  /// Descriptor sets with update after bind bindings are allocated from separate pools, that are created with `DESCRIPTOR_POOL_CREATE_UPDATE_AFTER_BIND_BIT_EXT`.
+  pub fn new_dset_variable(&self, layout: &DescriptorLayout, count: u32) -> Result<vk::DescriptorSet, crate::Error> {
     let mut pi = self.pool.lock().unwrap();
 
+    // only the allocated descriptors of a variable binding are taken from the pool
+    let mut sizes = layout.sizes;
+    let variable = layout.get_variable_binding();
+    if let Some(b) = variable {
+      if count > b.arrayelems {
+        Err(crate::Error::InvalidDescriptorCount)?;
+      }
+      sizes[b.desctype] -= b.arrayelems - count;
+    }
+    let update_after_bind = layout.is_update_after_bind();
+
     // make sure the pools can allocate such a descriptor
-    if !pi.capacity.iter().zip(layout.sizes.iter()).fold(true, |acc, (c, s)| acc && s <= c) {
+    if !pi.capacity.iter().zip(sizes.iter()).fold(true, |acc, (c, s)| acc && s <= c) {
       Err(crate::Error::InvalidDescriptorCount)?;
     }
 
     // find the first pool with enough space to hold the descriptor
-    let pool = match pi.pools.iter().find(|(_, pool_sizes)| {
-      let sum = pool_sizes.iter().zip(layout.sizes.iter()).map(|(p, s)| p + s);
-      pi.capacity.iter().zip(sum).fold(true, |acc, (cap, sum)| acc && sum <= *cap)
+    let pool = match pi.pools.iter().find(|(p, pool_sizes)| {
+      let sum = pool_sizes.iter().zip(sizes.iter()).map(|(p, s)| p + s);
+      pi.update_after_bind.contains(p) == update_after_bind && pi.capacity.iter().zip(sum).fold(true, |acc, (cap, sum)| acc && sum <= *cap)
     }) {
       Some((p, _)) => *p,
       None => {
@@ -93,7 +125,11 @@ impl DescriptorPool {
         let create_info = vk::DescriptorPoolCreateInfo {
           sType: vk::STRUCTURE_TYPE_DESCRIPTOR_POOL_CREATE_INFO,
           pNext: std::ptr::null(),
-          flags: vk::DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT,
+          flags: if update_after_bind {
+            vk::DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT | vk::DESCRIPTOR_POOL_CREATE_UPDATE_AFTER_BIND_BIT_EXT
+          } else {
+            vk::DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT
+          },
           poolSizeCount: pi.capacity_vec.len() as u32,
           pPoolSizes: pi.capacity_vec.as_ptr(),
           maxSets: pi.capacity.num_sets,
@@ -103,14 +139,26 @@ impl DescriptorPool {
           .map_err(|e| Error::DescriptorPoolCreateFail(e))?;
 
         pi.pools.insert(handle, DescriptorSizes::new());
+        if update_after_bind {
+          pi.update_after_bind.insert(handle);
+        }
         handle
       }
     };
 
     // create the descriptor set
+    let variable_info = vk::DescriptorSetVariableDescriptorCountAllocateInfoEXT {
+      sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_VARIABLE_DESCRIPTOR_COUNT_ALLOCATE_INFO_EXT,
+      pNext: std::ptr::null(),
+      descriptorSetCount: 1,
+      pDescriptorCounts: &count,
+    };
     let create_info = vk::DescriptorSetAllocateInfo {
       sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO,
-      pNext: std::ptr::null(),
+      pNext: match variable {
+        Some(_) => &variable_info as *const vk::DescriptorSetVariableDescriptorCountAllocateInfoEXT as *const std::os::raw::c_void,
+        None => std::ptr::null(),
+      },
       descriptorPool: pool,
       descriptorSetCount: 1,
       pSetLayouts: &layout.layout,
@@ -121,13 +169,13 @@ impl DescriptorPool {
 
     // update the descriptor counts in the pool
     let pool_sizes = pi.pools.get_mut(&pool).unwrap();
-    for (c, s) in pool_sizes.iter_mut().zip(layout.sizes.iter()) {
+    for (c, s) in pool_sizes.iter_mut().zip(sizes.iter()) {
       *c += s;
     }
 
     // register descriptor set
     let id = pi.dset_types.len();
-    let id = *pi.dset_types.entry(layout.sizes).or_insert(id);
+    let id = *pi.dset_types.entry(sizes).or_insert(id);
     pi.dsets.insert(handle, (pool, id));
 
     Ok(handle)
@@ -0,0 +1,173 @@
+use crate::pipeline::Binding;
+
+/// Allocator for the array elements of a descriptor array
+///
+/// Hands out indices into a (runtime sized) descriptor array, e.g. the slot of a texture in `uniform sampler2D textures[]`.
+/// Freed indices are recycled before new ones are handed out, so that the used part of the array stays compact.
+/// Use one allocator for every bindless binding, e.g. one for textures and one for buffers.
+///
+/// The allocator only manages the indices, the descriptors still have to be written with [Writes](writes/struct.Writes.html).
+/// An index must only be freed, when the descriptor is no longer used by any command buffer in flight.
+///
+/// The descriptor set should be allocated with [DescriptorPool::new_dset_variable](struct.DescriptorPool.html#method.new_dset_variable)
+/// and the number of descriptors, that are actually needed. [DescriptorPool::new_dset](struct.DescriptorPool.html#method.new_dset) allocates
+/// all array elements of the binding, which is 4096 for runtime sized arrays by default.
+///
+/// ## Example
+/// ```rust,ignore
+/// let mut slots = vk::pipes::DescriptorSlots::new(256);
+/// let ds = pool.new_dset_variable(&layout, slots.capacity())?;
+/// let slot = slots.alloc().unwrap();
+/// let info = vk::pipes::descriptor::writes::DescriptorImageInfoBuilder::with_layout(vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL)
+///   .image(view)
+///   .sampler(sampler);
+/// my_pipeline::dset::write(device, ds).textures_elem(slot, info.into()).update();
+/// // pass `slot` to the shader, e.g. with push constants
+///
+/// slots.free(slot);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DescriptorSlots {
+  capacity: u32,
+  next: u32,
+  free: Vec<u32>,
+}
+
+impl DescriptorSlots {
+  /// Creates an allocator for an array with `capacity` elements
+  pub fn new(capacity: u32) -> Self {
+    Self {
+      capacity,
+      next: 0,
+      free: Default::default(),
+    }
+  }
+
+  /// Creates an allocator for all array elements of the binding
+  pub fn from_binding(binding: &Binding) -> Self {
+    Self::new(binding.arrayelems)
+  }
+
+  /// Allocates an index
+  ///
+  /// # Returns
+  /// The index or `None` if all indices are in use.
+  pub fn alloc(&mut self) -> Option<u32> {
+    match self.free.pop() {
+      Some(i) => Some(i),
+      None if self.next < self.capacity => {
+        self.next += 1;
+        Some(self.next - 1)
+      }
+      None => None,
+    }
+  }
+
+  /// Frees an index, so that it can be handed out again
+  ///
+  /// Panics if the index was not allocated.
+  pub fn free(&mut self, i: u32) {
+    assert!(i < self.next && !self.free.contains(&i), "descriptor slot {} is not allocated", i);
+    self.free.push(i);
+  }
+
+  /// Get the number of allocated indices
+  pub fn len(&self) -> u32 {
+    self.next - self.free.len() as u32
+  }
+
+  /// Checks if no index is allocated
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Get the number of array elements managed by the allocator
+  pub fn capacity(&self) -> u32 {
+    self.capacity
+  }
+
+  /// Get the number of array elements, that have ever been handed out
+  ///
+  /// Can be used as the variable descriptor count of a descriptor set, see [DescriptorPool::new_dset_variable](struct.DescriptorPool.html#method.new_dset_variable).
+  pub fn high_water_mark(&self) -> u32 {
+    self.next
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn alloc_free() {
+    let mut s = DescriptorSlots::new(3);
+    assert!(s.is_empty());
+    assert_eq!((s.alloc(), s.alloc(), s.alloc()), (Some(0), Some(1), Some(2)));
+    assert_eq!(s.len(), 3);
+
+    // exhausted
+    assert_eq!(s.alloc(), None);
+    assert_eq!(s.len(), 3);
+
+    s.free(1);
+    assert_eq!(s.len(), 2);
+    assert_eq!(s.high_water_mark(), 3);
+    assert_eq!(s.alloc(), Some(1));
+    assert_eq!(s.alloc(), None);
+  }
+
+  #[test]
+  fn reuse() {
+    let mut s = DescriptorSlots::new(8);
+    (0..4).for_each(|_| {
+      s.alloc().unwrap();
+    });
+
+    // freed indices are handed out before new ones, the last freed first
+    s.free(0);
+    s.free(2);
+    assert_eq!(s.alloc(), Some(2));
+    assert_eq!(s.alloc(), Some(0));
+    assert_eq!(s.alloc(), Some(4));
+    assert_eq!(s.high_water_mark(), 5);
+
+    (0..5).for_each(|i| s.free(i));
+    assert!(s.is_empty());
+    assert_eq!(s.high_water_mark(), 5);
+    assert_eq!(s.capacity(), 8);
+  }
+
+  #[test]
+  fn from_binding() {
+    let binding = Binding {
+      name: "textures",
+      binding: 0,
+      descset: 0,
+      desctype: vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+      arrayelems: 4096,
+      stageflags: vk::SHADER_STAGE_FRAGMENT_BIT,
+      flags: vk::DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT_EXT,
+    };
+    assert_eq!(DescriptorSlots::from_binding(&binding).capacity(), 4096);
+
+    let mut empty = DescriptorSlots::new(0);
+    assert_eq!(empty.alloc(), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "descriptor slot 0 is not allocated")]
+  fn double_free() {
+    let mut s = DescriptorSlots::new(3);
+    s.alloc();
+    s.free(0);
+    s.free(0);
+  }
+
+  #[test]
+  #[should_panic(expected = "descriptor slot 1 is not allocated")]
+  fn free_unallocated() {
+    let mut s = DescriptorSlots::new(3);
+    s.alloc();
+    s.free(1);
+  }
+}
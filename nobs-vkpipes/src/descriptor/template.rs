@@ -0,0 +1,217 @@
+use crate::Error;
+use vk;
+
+use std::marker::PhantomData;
+
+/// Get the size of the descriptor info of a descriptor type in the payload of a template
+fn info_size(desctype: vk::DescriptorType) -> usize {
+  match desctype {
+    vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER
+    | vk::DESCRIPTOR_TYPE_STORAGE_BUFFER
+    | vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER_DYNAMIC
+    | vk::DESCRIPTOR_TYPE_STORAGE_BUFFER_DYNAMIC => std::mem::size_of::<vk::DescriptorBufferInfo>(),
+    vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER | vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER => std::mem::size_of::<vk::BufferView>(),
+    _ => std::mem::size_of::<vk::DescriptorImageInfo>(),
+  }
+}
+
+/// Descriptor update template with a typed payload
+///
+/// Updates all descriptors of a descriptor set with a single call to `vkUpdateDescriptorSetWithTemplate`,
+/// instead of building the `vk::WriteDescriptorSet` array of [Writes](writes/struct.Writes.html) for every update.
+///
+/// The entries of the template describe where the descriptor infos for a binding are located in the payload `T`.
+/// The [pipeline](../../nobs_vkpipes_macro/macro.pipeline.html) macro generates a `#[repr(C)]` struct `Template` and the entries
+/// for every descriptor set, so that the payload always matches the layout of the set.
+///
+/// ## Example
+/// ```rust,ignore
+/// let t = make_sequence::dset::create_template(device, p.dsets[0].layout)?;
+///
+/// // once per frame
+/// t.update(ds, &make_sequence::dset::Template {
+///   ub: DescriptorBufferInfoBuilder::default().buffer(buf_ub).into(),
+///   b_out: DescriptorBufferInfoBuilder::default().buffer(buf_out).into(),
+/// });
+/// ```
+pub struct DescriptorTemplate<T> {
+  pub device: vk::Device,
+  pub handle: vk::DescriptorUpdateTemplate,
+  payload: PhantomData<T>,
+}
+
+impl<T> Drop for DescriptorTemplate<T> {
+  fn drop(&mut self) {
+    vk::DestroyDescriptorUpdateTemplate(self.device, self.handle, std::ptr::null());
+  }
+}
+
+impl<T> DescriptorTemplate<T> {
+  /// Creates the template for descriptor sets with the specified layout
+  ///
+  /// The offsets and strides of `entries` must describe the descriptor infos in `T`.
+  /// Fails with `Error::InvalidTemplateEntry`, if the descriptor infos of an entry are not located inside of `T`, see [check_entries](struct.DescriptorTemplate.html#method.check_entries).
+  pub fn new(device: vk::Device, layout: vk::DescriptorSetLayout, entries: &[vk::DescriptorUpdateTemplateEntry]) -> Result<Self, Error> {
+    Self::check_entries(entries)?;
+
+    let create_info = vk::DescriptorUpdateTemplateCreateInfo {
+      sType: vk::STRUCTURE_TYPE_DESCRIPTOR_UPDATE_TEMPLATE_CREATE_INFO,
+      pNext: std::ptr::null(),
+      flags: 0,
+      descriptorUpdateEntryCount: entries.len() as u32,
+      pDescriptorUpdateEntries: entries.as_ptr(),
+      templateType: vk::DESCRIPTOR_UPDATE_TEMPLATE_TYPE_DESCRIPTOR_SET,
+      descriptorSetLayout: layout,
+      pipelineBindPoint: vk::PIPELINE_BIND_POINT_GRAPHICS,
+      pipelineLayout: vk::NULL_HANDLE,
+      set: 0,
+    };
+
+    let mut handle = vk::NULL_HANDLE;
+    vk_check!(vk::CreateDescriptorUpdateTemplate(
+      device,
+      &create_info,
+      std::ptr::null(),
+      &mut handle
+    ))
+    .map_err(|e| Error::DescriptorTemplateCreateFail(e))?;
+
+    Ok(Self {
+      device,
+      handle,
+      payload: PhantomData,
+    })
+  }
+
+  /// Checks that the descriptor infos of every entry are located inside of the payload `T`
+  ///
+  /// The descriptor infos of an entry must not overlap, i.e. the stride must be at least the size of the descriptor info.
+  /// Fails with `Error::InvalidTemplateEntry` with the binding of the first invalid entry.
+  pub fn check_entries(entries: &[vk::DescriptorUpdateTemplateEntry]) -> Result<(), Error> {
+    for e in entries.iter() {
+      let size = info_size(e.descriptorType);
+      let end = match e.descriptorCount {
+        0 => e.offset,
+        n => e.offset + e.stride * (n as usize - 1) + size,
+      };
+      if end > std::mem::size_of::<T>() || (e.descriptorCount > 1 && e.stride < size) {
+        Err(Error::InvalidTemplateEntry(e.dstBinding))?
+      }
+    }
+    Ok(())
+  }
+
+  /// Writes the descriptors of the payload into the descriptor set
+  pub fn update(&self, dset: vk::DescriptorSet, payload: &T) {
+    vk::UpdateDescriptorSetWithTemplate(self.device, dset, self.handle, payload as *const T as *const std::os::raw::c_void);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Payload as generated by the pipeline macro
+  #[repr(C)]
+  #[derive(Clone, Copy)]
+  struct Template {
+    ub: vk::DescriptorBufferInfo,
+    textures: [vk::DescriptorImageInfo; 4],
+    texels: vk::BufferView,
+    out: vk::DescriptorBufferInfo,
+  }
+
+  const TEMPLATE_ENTRIES: [vk::DescriptorUpdateTemplateEntry; 4] = [
+    vk::DescriptorUpdateTemplateEntry {
+      dstBinding: 0,
+      dstArrayElement: 0,
+      descriptorCount: 1,
+      descriptorType: vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER,
+      offset: std::mem::offset_of!(Template, ub),
+      stride: std::mem::size_of::<vk::DescriptorBufferInfo>(),
+    },
+    vk::DescriptorUpdateTemplateEntry {
+      dstBinding: 1,
+      dstArrayElement: 0,
+      descriptorCount: 4,
+      descriptorType: vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+      offset: std::mem::offset_of!(Template, textures),
+      stride: std::mem::size_of::<vk::DescriptorImageInfo>(),
+    },
+    vk::DescriptorUpdateTemplateEntry {
+      dstBinding: 2,
+      dstArrayElement: 0,
+      descriptorCount: 1,
+      descriptorType: vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER,
+      offset: std::mem::offset_of!(Template, texels),
+      stride: std::mem::size_of::<vk::BufferView>(),
+    },
+    vk::DescriptorUpdateTemplateEntry {
+      dstBinding: 3,
+      dstArrayElement: 0,
+      descriptorCount: 1,
+      descriptorType: vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
+      offset: std::mem::offset_of!(Template, out),
+      stride: std::mem::size_of::<vk::DescriptorBufferInfo>(),
+    },
+  ];
+
+  fn is_invalid(r: Result<(), Error>, binding: u32) -> bool {
+    match r {
+      Err(Error::InvalidTemplateEntry(b)) => b == binding,
+      _ => false,
+    }
+  }
+
+  #[test]
+  fn offsets() {
+    // the entries address consecutive descriptor infos in the payload
+    let e = &TEMPLATE_ENTRIES;
+    assert_eq!(e[0].offset, 0);
+    assert_eq!(e[1].offset, e[0].offset + info_size(e[0].descriptorType));
+    assert_eq!(e[2].offset, e[1].offset + 4 * info_size(e[1].descriptorType));
+    assert_eq!(e[3].offset, e[2].offset + info_size(e[2].descriptorType));
+    assert_eq!(std::mem::size_of::<Template>(), e[3].offset + info_size(e[3].descriptorType));
+    assert!(DescriptorTemplate::<Template>::check_entries(&TEMPLATE_ENTRIES).is_ok());
+  }
+
+  #[test]
+  fn out_of_bounds() {
+    // the payload is too small for the entries
+    assert!(is_invalid(
+      DescriptorTemplate::<vk::DescriptorBufferInfo>::check_entries(&TEMPLATE_ENTRIES),
+      1
+    ));
+
+    let mut entries = TEMPLATE_ENTRIES;
+    entries[3].offset += 1;
+    assert!(is_invalid(DescriptorTemplate::<Template>::check_entries(&entries), 3));
+
+    let mut entries = TEMPLATE_ENTRIES;
+    entries[1].descriptorCount = 6;
+    assert!(is_invalid(DescriptorTemplate::<Template>::check_entries(&entries), 1));
+
+    // overlapping descriptor infos
+    let mut entries = TEMPLATE_ENTRIES;
+    entries[1].stride = 8;
+    assert!(is_invalid(DescriptorTemplate::<Template>::check_entries(&entries), 1));
+
+    // entries without descriptors are always valid
+    let mut entries = TEMPLATE_ENTRIES;
+    entries[1].descriptorCount = 0;
+    entries[1].offset = std::mem::size_of::<Template>();
+    assert!(DescriptorTemplate::<Template>::check_entries(&entries).is_ok());
+  }
+
+  #[test]
+  fn create_invalid() {
+    let mut entries = TEMPLATE_ENTRIES;
+    entries[0].offset = std::mem::size_of::<Template>();
+    assert!(
+      match DescriptorTemplate::<Template>::new(vk::NULL_HANDLE, vk::NULL_HANDLE, &entries) {
+        Err(Error::InvalidTemplateEntry(0)) => true,
+        _ => false,
+      }
+    );
+  }
+}
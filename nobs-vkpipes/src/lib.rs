@@ -53,13 +53,8 @@
 //!     }
 //!   }
 //!
-//!   // The code generation will not create types for e.g. the uniform buffer
-//!   // If we want this, we need to do it our selves
-//!   pub struct ub {
-//!     pub num_elems: u32,
-//!     pub i_first: u32,
-//!     pub i_step: u32,
-//!   }
+//!   // The code generation creates #[repr(C)] structs for the uniform and storage blocks in the module blocks,
+//!   // e.g. blocks::ub with the members num_elems, i_first and i_step
 //! }
 //!
 //! // create an instance of the pipeline
@@ -72,6 +67,32 @@
 //!   .b_out(|b| b.buffer(buf_out))
 //!   .update();
 //! ```
+//!
+//! ## Runtime compilation
+//! With the `runtime` feature enabled, shaders can also be compiled at runtime with [ShaderSource](runtime/struct.ShaderSource.html).
+//! [HotPipeline](runtime/struct.HotPipeline.html) watches the shader sources and rebuilds the pipeline whenever they change,
+//! so that shaders can be edited without restarting the application.
+//!
+//! ## Shared descriptor set layouts
+//! Pipelines created with the same [DescriptorLayoutCache](struct.DescriptorLayoutCache.html) share descriptor set layouts with identical bindings.
+//! Layouts declared in the cache for a set index are used by all of these pipelines, so that a descriptor set can be bound once and used with all of them.
+//!
+//! ## Bindless descriptors
+//! Runtime sized descriptor arrays (e.g. `uniform sampler2D textures[]` with `GL_EXT_nonuniform_qualifier`) are reflected with the binding flags
+//! `DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT_EXT` and `DESCRIPTOR_BINDING_PARTIALLY_BOUND_BIT_EXT` of `VK_EXT_descriptor_indexing`.
+//! If the array is the last binding of its descriptor set, it also has a variable descriptor count.
+//! The number of array elements is set with the option `runtime_array_len` of the [pipeline](../nobs_vkpipes_macro/macro.pipeline.html) macro.
+//! Descriptor sets with variable count are allocated with [DescriptorPool::new_dset_variable](struct.DescriptorPool.html#method.new_dset_variable)
+//! and the array elements are handed out with [DescriptorSlots](struct.DescriptorSlots.html).
+//! The device needs to be created with the extension and the descriptor indexing features enabled.
+//!
+//! ## Immutable samplers
+//! Samplers can be baked into the descriptor set layout of a pipeline with the option `sampler["name"] = {...}` of the
+//! [pipeline](../nobs_vkpipes_macro/macro.pipeline.html) macro, or with `immutable_sampler` of the pipeline builders.
+//! The samplers are created and destroyed together with the pipeline. Descriptors of these bindings are written without a sampler.
+//!
+//! ## Pipeline descriptions
+//! With the `desc` feature enabled, the fixed function state of a graphics pipeline can be loaded from a RON file with [GraphicsDesc](struct.GraphicsDesc.html).
 
 #[macro_use]
 extern crate nobs_vk as vk;
@@ -94,13 +115,27 @@ pub use pipeline::builder::graphics::tesselation::Builder as Tesselation;
 pub use pipeline::builder::graphics::vertex_input::Builder as VertexInput;
 pub use pipeline::builder::graphics::viewport::Builder as Viewport;
 pub use pipeline::builder::graphics::Graphics as GraphicsBuilder;
+#[cfg(feature = "desc")]
+pub use pipeline::builder::graphics::desc::GraphicsDesc;
 pub use pipeline::Pipeline;
+pub use pipeline::PipelineCache;
+pub use pipeline::PushConstants;
+pub use pipeline::PushValue;
+pub use pipeline::SpecConstant;
+pub use pipeline::SpecValue;
+pub use pipeline::Specialization;
 pub use pipeline::builder;
 
 pub mod descriptor;
 pub use descriptor::DescriptorLayout;
+pub use descriptor::DescriptorLayoutCache;
 pub use descriptor::DescriptorPool;
 pub use descriptor::DescriptorSizes;
+pub use descriptor::DescriptorSlots;
+pub use descriptor::DescriptorTemplate;
+
+#[cfg(feature = "runtime")]
+pub mod runtime;
 
 #[derive(Debug)]
 pub enum Error {
@@ -109,6 +144,15 @@ pub enum Error {
   InvalidDescriptorCount,
   DescriptorSetCreateFail(vk::Error),
   DescriptorPoolCreateFail(vk::Error),
+  PipelineCacheCreateFail(vk::Error),
+  PipelineCacheDataFail(vk::Error),
+  PipelineCacheIo(std::io::Error),
+  ShaderCompileFail(String),
+  IncompatibleDescriptorLayout(u32),
+  DescriptorTemplateCreateFail(vk::Error),
+  SamplerCreateFail(vk::Error),
+  InvalidImmutableSampler(u32, u32),
+  InvalidTemplateEntry(u32),
 }
 
 /// For usage in build.rs to automatically detect changes in glsl/spv files and force the recompilation of the rust source that references the shader.
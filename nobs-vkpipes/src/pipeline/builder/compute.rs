@@ -1,6 +1,10 @@
 use crate::pipeline::builder;
 use crate::pipeline::Binding;
 use crate::pipeline::Pipeline;
+use crate::pipeline::PipelineCache;
+use crate::pipeline::SpecValue;
+use crate::pipeline::Specialization;
+use crate::DescriptorLayoutCache;
 use crate::Error;
 use vk;
 
@@ -13,6 +17,11 @@ use vk;
 pub struct Compute {
   device: vk::Device,
   bindings: Vec<Binding>,
+  push_constants: Vec<vk::PushConstantRange>,
+  specialization: Specialization,
+  cache: vk::PipelineCache,
+  layout_cache: Option<DescriptorLayoutCache>,
+  samplers: Vec<(u32, u32, vk::SamplerCreateInfo)>,
   comp: Option<vk::PipelineShaderStageCreateInfo>,
 }
 
@@ -24,6 +33,11 @@ impl Compute {
     Compute {
       device,
       bindings: Default::default(),
+      push_constants: Default::default(),
+      specialization: Default::default(),
+      cache: vk::NULL_HANDLE,
+      layout_cache: None,
+      samplers: Default::default(),
       comp: None,
     }
   }
@@ -36,6 +50,51 @@ impl Compute {
     self
   }
 
+  /// Configures the push constant ranges for the pipeline.
+  ///
+  /// The ranges are added to the pipeline layout.
+  pub fn push_constants(&mut self, push_constants: &[vk::PushConstantRange]) -> &mut Self {
+    self.push_constants = push_constants.to_vec();
+    self
+  }
+
+  /// Sets the value of a specialization constant for all shader stages of the pipeline.
+  ///
+  /// See [Specialization::set](../../struct.Specialization.html#method.set).
+  pub fn specialize<T: SpecValue>(&mut self, id: u32, value: T) -> &mut Self {
+    self.specialization.set(id, value);
+    self
+  }
+
+  /// Sets the pipeline cache, that is used for the pipeline creation.
+  ///
+  /// The cache must outlive the call to [create](struct.Compute.html#method.create).
+  /// Without a cache the pipeline is always compiled from scratch.
+  pub fn cache(&mut self, cache: &PipelineCache) -> &mut Self {
+    self.cache = cache.handle;
+    self
+  }
+
+  /// Sets the cache, from which the descriptor set layouts of the pipeline are taken.
+  ///
+  /// Pipelines created with the same cache share identical descriptor set layouts and use the declared layouts of the cache.
+  /// See [DescriptorLayoutCache](../../descriptor/struct.DescriptorLayoutCache.html).
+  pub fn layout_cache(&mut self, cache: &DescriptorLayoutCache) -> &mut Self {
+    self.layout_cache = Some(cache.clone());
+    self
+  }
+
+  /// Sets an immutable sampler for a binding of type sampler or combined image sampler.
+  ///
+  /// The sampler is created with the pipeline and baked into the descriptor set layout, it is used for all array elements of the binding.
+  /// Descriptors of the binding do not need a sampler when they are written.
+  /// Setting a sampler for the same binding twice replaces the previous one.
+  pub fn immutable_sampler(&mut self, set: u32, binding: u32, info: &vk::SamplerCreateInfo) -> &mut Self {
+    self.samplers.retain(|(s, b, _)| *s != set || *b != binding);
+    self.samplers.push((set, binding, *info));
+    self
+  }
+
   /// Configures the compute shader stage for the pipeline.
   pub fn comp(&mut self, comp: &vk::PipelineShaderStageCreateInfo) -> &mut Self {
     self.comp = Some(*comp);
@@ -44,12 +103,25 @@ impl Compute {
 
   /// Create the pipeline from the current configuration
   pub fn create(&self) -> Result<Pipeline, Error> {
-    let stage = self.comp.ok_or(Error::InvalidShaderModule)?;
+    let mut stage = self.comp.ok_or(Error::InvalidShaderModule)?;
     if stage.module == vk::NULL_HANDLE {
       Err(Error::InvalidShaderModule)?
     }
 
-    let (dsets, layout) = builder::create_layouts(self.device, &self.bindings);
+    let spec = self.specialization.get_info();
+    if !self.specialization.is_empty() {
+      stage.pSpecializationInfo = &spec;
+    }
+
+    let samplers = builder::create_samplers(self.device, &self.bindings, &self.samplers)?;
+    let (dsets, layout) = builder::create_layouts(
+      self.device,
+      &self.bindings,
+      &self.push_constants,
+      self.layout_cache.as_ref(),
+      &samplers,
+    )
+    .inspect_err(|_| builder::destroy_samplers(self.device, &samplers))?;
 
     let create_info = vk::ComputePipelineCreateInfo {
       sType: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
@@ -64,13 +136,17 @@ impl Compute {
     let mut handle = vk::NULL_HANDLE;
     vk_check!(vk::CreateComputePipelines(
       self.device,
-      vk::NULL_HANDLE,
+      self.cache,
       1,
       &create_info,
       std::ptr::null(),
       &mut handle
     ))
-    .map_err(|e| Error::PipelineCreateFail(e))?;
+    .map_err(|e| {
+      builder::destroy_layouts(self.device, &dsets, layout, self.layout_cache.as_ref());
+      builder::destroy_samplers(self.device, &samplers);
+      Error::PipelineCreateFail(e)
+    })?;
 
     vk::DestroyShaderModule(self.device, stage.module, std::ptr::null());
 
@@ -79,6 +155,8 @@ impl Compute {
       handle,
       dsets,
       layout,
+      layout_cache: self.layout_cache.clone(),
+      samplers: samplers.iter().map(|(_, _, s)| *s).collect(),
     })
   }
 }
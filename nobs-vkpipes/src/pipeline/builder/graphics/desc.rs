@@ -0,0 +1,916 @@
+use super::blend;
+use super::depth_stencil;
+use super::dynamic;
+use super::input_assembly;
+use super::multisample;
+use super::raster;
+use super::tesselation;
+use super::viewport;
+use super::Graphics;
+use vk;
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+
+/// Defines an enum with a variant for every vulkan constant, that can be written by name in a description
+macro_rules! desc_enum {
+  ($(#[$meta:meta])* $name:ident: $vk:ty, $(default $default:ident,)? { $($variant:ident => $constant:ident,)* }) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum $name {
+      $($variant,)*
+    }
+
+    $(
+      impl Default for $name {
+        fn default() -> Self {
+          $name::$default
+        }
+      }
+    )?
+
+    impl $name {
+      pub fn to_vk(self) -> $vk {
+        match self {
+          $($name::$variant => vk::$constant,)*
+        }
+      }
+    }
+  };
+}
+
+desc_enum!(Topology: vk::PrimitiveTopology, default TriangleList, {
+  PointList => PRIMITIVE_TOPOLOGY_POINT_LIST,
+  LineList => PRIMITIVE_TOPOLOGY_LINE_LIST,
+  LineStrip => PRIMITIVE_TOPOLOGY_LINE_STRIP,
+  TriangleList => PRIMITIVE_TOPOLOGY_TRIANGLE_LIST,
+  TriangleStrip => PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP,
+  TriangleFan => PRIMITIVE_TOPOLOGY_TRIANGLE_FAN,
+  LineListWithAdjacency => PRIMITIVE_TOPOLOGY_LINE_LIST_WITH_ADJACENCY,
+  LineStripWithAdjacency => PRIMITIVE_TOPOLOGY_LINE_STRIP_WITH_ADJACENCY,
+  TriangleListWithAdjacency => PRIMITIVE_TOPOLOGY_TRIANGLE_LIST_WITH_ADJACENCY,
+  TriangleStripWithAdjacency => PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP_WITH_ADJACENCY,
+  PatchList => PRIMITIVE_TOPOLOGY_PATCH_LIST,
+});
+
+desc_enum!(PolygonMode: vk::PolygonMode, default Fill, {
+  Fill => POLYGON_MODE_FILL,
+  Line => POLYGON_MODE_LINE,
+  Point => POLYGON_MODE_POINT,
+});
+
+desc_enum!(CullMode: vk::CullModeFlags, default Back, {
+  None => CULL_MODE_NONE,
+  Front => CULL_MODE_FRONT_BIT,
+  Back => CULL_MODE_BACK_BIT,
+  FrontAndBack => CULL_MODE_FRONT_AND_BACK,
+});
+
+desc_enum!(FrontFace: vk::FrontFace, default CounterClockwise, {
+  CounterClockwise => FRONT_FACE_COUNTER_CLOCKWISE,
+  Clockwise => FRONT_FACE_CLOCKWISE,
+});
+
+desc_enum!(CompareOp: vk::CompareOp, default LessOrEqual, {
+  Never => COMPARE_OP_NEVER,
+  Less => COMPARE_OP_LESS,
+  Equal => COMPARE_OP_EQUAL,
+  LessOrEqual => COMPARE_OP_LESS_OR_EQUAL,
+  Greater => COMPARE_OP_GREATER,
+  NotEqual => COMPARE_OP_NOT_EQUAL,
+  GreaterOrEqual => COMPARE_OP_GREATER_OR_EQUAL,
+  Always => COMPARE_OP_ALWAYS,
+});
+
+desc_enum!(StencilOp: vk::StencilOp, {
+  Keep => STENCIL_OP_KEEP,
+  Zero => STENCIL_OP_ZERO,
+  Replace => STENCIL_OP_REPLACE,
+  IncrementAndClamp => STENCIL_OP_INCREMENT_AND_CLAMP,
+  DecrementAndClamp => STENCIL_OP_DECREMENT_AND_CLAMP,
+  Invert => STENCIL_OP_INVERT,
+  IncrementAndWrap => STENCIL_OP_INCREMENT_AND_WRAP,
+  DecrementAndWrap => STENCIL_OP_DECREMENT_AND_WRAP,
+});
+
+desc_enum!(LogicOp: vk::LogicOp, default Copy, {
+  Clear => LOGIC_OP_CLEAR,
+  And => LOGIC_OP_AND,
+  AndReverse => LOGIC_OP_AND_REVERSE,
+  Copy => LOGIC_OP_COPY,
+  AndInverted => LOGIC_OP_AND_INVERTED,
+  NoOp => LOGIC_OP_NO_OP,
+  Xor => LOGIC_OP_XOR,
+  Or => LOGIC_OP_OR,
+  Nor => LOGIC_OP_NOR,
+  Equivalent => LOGIC_OP_EQUIVALENT,
+  Invert => LOGIC_OP_INVERT,
+  OrReverse => LOGIC_OP_OR_REVERSE,
+  CopyInverted => LOGIC_OP_COPY_INVERTED,
+  OrInverted => LOGIC_OP_OR_INVERTED,
+  Nand => LOGIC_OP_NAND,
+  Set => LOGIC_OP_SET,
+});
+
+desc_enum!(BlendFactor: vk::BlendFactor, {
+  Zero => BLEND_FACTOR_ZERO,
+  One => BLEND_FACTOR_ONE,
+  SrcColor => BLEND_FACTOR_SRC_COLOR,
+  OneMinusSrcColor => BLEND_FACTOR_ONE_MINUS_SRC_COLOR,
+  DstColor => BLEND_FACTOR_DST_COLOR,
+  OneMinusDstColor => BLEND_FACTOR_ONE_MINUS_DST_COLOR,
+  SrcAlpha => BLEND_FACTOR_SRC_ALPHA,
+  OneMinusSrcAlpha => BLEND_FACTOR_ONE_MINUS_SRC_ALPHA,
+  DstAlpha => BLEND_FACTOR_DST_ALPHA,
+  OneMinusDstAlpha => BLEND_FACTOR_ONE_MINUS_DST_ALPHA,
+  ConstantColor => BLEND_FACTOR_CONSTANT_COLOR,
+  OneMinusConstantColor => BLEND_FACTOR_ONE_MINUS_CONSTANT_COLOR,
+  ConstantAlpha => BLEND_FACTOR_CONSTANT_ALPHA,
+  OneMinusConstantAlpha => BLEND_FACTOR_ONE_MINUS_CONSTANT_ALPHA,
+  SrcAlphaSaturate => BLEND_FACTOR_SRC_ALPHA_SATURATE,
+  Src1Color => BLEND_FACTOR_SRC1_COLOR,
+  OneMinusSrc1Color => BLEND_FACTOR_ONE_MINUS_SRC1_COLOR,
+  Src1Alpha => BLEND_FACTOR_SRC1_ALPHA,
+  OneMinusSrc1Alpha => BLEND_FACTOR_ONE_MINUS_SRC1_ALPHA,
+});
+
+desc_enum!(BlendOp: vk::BlendOp, {
+  Add => BLEND_OP_ADD,
+  Subtract => BLEND_OP_SUBTRACT,
+  ReverseSubtract => BLEND_OP_REVERSE_SUBTRACT,
+  Min => BLEND_OP_MIN,
+  Max => BLEND_OP_MAX,
+});
+
+desc_enum!(DynamicState: vk::DynamicState, {
+  Viewport => DYNAMIC_STATE_VIEWPORT,
+  Scissor => DYNAMIC_STATE_SCISSOR,
+  LineWidth => DYNAMIC_STATE_LINE_WIDTH,
+  DepthBias => DYNAMIC_STATE_DEPTH_BIAS,
+  BlendConstants => DYNAMIC_STATE_BLEND_CONSTANTS,
+  DepthBounds => DYNAMIC_STATE_DEPTH_BOUNDS,
+  StencilCompareMask => DYNAMIC_STATE_STENCIL_COMPARE_MASK,
+  StencilWriteMask => DYNAMIC_STATE_STENCIL_WRITE_MASK,
+  StencilReference => DYNAMIC_STATE_STENCIL_REFERENCE,
+});
+
+/// Error of a pipeline description
+///
+/// `field` is the path of the offending field in the description, e.g. `blend.attachments[1].write_mask`.
+/// For syntax errors, unknown fields and unknown enum variants the field is the path of the field at the position of the error
+/// and the message contains the position in the source. The field is empty, if the error does not lie inside of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescError {
+  pub field: String,
+  pub message: String,
+}
+
+impl std::fmt::Display for DescError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self.field.is_empty() {
+      true => write!(f, "{}", self.message),
+      false => write!(f, "{}: {}", self.field, self.message),
+    }
+  }
+}
+
+/// Appends a field name to a path in a description
+fn push_field(path: &mut String, field: &str) {
+  if !path.is_empty() {
+    path.push('.');
+  }
+  path.push_str(field);
+}
+
+/// Gets the path of the field, that is read at byte `offset` of a RON string
+///
+/// Named fields are written as `a.b` and list elements as `a[i]`, unnamed tuples and enum variants do not show up in the path.
+/// The path is empty, if `offset` does not lie inside of the outermost struct.
+fn ron_path(s: &str, offset: usize) -> String {
+  enum Scope {
+    Fields(Option<String>),
+    List(usize),
+  }
+
+  let mut scopes = Vec::new();
+  let mut ident = String::new();
+  let mut chars = s.char_indices().take_while(|(i, _)| *i < offset).map(|(_, c)| c).peekable();
+  while let Some(c) = chars.next() {
+    let is_ident = c.is_alphanumeric() || c == '_';
+    match c {
+      '"' => {
+        let mut escaped = false;
+        for c in chars.by_ref() {
+          match (escaped, c) {
+            (false, '"') => break,
+            (false, '\\') => escaped = true,
+            _ => escaped = false,
+          }
+        }
+      }
+      '/' if chars.peek() == Some(&'/') => {
+        chars.by_ref().find(|c| *c == '\n');
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        let mut prev = chars.next();
+        for c in chars.by_ref() {
+          if prev == Some('*') && c == '/' {
+            break;
+          }
+          prev = Some(c);
+        }
+      }
+      '(' => scopes.push(Scope::Fields(None)),
+      '[' => scopes.push(Scope::List(0)),
+      ')' | ']' => {
+        scopes.pop();
+      }
+      ':' => {
+        if let Some(Scope::Fields(f)) = scopes.last_mut() {
+          *f = Some(ident.clone());
+        }
+      }
+      ',' => match scopes.last_mut() {
+        Some(Scope::Fields(f)) => *f = None,
+        Some(Scope::List(i)) => *i += 1,
+        None => (),
+      },
+      c if is_ident => ident.push(c),
+      _ => (),
+    }
+    if !is_ident && !c.is_whitespace() {
+      ident.clear();
+    }
+  }
+
+  scopes.iter().fold(String::new(), |mut path, scope| {
+    match scope {
+      Scope::Fields(Some(f)) => push_field(&mut path, f),
+      Scope::Fields(None) => (),
+      Scope::List(i) => path.push_str(&format!("[{}]", i)),
+    }
+    path
+  })
+}
+
+fn check(ok: bool, field: &str, message: &str) -> Result<(), DescError> {
+  match ok {
+    true => Ok(()),
+    false => Err(DescError {
+      field: field.to_string(),
+      message: message.to_string(),
+    }),
+  }
+}
+
+/// Input assembly state, see [InputAssembly](../../../../struct.InputAssembly.html)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputAssemblyDesc {
+  pub topology: Topology,
+  pub primitive_restart: bool,
+}
+
+/// Tesselation state, see [Tesselation](../../../../struct.Tesselation.html)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TesselationDesc {
+  pub patch_control_points: u32,
+}
+
+/// Viewport with position, extent and depth range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewportRectDesc {
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32,
+  pub min_depth: f32,
+  pub max_depth: f32,
+}
+
+impl Default for ViewportRectDesc {
+  fn default() -> Self {
+    Self {
+      x: 0.0,
+      y: 0.0,
+      width: 1.0,
+      height: 1.0,
+      min_depth: 0.0,
+      max_depth: 1.0,
+    }
+  }
+}
+
+/// Scissor rect with offset and extent
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScissorDesc {
+  pub x: i32,
+  pub y: i32,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Viewport state, see [Viewport](../../../../struct.Viewport.html)
+///
+/// If no viewports and scissor rects are specified, the default viewport state of the [Graphics](../struct.Graphics.html) builder is kept.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewportDesc {
+  pub viewports: Vec<ViewportRectDesc>,
+  pub scissors: Vec<ScissorDesc>,
+}
+
+/// Depth bias of the raster state
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepthBiasDesc {
+  pub constant_factor: f32,
+  pub clamp: f32,
+  pub slope_factor: f32,
+}
+
+/// Raster state, see [Raster](../../../../struct.Raster.html)
+///
+/// The depth bias is enabled, if it is specified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RasterDesc {
+  pub depth_clamp: bool,
+  pub discard: bool,
+  pub polygon_mode: PolygonMode,
+  pub line_width: f32,
+  pub cull_mode: CullMode,
+  pub front_face: FrontFace,
+  pub depth_bias: Option<DepthBiasDesc>,
+}
+
+impl Default for RasterDesc {
+  fn default() -> Self {
+    Self {
+      depth_clamp: false,
+      discard: false,
+      polygon_mode: Default::default(),
+      line_width: 1.0,
+      cull_mode: Default::default(),
+      front_face: Default::default(),
+      depth_bias: None,
+    }
+  }
+}
+
+/// Multisample state, see [Multisample](../../../../struct.Multisample.html)
+///
+/// Sample shading is enabled, if the minimum fraction of sample shading is specified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MultisampleDesc {
+  pub samples: u32,
+  pub sample_shading: Option<f32>,
+  pub alpha_to_coverage: bool,
+  pub alpha_to_one: bool,
+}
+
+impl Default for MultisampleDesc {
+  fn default() -> Self {
+    Self {
+      samples: 1,
+      sample_shading: None,
+      alpha_to_coverage: false,
+      alpha_to_one: false,
+    }
+  }
+}
+
+/// Stencil operations for either front or back facing primitives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StencilOpDesc {
+  pub fail: StencilOp,
+  pub pass: StencilOp,
+  pub depth_fail: StencilOp,
+  pub compare: CompareOp,
+  pub compare_mask: u32,
+  pub write_mask: u32,
+  pub reference: u32,
+}
+
+impl Default for StencilOpDesc {
+  fn default() -> Self {
+    Self {
+      fail: StencilOp::Keep,
+      pass: StencilOp::Keep,
+      depth_fail: StencilOp::Keep,
+      compare: CompareOp::Always,
+      compare_mask: !0,
+      write_mask: !0,
+      reference: 0,
+    }
+  }
+}
+
+impl StencilOpDesc {
+  pub fn to_vk(&self) -> vk::StencilOpState {
+    vk::StencilOpState {
+      failOp: self.fail.to_vk(),
+      passOp: self.pass.to_vk(),
+      depthFailOp: self.depth_fail.to_vk(),
+      compareOp: self.compare.to_vk(),
+      compareMask: self.compare_mask,
+      writeMask: self.write_mask,
+      reference: self.reference,
+    }
+  }
+}
+
+/// Stencil test of the depth stencil state
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StencilDesc {
+  pub front: StencilOpDesc,
+  pub back: StencilOpDesc,
+}
+
+/// Depth stencil state, see [DepthStencil](../../../../struct.DepthStencil.html)
+///
+/// The depth bounds test is enabled, if the bounds are specified as `(min, max)`.
+/// The stencil test is enabled, if it is specified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepthStencilDesc {
+  pub depth_test: bool,
+  pub depth_write: bool,
+  pub depth_compare: CompareOp,
+  pub depth_bounds: Option<(f32, f32)>,
+  pub stencil: Option<StencilDesc>,
+}
+
+impl Default for DepthStencilDesc {
+  fn default() -> Self {
+    Self {
+      depth_test: true,
+      depth_write: true,
+      depth_compare: Default::default(),
+      depth_bounds: None,
+      stencil: None,
+    }
+  }
+}
+
+/// Blend equation for either the color or alpha channels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlendEquationDesc {
+  pub src: BlendFactor,
+  pub dst: BlendFactor,
+  pub op: BlendOp,
+}
+
+impl Default for BlendEquationDesc {
+  fn default() -> Self {
+    Self {
+      src: BlendFactor::One,
+      dst: BlendFactor::Zero,
+      op: BlendOp::Add,
+    }
+  }
+}
+
+/// Blend state of a color attachment, see [BlendAttachment](../../../../struct.BlendAttachment.html)
+///
+/// The write mask is a combination of the characters `r`, `g`, `b` and `a`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentDesc {
+  pub enable: bool,
+  pub write_mask: String,
+  pub color: BlendEquationDesc,
+  pub alpha: BlendEquationDesc,
+}
+
+impl Default for AttachmentDesc {
+  fn default() -> Self {
+    Self {
+      enable: false,
+      write_mask: "rgba".to_string(),
+      color: Default::default(),
+      alpha: Default::default(),
+    }
+  }
+}
+
+impl AttachmentDesc {
+  fn get_write_mask(&self) -> vk::ColorComponentFlags {
+    self.write_mask.chars().fold(0, |mask, c| match c {
+      'r' => mask | vk::COLOR_COMPONENT_R_BIT,
+      'g' => mask | vk::COLOR_COMPONENT_G_BIT,
+      'b' => mask | vk::COLOR_COMPONENT_B_BIT,
+      'a' => mask | vk::COLOR_COMPONENT_A_BIT,
+      _ => mask,
+    })
+  }
+}
+
+/// Blend state, see [Blend](../../../../struct.Blend.html)
+///
+/// The logic op is enabled, if it is specified.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlendDesc {
+  pub logic_op: Option<LogicOp>,
+  pub constants: [f32; 4],
+  pub attachments: Vec<AttachmentDesc>,
+}
+
+/// Description of the fixed function state of a graphics pipeline
+///
+/// The description can be loaded from a [RON](https://github.com/ron-rs/ron) file, so that the state can be changed without recompiling.
+/// Every field is optional and defaults to the same values as the sub builders of the [Graphics](../struct.Graphics.html) builder.
+/// Shader stages, bindings and the vertex input state are not part of the description, they are still defined by the shaders.
+///
+/// Requires the `desc` feature.
+///
+/// ## Example
+/// ```rust,ignore
+/// let desc = vk::pipes::GraphicsDesc::from_ron("
+///   (
+///     raster: (cull_mode: None),
+///     depth_stencil: (depth_write: false),
+///     blend: (
+///       attachments: [
+///         (enable: true, color: (src: SrcAlpha, dst: OneMinusSrcAlpha)),
+///       ],
+///     ),
+///     dynamic: [Viewport, Scissor],
+///   )
+/// ")?;
+///
+/// let p = desc.apply(&mut my_pipeline::new(device, pass, 0)).create()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphicsDesc {
+  pub input_assembly: InputAssemblyDesc,
+  pub tesselation: TesselationDesc,
+  pub viewport: ViewportDesc,
+  pub raster: RasterDesc,
+  pub multisample: MultisampleDesc,
+  pub depth_stencil: DepthStencilDesc,
+  pub blend: BlendDesc,
+  pub dynamic: Vec<DynamicState>,
+}
+
+impl GraphicsDesc {
+  /// Parses and validates a description in RON
+  pub fn from_ron(s: &str) -> Result<Self, DescError> {
+    let desc: Self = ron::from_str(s).map_err(|e| {
+      // positions of ron start at line 1 and column 1 and count columns in bytes
+      let offset = s
+        .split_inclusive('\n')
+        .take(e.position.line.saturating_sub(1))
+        .map(|l| l.len())
+        .sum::<usize>()
+        + e.position.col.saturating_sub(1);
+
+      // the error position is right behind the name of an unknown or duplicate field
+      let mut field = ron_path(s, offset);
+      match e.code {
+        ron::Error::NoSuchStructField { ref found, .. } => push_field(&mut field, found),
+        ron::Error::DuplicateStructField { field: f, .. } => push_field(&mut field, f),
+        _ => (),
+      }
+
+      DescError {
+        field,
+        message: e.to_string(),
+      }
+    })?;
+    desc.validate()?;
+    Ok(desc)
+  }
+
+  /// Loads and validates a description from a RON file
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DescError> {
+    let path = path.as_ref();
+    let s = std::fs::read_to_string(path).map_err(|e| DescError {
+      field: String::new(),
+      message: format!("Could not read {}: {}", path.display(), e),
+    })?;
+    Self::from_ron(&s)
+  }
+
+  /// Writes the description in RON
+  pub fn to_ron(&self) -> Result<String, DescError> {
+    ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).map_err(|e| DescError {
+      field: String::new(),
+      message: e.to_string(),
+    })
+  }
+
+  /// Checks that all values are in their valid range
+  ///
+  /// Returns the path of the first offending field.
+  pub fn validate(&self) -> Result<(), DescError> {
+    for (i, v) in self.viewport.viewports.iter().enumerate() {
+      let field = |f| format!("viewport.viewports[{}].{}", i, f);
+      check(v.width > 0.0, &field("width"), "must be greater than 0")?;
+      check(v.height != 0.0, &field("height"), "must not be 0")?;
+      check((0.0..=1.0).contains(&v.min_depth), &field("min_depth"), "must be in [0, 1]")?;
+      check((0.0..=1.0).contains(&v.max_depth), &field("max_depth"), "must be in [0, 1]")?;
+    }
+
+    check(self.raster.line_width > 0.0, "raster.line_width", "must be greater than 0")?;
+
+    let samples = self.multisample.samples;
+    check(
+      samples.is_power_of_two() && samples <= 64,
+      "multisample.samples",
+      "must be one of [1, 2, 4, 8, 16, 32, 64]",
+    )?;
+    if let Some(s) = self.multisample.sample_shading {
+      check((0.0..=1.0).contains(&s), "multisample.sample_shading", "must be in [0, 1]")?;
+    }
+
+    if let Some((min, max)) = self.depth_stencil.depth_bounds {
+      check(
+        (0.0..=1.0).contains(&min) && (0.0..=1.0).contains(&max),
+        "depth_stencil.depth_bounds",
+        "must be in [0, 1]",
+      )?;
+      check(min <= max, "depth_stencil.depth_bounds", "min must not be greater than max")?;
+    }
+
+    for (i, a) in self.blend.attachments.iter().enumerate() {
+      check(
+        a.write_mask.chars().all(|c| "rgba".contains(c)),
+        &format!("blend.attachments[{}].write_mask", i),
+        "must only contain the characters r, g, b and a",
+      )?;
+    }
+
+    for (i, d) in self.dynamic.iter().enumerate() {
+      check(
+        !self.dynamic[..i].contains(d),
+        &format!("dynamic[{}]", i),
+        &format!("{:?} is listed more than once", d),
+      )?;
+    }
+
+    Ok(())
+  }
+
+  pub fn input_assembly(&self) -> input_assembly::Builder {
+    input_assembly::Builder::default()
+      .topology(self.input_assembly.topology.to_vk())
+      .primitive_restart_enable(self.input_assembly.primitive_restart as vk::Bool32)
+  }
+
+  pub fn tesselation(&self) -> tesselation::Builder {
+    tesselation::Builder::default().patch_control_points(self.tesselation.patch_control_points)
+  }
+
+  pub fn viewport(&self) -> viewport::Builder {
+    let b = self.viewport.viewports.iter().fold(viewport::Builder::default(), |b, v| {
+      b.push_viewport(vk::Viewport {
+        x: v.x,
+        y: v.y,
+        width: v.width,
+        height: v.height,
+        minDepth: v.min_depth,
+        maxDepth: v.max_depth,
+      })
+    });
+    self.viewport.scissors.iter().fold(b, |b, s| {
+      b.push_scissors_rect(vk::Rect2D {
+        offset: vk::Offset2D { x: s.x, y: s.y },
+        extent: vk::Extent2D {
+          width: s.width,
+          height: s.height,
+        },
+      })
+    })
+  }
+
+  pub fn raster(&self) -> raster::Builder {
+    let r = &self.raster;
+    let bias = r.depth_bias.clone().unwrap_or_default();
+    raster::Builder::default()
+      .depth_clamp_enable(r.depth_clamp as vk::Bool32)
+      .discard_enable(r.discard as vk::Bool32)
+      .polygon_mode(r.polygon_mode.to_vk())
+      .line_width(r.line_width)
+      .cull_mode(r.cull_mode.to_vk())
+      .front_face(r.front_face.to_vk())
+      .depth_bias_enable(r.depth_bias.is_some() as vk::Bool32)
+      .depth_bias_constantfactor(bias.constant_factor)
+      .depth_bias_clamp(bias.clamp)
+      .depth_bias_slopefactor(bias.slope_factor)
+  }
+
+  pub fn multisample(&self) -> multisample::Builder {
+    let m = &self.multisample;
+    // the sample count flag bits have the same value as the number of samples
+    multisample::Builder::default()
+      .rasterization_samples(m.samples)
+      .sample_shading_enable(m.sample_shading.is_some() as vk::Bool32)
+      .min_sample_shading(m.sample_shading.unwrap_or(1.0))
+      .alpha_to_coverage_enable(m.alpha_to_coverage as vk::Bool32)
+      .alpha_to_one_enable(m.alpha_to_one as vk::Bool32)
+  }
+
+  pub fn depth_stencil(&self) -> depth_stencil::Builder {
+    let d = &self.depth_stencil;
+    let (min, max) = d.depth_bounds.unwrap_or((0.0, 1.0));
+    let stencil = d.stencil.clone().unwrap_or_default();
+    depth_stencil::Builder::default()
+      .depth_test_enable(d.depth_test as vk::Bool32)
+      .depth_write_enable(d.depth_write as vk::Bool32)
+      .depth_compare(d.depth_compare.to_vk())
+      .depth_bounds_test_enable(d.depth_bounds.is_some() as vk::Bool32)
+      .min_depth_bounds(min)
+      .max_depth_bounds(max)
+      .stencil_test_enable(d.stencil.is_some() as vk::Bool32)
+      .front(stencil.front.to_vk())
+      .back(stencil.back.to_vk())
+  }
+
+  pub fn blend(&self) -> blend::Builder {
+    let b = &self.blend;
+    let builder = blend::Builder::default()
+      .logic_op_enable(b.logic_op.is_some() as vk::Bool32)
+      .logic_op(b.logic_op.unwrap_or_default().to_vk())
+      .blend_constants(b.constants);
+    b.attachments.iter().fold(builder, |builder, a| {
+      builder.push_attachment(
+        blend::AttachmentBuilder::default()
+          .enable(a.enable as vk::Bool32)
+          .color_write_mask(a.get_write_mask())
+          .color(a.color.src.to_vk(), a.color.dst.to_vk(), a.color.op.to_vk())
+          .alpha(a.alpha.src.to_vk(), a.alpha.dst.to_vk(), a.alpha.op.to_vk()),
+      )
+    })
+  }
+
+  pub fn dynamic(&self) -> dynamic::Builder {
+    self
+      .dynamic
+      .iter()
+      .fold(dynamic::Builder::default(), |b, s| b.push_state(s.to_vk()))
+  }
+
+  /// Configures all states of the description in the builder
+  pub fn apply<'a>(&self, b: &'a mut Graphics) -> &'a mut Graphics {
+    if !self.viewport.viewports.is_empty() || !self.viewport.scissors.is_empty() {
+      b.viewport(self.viewport());
+    }
+    b.input_assembly(self.input_assembly())
+      .tesselation(self.tesselation())
+      .raster(self.raster())
+      .multisample(self.multisample())
+      .depth_stencil(self.depth_stencil())
+      .blend(self.blend())
+      .dynamic(self.dynamic())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roundtrip_default() {
+    let desc = GraphicsDesc::default();
+    assert_eq!(GraphicsDesc::from_ron(&desc.to_ron().unwrap()).unwrap(), desc);
+    assert_eq!(GraphicsDesc::from_ron("()").unwrap(), desc);
+  }
+
+  #[test]
+  fn roundtrip() {
+    let mut desc = GraphicsDesc::default();
+    desc.input_assembly.topology = Topology::LineStrip;
+    desc.viewport.viewports.push(ViewportRectDesc {
+      width: 1920.0,
+      height: -1080.0,
+      ..Default::default()
+    });
+    desc.viewport.scissors.push(ScissorDesc {
+      x: 0,
+      y: 0,
+      width: 1920,
+      height: 1080,
+    });
+    desc.raster.cull_mode = CullMode::None;
+    desc.raster.depth_bias = Some(DepthBiasDesc {
+      constant_factor: 1.25,
+      clamp: 0.0,
+      slope_factor: 1.75,
+    });
+    desc.multisample.samples = 4;
+    desc.multisample.sample_shading = Some(0.5);
+    desc.depth_stencil.depth_compare = CompareOp::Greater;
+    desc.depth_stencil.depth_bounds = Some((0.25, 0.75));
+    desc.depth_stencil.stencil = Some(StencilDesc {
+      front: StencilOpDesc {
+        pass: StencilOp::Replace,
+        reference: 1,
+        ..Default::default()
+      },
+      back: Default::default(),
+    });
+    desc.blend.logic_op = Some(LogicOp::Xor);
+    desc.blend.attachments.push(AttachmentDesc {
+      enable: true,
+      write_mask: "rgb".to_string(),
+      color: BlendEquationDesc {
+        src: BlendFactor::SrcAlpha,
+        dst: BlendFactor::OneMinusSrcAlpha,
+        op: BlendOp::Add,
+      },
+      alpha: Default::default(),
+    });
+    desc.dynamic = vec![DynamicState::Viewport, DynamicState::Scissor];
+
+    let ron = desc.to_ron().unwrap();
+    assert_eq!(GraphicsDesc::from_ron(&ron).unwrap(), desc);
+    assert_eq!(GraphicsDesc::from_ron(&ron).unwrap().to_ron().unwrap(), ron);
+  }
+
+  #[test]
+  fn partial() {
+    let desc = GraphicsDesc::from_ron(
+      "(
+        raster: (cull_mode: Front),
+        blend: (attachments: [(enable: true), ()]),
+      )",
+    )
+    .unwrap();
+
+    assert_eq!(desc.raster.cull_mode, CullMode::Front);
+    assert_eq!(desc.raster.front_face, FrontFace::CounterClockwise);
+    assert_eq!(desc.blend.attachments.len(), 2);
+    assert_eq!(desc.blend.attachments[1], AttachmentDesc::default());
+    assert_eq!(desc.blend.attachments[0].get_write_mask(), 0xf);
+    assert_eq!(desc.depth_stencil, DepthStencilDesc::default());
+  }
+
+  #[test]
+  fn validation() {
+    let err = |s| GraphicsDesc::from_ron(s).unwrap_err().field;
+    assert_eq!(err("(raster: (line_width: 0.0))"), "raster.line_width");
+    assert_eq!(err("(multisample: (samples: 3))"), "multisample.samples");
+    assert_eq!(err("(multisample: (sample_shading: Some(1.5)))"), "multisample.sample_shading");
+    assert_eq!(
+      err("(depth_stencil: (depth_bounds: Some((0.75, 0.25))))"),
+      "depth_stencil.depth_bounds"
+    );
+    assert_eq!(
+      err("(blend: (attachments: [(), (write_mask: \"rgbx\")]))"),
+      "blend.attachments[1].write_mask"
+    );
+    assert_eq!(err("(dynamic: [Viewport, Scissor, Viewport])"), "dynamic[2]");
+    assert_eq!(err("(viewport: (viewports: [(width: 0.0)]))"), "viewport.viewports[0].width");
+  }
+
+  #[test]
+  fn syntax() {
+    let e = GraphicsDesc::from_ron("(raster: ()) x").unwrap_err();
+    assert!(e.field.is_empty());
+    assert!(!e.message.is_empty());
+
+    let e = GraphicsDesc::from_ron("(depth_stencil: (depth_compare: Less), raster: (line_width: \"wide\"))").unwrap_err();
+    assert_eq!(e.field, "raster.line_width");
+  }
+
+  #[test]
+  fn unknown_field() {
+    let e = GraphicsDesc::from_ron("(raster: (cull: None))").unwrap_err();
+    assert_eq!(e.field, "raster.cull");
+    assert!(e.message.contains("cull"));
+
+    let err = |s| GraphicsDesc::from_ron(s).unwrap_err().field;
+    assert_eq!(err("(rastr: ())"), "rastr");
+    assert_eq!(err("(blend: (attachments: [(), (enabled: true)]))"), "blend.attachments[1].enabled");
+    assert_eq!(
+      err("(\n  // raster: (\n  blend: (logic_op: Some(Xor), /* [ */ logic: Xor),\n)"),
+      "blend.logic"
+    );
+  }
+
+  #[test]
+  fn bad_variant() {
+    let e = GraphicsDesc::from_ron("(depth_stencil: (depth_compare: Sometimes))").unwrap_err();
+    assert_eq!(e.field, "depth_stencil.depth_compare");
+    assert!(e.message.contains("Sometimes"));
+
+    let err = |s| GraphicsDesc::from_ron(s).unwrap_err().field;
+    assert_eq!(err("(dynamic: [Viewport, Scisor])"), "dynamic[1]");
+    assert_eq!(err("(blend: (logic_op: Some(Xorr)))"), "blend.logic_op");
+    assert_eq!(
+      err("(blend: (attachments: [(write_mask: \"rgb\", color: (src: Onee))]))"),
+      "blend.attachments[0].color.src"
+    );
+  }
+}
@@ -1,5 +1,7 @@
 pub mod blend;
 pub mod depth_stencil;
+#[cfg(feature = "desc")]
+pub mod desc;
 pub mod dynamic;
 pub mod input_assembly;
 pub mod multisample;
@@ -11,6 +13,10 @@ pub mod viewport;
 use crate::pipeline::builder;
 use crate::pipeline::Binding;
 use crate::pipeline::Pipeline;
+use crate::pipeline::PipelineCache;
+use crate::pipeline::SpecValue;
+use crate::pipeline::Specialization;
+use crate::DescriptorLayoutCache;
 use crate::Error;
 use vk;
 
@@ -26,6 +32,11 @@ pub struct Graphics {
   subpass: u32,
 
   bindings: Vec<Binding>,
+  push_constants: Vec<vk::PushConstantRange>,
+  specialization: Specialization,
+  cache: vk::PipelineCache,
+  layout_cache: Option<DescriptorLayoutCache>,
+  samplers: Vec<(u32, u32, vk::SamplerCreateInfo)>,
   vert: Option<vk::PipelineShaderStageCreateInfo>,
   tesc: Option<vk::PipelineShaderStageCreateInfo>,
   tese: Option<vk::PipelineShaderStageCreateInfo>,
@@ -55,6 +66,11 @@ impl Graphics {
       subpass,
 
       bindings: Default::default(),
+      push_constants: Default::default(),
+      specialization: Default::default(),
+      cache: vk::NULL_HANDLE,
+      layout_cache: None,
+      samplers: Default::default(),
       vert: None,
       tesc: None,
       tese: None,
@@ -92,6 +108,46 @@ impl Graphics {
     self.bindings = bindings.to_vec();
     self
   }
+  /// Configures the push constant ranges for the pipeline.
+  ///
+  /// The ranges are added to the pipeline layout.
+  pub fn push_constants(&mut self, push_constants: &[vk::PushConstantRange]) -> &mut Self {
+    self.push_constants = push_constants.to_vec();
+    self
+  }
+  /// Sets the value of a specialization constant for all shader stages of the pipeline.
+  ///
+  /// See [Specialization::set](../../struct.Specialization.html#method.set).
+  pub fn specialize<T: SpecValue>(&mut self, id: u32, value: T) -> &mut Self {
+    self.specialization.set(id, value);
+    self
+  }
+  /// Sets the pipeline cache, that is used for the pipeline creation.
+  ///
+  /// The cache must outlive the call to [create](struct.Graphics.html#method.create).
+  /// Without a cache the pipeline is always compiled from scratch.
+  pub fn cache(&mut self, cache: &PipelineCache) -> &mut Self {
+    self.cache = cache.handle;
+    self
+  }
+  /// Sets the cache, from which the descriptor set layouts of the pipeline are taken.
+  ///
+  /// Pipelines created with the same cache share identical descriptor set layouts and use the declared layouts of the cache.
+  /// See [DescriptorLayoutCache](../../descriptor/struct.DescriptorLayoutCache.html).
+  pub fn layout_cache(&mut self, cache: &DescriptorLayoutCache) -> &mut Self {
+    self.layout_cache = Some(cache.clone());
+    self
+  }
+  /// Sets an immutable sampler for a binding of type sampler or combined image sampler.
+  ///
+  /// The sampler is created with the pipeline and baked into the descriptor set layout, it is used for all array elements of the binding.
+  /// Descriptors of the binding do not need a sampler when they are written.
+  /// Setting a sampler for the same binding twice replaces the previous one.
+  pub fn immutable_sampler(&mut self, set: u32, binding: u32, info: &vk::SamplerCreateInfo) -> &mut Self {
+    self.samplers.retain(|(s, b, _)| *s != set || *b != binding);
+    self.samplers.push((set, binding, *info));
+    self
+  }
   /// Configures the vertex shader stage for the pipeline.
   pub fn vert(&mut self, vert: &vk::PipelineShaderStageCreateInfo) -> &mut Self {
     self.vert = Some(*vert);
@@ -166,7 +222,7 @@ impl Graphics {
 
   /// Create the pipeline from the current configuration
   pub fn create(&self) -> Result<Pipeline, Error> {
-    let stages = [self.vert, self.tesc, self.tesc, self.geom, self.frag]
+    let mut stages = [self.vert, self.tesc, self.tesc, self.geom, self.frag]
       .iter()
       .filter(|s| s.is_some())
       .map(|s| s.unwrap())
@@ -175,7 +231,20 @@ impl Graphics {
       Err(Error::InvalidShaderModule)?
     }
 
-    let (dsets, layout) = builder::create_layouts(self.device, &self.bindings);
+    let spec = self.specialization.get_info();
+    if !self.specialization.is_empty() {
+      stages.iter_mut().for_each(|s| s.pSpecializationInfo = &spec);
+    }
+
+    let samplers = builder::create_samplers(self.device, &self.bindings, &self.samplers)?;
+    let (dsets, layout) = builder::create_layouts(
+      self.device,
+      &self.bindings,
+      &self.push_constants,
+      self.layout_cache.as_ref(),
+      &samplers,
+    )
+    .inspect_err(|_| builder::destroy_samplers(self.device, &samplers))?;
 
     let create_info = vk::GraphicsPipelineCreateInfo {
       sType: vk::STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO,
@@ -206,13 +275,17 @@ impl Graphics {
     let mut handle = vk::NULL_HANDLE;
     vk_check!(vk::CreateGraphicsPipelines(
       self.device,
-      vk::NULL_HANDLE,
+      self.cache,
       1,
       &create_info,
       std::ptr::null(),
       &mut handle
     ))
-    .map_err(|e| Error::PipelineCreateFail(e))?;
+    .map_err(|e| {
+      builder::destroy_layouts(self.device, &dsets, layout, self.layout_cache.as_ref());
+      builder::destroy_samplers(self.device, &samplers);
+      Error::PipelineCreateFail(e)
+    })?;
 
     stages
       .iter()
@@ -223,6 +296,8 @@ impl Graphics {
       handle,
       dsets,
       layout,
+      layout_cache: self.layout_cache.clone(),
+      samplers: samplers.iter().map(|(_, _, s)| *s).collect(),
     })
   }
 }
@@ -3,25 +3,69 @@ pub mod graphics;
 
 use crate::pipeline::Binding;
 use crate::DescriptorLayout;
+use crate::DescriptorLayoutCache;
+use crate::Error;
 use std::collections::HashMap;
 use vk;
 
 pub fn create_descriptor_layout(device: vk::Device, bindings: &[Binding]) -> vk::DescriptorSetLayout {
+  create_descriptor_layout_with_samplers(device, bindings, &[])
+}
+
+/// Creates a descriptor set layout with immutable samplers
+///
+/// `samplers` maps a binding to the sampler, that is used for all of its array elements.
+pub fn create_descriptor_layout_with_samplers(
+  device: vk::Device,
+  bindings: &[Binding],
+  samplers: &[(u32, vk::Sampler)],
+) -> vk::DescriptorSetLayout {
+  // one sampler for every array element, must outlive the layout creation
+  let immutable_samplers: Vec<Vec<vk::Sampler>> = bindings
+    .iter()
+    .map(|b| match samplers.iter().find(|(binding, _)| *binding == b.binding) {
+      Some((_, sampler)) => vec![*sampler; b.arrayelems as usize],
+      None => Vec::new(),
+    })
+    .collect();
+
   let layout_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
     .iter()
-    .map(|b| vk::DescriptorSetLayoutBinding {
+    .zip(immutable_samplers.iter())
+    .map(|(b, s)| vk::DescriptorSetLayoutBinding {
       binding: b.binding,
       descriptorType: b.desctype,
       descriptorCount: b.arrayelems,
       stageFlags: b.stageflags,
-      pImmutableSamplers: std::ptr::null(),
+      pImmutableSamplers: if s.is_empty() { std::ptr::null() } else { s.as_ptr() },
     })
     .collect();
 
+  // binding flags for descriptor indexing are only chained if any binding uses them
+  let binding_flags: Vec<vk::DescriptorBindingFlagsEXT> = bindings.iter().map(|b| b.flags).collect();
+  let has_flags = binding_flags.iter().any(|f| *f != 0);
+  let update_after_bind = binding_flags
+    .iter()
+    .any(|f| f & vk::DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT_EXT != 0);
+  let flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfoEXT {
+    sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_BINDING_FLAGS_CREATE_INFO_EXT,
+    pNext: std::ptr::null(),
+    bindingCount: binding_flags.len() as u32,
+    pBindingFlags: binding_flags.as_ptr(),
+  };
+
   let create_info = vk::DescriptorSetLayoutCreateInfo {
     sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
-    pNext: std::ptr::null(),
-    flags: 0,
+    pNext: if has_flags {
+      &flags_info as *const vk::DescriptorSetLayoutBindingFlagsCreateInfoEXT as *const std::os::raw::c_void
+    } else {
+      std::ptr::null()
+    },
+    flags: if update_after_bind {
+      vk::DESCRIPTOR_SET_LAYOUT_CREATE_UPDATE_AFTER_BIND_POOL_BIT_EXT
+    } else {
+      0
+    },
     bindingCount: layout_bindings.len() as u32,
     pBindings: layout_bindings.as_ptr(),
   };
@@ -32,6 +76,15 @@ pub fn create_descriptor_layout(device: vk::Device, bindings: &[Binding]) -> vk:
 }
 
 pub fn create_pipeline_layout(device: vk::Device, dset_layouts: &[vk::DescriptorSetLayout]) -> vk::PipelineLayout {
+  create_pipeline_layout_with_push_constants(device, dset_layouts, &[])
+}
+
+/// Creates a pipeline layout with push constant ranges
+pub fn create_pipeline_layout_with_push_constants(
+  device: vk::Device,
+  dset_layouts: &[vk::DescriptorSetLayout],
+  push_constants: &[vk::PushConstantRange],
+) -> vk::PipelineLayout {
   // create the pipeline layout
   let create_info = vk::PipelineLayoutCreateInfo {
     sType: vk::STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO,
@@ -39,8 +92,8 @@ pub fn create_pipeline_layout(device: vk::Device, dset_layouts: &[vk::Descriptor
     flags: 0,
     setLayoutCount: dset_layouts.len() as u32,
     pSetLayouts: dset_layouts.as_ptr(),
-    pushConstantRangeCount: 0,
-    pPushConstantRanges: std::ptr::null(),
+    pushConstantRangeCount: push_constants.len() as u32,
+    pPushConstantRanges: push_constants.as_ptr(),
   };
 
   let mut handle = vk::NULL_HANDLE;
@@ -48,7 +101,85 @@ pub fn create_pipeline_layout(device: vk::Device, dset_layouts: &[vk::Descriptor
   handle
 }
 
-fn create_layouts(device: vk::Device, bindings: &[Binding]) -> (Vec<DescriptorLayout>, vk::PipelineLayout) {
+/// Default description of a sampler
+///
+/// Immutable samplers of the [pipeline](../../../nobs_vkpipes_macro/macro.pipeline.html) macro take all parameters, that are not specified, from here.
+pub const DEFAULT_SAMPLER: vk::SamplerCreateInfo = vk::SamplerCreateInfo {
+  sType: vk::STRUCTURE_TYPE_SAMPLER_CREATE_INFO,
+  pNext: std::ptr::null(),
+  flags: 0,
+  magFilter: vk::FILTER_NEAREST,
+  minFilter: vk::FILTER_NEAREST,
+  mipmapMode: vk::SAMPLER_MIPMAP_MODE_LINEAR,
+  addressModeU: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+  addressModeV: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+  addressModeW: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+  mipLodBias: 0.0,
+  anisotropyEnable: vk::FALSE,
+  maxAnisotropy: 1.0,
+  compareEnable: vk::FALSE,
+  compareOp: vk::COMPARE_OP_ALWAYS,
+  minLod: 0.0,
+  maxLod: 1.0,
+  borderColor: vk::BORDER_COLOR_INT_OPAQUE_BLACK,
+  unnormalizedCoordinates: vk::FALSE,
+};
+
+/// Checks that there is a binding of type sampler or combined image sampler for every immutable sampler
+///
+/// Fails with `Error::InvalidImmutableSampler` with the set and binding of the first invalid sampler.
+fn check_samplers(bindings: &[Binding], samplers: &[(u32, u32)]) -> Result<(), Error> {
+  for (set, binding) in samplers.iter() {
+    let valid = bindings.iter().any(|b| {
+      b.descset == *set
+        && b.binding == *binding
+        && (b.desctype == vk::DESCRIPTOR_TYPE_SAMPLER || b.desctype == vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER)
+    });
+    if !valid {
+      Err(Error::InvalidImmutableSampler(*set, *binding))?
+    }
+  }
+  Ok(())
+}
+
+/// Creates the immutable samplers for the bindings of a pipeline
+///
+/// Fails if there is no binding of type sampler or combined image sampler for one of the samplers, see [check_samplers](fn.check_samplers.html).
+fn create_samplers(
+  device: vk::Device,
+  bindings: &[Binding],
+  infos: &[(u32, u32, vk::SamplerCreateInfo)],
+) -> Result<Vec<(u32, u32, vk::Sampler)>, Error> {
+  check_samplers(
+    bindings,
+    &infos.iter().map(|(set, binding, _)| (*set, *binding)).collect::<Vec<_>>(),
+  )?;
+
+  let mut samplers = Vec::with_capacity(infos.len());
+  for (set, binding, info) in infos.iter() {
+    let mut handle = vk::NULL_HANDLE;
+    if let Err(e) = vk_check!(vk::CreateSampler(device, info, std::ptr::null(), &mut handle)) {
+      destroy_samplers(device, &samplers);
+      Err(Error::SamplerCreateFail(e))?
+    }
+    samplers.push((*set, *binding, handle));
+  }
+  Ok(samplers)
+}
+
+fn destroy_samplers(device: vk::Device, samplers: &[(u32, u32, vk::Sampler)]) {
+  samplers
+    .iter()
+    .for_each(|(_, _, s)| vk::DestroySampler(device, *s, std::ptr::null()));
+}
+
+fn create_layouts(
+  device: vk::Device,
+  bindings: &[Binding],
+  push_constants: &[vk::PushConstantRange],
+  cache: Option<&DescriptorLayoutCache>,
+  samplers: &[(u32, u32, vk::Sampler)],
+) -> Result<(Vec<DescriptorLayout>, vk::PipelineLayout), Error> {
   // spilt up bindings by descriptor set
   let dset_bindings = bindings.iter().fold(HashMap::new(), |mut acc, b| {
     {
@@ -58,17 +189,117 @@ fn create_layouts(device: vk::Device, bindings: &[Binding]) -> (Vec<DescriptorLa
     acc
   });
 
-  // layout and sizes for every descriptor set
-  let mut dsets: Vec<(u32, DescriptorLayout)> = dset_bindings
-    .iter()
-    .map(|(set, b)| (*set, DescriptorLayout::from_bindings(device, b)))
-    .collect();
-  dsets.sort_by_key(|d| d.0);
-  let dsets: Vec<DescriptorLayout> = dsets.iter().map(|ds| ds.1.clone()).collect();
+  // sort by set index, so that the pipeline layout references the layouts in order
+  let mut dset_bindings: Vec<(u32, Vec<Binding>)> = dset_bindings.into_iter().collect();
+  dset_bindings.sort_by_key(|d| d.0);
+
+  // layout and sizes for every descriptor set, shared layouts are looked up in the cache
+  let dsets: Vec<DescriptorLayout> = match cache {
+    Some(cache) => {
+      let mut dsets = Vec::with_capacity(dset_bindings.len());
+      for (set, b) in dset_bindings.iter() {
+        match cache.get(*set, b, &get_set_samplers(*set, samplers)) {
+          Ok(l) => dsets.push(l),
+          Err(e) => {
+            dsets.iter().for_each(|l: &DescriptorLayout| cache.release(l.layout));
+            Err(e)?
+          }
+        }
+      }
+      dsets
+    }
+    None => dset_bindings
+      .iter()
+      .map(|(set, b)| DescriptorLayout::from_bindings_with_samplers(device, b, &get_set_samplers(*set, samplers)))
+      .collect(),
+  };
 
   // pipeline layout
   let layouts: Vec<vk::DescriptorSetLayout> = dsets.iter().map(|ds| ds.layout).collect();
-  let pipe_layout = create_pipeline_layout(device, &layouts);
+  let pipe_layout = create_pipeline_layout_with_push_constants(device, &layouts, push_constants);
+
+  Ok((dsets, pipe_layout))
+}
+
+/// Destroys the pipeline layout and descriptor set layouts created with [create_layouts](fn.create_layouts.html)
+///
+/// Descriptor set layouts from the cache are released instead.
+fn destroy_layouts(device: vk::Device, dsets: &[DescriptorLayout], layout: vk::PipelineLayout, cache: Option<&DescriptorLayoutCache>) {
+  for ds in dsets.iter() {
+    match cache {
+      Some(cache) => cache.release(ds.layout),
+      None => vk::DestroyDescriptorSetLayout(device, ds.layout, std::ptr::null()),
+    }
+  }
+  vk::DestroyPipelineLayout(device, layout, std::ptr::null());
+}
+
+fn get_set_samplers(set: u32, samplers: &[(u32, u32, vk::Sampler)]) -> Vec<(u32, vk::Sampler)> {
+  samplers
+    .iter()
+    .filter(|(s, _, _)| *s == set)
+    .map(|(_, binding, sampler)| (*binding, *sampler))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn binding(descset: u32, binding: u32, desctype: vk::DescriptorType) -> Binding {
+    Binding {
+      name: "b",
+      binding,
+      descset,
+      desctype,
+      arrayelems: 1,
+      stageflags: vk::SHADER_STAGE_FRAGMENT_BIT,
+      flags: 0,
+    }
+  }
+
+  fn is_invalid(r: Result<(), Error>, set: u32, b: u32) -> bool {
+    match r {
+      Err(Error::InvalidImmutableSampler(s, bb)) => s == set && bb == b,
+      _ => false,
+    }
+  }
+
+  #[test]
+  fn samplers() {
+    let bindings = [
+      binding(0, 0, vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER),
+      binding(0, 1, vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER),
+      binding(1, 0, vk::DESCRIPTOR_TYPE_SAMPLER),
+      binding(1, 1, vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE),
+    ];
+
+    assert!(check_samplers(&bindings, &[]).is_ok());
+    assert!(check_samplers(&bindings, &[(0, 1), (1, 0)]).is_ok());
+
+    // binding is not a sampler
+    assert!(is_invalid(check_samplers(&bindings, &[(0, 1), (0, 0)]), 0, 0));
+    assert!(is_invalid(check_samplers(&bindings, &[(1, 1)]), 1, 1));
+    // binding does not exist in the set
+    assert!(is_invalid(check_samplers(&bindings, &[(1, 2)]), 1, 2));
+    assert!(is_invalid(check_samplers(&bindings, &[(2, 0)]), 2, 0));
+  }
+
+  #[test]
+  fn create_invalid() {
+    // validation fails before any sampler is created
+    let bindings = [binding(0, 0, vk::DESCRIPTOR_TYPE_STORAGE_IMAGE)];
+    assert!(match create_samplers(vk::NULL_HANDLE, &bindings, &[(0, 0, DEFAULT_SAMPLER)]) {
+      Err(Error::InvalidImmutableSampler(0, 0)) => true,
+      _ => false,
+    });
+  }
 
-  (dsets, pipe_layout)
+  #[test]
+  fn set_samplers() {
+    let samplers = [(0, 1, 10), (1, 0, 20), (0, 3, 30), (2, 0, 40)];
+    assert_eq!(get_set_samplers(0, &samplers), vec![(1, 10), (3, 30)]);
+    assert_eq!(get_set_samplers(1, &samplers), vec![(0, 20)]);
+    assert_eq!(get_set_samplers(3, &samplers), vec![]);
+  }
 }
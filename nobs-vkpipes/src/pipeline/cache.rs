@@ -0,0 +1,404 @@
+use crate::Error;
+use vk;
+
+use std::path::Path;
+
+/// Size of the pipeline cache header version one in bytes
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE as usize;
+
+/// Size of the checksum, that is appended to the cache data in the file
+const CHECKSUM_SIZE: usize = 8;
+
+/// FNV-1a hash of the cache data, to detect truncated or otherwise corrupted files
+fn checksum(data: &[u8]) -> u64 {
+  data
+    .iter()
+    .fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Appends the checksum to the cache data
+fn encode(data: &[u8]) -> Vec<u8> {
+  let mut file = data.to_vec();
+  file.extend_from_slice(&checksum(data).to_le_bytes());
+  file
+}
+
+/// Splits the checksum from the cache data
+///
+/// Returns `None`, if the file is too short or the checksum does not match the data.
+fn decode(file: &[u8]) -> Option<&[u8]> {
+  if file.len() < CHECKSUM_SIZE {
+    return None;
+  }
+
+  let (data, sum) = file.split_at(file.len() - CHECKSUM_SIZE);
+  let mut bytes = [0u8; CHECKSUM_SIZE];
+  bytes.copy_from_slice(sum);
+  match u64::from_le_bytes(bytes) == checksum(data) {
+    true => Some(data),
+    false => None,
+  }
+}
+
+/// Reads a little endian `u32` of the cache header
+///
+/// The header fields are written in little endian byte order, regardless of the byte order of the host.
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+  let mut bytes = [0u8; 4];
+  bytes.copy_from_slice(&data[offset..offset + 4]);
+  u32::from_le_bytes(bytes)
+}
+
+/// Writes the cache data with its checksum to a temporary file next to `path`, that is then renamed to `path`
+fn write_file(path: &Path, data: &[u8]) -> Result<(), Error> {
+  if let Some(dir) = path.parent() {
+    if !dir.as_os_str().is_empty() {
+      std::fs::create_dir_all(dir).map_err(|e| Error::PipelineCacheIo(e))?;
+    }
+  }
+
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+  std::fs::write(&tmp, encode(data)).map_err(|e| Error::PipelineCacheIo(e))?;
+  std::fs::rename(&tmp, path).map_err(|e| Error::PipelineCacheIo(e))
+}
+
+/// Reads the cache data from a file, that was written with `write_file`
+///
+/// Returns `None`, if the file does not exist, the checksum does not match or the header does not match the physical device.
+/// Other errors reading the file are returned, instead of silently starting out with an empty cache.
+fn read_file(path: &Path, properties: &vk::PhysicalDeviceProperties) -> Result<Option<Vec<u8>>, Error> {
+  let file = match std::fs::read(path) {
+    Ok(file) => file,
+    Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(e) => Err(Error::PipelineCacheIo(e))?,
+  };
+
+  Ok(
+    decode(&file)
+      .filter(|data| !data.is_empty() && PipelineCache::is_compatible(properties, data))
+      .map(|data| data.to_vec()),
+  )
+}
+
+/// Gets the properties of the physical device, that identify compatible cache data
+fn get_properties(pdevice: vk::PhysicalDevice) -> vk::PhysicalDeviceProperties {
+  let mut properties = std::mem::MaybeUninit::uninit();
+  vk::GetPhysicalDeviceProperties(pdevice, properties.as_mut_ptr());
+  unsafe { properties.assume_init() }
+}
+
+/// Persistent pipeline cache
+///
+/// Wraps a `vk::PipelineCache`, that can be loaded from and saved to a file.
+/// Set the cache with [ComputeBuilder::cache](../struct.ComputeBuilder.html#method.cache) or
+/// [GraphicsBuilder::cache](../struct.GraphicsBuilder.html#method.cache), so that pipelines that have been compiled on a previous run can be reused.
+///
+/// The file contains the cache data as returned by `vk::GetPipelineCacheData` followed by a checksum of the data.
+/// Before the data is passed to the driver, the checksum and the cache header (header version, vendor id, device id and cache UUID) are validated against the physical device.
+/// Files that are missing, corrupted or have been written for a different device or driver are ignored and the cache starts out empty.
+///
+/// Pipeline caches are internally synchronized, the same cache can be used to create pipelines from multiple threads.
+///
+/// ## Example
+/// ```rust,ignore
+/// let cache = vk::pipes::PipelineCache::load(pdevice, device, "cache/pipelines.bin")?;
+///
+/// let p = my_pipeline::new(device).cache(&cache).create()?;
+///
+/// cache.save("cache/pipelines.bin")?;
+/// ```
+pub struct PipelineCache {
+  pub device: vk::Device,
+  pub handle: vk::PipelineCache,
+  loaded: bool,
+}
+
+impl Drop for PipelineCache {
+  fn drop(&mut self) {
+    vk::DestroyPipelineCache(self.device, self.handle, std::ptr::null());
+  }
+}
+
+impl PipelineCache {
+  /// Creates an empty pipeline cache
+  pub fn new(pdevice: vk::PhysicalDevice, device: vk::Device) -> Result<Self, Error> {
+    Self::with_data(pdevice, device, &[])
+  }
+
+  /// Creates a pipeline cache from the data of a previous [get_data](struct.PipelineCache.html#method.get_data)
+  ///
+  /// If the data does not have a valid header for the physical device, it is discarded and an empty cache is created.
+  pub fn with_data(pdevice: vk::PhysicalDevice, device: vk::Device, data: &[u8]) -> Result<Self, Error> {
+    let properties = get_properties(pdevice);
+    let data = match Self::is_compatible(&properties, data) {
+      true => data,
+      false => &[],
+    };
+
+    let (handle, loaded) = match Self::create(device, data) {
+      Ok(handle) => (handle, !data.is_empty()),
+      // the driver might still reject the data, fall back to an empty cache
+      Err(_) if !data.is_empty() => (Self::create(device, &[])?, false),
+      Err(e) => Err(e)?,
+    };
+
+    Ok(Self { device, handle, loaded })
+  }
+
+  /// Creates a pipeline cache from a file, that was written with [save](struct.PipelineCache.html#method.save)
+  ///
+  /// If the file does not exist, is corrupted or was written for a different device or driver an empty cache is created.
+  /// Use [is_loaded](struct.PipelineCache.html#method.is_loaded) to check if the data of the file is used.
+  /// Returns `Error::PipelineCacheIo`, if the file exists but can not be read.
+  pub fn load<P: AsRef<Path>>(pdevice: vk::PhysicalDevice, device: vk::Device, path: P) -> Result<Self, Error> {
+    match read_file(path.as_ref(), &get_properties(pdevice))? {
+      Some(data) => Self::with_data(pdevice, device, &data),
+      None => Self::new(pdevice, device),
+    }
+  }
+
+  /// Writes the current cache data to a file
+  ///
+  /// The data is first written to a temporary file next to `path`, that is then renamed.
+  /// This way an interrupted write never leaves a partially written cache file behind.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    write_file(path.as_ref(), &self.get_data()?)
+  }
+
+  /// Get the data of the cache
+  pub fn get_data(&self) -> Result<Vec<u8>, Error> {
+    let mut size = 0;
+    vk_check!(vk::GetPipelineCacheData(self.device, self.handle, &mut size, std::ptr::null_mut()))
+      .map_err(|e| Error::PipelineCacheDataFail(e))?;
+
+    let mut data = vec![0u8; size];
+    vk_check!(vk::GetPipelineCacheData(
+      self.device,
+      self.handle,
+      &mut size,
+      data.as_mut_ptr() as *mut std::os::raw::c_void
+    ))
+    .map_err(|e| Error::PipelineCacheDataFail(e))?;
+    data.truncate(size);
+
+    Ok(data)
+  }
+
+  /// Merges the pipelines of other caches into this cache
+  pub fn merge(&self, caches: &[&PipelineCache]) -> Result<(), Error> {
+    let handles = caches.iter().map(|c| c.handle).collect::<Vec<_>>();
+    vk_check!(vk::MergePipelineCaches(
+      self.device,
+      self.handle,
+      handles.len() as u32,
+      handles.as_ptr()
+    ))
+    .map_err(|e| Error::PipelineCacheDataFail(e))?;
+    Ok(())
+  }
+
+  /// Checks if the cache was created with data from a previous run
+  ///
+  /// Returns false, if the cache was created empty, or the data was discarded because it was invalid.
+  pub fn is_loaded(&self) -> bool {
+    self.loaded
+  }
+
+  /// Checks if cache data matches the header of the physical device
+  ///
+  /// Empty data is always compatible.
+  pub fn is_compatible(properties: &vk::PhysicalDeviceProperties, data: &[u8]) -> bool {
+    if data.is_empty() {
+      return true;
+    }
+    if data.len() < HEADER_SIZE {
+      return false;
+    }
+
+    let header_size = read_u32(data, 0) as usize;
+    header_size >= HEADER_SIZE
+      && header_size <= data.len()
+      && read_u32(data, 4) == vk::PIPELINE_CACHE_HEADER_VERSION_ONE
+      && read_u32(data, 8) == properties.vendorID
+      && read_u32(data, 12) == properties.deviceID
+      && data[16..HEADER_SIZE] == properties.pipelineCacheUUID[..]
+  }
+
+  fn create(device: vk::Device, data: &[u8]) -> Result<vk::PipelineCache, Error> {
+    let create_info = vk::PipelineCacheCreateInfo {
+      sType: vk::STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO,
+      pNext: std::ptr::null(),
+      flags: 0,
+      initialDataSize: data.len(),
+      pInitialData: data.as_ptr() as *const std::os::raw::c_void,
+    };
+
+    let mut handle = vk::NULL_HANDLE;
+    vk_check!(vk::CreatePipelineCache(device, &create_info, std::ptr::null(), &mut handle))
+      .map_err(|e| Error::PipelineCacheCreateFail(e))?;
+    Ok(handle)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn properties() -> vk::PhysicalDeviceProperties {
+    let mut properties: vk::PhysicalDeviceProperties = unsafe { std::mem::zeroed() };
+    properties.vendorID = 0x10de;
+    properties.deviceID = 0x1b80;
+    for (i, b) in properties.pipelineCacheUUID.iter_mut().enumerate() {
+      *b = i as u8;
+    }
+    properties
+  }
+
+  fn header(properties: &vk::PhysicalDeviceProperties) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+    data.extend_from_slice(&vk::PIPELINE_CACHE_HEADER_VERSION_ONE.to_le_bytes());
+    data.extend_from_slice(&properties.vendorID.to_le_bytes());
+    data.extend_from_slice(&properties.deviceID.to_le_bytes());
+    data.extend_from_slice(&properties.pipelineCacheUUID);
+    data.extend_from_slice(&[1, 2, 3, 4]);
+    data
+  }
+
+  #[test]
+  fn compatible() {
+    let properties = properties();
+    let data = header(&properties);
+    assert!(PipelineCache::is_compatible(&properties, &data));
+    assert!(PipelineCache::is_compatible(&properties, &[]));
+  }
+
+  #[test]
+  fn truncated_header() {
+    let properties = properties();
+    let data = header(&properties);
+    assert!(!PipelineCache::is_compatible(&properties, &data[..HEADER_SIZE - 1]));
+
+    // header size larger than the data
+    let mut data = header(&properties);
+    let len = data.len() as u32;
+    data[0..4].copy_from_slice(&(len + 1).to_le_bytes());
+    assert!(!PipelineCache::is_compatible(&properties, &data));
+
+    // header size smaller than the header version one
+    let mut data = header(&properties);
+    data[0..4].copy_from_slice(&(HEADER_SIZE as u32 - 1).to_le_bytes());
+    assert!(!PipelineCache::is_compatible(&properties, &data));
+  }
+
+  #[test]
+  fn wrong_device() {
+    let properties = properties();
+
+    let mut vendor = properties;
+    vendor.vendorID = 0x1002;
+    assert!(!PipelineCache::is_compatible(&vendor, &header(&properties)));
+
+    let mut device = properties;
+    device.deviceID += 1;
+    assert!(!PipelineCache::is_compatible(&device, &header(&properties)));
+
+    let mut uuid = properties;
+    uuid.pipelineCacheUUID[vk::UUID_SIZE as usize - 1] ^= 0xff;
+    assert!(!PipelineCache::is_compatible(&uuid, &header(&properties)));
+
+    // the header fields are little endian
+    let mut data = header(&properties);
+    data[8..12].copy_from_slice(&properties.vendorID.to_be_bytes());
+    assert!(!PipelineCache::is_compatible(&properties, &data));
+
+    let mut data = header(&properties);
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    assert!(!PipelineCache::is_compatible(&properties, &data));
+  }
+
+  #[test]
+  fn checksum_mismatch() {
+    let data = header(&properties());
+    let mut file = encode(&data);
+    assert_eq!(decode(&file), Some(&data[..]));
+
+    let last = file.len() - 1;
+    file[last] ^= 1;
+    assert_eq!(decode(&file), None);
+
+    let mut file = encode(&data);
+    file[20] ^= 1;
+    assert_eq!(decode(&file), None);
+
+    let file = encode(&data);
+    assert_eq!(decode(&file[..file.len() - 1]), None);
+    assert_eq!(decode(&file[..CHECKSUM_SIZE - 1]), None);
+    assert_eq!(decode(&encode(&[])), Some(&[][..]));
+  }
+
+  fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("nobs-vkpipes-cache-{}-{}", std::process::id(), name))
+  }
+
+  #[test]
+  fn round_trip() {
+    let properties = properties();
+    let data = header(&properties);
+    let dir = temp_path("round_trip");
+    let path = dir.join("cache").join("pipelines.bin");
+
+    // save creates the directory and leaves no temporary file behind
+    write_file(&path, &data).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), encode(&data));
+    assert!(!path.with_extension("bin.tmp").exists());
+    assert_eq!(read_file(&path, &properties).unwrap(), Some(data.clone()));
+
+    // an existing file is replaced
+    let mut other = header(&properties);
+    other.extend_from_slice(&[5, 6]);
+    write_file(&path, &other).unwrap();
+    assert_eq!(read_file(&path, &properties).unwrap(), Some(other));
+
+    // the header is validated against the device on load
+    let mut device = properties;
+    device.deviceID += 1;
+    assert_eq!(read_file(&path, &device).unwrap(), None);
+
+    // corrupted files are ignored
+    let mut file = std::fs::read(&path).unwrap();
+    file[20] ^= 1;
+    std::fs::write(&path, &file).unwrap();
+    assert_eq!(read_file(&path, &properties).unwrap(), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn read_errors() {
+    let properties = properties();
+
+    // a missing file starts out with an empty cache
+    let path = temp_path("missing.bin");
+    assert_eq!(read_file(&path, &properties).unwrap(), None);
+
+    // a file that can not be read is an error and not silently ignored
+    let dir = temp_path("read_errors");
+    std::fs::create_dir_all(&dir).unwrap();
+    match read_file(&dir, &properties) {
+      Err(Error::PipelineCacheIo(_)) => (),
+      r => panic!("expected PipelineCacheIo, got {:?}", r),
+    }
+
+    // the rename fails if the target is a directory, the temporary file is written anyway
+    match write_file(&dir, &header(&properties)) {
+      Err(Error::PipelineCacheIo(_)) => (),
+      r => panic!("expected PipelineCacheIo, got {:?}", r),
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+    let mut tmp = dir.into_os_string();
+    tmp.push(".tmp");
+    std::fs::remove_file(&tmp).unwrap();
+  }
+}
@@ -1,6 +1,10 @@
 pub mod builder;
+mod cache;
+
+pub use cache::PipelineCache;
 
 use crate::DescriptorLayout;
+use crate::DescriptorLayoutCache;
 use vk;
 
 /// Binding for a uniform variable in a vulkan pipeline
@@ -12,6 +16,152 @@ pub struct Binding {
   pub desctype: vk::DescriptorType,
   pub arrayelems: u32,
   pub stageflags: vk::ShaderStageFlagBits,
+  pub flags: vk::DescriptorBindingFlagsEXT,
+}
+
+/// Specialization constant of a shader
+///
+/// The generated pipeline and shader modules define a constant with the id and default value for every specialization constant.
+#[derive(Debug, Clone, Copy)]
+pub struct SpecConstant<T: Copy> {
+  pub name: &'static str,
+  pub id: u32,
+  pub default: T,
+}
+
+/// Marker trait for the scalar types of specialization constant values
+///
+/// The value is passed to the driver byte wise, so the type must not have padding bytes and every bit pattern must be a valid value.
+/// Implemented for the integer and floating point types, booleans are set with `vk::Bool32`.
+///
+/// # Safety
+/// Implementing this trait is only sound for types without padding bytes, pointers or `Drop` implementation.
+pub unsafe trait SpecValue: Copy + 'static {}
+
+macro_rules! impl_spec_value {
+  ($($t:ty),*) => {
+    $(unsafe impl SpecValue for $t {})*
+  };
+}
+
+impl_spec_value!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+/// Values of specialization constants, that are set during pipeline creation
+///
+/// The same values are used for all shader stages of a pipeline.
+/// Constants, that are not set, keep the default value of the shader.
+#[derive(Debug, Clone, Default)]
+pub struct Specialization {
+  entries: Vec<vk::SpecializationMapEntry>,
+  data: Vec<u8>,
+}
+
+impl Specialization {
+  /// Sets the value of the specialization constant with id `id`
+  ///
+  /// If the constant was already set, its value is replaced.
+  /// The type of the value must match the type of the constant in the shader, booleans are set with `vk::Bool32`.
+  pub fn set<T: SpecValue>(&mut self, id: u32, value: T) -> &mut Self {
+    // SpecValue types have no padding, so all bytes of the value are initialized
+    let bytes = unsafe { std::slice::from_raw_parts(&value as *const T as *const u8, std::mem::size_of::<T>()) };
+    match self.entries.iter().find(|e| e.constantID == id) {
+      Some(e) if e.size == bytes.len() => {
+        let offset = e.offset as usize;
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+      }
+      _ => {
+        self.entries.retain(|e| e.constantID != id);
+        self.entries.push(vk::SpecializationMapEntry {
+          constantID: id,
+          offset: self.data.len() as u32,
+          size: bytes.len(),
+        });
+        self.data.extend_from_slice(bytes);
+      }
+    }
+    self
+  }
+
+  /// Checks if no specialization constant is set
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Get the specialization info referencing the set constants
+  ///
+  /// The info is only valid as long as the specialization is not modified or dropped.
+  pub fn get_info(&self) -> vk::SpecializationInfo {
+    vk::SpecializationInfo {
+      mapEntryCount: self.entries.len() as u32,
+      pMapEntries: self.entries.as_ptr(),
+      dataSize: self.data.len(),
+      pData: self.data.as_ptr() as *const std::os::raw::c_void,
+    }
+  }
+}
+
+/// Marker trait for values, that are pushed with [PushConstants](struct.PushConstants.html)
+///
+/// The value is copied byte wise, so the type must not have padding bytes.
+/// Implemented for the integer and floating point types, arrays of them and the `PushConstants` structs generated by the pipeline and shader macros,
+/// which declare the gaps between members as explicit padding fields.
+///
+/// # Safety
+/// Implementing this trait is only sound for `#[repr(C)]` types without padding bytes, pointers or `Drop` implementation.
+pub unsafe trait PushValue: Copy + 'static {}
+
+macro_rules! impl_push_value {
+  ($($t:ty),*) => {
+    $(unsafe impl PushValue for $t {})*
+  };
+}
+
+impl_push_value!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+unsafe impl<T: PushValue, const N: usize> PushValue for [T; N] {}
+
+/// Command to update the push constants of a pipeline layout
+///
+/// Records `vkCmdPushConstants` with the stage flags and offset of the push constant range.
+/// The generated pipeline modules define a typed `PushConstants` struct, that creates this command with `push(layout)`.
+#[derive(Debug, Clone)]
+pub struct PushConstants {
+  pub layout: vk::PipelineLayout,
+  pub stageflags: vk::ShaderStageFlags,
+  pub offset: u32,
+  pub data: Vec<u8>,
+}
+
+impl PushConstants {
+  /// Creates the command from the first `range.size` bytes of `value`
+  ///
+  /// Panics if `value` is smaller than the range.
+  pub fn new<T: PushValue>(layout: vk::PipelineLayout, range: vk::PushConstantRange, value: &T) -> Self {
+    assert!(
+      range.size as usize <= std::mem::size_of::<T>(),
+      "push constant range is larger than the pushed value"
+    );
+    // PushValue types have no padding and the range is checked to lie within the value
+    let data = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, range.size as usize) }.to_vec();
+    Self {
+      layout,
+      stageflags: range.stageFlags,
+      offset: range.offset,
+      data,
+    }
+  }
+
+  /// Records the command into the command buffer
+  pub fn enqueue(&self, cb: vk::CommandBuffer) {
+    vk::CmdPushConstants(
+      cb,
+      self.layout,
+      self.stageflags,
+      self.offset,
+      self.data.len() as u32,
+      self.data.as_ptr() as *const std::os::raw::c_void,
+    );
+  }
 }
 
 /// A managed vulkan pipeline object.
@@ -23,15 +173,73 @@ pub struct Pipeline {
   pub handle: vk::Pipeline,
   pub dsets: Vec<DescriptorLayout>,
   pub layout: vk::PipelineLayout,
+  pub layout_cache: Option<DescriptorLayoutCache>,
+  pub samplers: Vec<vk::Sampler>,
 }
 
 impl Drop for Pipeline {
-  /// Cleans up the pipeline, the pipeline layout and all descriptor set layouts
+  /// Cleans up the pipeline, the pipeline layout, all descriptor set layouts and immutable samplers
+  ///
+  /// Descriptor set layouts from a [DescriptorLayoutCache](../descriptor/struct.DescriptorLayoutCache.html) are released to the cache instead.
   fn drop(&mut self) {
     for ds in self.dsets.iter() {
-      vk::DestroyDescriptorSetLayout(self.device, ds.layout, std::ptr::null());
+      match self.layout_cache {
+        Some(ref cache) => cache.release(ds.layout),
+        None => vk::DestroyDescriptorSetLayout(self.device, ds.layout, std::ptr::null()),
+      }
     }
     vk::DestroyPipelineLayout(self.device, self.layout, std::ptr::null());
     vk::DestroyPipeline(self.device, self.handle, std::ptr::null());
+    for s in self.samplers.iter() {
+      vk::DestroySampler(self.device, *s, std::ptr::null());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn specialization() {
+    let mut s = Specialization::default();
+    assert!(s.is_empty());
+    s.set(0, 16u32).set(1, vk::TRUE).set(2, 0.5f64);
+    assert_eq!(s.data.len(), 16);
+    assert_eq!(&s.data[0..4], &16u32.to_ne_bytes());
+
+    // replacing a value with the same size keeps its offset
+    s.set(0, 32u32);
+    assert_eq!(s.data.len(), 16);
+    assert_eq!(&s.data[0..4], &32u32.to_ne_bytes());
+
+    // replacing a value with a different size moves it to the end
+    s.set(1, 1u8);
+    let e = s.entries.iter().find(|e| e.constantID == 1).unwrap();
+    assert_eq!((e.offset, e.size), (16, 1));
+    assert_eq!(s.entries.len(), 3);
+    assert_eq!(s.get_info().dataSize, 17);
+  }
+
+  fn range(offset: u32, size: u32) -> vk::PushConstantRange {
+    vk::PushConstantRange {
+      stageFlags: vk::SHADER_STAGE_VERTEX_BIT,
+      offset,
+      size,
+    }
+  }
+
+  #[test]
+  fn push_constants() {
+    let pc = PushConstants::new(vk::NULL_HANDLE, range(16, 8), &[1u32, 2, 3, 4]);
+    assert_eq!(pc.offset, 16);
+    assert_eq!(pc.stageflags, vk::SHADER_STAGE_VERTEX_BIT);
+    assert_eq!(pc.data, [1u32.to_ne_bytes(), 2u32.to_ne_bytes()].concat());
+  }
+
+  #[test]
+  #[should_panic(expected = "push constant range is larger than the pushed value")]
+  fn push_constants_out_of_bounds() {
+    PushConstants::new(vk::NULL_HANDLE, range(0, 12), &[1.0f32, 2.0]);
   }
 }
@@ -0,0 +1,211 @@
+use crate::Error;
+use nobs_vkpipes_include as include;
+use vk;
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Result of a shader compilation
+pub struct Compiled {
+  /// The compiled SPIR-V
+  pub binary: Vec<u32>,
+  /// The root source file and every file, that was included while compiling
+  pub dependencies: Vec<PathBuf>,
+  /// Warnings of the compiler, empty if there were none
+  pub warnings: String,
+}
+
+/// GLSL shader source file, that is compiled at runtime
+///
+/// Includes are resolved the same way as in the [shader](../../nobs_vkpipes_macro/macro.shader.html) macro:
+/// - `#include "file"` is searched relative to the including file
+/// - `#include <file>` is searched in the include directories and the directory of the root source file
+pub struct ShaderSource {
+  pub stage: vk::ShaderStageFlagBits,
+  pub path: PathBuf,
+  pub includes: Vec<PathBuf>,
+  entry: CString,
+}
+
+impl ShaderSource {
+  /// Creates the shader source for a stage from a glsl file
+  ///
+  /// The entry point is `main`.
+  pub fn new<P: AsRef<Path>>(stage: vk::ShaderStageFlagBits, path: P) -> Self {
+    Self {
+      stage,
+      path: path.as_ref().to_path_buf(),
+      includes: Default::default(),
+      entry: CString::new("main").unwrap(),
+    }
+  }
+
+  /// Sets the name of the entry point
+  ///
+  /// Fails with `Error::ShaderCompileFail`, if the name contains a nul byte.
+  pub fn entry(mut self, entry: &str) -> Result<Self, Error> {
+    self.entry = CString::new(entry).map_err(|_| Error::ShaderCompileFail(format!("Entry point name {:?} contains a nul byte", entry)))?;
+    Ok(self)
+  }
+
+  /// Adds a directory in which includes are searched
+  pub fn include<P: AsRef<Path>>(mut self, dir: P) -> Self {
+    self.includes.push(dir.as_ref().to_path_buf());
+    self
+  }
+
+  /// Compiles the glsl source with shaderc
+  ///
+  /// If the compilation fails the error contains the diagnostics of shaderc.
+  pub fn compile(&self) -> Result<Compiled, Error> {
+    let mut dependencies = Vec::new();
+    let (binary, warnings) = self.compile_tracked(&mut dependencies)?;
+    Ok(Compiled {
+      binary,
+      dependencies,
+      warnings,
+    })
+  }
+
+  /// Compiles the glsl source with shaderc and appends the root source file and every included file to `dependencies`
+  ///
+  /// The dependencies are appended even if the compilation fails, so that includes of a broken source can still be watched.
+  /// Returns the SPIR-V and the warnings of the compiler.
+  pub(crate) fn compile_tracked(&self, dependencies: &mut Vec<PathBuf>) -> Result<(Vec<u32>, String), Error> {
+    dependencies.push(self.path.clone());
+
+    let kind = match self.stage {
+      vk::SHADER_STAGE_VERTEX_BIT => shaderc::ShaderKind::Vertex,
+      vk::SHADER_STAGE_TESSELLATION_CONTROL_BIT => shaderc::ShaderKind::TessControl,
+      vk::SHADER_STAGE_TESSELLATION_EVALUATION_BIT => shaderc::ShaderKind::TessEvaluation,
+      vk::SHADER_STAGE_GEOMETRY_BIT => shaderc::ShaderKind::Geometry,
+      vk::SHADER_STAGE_FRAGMENT_BIT => shaderc::ShaderKind::Fragment,
+      vk::SHADER_STAGE_COMPUTE_BIT => shaderc::ShaderKind::Compute,
+      _ => Err(Error::ShaderCompileFail(format!("unsupported shader stage {}", self.stage)))?,
+    };
+
+    let path = self.path.to_str().ok_or(Error::ShaderCompileFail(format!(
+      "Path {} is not valid unicode",
+      self.path.display()
+    )))?;
+    // the entry point was created from a str and is always valid unicode
+    let entry = self.entry.to_str().unwrap_or_default();
+
+    let src = std::fs::read_to_string(&self.path)
+      .map_err(|e| Error::ShaderCompileFail(format!("Could not read {}: {}", self.path.display(), e)))?;
+
+    // includes with angle brackets are also searched next to the root source file
+    let mut includes = self.includes.clone();
+    if let Some(parent) = self.path.parent() {
+      includes.push(parent.to_path_buf());
+    }
+
+    let included = RefCell::new(Vec::new());
+    let binary = {
+      let mut compiler = shaderc::Compiler::new().ok_or(Error::ShaderCompileFail("Could not create shaderc compiler".to_string()))?;
+      let mut options =
+        shaderc::CompileOptions::new().ok_or(Error::ShaderCompileFail("Could not create shaderc compile options".to_string()))?;
+
+      options.set_include_callback(|includee, include_type, includer, _depth| {
+        let resolved = include::resolve_include(includee, include_type, includer, &includes)?;
+        included.borrow_mut().push(PathBuf::from(&resolved.resolved_name));
+        Ok(resolved)
+      });
+
+      compiler.compile_into_spirv(&src, kind, path, entry, Some(&options))
+    };
+    dependencies.extend(included.into_inner());
+
+    let binary = binary.map_err(|e| Error::ShaderCompileFail(format!("shader compilation failed:\n {}", e)))?;
+    Ok((binary.as_binary().to_vec(), binary.get_warning_messages()))
+  }
+
+  /// Creates the shader module and the shader stage info
+  ///
+  /// The stage info references the entry point name of the source and is only valid as long as the source is not dropped.
+  /// The shader module is destroyed by the pipeline builders, after the pipeline is created.
+  pub fn create_module(&self, device: vk::Device, binary: &[u32]) -> Result<vk::PipelineShaderStageCreateInfo, Error> {
+    let create_info = vk::ShaderModuleCreateInfo {
+      sType: vk::STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
+      pNext: std::ptr::null(),
+      flags: 0,
+      codeSize: std::mem::size_of_val(binary),
+      pCode: binary.as_ptr(),
+    };
+
+    let mut module = vk::NULL_HANDLE;
+    vk_check!(vk::CreateShaderModule(device, &create_info, std::ptr::null(), &mut module)).map_err(|_| Error::InvalidShaderModule)?;
+
+    Ok(vk::PipelineShaderStageCreateInfo {
+      sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+      pNext: std::ptr::null(),
+      flags: 0,
+      stage: self.stage,
+      module,
+      pName: self.entry.as_ptr(),
+      pSpecializationInfo: std::ptr::null(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn compile_error(source: &ShaderSource) -> String {
+    match source.compile() {
+      Err(Error::ShaderCompileFail(e)) => e,
+      _ => panic!("expected a compile error"),
+    }
+  }
+
+  #[test]
+  fn source() {
+    let s = ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, "shaders/main.comp");
+    assert_eq!(s.stage, vk::SHADER_STAGE_COMPUTE_BIT);
+    assert_eq!(s.path, PathBuf::from("shaders/main.comp"));
+    assert!(s.includes.is_empty());
+    assert_eq!(s.entry.to_str().unwrap(), "main");
+
+    let s = s.include("include").include("shaders/common").entry("cs_main").unwrap();
+    assert_eq!(s.includes, vec![PathBuf::from("include"), PathBuf::from("shaders/common")]);
+    assert_eq!(s.entry.to_str().unwrap(), "cs_main");
+  }
+
+  #[test]
+  fn entry_nul() {
+    let s = ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, "shaders/main.comp");
+    assert!(match s.entry("ma\0in") {
+      Err(Error::ShaderCompileFail(e)) => e.contains("nul byte"),
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn compile_errors() {
+    let s = ShaderSource::new(vk::SHADER_STAGE_ALL, "shaders/main.comp");
+    assert!(compile_error(&s).contains("unsupported shader stage"));
+
+    let s = ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, "shaders/missing.comp");
+    assert!(compile_error(&s).contains("Could not read shaders/missing.comp"));
+  }
+
+  #[test]
+  fn dependencies_of_failed_compile() {
+    // the root source is tracked even if it can not be read, so that it is watched until it exists
+    let s = ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, "shaders/missing.comp");
+    let mut dependencies = vec![PathBuf::from("shaders/other.comp")];
+    assert!(s.compile_tracked(&mut dependencies).is_err());
+    assert_eq!(dependencies, vec![PathBuf::from("shaders/other.comp"), PathBuf::from("shaders/missing.comp")]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn path_not_unicode() {
+    use std::os::unix::ffi::OsStrExt;
+    let s = ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, std::ffi::OsStr::from_bytes(b"shaders/\xff.comp"));
+    assert!(compile_error(&s).contains("is not valid unicode"));
+  }
+}
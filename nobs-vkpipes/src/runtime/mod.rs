@@ -0,0 +1,9 @@
+//! Runtime shader compilation and hot reloading of pipelines
+//!
+//! Requires the `runtime` feature.
+mod compile;
+mod reload;
+
+pub use compile::Compiled;
+pub use compile::ShaderSource;
+pub use reload::HotPipeline;
@@ -0,0 +1,221 @@
+use crate::runtime::ShaderSource;
+use crate::Error;
+use crate::Pipeline;
+use vk;
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Pipeline, that is rebuilt when one of its shader sources changes
+///
+/// The shader stages are compiled at runtime from [ShaderSource](struct.ShaderSource.html).
+/// The pipeline itself is created with a user defined function from the compiled stages, so that the bindings, push constants and
+/// states can still be taken from the code generated by the [pipeline](../../nobs_vkpipes_macro/macro.pipeline.html) macro.
+/// The stages are passed to the function in the same order as the sources.
+///
+/// The root source files and all their includes are watched by polling the modification time of the files with [poll](struct.HotPipeline.html#method.poll).
+/// If any file changes, all stages are recompiled and the pipeline is recreated.
+/// When compilation fails the old pipeline is kept and the shaderc diagnostics are returned as error.
+///
+/// The interface of the shaders must not change, since the descriptor set and pipeline layouts are not reflected at runtime.
+///
+/// ## Example
+/// Replaced pipelines are kept until no frame in flight uses them anymore, they are destroyed when they are dropped.
+/// Pipelines created from the code of the [pipeline](../../nobs_vkpipes_macro/macro.pipeline.html) macro would also set the bindings and push constants of the generated module in `create`.
+/// ```rust,no_run
+/// # extern crate nobs_vk as vk;
+/// # extern crate nobs_vkpipes as vkpipes;
+/// # use vkpipes::runtime::HotPipeline;
+/// # use vkpipes::runtime::ShaderSource;
+/// # fn main() -> Result<(), vkpipes::Error> {
+/// # let device = vk::NULL_HANDLE;
+/// const FRAMES_IN_FLIGHT: u64 = 2;
+///
+/// let mut p = HotPipeline::new(
+///   device,
+///   vec![ShaderSource::new(vk::SHADER_STAGE_COMPUTE_BIT, "src/make_sequence.comp")],
+///   move |stages| vkpipes::ComputeBuilder::from_device(device).comp(&stages[0]).create(),
+/// )?;
+///
+/// let mut retired = std::collections::VecDeque::new();
+/// for frame in 0..1000u64 {
+///   match p.poll() {
+///     Ok(Some(old)) => retired.push_back((frame, old)),
+///     Ok(None) => (),
+///     Err(e) => println!("{:?}", e),
+///   }
+///   while retired.front().map_or(false, |(f, _)| f + FRAMES_IN_FLIGHT <= frame) {
+///     retired.pop_front();
+///   }
+///
+///   // record and submit commands with p.get()
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct HotPipeline<F: Fn(&[vk::PipelineShaderStageCreateInfo]) -> Result<Pipeline, Error>> {
+  device: vk::Device,
+  sources: Vec<ShaderSource>,
+  create: F,
+  pipeline: Pipeline,
+  watch: Watch,
+}
+
+impl<F: Fn(&[vk::PipelineShaderStageCreateInfo]) -> Result<Pipeline, Error>> HotPipeline<F> {
+  /// Compiles the sources and creates the pipeline
+  pub fn new(device: vk::Device, sources: Vec<ShaderSource>, create: F) -> Result<Self, Error> {
+    let (pipeline, dependencies) = Self::build(device, &sources, &create);
+    let pipeline = pipeline?;
+    Ok(Self {
+      device,
+      sources,
+      create,
+      pipeline,
+      watch: Watch::new(dependencies),
+    })
+  }
+
+  /// Get the current pipeline
+  pub fn get(&self) -> &Pipeline {
+    &self.pipeline
+  }
+
+  /// Checks if any of the watched files has changed and rebuilds the pipeline
+  ///
+  /// # Returns
+  /// - `Ok(None)` if no file has changed
+  /// - `Ok(Some(old))` if the pipeline was rebuilt. The old pipeline is returned, so that it can be destroyed once it is no longer in use by the device.
+  /// - `Err(e)` if compilation or pipeline creation failed. The old pipeline is kept and the error is only reported once for every change of the files.
+  ///
+  /// The watched files are updated with the includes of the sources in any case, so that includes added in a broken edit are watched as well.
+  pub fn poll(&mut self) -> Result<Option<Pipeline>, Error> {
+    if !self.watch.poll() {
+      return Ok(None);
+    }
+    self.reload().map(Some)
+  }
+
+  /// Recompiles the sources and rebuilds the pipeline, regardless of whether the files have changed
+  pub fn reload(&mut self) -> Result<Pipeline, Error> {
+    let (pipeline, dependencies) = Self::build(self.device, &self.sources, &self.create);
+    self.watch = Watch::new(dependencies);
+    Ok(std::mem::replace(&mut self.pipeline, pipeline?))
+  }
+
+  /// Compiles the sources and creates the pipeline
+  ///
+  /// Returns the sorted files, that the sources depend on, even if compilation or pipeline creation failed.
+  fn build(device: vk::Device, sources: &[ShaderSource], create: &F) -> (Result<Pipeline, Error>, Vec<PathBuf>) {
+    // every source is compiled, so that the dependencies of all sources are known if one of them fails
+    let mut dependencies = Vec::new();
+    let compiled = sources.iter().map(|s| s.compile_tracked(&mut dependencies)).collect::<Vec<_>>();
+    dependencies.sort();
+    dependencies.dedup();
+
+    let pipeline = compiled
+      .into_iter()
+      .collect::<Result<Vec<_>, _>>()
+      .and_then(|binaries| Self::create(device, sources, create, &binaries));
+    (pipeline, dependencies)
+  }
+
+  fn create(device: vk::Device, sources: &[ShaderSource], create: &F, binaries: &[(Vec<u32>, String)]) -> Result<Pipeline, Error> {
+    let mut stages = Vec::with_capacity(sources.len());
+    for (s, (binary, _)) in sources.iter().zip(binaries.iter()) {
+      match s.create_module(device, binary) {
+        Ok(stage) => stages.push(stage),
+        Err(e) => {
+          Self::destroy_modules(device, &stages);
+          Err(e)?
+        }
+      }
+    }
+
+    // the builders only destroy the modules if the pipeline was created successfully
+    create(&stages).inspect_err(|_| Self::destroy_modules(device, &stages))
+  }
+
+  fn destroy_modules(device: vk::Device, stages: &[vk::PipelineShaderStageCreateInfo]) {
+    stages
+      .iter()
+      .for_each(|s| vk::DestroyShaderModule(device, s.module, std::ptr::null()));
+  }
+}
+
+/// Modification times of the watched files
+struct Watch {
+  files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Watch {
+  fn new(files: Vec<PathBuf>) -> Self {
+    Self {
+      files: Self::get_modified(files),
+    }
+  }
+
+  /// Checks if any file has been modified, created or removed since the last poll
+  fn poll(&mut self) -> bool {
+    let modified = Self::get_modified(self.files.iter().map(|(f, _)| f.clone()).collect());
+    match modified == self.files {
+      true => false,
+      false => {
+        self.files = modified;
+        true
+      }
+    }
+  }
+
+  fn get_modified(files: Vec<PathBuf>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    files
+      .into_iter()
+      .map(|f| {
+        let modified = std::fs::metadata(&f).and_then(|m| m.modified()).ok();
+        (f, modified)
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  fn touch(path: &PathBuf, secs: u64) {
+    let f = std::fs::OpenOptions::new().write(true).create(true).truncate(false).open(path).unwrap();
+    f.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+  }
+
+  #[test]
+  fn poll() {
+    let dir = std::env::temp_dir().join(format!("nobs-vkpipes-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.comp");
+    let util = dir.join("util.glsl");
+    touch(&main, 1000);
+    touch(&util, 1000);
+
+    let mut watch = Watch::new(vec![main.clone(), util.clone()]);
+    assert!(!watch.poll());
+
+    // every change is only reported once
+    touch(&util, 2000);
+    assert!(watch.poll());
+    assert!(!watch.poll());
+
+    touch(&main, 3000);
+    assert!(watch.poll());
+    assert!(!watch.poll());
+
+    // removed and recreated files are changes as well
+    std::fs::remove_file(&util).unwrap();
+    assert!(watch.poll());
+    assert!(!watch.poll());
+    touch(&util, 2000);
+    assert!(watch.poll());
+    assert!(!watch.poll());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
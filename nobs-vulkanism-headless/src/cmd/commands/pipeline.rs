@@ -70,6 +70,16 @@ impl StreamPush for BindDset {
   }
 }
 
+/// Updates the push constants of a pipeline layout, see [PushConstants](../../pipes/pipeline/struct.PushConstants.html)
+pub use crate::pipes::pipeline::PushConstants;
+
+impl StreamPush for PushConstants {
+  fn enqueue(&self, cs: CmdBuffer) -> CmdBuffer {
+    PushConstants::enqueue(self, cs.buffer);
+    cs
+  }
+}
+
 /// Sets a viewport for the command stream
 #[derive(Debug, Clone, Copy)]
 pub struct Viewport {
@@ -35,7 +35,7 @@
 //!
 //!   // Symbols of dependent moduls are put in their own namespace within vk::
 //!   // e.g.:
-//!   let mut allocator = vk::mem::Allocator::new(pdevice.handle, device.handle);
+//!   let allocator = vk::mem::Allocator::new(pdevice.handle, device.handle);
 //!   //...
 //! }
 //! ```
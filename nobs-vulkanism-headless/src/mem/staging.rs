@@ -25,13 +25,13 @@ impl std::fmt::Debug for Staging {
 }
 
 impl Staging {
-  pub fn new(mut mem: mem::Mem, size: vk::DeviceSize) -> Result<Self, mem::Error> {
+  pub fn new(mem: mem::Mem, size: vk::DeviceSize) -> Result<Self, mem::Error> {
     let mut buffer = vk::NULL_HANDLE;
     mem::Buffer::new(&mut buffer)
       .size(size)
       .usage(vk::BUFFER_USAGE_TRANSFER_SRC_BIT | vk::BUFFER_USAGE_TRANSFER_DST_BIT | vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT)
       .devicelocal(false)
-      .bind(&mut mem.alloc, mem::BindType::Scatter)?;
+      .bind(&mem.alloc, mem::BindType::Scatter)?;
 
     Ok(Self {
       range: StagingRange {
@@ -34,7 +34,7 @@ impl Framebuffer {
     Builder::new(device, pass)
   }
 
-  pub fn build_from_pass<'a, 'b>(pass: &'b Renderpass, alloc: &'a mut mem::Allocator) -> RenderpassFramebufferBuilder<'a, 'b> {
+  pub fn build_from_pass<'a, 'b>(pass: &'b Renderpass, alloc: &'a mem::Allocator) -> RenderpassFramebufferBuilder<'a, 'b> {
     RenderpassFramebufferBuilder::new(pass, alloc)
   }
 
@@ -164,7 +164,7 @@ impl Builder {
 /// For attachments in the renderpass that have not been externally specified with [target](struct.RenderpassFramebufferBuilder.html#method.target)
 /// the builder creates new images automatically.
 pub struct RenderpassFramebufferBuilder<'a, 'b> {
-  alloc: &'a mut mem::Allocator,
+  alloc: &'a mem::Allocator,
   pass: &'b Renderpass,
   images: Vec<vk::Image>,
   extent: vk::Extent2D,
@@ -172,7 +172,7 @@ pub struct RenderpassFramebufferBuilder<'a, 'b> {
 
 impl<'a, 'b> RenderpassFramebufferBuilder<'a, 'b> {
   /// Build a new framebuffer for the specified renderpass
-  pub fn new(pass: &'b Renderpass, alloc: &'a mut mem::Allocator) -> Self {
+  pub fn new(pass: &'b Renderpass, alloc: &'a mem::Allocator) -> Self {
     let mut images = Vec::new();
     images.resize(pass.attachments.len(), vk::NULL_HANDLE);
     Self {
@@ -7,28 +7,10 @@ pub struct SamplerBuilder {
 vk_builder!(vk::SamplerCreateInfo, SamplerBuilder, info);
 
 impl Default for SamplerBuilder {
+  /// Same defaults as for immutable samplers of the `pipeline` macro, see [DEFAULT_SAMPLER](../../pipes/builder/constant.DEFAULT_SAMPLER.html)
   fn default() -> Self {
     Self {
-      info: vk::SamplerCreateInfo {
-      sType: vk::STRUCTURE_TYPE_SAMPLER_CREATE_INFO,
-      flags: 0,
-      pNext: std::ptr::null(),
-      magFilter: vk::FILTER_NEAREST,
-      minFilter: vk::FILTER_NEAREST,
-      mipmapMode: vk::SAMPLER_MIPMAP_MODE_LINEAR,
-      addressModeU: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
-      addressModeV: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
-      addressModeW: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
-      anisotropyEnable: vk::FALSE,
-      maxAnisotropy: 1.0,
-      borderColor: vk::BORDER_COLOR_INT_OPAQUE_BLACK,
-      unnormalizedCoordinates: vk::FALSE,
-      compareEnable: vk::FALSE,
-      compareOp: vk::COMPARE_OP_ALWAYS,
-      mipLodBias: 0.0,
-      minLod: 0.0,
-      maxLod: 1.0,
-      },
+      info: crate::pipes::builder::DEFAULT_SAMPLER,
     }
   }
 }